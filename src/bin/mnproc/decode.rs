@@ -0,0 +1,148 @@
+use crate::Args;
+use dash_spv_masternode_processor::codec::{ListDiffMessage, QRInfoMessage, SnapshotMessage};
+use dash_spv_masternode_processor::core_json::{hash_to_json, list_diff_to_json, qr_info_to_json};
+use dash_spv_models::common::ChainType;
+use std::path::Path;
+
+/// Heights aren't known without the block store
+fn unknown_height<T>(_: T) -> u32 {
+    u32::MAX
+}
+
+pub fn read_file(path: &str) -> Result<Vec<u8>, String> {
+    std::fs::read(path).map_err(|err| format!("can't read {}: {}", path, err))
+}
+
+pub fn is_qr_info_file(path: &str) -> bool {
+    Path::new(path)
+        .file_name()
+        .map_or(false, |name| name.to_string_lossy().to_uppercase().starts_with("QRINFO"))
+}
+
+pub fn run(args: &Args) -> Result<(), String> {
+    let path = args
+        .positional
+        .first()
+        .ok_or_else(|| "decode: file isn't given".to_string())?;
+    let bytes = read_file(path)?;
+    let is_qr_info = args.has("--qrinfo") || (!args.has("--mnlistdiff") && is_qr_info_file(path));
+    let chain = args.chain();
+    if is_qr_info {
+        let message = QRInfoMessage::from_bytes(&bytes, &mut 0, unknown_height)
+            .ok_or_else(|| format!("{}: can't decode QRINFO", path))?;
+        if args.has("--json") {
+            print_json(&qr_info_to_json(&message, &chain));
+        } else {
+            print_qr_info(&message, args.has("--verbose"), &chain);
+        }
+    } else {
+        let message = ListDiffMessage::from_bytes(&bytes, &mut 0, unknown_height)
+            .ok_or_else(|| format!("{}: can't decode MNLISTDIFF", path))?;
+        if args.has("--json") {
+            print_json(&list_diff_to_json(&message, &chain));
+        } else {
+            print_list_diff("MNLISTDIFF", &message, args.has("--verbose"), &chain);
+        }
+    }
+    Ok(())
+}
+
+fn print_json<T: serde::Serialize>(value: &T) {
+    println!("{}", serde_json::to_string_pretty(value).unwrap());
+}
+
+pub fn print_list_diff(title: &str, message: &ListDiffMessage, verbose: bool, chain: &ChainType) {
+    let diff = &message.diff;
+    let coinbase = &diff.coinbase_transaction;
+    println!("{}: {} -> {}", title, hash_to_json(&diff.base_block_hash), hash_to_json(&diff.block_hash));
+    println!("    total transactions: {}", diff.total_transactions);
+    println!(
+        "    coinbase: version {}, height {}, {} bytes",
+        coinbase.coinbase_transaction_version,
+        coinbase.height,
+        message.coinbase_transaction.len()
+    );
+    println!("    merkleRootMNList: {}", hash_to_json(&coinbase.merkle_root_mn_list));
+    if let Some(root) = &coinbase.merkle_root_llmq_list {
+        println!("    merkleRootQuorums: {}", hash_to_json(root));
+    }
+    println!(
+        "    masternodes: {} added or modified, {} deleted",
+        diff.added_or_modified_masternodes.len(),
+        diff.deleted_masternode_hashes.len()
+    );
+    if verbose {
+        let list = list_diff_to_json(message, chain);
+        list.mn_list.iter().for_each(|node| {
+            println!(
+                "        + {} {} {}",
+                node.pro_reg_tx_hash,
+                node.service,
+                if node.is_valid { "valid" } else { "invalid" }
+            )
+        });
+        list.deleted_mns.iter().for_each(|hash| println!("        - {}", hash));
+    }
+    let added_quorums = diff.added_quorums.values().map(|quorums| quorums.len()).sum::<usize>();
+    let deleted_quorums = diff.deleted_quorums.values().map(|hashes| hashes.len()).sum::<usize>();
+    println!("    quorums: {} added, {} deleted", added_quorums, deleted_quorums);
+    if verbose {
+        diff.added_quorums.iter().for_each(|(&llmq_type, quorums)| {
+            let llmq_type: u8 = llmq_type.into();
+            quorums.values().for_each(|quorum| {
+                println!(
+                    "        + type {} {}{} signers {} valid members {}",
+                    llmq_type,
+                    hash_to_json(&quorum.llmq_hash),
+                    quorum.index.map(|index| format!(" index {}", index)).unwrap_or_default(),
+                    quorum.signers_count.0,
+                    quorum.valid_members_count.0
+                )
+            });
+        });
+        diff.deleted_quorums.iter().for_each(|(&llmq_type, hashes)| {
+            let llmq_type: u8 = llmq_type.into();
+            hashes.iter().for_each(|hash| println!("        - type {} {}", llmq_type, hash_to_json(hash)));
+        });
+    }
+}
+
+fn print_snapshot(title: &str, message: &SnapshotMessage) {
+    let snapshot = &message.snapshot;
+    let skip_list_mode: u32 = snapshot.skip_list_mode.clone().into();
+    let active_members = snapshot
+        .member_list
+        .iter()
+        .map(|byte| byte.count_ones())
+        .sum::<u32>();
+    println!(
+        "{}: skip mode {}, {} of {} members active, skip list {:?}",
+        title, skip_list_mode, active_members, message.member_count, snapshot.skip_list
+    );
+}
+
+fn print_qr_info(message: &QRInfoMessage, verbose: bool, chain: &ChainType) {
+    println!("QRINFO: extra share: {}", message.extra_share.is_some());
+    if let Some((snapshot, diff)) = &message.extra_share {
+        print_snapshot("snapshot at h-4c", snapshot);
+        print_list_diff("diff at h-4c", diff, verbose, chain);
+    }
+    print_snapshot("snapshot at h-3c", &message.snapshot_at_h_3c);
+    print_list_diff("diff at h-3c", &message.diff_h_3c, verbose, chain);
+    print_snapshot("snapshot at h-2c", &message.snapshot_at_h_2c);
+    print_list_diff("diff at h-2c", &message.diff_h_2c, verbose, chain);
+    print_snapshot("snapshot at h-c", &message.snapshot_at_h_c);
+    print_list_diff("diff at h-c", &message.diff_h_c, verbose, chain);
+    print_list_diff("diff at h", &message.diff_h, verbose, chain);
+    print_list_diff("diff at tip", &message.diff_tip, verbose, chain);
+    println!("last commitments per index: {}", message.last_quorum_per_index.len());
+    message
+        .quorum_snapshot_list
+        .iter()
+        .zip(message.mn_list_diff_list.iter())
+        .enumerate()
+        .for_each(|(index, (snapshot, diff))| {
+            print_snapshot(&format!("extra snapshot {}", index), snapshot);
+            print_list_diff(&format!("extra diff {}", index), diff, verbose, chain);
+        });
+}
@@ -0,0 +1,69 @@
+//! Command line tools for masternode list messages (available with 'cli' feature)
+mod decode;
+mod replay;
+
+use dash_spv_models::common::ChainType;
+use std::process::exit;
+
+const USAGE: &str = "\
+Usage:
+    mnproc decode [--json] [--verbose] [--testnet] [--mnlistdiff | --qrinfo] <file>
+    mnproc replay [--verbose] [--testnet] <dir> <blocks.json>
+
+Commands:
+    decode    Prints MNLISTDIFF or QRINFO payload (the kind is taken from the file name if not given)
+    replay    Processes MNL_* and QRINFO* files of the directory in the order they're based on each other
+              and prints validation flags and missing lists and blocks of each step.
+              Blocks are taken from 'getblock' results (hash, height, merkleroot) like in scripts/testnet.json
+
+Options:
+    --json       Output in the format of 'protx diff' / 'quorum rotationinfo'
+    --verbose    List masternodes and quorums, not only their counts
+    --testnet    Voting addresses are shown for testnet (replay: diffs are based on testnet genesis)";
+
+/// Flags and positional arguments of the command
+pub struct Args {
+    flags: Vec<String>,
+    pub positional: Vec<String>,
+}
+
+impl Args {
+    fn parse(args: impl Iterator<Item = String>) -> Self {
+        let (flags, positional) = args.partition(|arg| arg.starts_with("--"));
+        Self { flags, positional }
+    }
+
+    pub fn has(&self, flag: &str) -> bool {
+        self.flags.iter().any(|arg| arg == flag)
+    }
+
+    pub fn chain(&self) -> ChainType {
+        if self.has("--testnet") {
+            ChainType::TestNet
+        } else {
+            ChainType::MainNet
+        }
+    }
+}
+
+pub fn fail(message: &str) -> ! {
+    eprintln!("mnproc: {}", message);
+    exit(1)
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let command = args.next().unwrap_or_default();
+    let args = Args::parse(args);
+    let result = match command.as_str() {
+        "decode" => decode::run(&args),
+        "replay" => replay::run(&args),
+        _ => {
+            eprintln!("{}", USAGE);
+            exit(2)
+        }
+    };
+    if let Err(message) = result {
+        fail(&message);
+    }
+}
@@ -0,0 +1,448 @@
+use crate::decode::{is_qr_info_file, read_file};
+use crate::Args;
+use dash_spv_ffi::ffi::boxer::boxed;
+use dash_spv_ffi::ffi::from::FromFFI;
+use dash_spv_ffi::ffi::to::ToFFI;
+use dash_spv_ffi::ffi::unboxer::{unbox_any, unbox_llmq_snapshot, unbox_llmq_validation_data};
+use dash_spv_ffi::types;
+use dash_spv_masternode_processor::codec::{ListDiffMessage, QRInfoMessage};
+use dash_spv_masternode_processor::core_json::{hash_from_json, hash_to_json};
+use dash_spv_masternode_processor::processing::{MasternodeProcessorCache, ProcessingError};
+use dash_spv_masternode_processor::{
+    process_mnlistdiff_from_message, process_qrinfo_from_message, processor_cache_stats,
+    processor_create_cache, processor_destroy_cache, processor_destroy_cache_stats,
+    processor_destroy_mnlistdiff_result, processor_destroy_qr_info_result, register_processor,
+    unregister_processor,
+};
+use dash_spv_models::common::chain_type::IHaveChainSettings;
+use dash_spv_models::llmq::LLMQSnapshot;
+use dash_spv_primitives::crypto::byte_util::Zeroable;
+use dash_spv_primitives::crypto::UInt256;
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ffi::{c_void, CStr};
+use std::path::{Path, PathBuf};
+use std::ptr::null_mut;
+
+/// Block as it's given by 'getblock' (other fields are ignored)
+#[derive(Deserialize)]
+struct BlockJson {
+    hash: String,
+    height: u32,
+    merkleroot: String,
+}
+
+/// Block store the processor callbacks are answered from
+struct ReplayContext {
+    blocks: BTreeMap<UInt256, (u32, UInt256)>,
+    hashes: BTreeMap<u32, UInt256>,
+    snapshots: BTreeMap<UInt256, LLMQSnapshot>,
+    /// Blocks the processor asked for but the store doesn't have, reported after each step
+    unknown_blocks: BTreeSet<UInt256>,
+    unknown_heights: BTreeSet<u32>,
+    verbose: bool,
+}
+
+impl ReplayContext {
+    fn from_json(json: &str, verbose: bool) -> Result<Self, String> {
+        let blocks = serde_json::from_str::<Vec<BlockJson>>(json)
+            .map_err(|err| format!("can't parse blocks: {}", err))?;
+        let mut context = Self {
+            blocks: BTreeMap::new(),
+            hashes: BTreeMap::new(),
+            snapshots: BTreeMap::new(),
+            unknown_blocks: BTreeSet::new(),
+            unknown_heights: BTreeSet::new(),
+            verbose,
+        };
+        for block in blocks {
+            let hash = hash_from_json(&block.hash)
+                .ok_or_else(|| format!("invalid block hash {}", block.hash))?;
+            let merkle_root = hash_from_json(&block.merkleroot)
+                .ok_or_else(|| format!("invalid merkle root {}", block.merkleroot))?;
+            context.blocks.insert(hash, (block.height, merkle_root));
+            context.hashes.insert(block.height, hash);
+        }
+        Ok(context)
+    }
+
+    fn height(&self, block_hash: UInt256) -> u32 {
+        self.blocks.get(&block_hash).map_or(u32::MAX, |(height, _)| *height)
+    }
+
+    fn describe(&self, block_hash: &UInt256) -> String {
+        match self.blocks.get(block_hash) {
+            Some((height, _)) => format!("{} ({})", height, hash_to_json(block_hash)),
+            None if block_hash.is_zero() => "0".to_string(),
+            None => format!("? ({})", hash_to_json(block_hash)),
+        }
+    }
+
+    unsafe fn from_ptr<'a>(context: *const c_void) -> &'a mut Self {
+        &mut *(context as *mut ReplayContext)
+    }
+}
+
+unsafe extern "C" fn get_merkle_root_by_hash(block_hash: *mut [u8; 32], context: *const c_void) -> *mut u8 {
+    let context = ReplayContext::from_ptr(context);
+    let block_hash = UInt256(*block_hash);
+    match context.blocks.get(&block_hash) {
+        Some((_, merkle_root)) => boxed(merkle_root.0) as *mut u8,
+        None => {
+            context.unknown_blocks.insert(block_hash);
+            null_mut()
+        }
+    }
+}
+
+unsafe extern "C" fn get_block_height_by_hash(block_hash: *mut [u8; 32], context: *const c_void) -> u32 {
+    let context = ReplayContext::from_ptr(context);
+    let block_hash = UInt256(*block_hash);
+    let height = context.height(block_hash);
+    if height == u32::MAX && !block_hash.is_zero() {
+        context.unknown_blocks.insert(block_hash);
+    }
+    height
+}
+
+unsafe extern "C" fn get_block_hash_by_height(block_height: u32, context: *const c_void) -> *mut u8 {
+    let context = ReplayContext::from_ptr(context);
+    match context.hashes.get(&block_height) {
+        Some(block_hash) => boxed(block_hash.0) as *mut u8,
+        None => {
+            context.unknown_heights.insert(block_height);
+            null_mut()
+        }
+    }
+}
+
+unsafe extern "C" fn get_llmq_snapshot_by_block_hash(
+    block_hash: *mut [u8; 32],
+    context: *const c_void,
+) -> *mut types::LLMQSnapshot {
+    let context = ReplayContext::from_ptr(context);
+    context
+        .snapshots
+        .get(&UInt256(*block_hash))
+        .map_or(null_mut(), |snapshot| boxed(snapshot.encode()))
+}
+
+unsafe extern "C" fn save_llmq_snapshot(
+    block_hash: *mut [u8; 32],
+    snapshot: *mut types::LLMQSnapshot,
+    context: *const c_void,
+) -> bool {
+    let context = ReplayContext::from_ptr(context);
+    context.snapshots.insert(UInt256(*block_hash), (*snapshot).decode());
+    true
+}
+
+/// Lists are kept by the processor cache
+unsafe extern "C" fn get_masternode_list_by_block_hash(
+    _block_hash: *mut [u8; 32],
+    _context: *const c_void,
+) -> *mut types::MasternodeList {
+    null_mut()
+}
+
+unsafe extern "C" fn save_masternode_list(
+    _block_hash: *mut [u8; 32],
+    _masternode_list: *mut types::MasternodeList,
+    _context: *const c_void,
+) -> bool {
+    true
+}
+
+unsafe extern "C" fn destroy_masternode_list(_masternode_list: *mut types::MasternodeList) {}
+
+unsafe extern "C" fn add_insight(_block_hash: *mut [u8; 32], _context: *const c_void) {}
+
+unsafe extern "C" fn should_process_llmq_of_type(_llmq_type: u8, _context: *const c_void) -> bool {
+    true
+}
+
+/// There is no BLS here, so quorum signatures are taken as valid
+unsafe extern "C" fn validate_llmq(data: *mut types::LLMQValidationData, _context: *const c_void) -> bool {
+    unbox_llmq_validation_data(data);
+    true
+}
+
+unsafe extern "C" fn destroy_hash(hash: *mut u8) {
+    if !hash.is_null() {
+        unbox_any(hash as *mut [u8; 32]);
+    }
+}
+
+unsafe extern "C" fn destroy_snapshot(snapshot: *mut types::LLMQSnapshot) {
+    if !snapshot.is_null() {
+        unbox_llmq_snapshot(snapshot);
+    }
+}
+
+unsafe extern "C" fn should_process_diff_with_range(
+    _base_block_hash: *mut [u8; 32],
+    _block_hash: *mut [u8; 32],
+    _context: *const c_void,
+) -> u8 {
+    ProcessingError::None.into()
+}
+
+unsafe extern "C" fn log_message(message: *const libc::c_char, context: *const c_void) {
+    if ReplayContext::from_ptr(context).verbose && !message.is_null() {
+        println!("    | {}", CStr::from_ptr(message).to_string_lossy());
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum StepKind {
+    ListDiff,
+    QRInfo,
+}
+
+/// Message file along with the blocks it depends on and the blocks it gives lists for.
+/// Each diff of QRINFO has its own base, so all of them are needed before it's processed
+struct Step {
+    path: PathBuf,
+    kind: StepKind,
+    bytes: Vec<u8>,
+    base_block_hash: UInt256,
+    base_block_hashes: BTreeSet<UInt256>,
+    block_hash: UInt256,
+    block_hashes: Vec<UInt256>,
+}
+
+impl Step {
+    fn read(path: PathBuf, context: &ReplayContext) -> Result<Option<Self>, String> {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_uppercase())
+            .unwrap_or_default();
+        let kind = if is_qr_info_file(&path.to_string_lossy()) {
+            StepKind::QRInfo
+        } else if name.starts_with("MNL") {
+            StepKind::ListDiff
+        } else {
+            return Ok(None);
+        };
+        let bytes = read_file(&path.to_string_lossy())?;
+        let height_lookup = |hash: UInt256| context.height(hash);
+        let (base_block_hash, base_block_hashes, block_hash, block_hashes) = match kind {
+            StepKind::ListDiff => {
+                let message = ListDiffMessage::from_bytes(&bytes, &mut 0, height_lookup)
+                    .ok_or_else(|| format!("{}: can't decode MNLISTDIFF", path.display()))?;
+                (
+                    message.diff.base_block_hash,
+                    BTreeSet::from([message.diff.base_block_hash]),
+                    message.diff.block_hash,
+                    vec![message.diff.block_hash],
+                )
+            }
+            StepKind::QRInfo => {
+                let message = QRInfoMessage::from_bytes(&bytes, &mut 0, height_lookup)
+                    .ok_or_else(|| format!("{}: can't decode QRINFO", path.display()))?;
+                let diffs = message.diffs();
+                (
+                    diffs[0].diff.base_block_hash,
+                    diffs.iter().map(|message| message.diff.base_block_hash).collect(),
+                    message.diff_tip.diff.block_hash,
+                    diffs.iter().map(|message| message.diff.block_hash).collect(),
+                )
+            }
+        };
+        Ok(Some(Self { path, kind, bytes, base_block_hash, base_block_hashes, block_hash, block_hashes }))
+    }
+
+    fn file_name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+}
+
+/// Steps which can be processed one after another: each diff of the step is based on the genesis
+/// or on the list given by one of the steps before. Lower blocks go first.
+/// Returns the steps whose bases never become available separately
+fn dependency_order(mut pending: Vec<Step>, genesis_hash: UInt256, context: &ReplayContext) -> (Vec<Step>, Vec<Step>) {
+    pending.sort_by_key(|step| (context.height(step.block_hash), step.file_name()));
+    let mut known = BTreeSet::from([UInt256::MIN, genesis_hash]);
+    let mut ordered = Vec::<Step>::new();
+    while let Some(index) = pending.iter().position(|step| step.base_block_hashes.is_subset(&known)) {
+        let step = pending.remove(index);
+        known.extend(step.block_hashes.iter().copied());
+        ordered.push(step);
+    }
+    (ordered, pending)
+}
+
+fn flag(name: &str, value: bool) -> String {
+    if value {
+        name.to_string()
+    } else {
+        format!("{} FAILED", name)
+    }
+}
+
+unsafe fn print_list_diff_result(title: &str, result: *const types::MNListDiffResult, context: &ReplayContext) {
+    if result.is_null() {
+        return;
+    }
+    let result = &*result;
+    let error = ProcessingError::from(result.error_status);
+    let base_block_hash = UInt256(*result.base_block_hash);
+    let block_hash = UInt256(*result.block_hash);
+    println!("    {}: {} -> {}", title, context.describe(&base_block_hash), context.describe(&block_hash));
+    if error != ProcessingError::None {
+        println!("        error: {:?}", error);
+        return;
+    }
+    // Signatures are taken as valid by 'validate_llmq', so quorums can't be reported as verified
+    println!(
+        "        {}, {}, {}, {}, quorums not verified",
+        flag("coinbase found", result.has_found_coinbase),
+        flag("coinbase valid", result.has_valid_coinbase),
+        flag("masternode list root", result.has_valid_mn_list_root),
+        flag("quorum list root", result.has_valid_llmq_list_root)
+    );
+    (0..result.needed_masternode_lists_count).for_each(|index| {
+        let block_hash = UInt256(**result.needed_masternode_lists.add(index));
+        println!("        missing masternode list at {}", context.describe(&block_hash));
+    });
+}
+
+unsafe fn print_qr_info_result(result: *const types::QRInfoResult, context: &ReplayContext) {
+    let result = &*result;
+    let error = ProcessingError::from(result.error_status);
+    if error != ProcessingError::None {
+        println!("    error: {:?}", error);
+        return;
+    }
+    print_list_diff_result("diff at h-4c", result.result_at_h_4c, context);
+    print_list_diff_result("diff at h-3c", result.result_at_h_3c, context);
+    print_list_diff_result("diff at h-2c", result.result_at_h_2c, context);
+    print_list_diff_result("diff at h-c", result.result_at_h_c, context);
+    print_list_diff_result("diff at h", result.result_at_h, context);
+    print_list_diff_result("diff at tip", result.result_at_tip, context);
+    (0..result.mn_list_diff_list_count).for_each(|index| {
+        print_list_diff_result(&format!("extra diff {}", index), *result.mn_list_diff_list.add(index), context);
+    });
+}
+
+/// Blocks which were asked for while processing the step, but aren't in the store
+fn print_unknown_blocks(context: &mut ReplayContext) {
+    std::mem::take(&mut context.unknown_blocks)
+        .iter()
+        .for_each(|block_hash| println!("    missing block {}", hash_to_json(block_hash)));
+    std::mem::take(&mut context.unknown_heights)
+        .iter()
+        .for_each(|height| println!("    missing block at height {}", height));
+}
+
+fn read_steps(dir: &Path, context: &ReplayContext) -> Result<Vec<Step>, String> {
+    let entries = std::fs::read_dir(dir).map_err(|err| format!("can't read {}: {}", dir.display(), err))?;
+    let mut steps = Vec::<Step>::new();
+    for entry in entries {
+        let path = entry.map_err(|err| err.to_string())?.path();
+        if path.is_file() {
+            if let Some(step) = Step::read(path, context)? {
+                steps.push(step);
+            }
+        }
+    }
+    Ok(steps)
+}
+
+pub fn run(args: &Args) -> Result<(), String> {
+    let (dir, blocks_path) = match args.positional.as_slice() {
+        [dir, blocks_path, ..] => (dir, blocks_path),
+        _ => return Err("replay: directory and blocks file must be given".to_string()),
+    };
+    let blocks_json = String::from_utf8(read_file(blocks_path)?)
+        .map_err(|_| format!("{}: blocks file isn't UTF-8", blocks_path))?;
+    let mut context = ReplayContext::from_json(&blocks_json, args.has("--verbose"))?;
+    let chain = args.chain();
+    let genesis_hash = chain.genesis_hash();
+    let steps = read_steps(Path::new(dir), &context)?;
+    let (ordered, unresolved) = dependency_order(steps, genesis_hash, &context);
+    println!("replaying {} of {} messages ({} blocks known)", ordered.len(), ordered.len() + unresolved.len(), context.blocks.len());
+    println!("quorum signatures aren't verified");
+    unsafe {
+        let processor = register_processor(
+            get_merkle_root_by_hash,
+            get_block_height_by_hash,
+            get_block_hash_by_height,
+            get_llmq_snapshot_by_block_hash,
+            save_llmq_snapshot,
+            get_masternode_list_by_block_hash,
+            save_masternode_list,
+            destroy_masternode_list,
+            add_insight,
+            should_process_llmq_of_type,
+            validate_llmq,
+            destroy_hash,
+            destroy_snapshot,
+            should_process_diff_with_range,
+            log_message,
+        );
+        let cache: *mut MasternodeProcessorCache = processor_create_cache();
+        for (index, step) in ordered.iter().enumerate() {
+            println!(
+                "[{}/{}] {}: {} -> {}",
+                index + 1,
+                ordered.len(),
+                step.file_name(),
+                context.describe(&step.base_block_hash),
+                context.describe(&step.block_hash)
+            );
+            let context_ptr = &mut context as *mut ReplayContext as *const c_void;
+            match step.kind {
+                StepKind::ListDiff => {
+                    let result = process_mnlistdiff_from_message(
+                        step.bytes.as_ptr(),
+                        step.bytes.len(),
+                        false,
+                        false,
+                        genesis_hash.0.as_ptr(),
+                        processor,
+                        cache,
+                        context_ptr,
+                    );
+                    print_list_diff_result("MNLISTDIFF", result, &context);
+                    processor_destroy_mnlistdiff_result(result);
+                }
+                StepKind::QRInfo => {
+                    let result = process_qrinfo_from_message(
+                        step.bytes.as_ptr(),
+                        step.bytes.len(),
+                        false,
+                        false,
+                        genesis_hash.0.as_ptr(),
+                        processor,
+                        cache,
+                        context_ptr,
+                    );
+                    print_qr_info_result(result, &context);
+                    processor_destroy_qr_info_result(result);
+                }
+            }
+            print_unknown_blocks(&mut context);
+        }
+        let stats = processor_cache_stats(cache);
+        println!("lists in cache: {}, snapshots: {}", (*stats).mn_lists_count, context.snapshots.len());
+        processor_destroy_cache_stats(stats);
+        processor_destroy_cache(cache);
+        unregister_processor(processor);
+    }
+    unresolved.iter().for_each(|step| {
+        println!(
+            "not replayed {}: no list for the base blocks {}",
+            step.file_name(),
+            step.base_block_hashes
+                .iter()
+                .map(|block_hash| context.describe(block_hash))
+                .collect::<Vec<String>>()
+                .join(", ")
+        )
+    });
+    Ok(())
+}
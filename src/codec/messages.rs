@@ -0,0 +1,510 @@
+use crate::codec::{read_bytes, read_llmq_entry, read_llmq_snapshot, read_masternode_entry, read_var_int, write_bitset, write_var_int, BytesEncodable};
+use crate::processing::SharedMasternodeList;
+use byte::BytesExt;
+use dash_spv_models::llmq::{LLMQSnapshot, MNListDiff};
+use dash_spv_models::masternode::{LLMQEntry, MasternodeEntry};
+use dash_spv_models::tx::CoinbaseTransaction;
+use dash_spv_primitives::consensus::encode::VarInt;
+use dash_spv_primitives::crypto::byte_util::BytesDecodable;
+use dash_spv_primitives::crypto::var_array::VarArray;
+use dash_spv_primitives::crypto::UInt256;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Order of the entries as they came with MNLISTDIFF: Dash Core sends them in the order
+/// of its internal maps, which can't be derived from the entries and isn't kept by MNListDiff.
+/// Masternodes are given by their ProRegTx hashes, quorums by their types and hashes
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ListDiffOrder {
+    pub masternodes: Vec<UInt256>,
+    pub deleted_quorums: Vec<(u8, UInt256)>,
+    pub quorums: Vec<(u8, UInt256)>,
+}
+
+impl ListDiffOrder {
+    /// Reads the sections after cbTx
+    fn from_bytes(bytes: &[u8], offset: &mut usize, has_quorums: bool) -> Option<Self> {
+        let deleted_count = read_var_int(bytes, offset)?;
+        read_bytes(bytes, offset, deleted_count.checked_mul(32)?)?;
+        let masternodes = (0..read_var_int(bytes, offset)?)
+            .map(|_| read_masternode_entry(bytes, offset).map(|entry| entry.provider_registration_transaction_hash))
+            .collect::<Option<Vec<UInt256>>>()?;
+        if !has_quorums {
+            return Some(Self { masternodes, ..Default::default() });
+        }
+        let deleted_quorums = (0..read_var_int(bytes, offset)?)
+            .map(|_| Some((read_bytes(bytes, offset, 1)?[0], UInt256::from_bytes(bytes, offset)?)))
+            .collect::<Option<Vec<(u8, UInt256)>>>()?;
+        let quorums = (0..read_var_int(bytes, offset)?)
+            .map(|_| read_llmq_entry(bytes, offset).map(|entry| (entry.llmq_type.into(), entry.llmq_hash)))
+            .collect::<Option<Vec<(u8, UInt256)>>>()?;
+        Some(Self { masternodes, deleted_quorums, quorums })
+    }
+}
+
+/// Entries sorted by the position of their keys in the order,
+/// the ones which aren't there go last in the order they're given
+fn in_order<T, K: Ord, KF: Fn(&T) -> K>(entries: impl Iterator<Item = T>, order: &[K], key: KF) -> Vec<T> {
+    let positions = order
+        .iter()
+        .enumerate()
+        .map(|(position, key)| (key, position))
+        .collect::<BTreeMap<&K, usize>>();
+    let mut entries = entries.collect::<Vec<T>>();
+    entries.sort_by_key(|entry| positions.get(&key(entry)).copied().unwrap_or(usize::MAX));
+    entries
+}
+
+/// MNLISTDIFF along with the raw coinbase transaction and the order of the entries,
+/// which aren't kept by MNListDiff in the form they came with the message
+pub struct ListDiffMessage {
+    pub diff: MNListDiff,
+    pub coinbase_transaction: Vec<u8>,
+    pub order: ListDiffOrder,
+}
+
+impl ListDiffMessage {
+    pub fn from_bytes<BHL: Fn(UInt256) -> u32>(
+        bytes: &[u8],
+        offset: &mut usize,
+        block_height_lookup: BHL,
+    ) -> Option<Self> {
+        // baseBlockHash, blockHash, totalTransactions, merkleHashes and merkleFlags go before cbTx
+        let cb_offset = &mut (*offset + 68);
+        VarArray::<UInt256>::from_bytes(bytes, cb_offset)?;
+        let flags_count = VarInt::from_bytes(bytes, cb_offset)?.0 as usize;
+        read_bytes(bytes, cb_offset, flags_count)?;
+        let cb_start = *cb_offset;
+        let coinbase = CoinbaseTransaction::from_bytes(bytes, cb_offset)?;
+        let coinbase_transaction = bytes[cb_start..*cb_offset].to_vec();
+        let order = ListDiffOrder::from_bytes(bytes, cb_offset, coinbase.coinbase_transaction_version >= 2)?;
+        let diff = MNListDiff::new(bytes, offset, block_height_lookup)?;
+        Some(Self { diff, coinbase_transaction, order })
+    }
+
+    pub fn merkle_tree(&self) -> PartialMerkleTree {
+        PartialMerkleTree {
+            total_transactions: self.diff.total_transactions,
+            merkle_hashes: self.diff.merkle_hashes.1.clone(),
+            merkle_flags: self.diff.merkle_flags.clone(),
+        }
+    }
+}
+
+/// Partial merkle tree proving the coinbase is in the block:
+/// total transactions of the block, hashes and flags
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PartialMerkleTree {
+    pub total_transactions: u32,
+    pub merkle_hashes: Vec<UInt256>,
+    pub merkle_flags: Vec<u8>,
+}
+
+impl BytesEncodable for PartialMerkleTree {
+    fn encode_to(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(&self.total_transactions.to_le_bytes());
+        write_var_int(self.merkle_hashes.len(), buffer);
+        self.merkle_hashes
+            .iter()
+            .for_each(|hash| buffer.extend_from_slice(&hash.0));
+        write_var_int(self.merkle_flags.len(), buffer);
+        buffer.extend_from_slice(&self.merkle_flags);
+    }
+}
+
+/// Sections of MNLISTDIFF after cbTx, quorums go only with coinbase of version 2 and above
+fn encode_list_diff_entries<'a>(
+    deleted_masternode_hashes: &[UInt256],
+    masternodes: impl ExactSizeIterator<Item = &'a MasternodeEntry>,
+    deleted_quorums: &[(u8, UInt256)],
+    added_quorums: &[&LLMQEntry],
+    has_quorums: bool,
+    buffer: &mut Vec<u8>,
+) {
+    write_var_int(deleted_masternode_hashes.len(), buffer);
+    deleted_masternode_hashes
+        .iter()
+        .for_each(|hash| buffer.extend_from_slice(&hash.0));
+    write_var_int(masternodes.len(), buffer);
+    masternodes.for_each(|entry| entry.encode_to(buffer));
+    if has_quorums {
+        write_var_int(deleted_quorums.len(), buffer);
+        deleted_quorums.iter().for_each(|(llmq_type, hash)| {
+            buffer.push(*llmq_type);
+            buffer.extend_from_slice(&hash.0);
+        });
+        write_var_int(added_quorums.len(), buffer);
+        added_quorums.iter().for_each(|entry| entry.encode_to(buffer));
+    }
+}
+
+/// MNLISTDIFF payload as it's sent by Dash Core.
+/// Masternodes and quorums go in the order they came with the message,
+/// so the payload is the same as the received one
+impl BytesEncodable for ListDiffMessage {
+    fn encode_to(&self, buffer: &mut Vec<u8>) {
+        let diff = &self.diff;
+        buffer.extend_from_slice(&diff.base_block_hash.0);
+        buffer.extend_from_slice(&diff.block_hash.0);
+        self.merkle_tree().encode_to(buffer);
+        buffer.extend_from_slice(&self.coinbase_transaction);
+        let deleted_quorums = in_order(
+            diff.deleted_quorums.iter().flat_map(|(&llmq_type, hashes)| {
+                let llmq_type: u8 = llmq_type.into();
+                hashes.iter().map(move |hash| (llmq_type, *hash))
+            }),
+            &self.order.deleted_quorums,
+            |deleted| *deleted,
+        );
+        let added_quorums = in_order(
+            diff.added_quorums
+                .values()
+                .flat_map(|quorums_of_type| quorums_of_type.values()),
+            &self.order.quorums,
+            |entry| (entry.llmq_type.into(), entry.llmq_hash),
+        );
+        let masternodes = in_order(
+            diff.added_or_modified_masternodes.values(),
+            &self.order.masternodes,
+            |entry| entry.provider_registration_transaction_hash,
+        );
+        encode_list_diff_entries(
+            &diff.deleted_masternode_hashes,
+            masternodes.into_iter(),
+            &deleted_quorums,
+            &added_quorums,
+            diff.coinbase_transaction.coinbase_transaction_version >= 2,
+            buffer,
+        );
+    }
+}
+
+/// MNLISTDIFF payload which turns the base list into the list: masternodes and quorums
+/// missing in the list are deleted, new and changed ones are sent.
+/// Lists don't keep the order of Dash Core, so the entries go in the order of the maps.
+/// Diff carrying the whole list is made against an empty list of the genesis (or zero hash).
+/// Coinbase transaction is taken as is, it must be the one committed to the list roots
+pub fn encode_list_diff(
+    base_list: &SharedMasternodeList,
+    list: &SharedMasternodeList,
+    merkle_tree: &PartialMerkleTree,
+    coinbase_transaction: &[u8],
+) -> Option<Vec<u8>> {
+    let coinbase = CoinbaseTransaction::from_bytes(coinbase_transaction, &mut 0)?;
+    let mut buffer = Vec::<u8>::new();
+    buffer.extend_from_slice(&base_list.block_hash.0);
+    buffer.extend_from_slice(&list.block_hash.0);
+    merkle_tree.encode_to(&mut buffer);
+    buffer.extend_from_slice(coinbase_transaction);
+    let deleted_masternode_hashes = base_list
+        .masternodes
+        .iter()
+        .filter(|(hash, _)| !list.masternodes.contains_key(hash))
+        .map(|(_, entry)| entry.provider_registration_transaction_hash)
+        .collect::<Vec<UInt256>>();
+    let masternodes = list
+        .masternodes
+        .iter()
+        .filter(|(hash, entry)| {
+            base_list
+                .masternodes
+                .get(hash)
+                .map_or(true, |base_entry| !Arc::ptr_eq(base_entry, entry) && base_entry.to_bytes() != entry.to_bytes())
+        })
+        .map(|(_, entry)| entry.as_ref())
+        .collect::<Vec<&MasternodeEntry>>();
+    let deleted_quorums = base_list
+        .quorums
+        .iter()
+        .flat_map(|(llmq_type, quorums_of_type)| {
+            let quorums = list.quorums.get(llmq_type);
+            let llmq_type: u8 = (*llmq_type).into();
+            quorums_of_type
+                .keys()
+                .filter(move |hash| quorums.map_or(true, |quorums| !quorums.contains_key(hash)))
+                .map(move |hash| (llmq_type, *hash))
+        })
+        .collect::<Vec<(u8, UInt256)>>();
+    let added_quorums = list
+        .quorums
+        .iter()
+        .flat_map(|(llmq_type, quorums_of_type)| {
+            quorums_of_type.iter().filter_map(move |(hash, entry)| {
+                let base_entry = base_list.quorums.get(llmq_type).and_then(|quorums| quorums.get(hash));
+                base_entry
+                    .map_or(true, |base_entry| base_entry.to_bytes() != entry.to_bytes())
+                    .then(|| entry)
+            })
+        })
+        .collect::<Vec<&LLMQEntry>>();
+    encode_list_diff_entries(
+        &deleted_masternode_hashes,
+        masternodes.into_iter(),
+        &deleted_quorums,
+        &added_quorums,
+        coinbase.coinbase_transaction_version >= 2,
+        &mut buffer,
+    );
+    Some(buffer)
+}
+
+/// Quorum snapshot along with the count of its members,
+/// which isn't kept by LLMQSnapshot (only the bytes of the member bitset are)
+#[derive(Clone, Debug)]
+pub struct SnapshotMessage {
+    pub snapshot: LLMQSnapshot,
+    pub member_count: usize,
+}
+
+impl SnapshotMessage {
+    pub fn from_bytes(bytes: &[u8], offset: &mut usize) -> Option<Self> {
+        // skipListMode goes before the member count
+        let count_offset = &mut (*offset + 4);
+        let member_count = read_var_int(bytes, count_offset)?;
+        let snapshot = read_llmq_snapshot(bytes, offset)?;
+        Some(Self { snapshot, member_count })
+    }
+
+    /// Members of the snapshot are the masternodes of the list at its block (the work block of the cycle).
+    /// Count which doesn't fit the bitset (i.e. the list isn't the one the snapshot is made for) is replaced by its size
+    pub fn with_list(snapshot: LLMQSnapshot, list: &SharedMasternodeList) -> Self {
+        let bitset_size = snapshot.member_list.len() * 8;
+        let member_count = Some(list.masternodes.len())
+            .filter(|&count| count <= bitset_size && count + 8 > bitset_size)
+            .unwrap_or(bitset_size);
+        Self { snapshot, member_count }
+    }
+}
+
+/// Quorum snapshot as it comes within QRINFO
+impl BytesEncodable for SnapshotMessage {
+    fn encode_to(&self, buffer: &mut Vec<u8>) {
+        let snapshot = &self.snapshot;
+        let skip_list_mode: u32 = snapshot.skip_list_mode.clone().into();
+        buffer.extend_from_slice(&skip_list_mode.to_le_bytes());
+        write_bitset(self.member_count as u64, &snapshot.member_list, buffer);
+        write_var_int(snapshot.skip_list.len(), buffer);
+        snapshot
+            .skip_list
+            .iter()
+            .for_each(|skipped| buffer.extend_from_slice(&skipped.to_le_bytes()));
+    }
+}
+
+/// Blocks QRINFO is made for (see DIP-0024): the tip, the cycle blocks h, h-c, h-2c, h-3c,
+/// h-4c when extra share is sent, and the blocks of the snapshot and diff lists.
+/// Snapshots are kept under the blocks of the diffs they go with
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct QRInfoBlocks {
+    pub tip: UInt256,
+    pub h: UInt256,
+    pub h_c: UInt256,
+    pub h_2c: UInt256,
+    pub h_3c: UInt256,
+    pub h_4c: Option<UInt256>,
+    pub extra: Vec<UInt256>,
+}
+
+/// QRINFO: snapshots and diffs for the quorum rotation cycles (see DIP-0024).
+/// Snapshot and diff at h-4c come only with extra share.
+/// Older peers don't send the lists at the end, 'has_tail' tells whether they were sent
+pub struct QRInfoMessage {
+    pub snapshot_at_h_c: SnapshotMessage,
+    pub snapshot_at_h_2c: SnapshotMessage,
+    pub snapshot_at_h_3c: SnapshotMessage,
+    pub diff_tip: ListDiffMessage,
+    pub diff_h: ListDiffMessage,
+    pub diff_h_c: ListDiffMessage,
+    pub diff_h_2c: ListDiffMessage,
+    pub diff_h_3c: ListDiffMessage,
+    pub extra_share: Option<(SnapshotMessage, ListDiffMessage)>,
+    pub last_quorum_per_index: Vec<LLMQEntry>,
+    pub quorum_snapshot_list: Vec<SnapshotMessage>,
+    pub mn_list_diff_list: Vec<ListDiffMessage>,
+    pub has_tail: bool,
+}
+
+impl QRInfoMessage {
+    /// Parses the whole QRINFO payload: None if any part of it is broken
+    /// (including the lists at the end) or the payload has bytes after the message
+    pub fn from_bytes<BHL: Fn(UInt256) -> u32>(
+        bytes: &[u8],
+        offset: &mut usize,
+        block_height_lookup: BHL,
+    ) -> Option<Self> {
+        let read_diff = |offset: &mut usize| ListDiffMessage::from_bytes(bytes, offset, |hash| block_height_lookup(hash));
+        let snapshot_at_h_c = SnapshotMessage::from_bytes(bytes, offset)?;
+        let snapshot_at_h_2c = SnapshotMessage::from_bytes(bytes, offset)?;
+        let snapshot_at_h_3c = SnapshotMessage::from_bytes(bytes, offset)?;
+        let diff_tip = read_diff(offset)?;
+        let diff_h = read_diff(offset)?;
+        let diff_h_c = read_diff(offset)?;
+        let diff_h_2c = read_diff(offset)?;
+        let diff_h_3c = read_diff(offset)?;
+        let extra_share = if bytes.read_with::<bool>(offset, ()).ok()? {
+            Some((SnapshotMessage::from_bytes(bytes, offset)?, read_diff(offset)?))
+        } else {
+            None
+        };
+        let mut message = Self {
+            snapshot_at_h_c,
+            snapshot_at_h_2c,
+            snapshot_at_h_3c,
+            diff_tip,
+            diff_h,
+            diff_h_c,
+            diff_h_2c,
+            diff_h_3c,
+            extra_share,
+            last_quorum_per_index: vec![],
+            quorum_snapshot_list: vec![],
+            mn_list_diff_list: vec![],
+            has_tail: false,
+        };
+        // Older peers don't send the lists at the end of the message,
+        // but the ones which are sent must be complete
+        if *offset < bytes.len() {
+            let (last_quorum_per_index, quorum_snapshot_list, mn_list_diff_list) =
+                Self::read_tail(bytes, offset, read_diff)?;
+            message.last_quorum_per_index = last_quorum_per_index;
+            message.quorum_snapshot_list = quorum_snapshot_list;
+            message.mn_list_diff_list = mn_list_diff_list;
+            message.has_tail = true;
+        }
+        // The payload is the message, so nothing may follow it
+        if *offset != bytes.len() {
+            return None;
+        }
+        Some(message)
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn read_tail<RD: Fn(&mut usize) -> Option<ListDiffMessage>>(
+        bytes: &[u8],
+        offset: &mut usize,
+        read_diff: RD,
+    ) -> Option<(Vec<LLMQEntry>, Vec<SnapshotMessage>, Vec<ListDiffMessage>)> {
+        let quorums_count = read_var_int(bytes, offset)?;
+        let last_quorum_per_index = (0..quorums_count)
+            .map(|_| read_llmq_entry(bytes, offset))
+            .collect::<Option<Vec<LLMQEntry>>>()?;
+        let snapshots_count = read_var_int(bytes, offset)?;
+        let quorum_snapshot_list = (0..snapshots_count)
+            .map(|_| SnapshotMessage::from_bytes(bytes, offset))
+            .collect::<Option<Vec<SnapshotMessage>>>()?;
+        let diffs_count = read_var_int(bytes, offset)?;
+        let mn_list_diff_list = (0..diffs_count)
+            .map(|_| read_diff(offset))
+            .collect::<Option<Vec<ListDiffMessage>>>()?;
+        Some((last_quorum_per_index, quorum_snapshot_list, mn_list_diff_list))
+    }
+
+    /// Builds QRINFO from the stored lists and snapshots. Each diff is made against the latest
+    /// of the base lists which isn't above its block (or against the empty list of the genesis),
+    /// the same way Dash Core does it with 'baseBlockHashes' of 'getqrinfo'.
+    /// Lists don't keep coinbase, so it's given by 'coinbase_lookup' along with its merkle proof.
+    /// Snapshots don't keep the count of their members, so it's taken from the list at their block
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_lists<CL: Fn(UInt256) -> Option<(PartialMerkleTree, Vec<u8>)>>(
+        blocks: &QRInfoBlocks,
+        base_block_hashes: &[UInt256],
+        genesis_hash: UInt256,
+        lists: &BTreeMap<UInt256, Arc<SharedMasternodeList>>,
+        snapshots: &BTreeMap<UInt256, LLMQSnapshot>,
+        last_quorum_per_index: Vec<LLMQEntry>,
+        coinbase_lookup: CL,
+    ) -> Option<Self> {
+        let genesis_list = SharedMasternodeList::empty(genesis_hash, 0);
+        let height_lookup = |hash: UInt256| lists.get(&hash).map_or(u32::MAX, |list| list.known_height);
+        let list_diff = |block_hash: UInt256| {
+            let list = lists.get(&block_hash)?;
+            let base = base_block_hashes
+                .iter()
+                .filter_map(|hash| lists.get(hash))
+                .filter(|base| base.known_height <= list.known_height)
+                .max_by_key(|base| base.known_height)
+                .map_or(&genesis_list, |base| base.as_ref());
+            let (merkle_tree, coinbase_transaction) = coinbase_lookup(block_hash)?;
+            let bytes = encode_list_diff(base, list, &merkle_tree, &coinbase_transaction)?;
+            ListDiffMessage::from_bytes(&bytes, &mut 0, height_lookup)
+        };
+        let snapshot = |block_hash: UInt256| {
+            let list = lists.get(&block_hash)?;
+            snapshots
+                .get(&block_hash)
+                .map(|snapshot| SnapshotMessage::with_list(snapshot.clone(), list))
+        };
+        let extra_share = match blocks.h_4c {
+            Some(h_4c) => Some((snapshot(h_4c)?, list_diff(h_4c)?)),
+            None => None,
+        };
+        Some(Self {
+            snapshot_at_h_c: snapshot(blocks.h_c)?,
+            snapshot_at_h_2c: snapshot(blocks.h_2c)?,
+            snapshot_at_h_3c: snapshot(blocks.h_3c)?,
+            diff_tip: list_diff(blocks.tip)?,
+            diff_h: list_diff(blocks.h)?,
+            diff_h_c: list_diff(blocks.h_c)?,
+            diff_h_2c: list_diff(blocks.h_2c)?,
+            diff_h_3c: list_diff(blocks.h_3c)?,
+            extra_share,
+            last_quorum_per_index,
+            quorum_snapshot_list: blocks
+                .extra
+                .iter()
+                .map(|&block_hash| snapshot(block_hash))
+                .collect::<Option<Vec<SnapshotMessage>>>()?,
+            mn_list_diff_list: blocks
+                .extra
+                .iter()
+                .map(|&block_hash| list_diff(block_hash))
+                .collect::<Option<Vec<ListDiffMessage>>>()?,
+            has_tail: true,
+        })
+    }
+
+    /// All the diffs in the order they're processed: from the oldest cycle to the tip
+    pub fn diffs(&self) -> Vec<&ListDiffMessage> {
+        let mut diffs = Vec::<&ListDiffMessage>::new();
+        if let Some((_, diff_h_4c)) = &self.extra_share {
+            diffs.push(diff_h_4c);
+        }
+        diffs.extend([&self.diff_h_3c, &self.diff_h_2c, &self.diff_h_c, &self.diff_h, &self.diff_tip]);
+        diffs.extend(self.mn_list_diff_list.iter());
+        diffs
+    }
+}
+
+/// QRINFO payload as it's sent by Dash Core, the lists at the end are written if the message has them
+impl BytesEncodable for QRInfoMessage {
+    fn encode_to(&self, buffer: &mut Vec<u8>) {
+        self.snapshot_at_h_c.encode_to(buffer);
+        self.snapshot_at_h_2c.encode_to(buffer);
+        self.snapshot_at_h_3c.encode_to(buffer);
+        self.diff_tip.encode_to(buffer);
+        self.diff_h.encode_to(buffer);
+        self.diff_h_c.encode_to(buffer);
+        self.diff_h_2c.encode_to(buffer);
+        self.diff_h_3c.encode_to(buffer);
+        buffer.push(u8::from(self.extra_share.is_some()));
+        if let Some((snapshot, diff)) = &self.extra_share {
+            snapshot.encode_to(buffer);
+            diff.encode_to(buffer);
+        }
+        if !self.has_tail {
+            return;
+        }
+        write_var_int(self.last_quorum_per_index.len(), buffer);
+        self.last_quorum_per_index
+            .iter()
+            .for_each(|entry| entry.encode_to(buffer));
+        write_var_int(self.quorum_snapshot_list.len(), buffer);
+        self.quorum_snapshot_list
+            .iter()
+            .for_each(|snapshot| snapshot.encode_to(buffer));
+        write_var_int(self.mn_list_diff_list.len(), buffer);
+        self.mn_list_diff_list
+            .iter()
+            .for_each(|diff| diff.encode_to(buffer));
+    }
+}
@@ -0,0 +1,107 @@
+pub mod messages;
+pub mod network;
+pub mod stored;
+
+pub use self::messages::{encode_list_diff, ListDiffMessage, ListDiffOrder, PartialMerkleTree, QRInfoBlocks, QRInfoMessage, SnapshotMessage};
+pub use self::network::{encode_network_message, network_magic, GetMNListDiff, GetQRInfo, MasternodeMessage, MessageError, MessageHeader, NetworkMessage};
+pub use self::stored::{read_stored_masternode_entries, read_stored_masternode_list, read_stored_snapshot, write_stored_masternode_entries, write_stored_masternode_list, write_stored_snapshot};
+
+use byte::ctx::Bytes;
+use byte::{BytesExt, BE, LE};
+use dash_spv_models::common::SocketAddress;
+use dash_spv_models::llmq::LLMQSnapshot;
+use dash_spv_models::masternode::{LLMQEntry, MasternodeEntry};
+use dash_spv_primitives::consensus::encode::VarInt;
+use dash_spv_primitives::consensus::Encodable;
+use dash_spv_primitives::crypto::byte_util::BytesDecodable;
+use dash_spv_primitives::crypto::{UInt128, UInt160, UInt256, UInt384};
+
+/// Serialization of the models into the byte format used by Dash Core,
+/// so the same bytes can be read back with the decoders of dash-spv-models
+pub trait BytesEncodable {
+    fn encode_to(&self, buffer: &mut Vec<u8>);
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut buffer = Vec::<u8>::new();
+        self.encode_to(&mut buffer);
+        buffer
+    }
+}
+
+pub fn write_var_int(count: usize, buffer: &mut Vec<u8>) {
+    VarInt(count as u64).consensus_encode(buffer).unwrap();
+}
+
+/// Bitsets are prefixed with the count of bits, not bytes
+pub(crate) fn write_bitset(count: u64, bitset: &[u8], buffer: &mut Vec<u8>) {
+    VarInt(count).consensus_encode(buffer).unwrap();
+    buffer.extend_from_slice(bitset);
+}
+
+/// Simplified masternode list entry (151 bytes):
+/// proRegTxHash, confirmedHash, service (ipv6 + port in network byte order),
+/// pubKeyOperator, keyIDVoting and isValid
+impl BytesEncodable for MasternodeEntry {
+    fn encode_to(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(&self.provider_registration_transaction_hash.0);
+        buffer.extend_from_slice(&self.confirmed_hash.0);
+        buffer.extend_from_slice(&self.socket_address.ip_address.0);
+        buffer.extend_from_slice(&self.socket_address.port.to_be_bytes());
+        buffer.extend_from_slice(&self.operator_public_key.0);
+        buffer.extend_from_slice(&self.key_id_voting.0);
+        buffer.push(u8::from(self.is_valid));
+    }
+}
+
+/// Final commitment as it comes within 'newQuorums' of MNLISTDIFF
+impl BytesEncodable for LLMQEntry {
+    fn encode_to(&self, buffer: &mut Vec<u8>) {
+        let llmq_type: u8 = self.llmq_type.into();
+        buffer.extend_from_slice(&self.version.to_le_bytes());
+        buffer.push(llmq_type);
+        buffer.extend_from_slice(&self.llmq_hash.0);
+        if let Some(index) = self.index {
+            buffer.extend_from_slice(&index.to_le_bytes());
+        }
+        write_bitset(self.signers_count.0, &self.signers_bitset, buffer);
+        write_bitset(self.valid_members_count.0, &self.valid_members_bitset, buffer);
+        buffer.extend_from_slice(&self.public_key.0);
+        buffer.extend_from_slice(&self.verification_vector_hash.0);
+        buffer.extend_from_slice(&self.threshold_signature.0);
+        buffer.extend_from_slice(&self.all_commitment_aggregated_signature.0);
+    }
+}
+
+pub fn read_var_int(bytes: &[u8], offset: &mut usize) -> Option<usize> {
+    VarInt::from_bytes(bytes, offset).map(|count| count.0 as usize)
+}
+
+pub fn read_masternode_entry(bytes: &[u8], offset: &mut usize) -> Option<MasternodeEntry> {
+    let provider_registration_transaction_hash = UInt256::from_bytes(bytes, offset)?;
+    let confirmed_hash = UInt256::from_bytes(bytes, offset)?;
+    let ip_address = UInt128::from_bytes(bytes, offset)?;
+    let port = bytes.read_with::<u16>(offset, BE).ok()?;
+    let operator_public_key = UInt384::from_bytes(bytes, offset)?;
+    let key_id_voting = UInt160::from_bytes(bytes, offset)?;
+    let is_valid = bytes.read_with::<u8>(offset, LE).ok()?;
+    Some(MasternodeEntry::new(
+        provider_registration_transaction_hash,
+        confirmed_hash,
+        SocketAddress { ip_address, port },
+        key_id_voting,
+        operator_public_key,
+        is_valid,
+    ))
+}
+
+pub fn read_llmq_entry(bytes: &[u8], offset: &mut usize) -> Option<LLMQEntry> {
+    LLMQEntry::from_bytes(bytes, offset)
+}
+
+pub fn read_llmq_snapshot(bytes: &[u8], offset: &mut usize) -> Option<LLMQSnapshot> {
+    LLMQSnapshot::from_bytes(bytes, offset)
+}
+
+pub fn read_bytes<'a>(bytes: &'a [u8], offset: &mut usize, len: usize) -> Option<&'a [u8]> {
+    bytes.read_with::<&[u8]>(offset, Bytes::Len(len)).ok()
+}
@@ -0,0 +1,242 @@
+use crate::codec::{read_bytes, read_var_int, write_var_int, BytesEncodable, ListDiffMessage, QRInfoMessage};
+use byte::{BytesExt, LE};
+use dash_spv_models::common::ChainType;
+use dash_spv_primitives::crypto::byte_util::BytesDecodable;
+use dash_spv_primitives::crypto::UInt256;
+use dash_spv_primitives::hashes::{sha256d, Hash};
+use std::fmt;
+
+/// Network magic as it goes at the start of the message (little-endian)
+pub const MAINNET_MAGIC: u32 = 0xbd6b0cbf;
+pub const TESTNET_MAGIC: u32 = 0xffcae2ce;
+pub const DEVNET_MAGIC: u32 = 0xceffcae2;
+pub const REGTEST_MAGIC: u32 = 0xdcb7c1fc;
+
+pub const MNLISTDIFF_COMMAND: &str = "mnlistdiff";
+pub const QRINFO_COMMAND: &str = "qrinfo";
+/// Commands are limited to 12 bytes, so Dash Core names the request this way
+pub const GETMNLISTDIFF_COMMAND: &str = "getmnlistd";
+pub const GETQRINFO_COMMAND: &str = "getqrinfo";
+
+/// magic (4), command (12), payload length (4), checksum (4)
+pub const MESSAGE_HEADER_SIZE: usize = 24;
+const COMMAND_SIZE: usize = 12;
+/// Same limit as MAX_SIZE of Dash Core
+pub const MAX_PAYLOAD_SIZE: u32 = 0x0200_0000;
+
+pub fn network_magic(chain: &ChainType) -> u32 {
+    match chain {
+        ChainType::MainNet => MAINNET_MAGIC,
+        ChainType::TestNet => TESTNET_MAGIC,
+        ChainType::DevNet(_) => DEVNET_MAGIC,
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MessageError {
+    /// Message is of another network
+    WrongMagic(u32),
+    /// Command isn't null-padded ASCII
+    InvalidCommand,
+    PayloadTooLarge(u32),
+    ChecksumMismatch,
+    /// Payload can't be read as the message of its command
+    InvalidPayload(String),
+}
+
+impl fmt::Display for MessageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MessageError::WrongMagic(magic) => write!(f, "wrong network magic {:#010x}", magic),
+            MessageError::InvalidCommand => write!(f, "invalid command"),
+            MessageError::PayloadTooLarge(length) => write!(f, "payload of {} bytes is too large", length),
+            MessageError::ChecksumMismatch => write!(f, "payload checksum mismatch"),
+            MessageError::InvalidPayload(command) => write!(f, "invalid '{}' payload", command),
+        }
+    }
+}
+
+impl std::error::Error for MessageError {}
+
+/// First 4 bytes of double SHA-256 of the payload
+pub fn payload_checksum(payload: &[u8]) -> [u8; 4] {
+    let hash = sha256d::Hash::hash(payload).into_inner();
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MessageHeader {
+    pub magic: u32,
+    pub command: String,
+    pub payload_length: u32,
+    pub checksum: [u8; 4],
+}
+
+impl MessageHeader {
+    /// None if there are not enough bytes yet
+    pub fn from_bytes(bytes: &[u8], offset: &mut usize) -> Option<Result<Self, MessageError>> {
+        let magic = bytes.read_with::<u32>(offset, LE).ok()?;
+        let command = read_bytes(bytes, offset, COMMAND_SIZE)?;
+        let payload_length = bytes.read_with::<u32>(offset, LE).ok()?;
+        let checksum = read_bytes(bytes, offset, 4)?;
+        let command_length = command.iter().position(|&byte| byte == 0).unwrap_or(COMMAND_SIZE);
+        let (name, padding) = command.split_at(command_length);
+        if !name.iter().all(|byte| byte.is_ascii_graphic()) || padding.iter().any(|&byte| byte != 0) {
+            return Some(Err(MessageError::InvalidCommand));
+        }
+        Some(Ok(Self {
+            magic,
+            command: String::from_utf8_lossy(name).into_owned(),
+            payload_length,
+            checksum: [checksum[0], checksum[1], checksum[2], checksum[3]],
+        }))
+    }
+}
+
+impl BytesEncodable for MessageHeader {
+    fn encode_to(&self, buffer: &mut Vec<u8>) {
+        let mut command = [0u8; COMMAND_SIZE];
+        let name = self.command.as_bytes();
+        let length = name.len().min(COMMAND_SIZE);
+        command[..length].copy_from_slice(&name[..length]);
+        buffer.extend_from_slice(&self.magic.to_le_bytes());
+        buffer.extend_from_slice(&command);
+        buffer.extend_from_slice(&self.payload_length.to_le_bytes());
+        buffer.extend_from_slice(&self.checksum);
+    }
+}
+
+/// P2P message with the verified envelope
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NetworkMessage<'a> {
+    pub command: String,
+    pub payload: &'a [u8],
+}
+
+/// Payloads the processor handles
+pub enum MasternodeMessage {
+    ListDiff(ListDiffMessage),
+    QRInfo(QRInfoMessage),
+}
+
+impl<'a> NetworkMessage<'a> {
+    /// Reads the message from the start of the stream, so the bytes may go beyond it.
+    /// Returns None while the message isn't complete, otherwise the message with its full size
+    pub fn from_bytes(bytes: &'a [u8], magic: u32) -> Result<Option<(Self, usize)>, MessageError> {
+        let offset = &mut 0;
+        let header = match MessageHeader::from_bytes(bytes, offset) {
+            Some(header) => header?,
+            None => return Ok(None),
+        };
+        if header.magic != magic {
+            return Err(MessageError::WrongMagic(header.magic));
+        }
+        if header.payload_length > MAX_PAYLOAD_SIZE {
+            return Err(MessageError::PayloadTooLarge(header.payload_length));
+        }
+        let payload = match read_bytes(bytes, offset, header.payload_length as usize) {
+            Some(payload) => payload,
+            None => return Ok(None),
+        };
+        if payload_checksum(payload) != header.checksum {
+            return Err(MessageError::ChecksumMismatch);
+        }
+        Ok(Some((Self { command: header.command, payload }, *offset)))
+    }
+
+    /// 'mnlistdiff' or 'qrinfo' payload, None for other commands
+    pub fn masternode_message<BHL: Fn(UInt256) -> u32>(
+        &self,
+        block_height_lookup: BHL,
+    ) -> Result<Option<MasternodeMessage>, MessageError> {
+        let invalid_payload = || MessageError::InvalidPayload(self.command.clone());
+        match self.command.as_str() {
+            MNLISTDIFF_COMMAND => ListDiffMessage::from_bytes(self.payload, &mut 0, block_height_lookup)
+                .map(|message| Some(MasternodeMessage::ListDiff(message)))
+                .ok_or_else(invalid_payload),
+            QRINFO_COMMAND => QRInfoMessage::from_bytes(self.payload, &mut 0, block_height_lookup)
+                .map(|message| Some(MasternodeMessage::QRInfo(message)))
+                .ok_or_else(invalid_payload),
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Message with the envelope for the network given by magic
+pub fn encode_network_message(magic: u32, command: &str, payload: &[u8]) -> Vec<u8> {
+    let mut buffer = Vec::<u8>::with_capacity(MESSAGE_HEADER_SIZE + payload.len());
+    MessageHeader {
+        magic,
+        command: command.to_string(),
+        payload_length: payload.len() as u32,
+        checksum: payload_checksum(payload),
+    }
+    .encode_to(&mut buffer);
+    buffer.extend_from_slice(payload);
+    buffer
+}
+
+/// Request for the diff between the lists at two blocks
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GetMNListDiff {
+    pub base_block_hash: UInt256,
+    pub block_hash: UInt256,
+}
+
+impl BytesEncodable for GetMNListDiff {
+    fn encode_to(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(&self.base_block_hash.0);
+        buffer.extend_from_slice(&self.block_hash.0);
+    }
+}
+
+impl GetMNListDiff {
+    pub fn from_bytes(bytes: &[u8], offset: &mut usize) -> Option<Self> {
+        Some(Self {
+            base_block_hash: UInt256::from_bytes(bytes, offset)?,
+            block_hash: UInt256::from_bytes(bytes, offset)?,
+        })
+    }
+
+    pub fn to_network_message(&self, magic: u32) -> Vec<u8> {
+        encode_network_message(magic, GETMNLISTDIFF_COMMAND, &self.to_bytes())
+    }
+}
+
+/// Request for the rotation info at the block (see DIP-0024).
+/// Base block hashes are the blocks of the lists the client already has
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GetQRInfo {
+    pub base_block_hashes: Vec<UInt256>,
+    pub block_request_hash: UInt256,
+    pub extra_share: bool,
+}
+
+impl BytesEncodable for GetQRInfo {
+    fn encode_to(&self, buffer: &mut Vec<u8>) {
+        write_var_int(self.base_block_hashes.len(), buffer);
+        self.base_block_hashes
+            .iter()
+            .for_each(|hash| buffer.extend_from_slice(&hash.0));
+        buffer.extend_from_slice(&self.block_request_hash.0);
+        buffer.push(u8::from(self.extra_share));
+    }
+}
+
+impl GetQRInfo {
+    pub fn from_bytes(bytes: &[u8], offset: &mut usize) -> Option<Self> {
+        let count = read_var_int(bytes, offset)?;
+        let base_block_hashes = (0..count)
+            .map(|_| UInt256::from_bytes(bytes, offset))
+            .collect::<Option<Vec<UInt256>>>()?;
+        Some(Self {
+            base_block_hashes,
+            block_request_hash: UInt256::from_bytes(bytes, offset)?,
+            extra_share: bytes.read_with::<bool>(offset, ()).ok()?,
+        })
+    }
+
+    pub fn to_network_message(&self, magic: u32) -> Vec<u8> {
+        encode_network_message(magic, GETQRINFO_COMMAND, &self.to_bytes())
+    }
+}
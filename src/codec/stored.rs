@@ -0,0 +1,166 @@
+//! Form of the models kept by the stores. Unlike the messages, it keeps the whole state
+//! the processing relies on: history of the masternode entries and whether quorums are verified
+
+use crate::codec::{read_bytes, read_llmq_entry, read_masternode_entry, read_var_int, write_var_int, BytesEncodable};
+use crate::processing::SharedMasternodeList;
+use byte::{BytesExt, LE};
+use dash_spv_models::common::{Block, LLMQSnapshotSkipMode, LLMQType};
+use dash_spv_models::llmq::LLMQSnapshot;
+use dash_spv_models::masternode::{LLMQEntry, MasternodeEntry, MasternodeList};
+use dash_spv_primitives::crypto::byte_util::{BytesDecodable, Reversable};
+use dash_spv_primitives::crypto::{UInt256, UInt384};
+use std::collections::BTreeMap;
+
+fn write_block(block: &Block, buffer: &mut Vec<u8>) {
+    buffer.extend_from_slice(&block.height.to_le_bytes());
+    buffer.extend_from_slice(&block.hash.0);
+}
+
+fn read_block(bytes: &[u8], offset: &mut usize) -> Option<Block> {
+    let height = bytes.read_with::<u32>(offset, LE).ok()?;
+    let hash = UInt256::from_bytes(bytes, offset)?;
+    Some(Block { height, hash })
+}
+
+fn write_history<T, W: Fn(&T, &mut Vec<u8>)>(history: &BTreeMap<Block, T>, write: W, buffer: &mut Vec<u8>) {
+    write_var_int(history.len(), buffer);
+    history.iter().for_each(|(block, value)| {
+        write_block(block, buffer);
+        write(value, buffer);
+    });
+}
+
+fn read_history<T, R: Fn(&[u8], &mut usize) -> Option<T>>(bytes: &[u8], offset: &mut usize, read: R) -> Option<BTreeMap<Block, T>> {
+    let count = read_var_int(bytes, offset)?;
+    (0..count)
+        .map(|_| Some((read_block(bytes, offset)?, read(bytes, offset)?)))
+        .collect()
+}
+
+fn write_optional_height(height: Option<u32>, buffer: &mut Vec<u8>) {
+    match height {
+        Some(height) => {
+            buffer.push(1);
+            buffer.extend_from_slice(&height.to_le_bytes());
+        }
+        None => buffer.push(0),
+    }
+}
+
+fn read_optional_height(bytes: &[u8], offset: &mut usize) -> Option<Option<u32>> {
+    match read_bytes(bytes, offset, 1)?[0] {
+        0 => Some(None),
+        _ => bytes.read_with::<u32>(offset, LE).ok().map(Some),
+    }
+}
+
+/// Entry as it comes with MNLISTDIFF followed by update height, height the entry is known
+/// to be confirmed at and its history: operator keys, entry hashes and validity by block
+pub fn write_stored_masternode_entry(entry: &MasternodeEntry, buffer: &mut Vec<u8>) {
+    entry.encode_to(buffer);
+    buffer.extend_from_slice(&entry.update_height.to_le_bytes());
+    write_optional_height(entry.known_confirmed_at_height, buffer);
+    write_history(&entry.previous_operator_public_keys, |key, buffer| buffer.extend_from_slice(&key.0), buffer);
+    write_history(&entry.previous_entry_hashes, |hash, buffer| buffer.extend_from_slice(&hash.0), buffer);
+    write_history(&entry.previous_validity, |is_valid, buffer| buffer.push(u8::from(*is_valid)), buffer);
+}
+
+pub fn read_stored_masternode_entry(bytes: &[u8], offset: &mut usize) -> Option<MasternodeEntry> {
+    let mut entry = read_masternode_entry(bytes, offset)?;
+    entry.update_height = bytes.read_with::<u32>(offset, LE).ok()?;
+    entry.known_confirmed_at_height = read_optional_height(bytes, offset)?;
+    entry.previous_operator_public_keys = read_history(bytes, offset, |bytes, offset| UInt384::from_bytes(bytes, offset))?;
+    entry.previous_entry_hashes = read_history(bytes, offset, |bytes, offset| UInt256::from_bytes(bytes, offset))?;
+    entry.previous_validity = read_history(bytes, offset, |bytes, offset| read_bytes(bytes, offset, 1).map(|is_valid| is_valid[0] != 0))?;
+    Some(entry)
+}
+
+pub fn write_stored_masternode_entries(entries: &[MasternodeEntry], buffer: &mut Vec<u8>) {
+    write_var_int(entries.len(), buffer);
+    entries.iter().for_each(|entry| write_stored_masternode_entry(entry, buffer));
+}
+
+pub fn read_stored_masternode_entries(bytes: &[u8], offset: &mut usize) -> Option<Vec<MasternodeEntry>> {
+    let count = read_var_int(bytes, offset)?;
+    (0..count)
+        .map(|_| read_stored_masternode_entry(bytes, offset))
+        .collect()
+}
+
+/// Commitment followed by whether its signatures are verified
+pub fn write_stored_llmq_entry(entry: &LLMQEntry, buffer: &mut Vec<u8>) {
+    entry.encode_to(buffer);
+    buffer.push(u8::from(entry.verified));
+}
+
+pub fn read_stored_llmq_entry(bytes: &[u8], offset: &mut usize) -> Option<LLMQEntry> {
+    let mut entry = read_llmq_entry(bytes, offset)?;
+    entry.verified = read_bytes(bytes, offset, 1)?[0] != 0;
+    Some(entry)
+}
+
+/// Snapshot doesn't keep the count of its members, so the member list is prefixed with the count of bytes
+pub fn write_stored_snapshot(snapshot: &LLMQSnapshot, buffer: &mut Vec<u8>) {
+    let skip_list_mode: u32 = snapshot.skip_list_mode.clone().into();
+    buffer.extend_from_slice(&skip_list_mode.to_le_bytes());
+    write_var_int(snapshot.member_list.len(), buffer);
+    buffer.extend_from_slice(&snapshot.member_list);
+    write_var_int(snapshot.skip_list.len(), buffer);
+    snapshot
+        .skip_list
+        .iter()
+        .for_each(|skipped| buffer.extend_from_slice(&skipped.to_le_bytes()));
+}
+
+pub fn read_stored_snapshot(bytes: &[u8], offset: &mut usize) -> Option<LLMQSnapshot> {
+    let skip_list_mode = LLMQSnapshotSkipMode::from(bytes.read_with::<u32>(offset, LE).ok()?);
+    let member_list_len = read_var_int(bytes, offset)?;
+    let member_list = read_bytes(bytes, offset, member_list_len)?.to_vec();
+    let skip_list_len = read_var_int(bytes, offset)?;
+    let skip_list = (0..skip_list_len)
+        .map(|_| bytes.read_with::<i32>(offset, LE).ok())
+        .collect::<Option<Vec<i32>>>()?;
+    Some(LLMQSnapshot::new(member_list, skip_list, skip_list_mode))
+}
+
+/// Block hash, height, whether quorums are active, masternode entries and quorum commitments.
+/// Roots aren't kept, they're computed from the entries when the list is read
+pub fn write_stored_masternode_list(list: &SharedMasternodeList, buffer: &mut Vec<u8>) {
+    buffer.extend_from_slice(&list.block_hash.0);
+    buffer.extend_from_slice(&list.known_height.to_le_bytes());
+    buffer.push(u8::from(list.llmq_merkle_root.is_some()));
+    write_var_int(list.masternodes.len(), buffer);
+    list.masternode_entries()
+        .for_each(|entry| write_stored_masternode_entry(entry, buffer));
+    write_var_int(list.quorums_count(), buffer);
+    list.quorums
+        .values()
+        .flat_map(|quorums_of_type| quorums_of_type.values())
+        .for_each(|entry| write_stored_llmq_entry(entry, buffer));
+}
+
+pub fn read_stored_masternode_list(bytes: &[u8], offset: &mut usize) -> Option<SharedMasternodeList> {
+    let block_hash = UInt256::from_bytes(bytes, offset)?;
+    let known_height = bytes.read_with::<u32>(offset, LE).ok()?;
+    let quorums_active = read_bytes(bytes, offset, 1)?[0] != 0;
+    let masternodes = read_stored_masternode_entries(bytes, offset)?
+        .into_iter()
+        .map(|entry| (entry.provider_registration_transaction_hash.clone().reversed(), entry))
+        .collect::<BTreeMap<UInt256, MasternodeEntry>>();
+    let quorums_count = read_var_int(bytes, offset)?;
+    let mut quorums = BTreeMap::<LLMQType, BTreeMap<UInt256, LLMQEntry>>::new();
+    for _i in 0..quorums_count {
+        let entry = read_stored_llmq_entry(bytes, offset)?;
+        quorums
+            .entry(entry.llmq_type)
+            .or_default()
+            .insert(entry.llmq_hash, entry);
+    }
+    Some(SharedMasternodeList::from_list(&MasternodeList::new(
+        masternodes,
+        quorums,
+        block_hash,
+        known_height,
+        quorums_active,
+    )))
+}
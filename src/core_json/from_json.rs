@@ -0,0 +1,171 @@
+use crate::core_json::{DeletedQuorum, ListDiff, Node, Snapshot, LLMQ};
+use byte::ctx::Bytes;
+use byte::BytesExt;
+use dash_spv_models::common::{LLMQSnapshotSkipMode, LLMQType, SocketAddress};
+use dash_spv_models::llmq::{LLMQSnapshot, MNListDiff};
+use dash_spv_models::masternode::{LLMQEntry, MasternodeEntry, MasternodeList};
+use dash_spv_models::tx::CoinbaseTransaction;
+use dash_spv_primitives::consensus::encode::VarInt;
+use dash_spv_primitives::crypto::byte_util::{BytesDecodable, Reversable};
+use dash_spv_primitives::crypto::var_array::VarArray;
+use dash_spv_primitives::crypto::{UInt128, UInt160, UInt256, UInt384, UInt768};
+use dash_spv_primitives::hashes::hex::FromHex;
+use dash_spv_primitives::util::base58;
+use std::collections::BTreeMap;
+use std::net::{IpAddr, SocketAddr};
+
+/// Core shows uint256 reversed, so it's turned back into the byte order of the p2p messages
+pub fn hash_from_json(hex: &str) -> Option<UInt256> {
+    UInt256::from_hex(hex).ok().map(|hash| hash.clone().reversed())
+}
+
+/// Packs 'activeQuorumMembers' into the bitset the same way as it comes with QRINFO
+pub fn bools_to_bytes(bools: &[bool]) -> Vec<u8> {
+    let mut bytes = vec![0u8; (bools.len() + 7) / 8];
+    bools.iter().enumerate().for_each(|(index, &bit)| {
+        bytes[index / 8] |= (bit as u8) << (7 - index % 8);
+    });
+    bytes
+}
+
+pub fn snapshot_from_json(snapshot: &Snapshot) -> LLMQSnapshot {
+    LLMQSnapshot::new(
+        bools_to_bytes(&snapshot.active_quorum_members),
+        snapshot.mn_skip_list.clone(),
+        LLMQSnapshotSkipMode::from(snapshot.mn_skip_list_mode as u32),
+    )
+}
+
+/// "ip:port" where ipv4 is mapped into ipv6 as it's done within the masternode entry
+pub fn socket_address_from_json(service: &str) -> Option<SocketAddress> {
+    let address = service.parse::<SocketAddr>().ok()?;
+    let octets = match address.ip() {
+        IpAddr::V4(ip) => ip.to_ipv6_mapped().octets(),
+        IpAddr::V6(ip) => ip.octets(),
+    };
+    Some(SocketAddress { ip_address: UInt128(octets), port: address.port() })
+}
+
+/// Voting key id from base58check address (version byte + 20 bytes)
+pub fn key_id_from_address(address: &str) -> Option<UInt160> {
+    let bytes = base58::from_check(address).ok()?;
+    if bytes.len() != 21 {
+        return None;
+    }
+    UInt160::from_bytes(&bytes, &mut 1)
+}
+
+pub fn masternode_from_json(node: &Node) -> Option<MasternodeEntry> {
+    Some(MasternodeEntry::new(
+        hash_from_json(&node.pro_reg_tx_hash)?,
+        hash_from_json(&node.confirmed_hash)?,
+        socket_address_from_json(&node.service)?,
+        key_id_from_address(&node.voting_address)?,
+        UInt384::from_hex(&node.pub_key_operator).ok()?,
+        u8::from(node.is_valid),
+    ))
+}
+
+/// Keyed the same way as the masternodes of MasternodeList
+pub fn masternodes_from_json(nodes: &[Node]) -> Option<BTreeMap<UInt256, MasternodeEntry>> {
+    nodes
+        .iter()
+        .map(|node| {
+            masternode_from_json(node).map(|entry| {
+                (entry.provider_registration_transaction_hash.clone().reversed(), entry)
+            })
+        })
+        .collect()
+}
+
+pub fn quorum_from_json(llmq: &LLMQ) -> Option<LLMQEntry> {
+    Some(LLMQEntry::new(
+        llmq.version as u16,
+        LLMQType::from(llmq.llmq_type as u8),
+        hash_from_json(&llmq.quorum_hash)?,
+        llmq.quorum_index.map(|index| index as u16),
+        VarInt(llmq.signers_count as u64),
+        VarInt(llmq.valid_members_count as u64),
+        Vec::from_hex(&llmq.signers).ok()?,
+        Vec::from_hex(&llmq.valid_members).ok()?,
+        UInt384::from_hex(&llmq.quorum_public_key).ok()?,
+        hash_from_json(&llmq.quorum_vvec_hash)?,
+        UInt768::from_hex(&llmq.quorum_sig).ok()?,
+        UInt768::from_hex(&llmq.members_sig).ok()?,
+    ))
+}
+
+pub fn quorums_from_json(llmqs: &[LLMQ]) -> Option<BTreeMap<LLMQType, BTreeMap<UInt256, LLMQEntry>>> {
+    let mut quorums = BTreeMap::<LLMQType, BTreeMap<UInt256, LLMQEntry>>::new();
+    for llmq in llmqs {
+        let entry = quorum_from_json(llmq)?;
+        quorums
+            .entry(entry.llmq_type)
+            .or_default()
+            .insert(entry.llmq_hash, entry);
+    }
+    Some(quorums)
+}
+
+pub fn deleted_quorums_from_json(deleted: &[DeletedQuorum]) -> Option<BTreeMap<LLMQType, Vec<UInt256>>> {
+    let mut quorums = BTreeMap::<LLMQType, Vec<UInt256>>::new();
+    for quorum in deleted {
+        quorums
+            .entry(LLMQType::from(quorum.llmq_type as u8))
+            .or_default()
+            .push(hash_from_json(&quorum.quorum_hash)?);
+    }
+    Some(quorums)
+}
+
+/// Same content as MNLISTDIFF message, so it can go through the regular diff processing
+pub fn list_diff_from_json<BHL: Fn(UInt256) -> u32>(diff: &ListDiff, block_height_lookup: BHL) -> Option<MNListDiff> {
+    let block_hash = hash_from_json(&diff.block_hash)?;
+    let cb_tx_bytes = Vec::from_hex(&diff.cb_tx).ok()?;
+    let tree_bytes = Vec::from_hex(&diff.cb_tx_merkle_tree).ok()?;
+    let tree_bytes = tree_bytes.as_slice();
+    let offset = &mut 0;
+    let total_transactions = u32::from_bytes(tree_bytes, offset)?;
+    let merkle_hashes = VarArray::<UInt256>::from_bytes(tree_bytes, offset)?;
+    let merkle_flags_count = VarInt::from_bytes(tree_bytes, offset)?.0 as usize;
+    let merkle_flags: &[u8] = tree_bytes.read_with(offset, Bytes::Len(merkle_flags_count)).ok()?;
+    Some(MNListDiff {
+        base_block_hash: hash_from_json(&diff.base_block_hash)?,
+        block_hash,
+        total_transactions,
+        merkle_hashes,
+        merkle_flags: merkle_flags.to_vec(),
+        coinbase_transaction: CoinbaseTransaction::from_bytes(&cb_tx_bytes, &mut 0)?,
+        deleted_masternode_hashes: diff
+            .deleted_mns
+            .iter()
+            .map(|hash| hash_from_json(hash))
+            .collect::<Option<Vec<UInt256>>>()?,
+        added_or_modified_masternodes: masternodes_from_json(&diff.mn_list)?,
+        deleted_quorums: deleted_quorums_from_json(&diff.deleted_quorums)?,
+        added_quorums: quorums_from_json(&diff.new_quorums)?,
+        block_height: block_height_lookup(block_hash),
+    })
+}
+
+/// Full list from 'protx diff' against the genesis (or a zero hash) at the given height.
+/// Roots are recomputed, so they could be checked with 'merkleRootMNList' and 'merkleRootQuorums'
+pub fn masternode_list_from_json(diff: &ListDiff, height: u32) -> Option<MasternodeList> {
+    if !diff.deleted_mns.is_empty() || !diff.deleted_quorums.is_empty() {
+        // That's not a full list
+        return None;
+    }
+    Some(MasternodeList::new(
+        masternodes_from_json(&diff.mn_list)?,
+        quorums_from_json(&diff.new_quorums)?,
+        hash_from_json(&diff.block_hash)?,
+        height,
+        !diff.merkle_root_quorums.is_empty(),
+    ))
+}
+
+pub fn masternode_list_from_json_str(json: &str, height: u32) -> Option<MasternodeList> {
+    serde_json::from_str::<ListDiff>(json)
+        .ok()
+        .and_then(|diff| masternode_list_from_json(&diff, height))
+}
@@ -0,0 +1,18 @@
+//! Dash Core RPC JSON ('protx diff', 'quorum rotationinfo') import and export,
+//! available with 'core-json' feature.
+//! Lets the processor be seeded from a trusted local node dump and its outputs be compared with Core
+pub mod from_json;
+pub mod models;
+pub mod to_json;
+
+pub use self::from_json::{
+    bools_to_bytes, hash_from_json, list_diff_from_json, masternode_list_from_json,
+    masternode_list_from_json_str, masternodes_from_json, quorum_from_json, quorums_from_json,
+    snapshot_from_json, socket_address_from_json,
+};
+pub use self::models::{DeletedQuorum, ListDiff, Node, QRInfo, Snapshot, LLMQ};
+pub use self::to_json::{
+    bytes_to_bools, hash_to_json, list_diff_to_json, masternode_list_to_json,
+    masternode_list_to_json_string, masternode_to_json, qr_info_to_json, quorum_to_json,
+    snapshot_to_json, socket_address_to_json,
+};
@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+
+/// Result of 'quorum rotationinfo'
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct QRInfo {
+    #[serde(rename = "extraShare")]
+    pub extra_share: bool,
+    #[serde(rename = "quorumSnapshotAtHMinusC")]
+    pub quorum_snapshot_at_hminus_c: Snapshot,
+    #[serde(rename = "quorumSnapshotAtHMinus2C")]
+    pub quorum_snapshot_at_hminus2c: Snapshot,
+    #[serde(rename = "quorumSnapshotAtHMinus3C")]
+    pub quorum_snapshot_at_hminus3c: Snapshot,
+    #[serde(rename = "mnListDiffTip")]
+    pub mn_list_diff_tip: ListDiff,
+    #[serde(rename = "mnListDiffH")]
+    pub mn_list_diff_h: ListDiff,
+    #[serde(rename = "mnListDiffAtHMinusC")]
+    pub mn_list_diff_at_hminus_c: ListDiff,
+    #[serde(rename = "mnListDiffAtHMinus2C")]
+    pub mn_list_diff_at_hminus2c: ListDiff,
+    #[serde(rename = "mnListDiffAtHMinus3C")]
+    pub mn_list_diff_at_hminus3c: ListDiff,
+    /// Only with extra share
+    #[serde(rename = "quorumSnapshotAtHMinus4C", default, skip_serializing_if = "Option::is_none")]
+    pub quorum_snapshot_at_hminus4c: Option<Snapshot>,
+    #[serde(rename = "mnListDiffAtHMinus4C", default, skip_serializing_if = "Option::is_none")]
+    pub mn_list_diff_at_hminus4c: Option<ListDiff>,
+    #[serde(rename = "lastCommitmentPerIndex")]
+    pub last_commitment_per_index: Vec<LLMQ>,
+    #[serde(rename = "quorumSnapshotList")]
+    pub quorum_snapshot_list: Vec<Snapshot>,
+    #[serde(rename = "mnListDiffList")]
+    pub mn_list_diff_list: Vec<ListDiff>,
+}
+
+/// Quorum snapshot as it comes within 'quorum rotationinfo'
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    #[serde(rename = "activeQuorumMembers")]
+    pub active_quorum_members: Vec<bool>,
+    #[serde(rename = "mnSkipListMode")]
+    pub mn_skip_list_mode: i64,
+    #[serde(rename = "mnSkipList")]
+    pub mn_skip_list: Vec<i32>,
+}
+
+/// Final commitment as it comes within 'newQuorums' of 'protx diff'
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LLMQ {
+    pub version: i64,
+    #[serde(rename = "llmqType")]
+    pub llmq_type: i64,
+    #[serde(rename = "quorumHash")]
+    pub quorum_hash: String,
+    /// Only rotated quorums (version 2+) have it
+    #[serde(rename = "quorumIndex", default, skip_serializing_if = "Option::is_none")]
+    pub quorum_index: Option<i64>,
+    #[serde(rename = "signersCount")]
+    pub signers_count: i64,
+    pub signers: String,
+    #[serde(rename = "validMembersCount")]
+    pub valid_members_count: i64,
+    #[serde(rename = "validMembers")]
+    pub valid_members: String,
+    #[serde(rename = "quorumPublicKey")]
+    pub quorum_public_key: String,
+    #[serde(rename = "quorumVvecHash")]
+    pub quorum_vvec_hash: String,
+    #[serde(rename = "quorumSig")]
+    pub quorum_sig: String,
+    #[serde(rename = "membersSig")]
+    pub members_sig: String,
+}
+
+/// Simplified masternode list entry as it comes within 'mnList' of 'protx diff'
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Node {
+    #[serde(rename = "proRegTxHash")]
+    pub pro_reg_tx_hash: String,
+    #[serde(rename = "confirmedHash")]
+    pub confirmed_hash: String,
+    pub service: String,
+    #[serde(rename = "pubKeyOperator")]
+    pub pub_key_operator: String,
+    #[serde(rename = "votingAddress")]
+    pub voting_address: String,
+    #[serde(rename = "isValid")]
+    pub is_valid: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DeletedQuorum {
+    #[serde(rename = "llmqType")]
+    pub llmq_type: i64,
+    #[serde(rename = "quorumHash")]
+    pub quorum_hash: String,
+}
+
+/// Result of 'protx diff'. With the genesis as a base it's the full list at 'block_hash'
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ListDiff {
+    #[serde(rename = "baseBlockHash")]
+    pub base_block_hash: String,
+    #[serde(rename = "blockHash")]
+    pub block_hash: String,
+    #[serde(rename = "cbTxMerkleTree")]
+    pub cb_tx_merkle_tree: String,
+    #[serde(rename = "cbTx")]
+    pub cb_tx: String,
+    #[serde(rename = "deletedMNs")]
+    pub deleted_mns: Vec<String>,
+    #[serde(rename = "mnList")]
+    pub mn_list: Vec<Node>,
+    #[serde(rename = "deletedQuorums")]
+    pub deleted_quorums: Vec<DeletedQuorum>,
+    #[serde(rename = "newQuorums")]
+    pub new_quorums: Vec<LLMQ>,
+    #[serde(rename = "merkleRootMNList")]
+    pub merkle_root_mnlist: String,
+    #[serde(rename = "merkleRootQuorums")]
+    pub merkle_root_quorums: String,
+}
@@ -0,0 +1,201 @@
+use crate::codec::{write_var_int, ListDiffMessage, QRInfoMessage, SnapshotMessage};
+use crate::core_json::{DeletedQuorum, ListDiff, Node, QRInfo, Snapshot, LLMQ};
+use dash_spv_models::common::{ChainType, SocketAddress};
+use dash_spv_models::llmq::LLMQSnapshot;
+use dash_spv_models::masternode::{LLMQEntry, MasternodeEntry, MasternodeList};
+use dash_spv_primitives::crypto::byte_util::Reversable;
+use dash_spv_primitives::crypto::{UInt160, UInt256};
+use dash_spv_primitives::hashes::hex::ToHex;
+use dash_spv_primitives::util::base58;
+use std::net::{IpAddr, Ipv6Addr, SocketAddr};
+
+/// Reversed as Core shows uint256
+pub fn hash_to_json(hash: &UInt256) -> String {
+    hash.clone().reversed().0.to_hex()
+}
+
+/// Reverse of 'bools_to_bytes', 'members_count' is the size of the quorum
+pub fn bytes_to_bools(bytes: &[u8], members_count: usize) -> Vec<bool> {
+    (0..members_count)
+        .map(|index| bytes.get(index / 8).map_or(false, |byte| byte & (1 << (7 - index % 8)) != 0))
+        .collect()
+}
+
+pub fn snapshot_to_json(snapshot: &LLMQSnapshot, members_count: usize) -> Snapshot {
+    let skip_list_mode: u32 = snapshot.skip_list_mode.into();
+    Snapshot {
+        active_quorum_members: bytes_to_bools(&snapshot.member_list, members_count),
+        mn_skip_list_mode: skip_list_mode as i64,
+        mn_skip_list: snapshot.skip_list.clone(),
+    }
+}
+
+/// Mapped ipv4 is shown as ipv4
+pub fn socket_address_to_json(address: &SocketAddress) -> String {
+    let ip = Ipv6Addr::from(address.ip_address.0);
+    let ip = match ip.octets() {
+        [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, a, b, c, d] => IpAddr::from([a, b, c, d]),
+        _ => IpAddr::V6(ip),
+    };
+    SocketAddr::new(ip, address.port).to_string()
+}
+
+/// Base58check p2pkh address of the voting key
+pub fn key_id_to_address(key_id: &UInt160, chain: &ChainType) -> String {
+    let version = match chain {
+        ChainType::MainNet => 76u8,
+        _ => 140u8,
+    };
+    let mut bytes = Vec::<u8>::with_capacity(21);
+    bytes.push(version);
+    bytes.extend_from_slice(&key_id.0);
+    base58::check_encode_slice(&bytes)
+}
+
+pub fn masternode_to_json(entry: &MasternodeEntry, chain: &ChainType) -> Node {
+    Node {
+        pro_reg_tx_hash: hash_to_json(&entry.provider_registration_transaction_hash),
+        confirmed_hash: hash_to_json(&entry.confirmed_hash),
+        service: socket_address_to_json(&entry.socket_address),
+        pub_key_operator: entry.operator_public_key.0.to_hex(),
+        voting_address: key_id_to_address(&entry.key_id_voting, chain),
+        is_valid: entry.is_valid,
+    }
+}
+
+pub fn quorum_to_json(entry: &LLMQEntry) -> LLMQ {
+    let llmq_type: u8 = entry.llmq_type.into();
+    LLMQ {
+        version: entry.version as i64,
+        llmq_type: llmq_type as i64,
+        quorum_hash: hash_to_json(&entry.llmq_hash),
+        quorum_index: entry.index.map(|index| index as i64),
+        signers_count: entry.signers_count.0 as i64,
+        signers: entry.signers_bitset.to_hex(),
+        valid_members_count: entry.valid_members_count.0 as i64,
+        valid_members: entry.valid_members_bitset.to_hex(),
+        quorum_public_key: entry.public_key.0.to_hex(),
+        quorum_vvec_hash: hash_to_json(&entry.verification_vector_hash),
+        quorum_sig: entry.threshold_signature.0.to_hex(),
+        members_sig: entry.all_commitment_aggregated_signature.0.to_hex(),
+    }
+}
+
+/// Emits the list as 'protx diff' from the genesis would show it.
+/// Coinbase and its merkle tree aren't kept within the list, so they're left empty
+pub fn masternode_list_to_json(list: &MasternodeList, chain: &ChainType) -> ListDiff {
+    ListDiff {
+        base_block_hash: hash_to_json(&UInt256::MIN),
+        block_hash: hash_to_json(&list.block_hash),
+        cb_tx_merkle_tree: String::new(),
+        cb_tx: String::new(),
+        deleted_mns: vec![],
+        mn_list: list
+            .masternodes
+            .values()
+            .map(|entry| masternode_to_json(entry, chain))
+            .collect(),
+        deleted_quorums: vec![],
+        new_quorums: list
+            .quorums
+            .values()
+            .flat_map(|quorums_of_type| quorums_of_type.values())
+            .map(quorum_to_json)
+            .collect(),
+        merkle_root_mnlist: list.masternode_merkle_root.as_ref().map(hash_to_json).unwrap_or_default(),
+        merkle_root_quorums: list.llmq_merkle_root.as_ref().map(hash_to_json).unwrap_or_default(),
+    }
+}
+
+pub fn masternode_list_to_json_string(list: &MasternodeList, chain: &ChainType) -> String {
+    serde_json::to_string_pretty(&masternode_list_to_json(list, chain)).unwrap()
+}
+
+/// Merkle tree of the coinbase as 'cbTxMerkleTree' of 'protx diff':
+/// total transactions, hashes and flags
+fn merkle_tree_to_json(message: &ListDiffMessage) -> String {
+    let diff = &message.diff;
+    let mut bytes = Vec::<u8>::new();
+    bytes.extend_from_slice(&diff.total_transactions.to_le_bytes());
+    write_var_int(diff.merkle_hashes.1.len(), &mut bytes);
+    diff.merkle_hashes.1.iter().for_each(|hash| bytes.extend_from_slice(&hash.0));
+    write_var_int(diff.merkle_flags.len(), &mut bytes);
+    bytes.extend_from_slice(&diff.merkle_flags);
+    bytes.to_hex()
+}
+
+/// Emits MNLISTDIFF as 'protx diff' shows it.
+/// Added and modified masternodes can't be told apart without the base list, so all of them are in 'mnList'
+pub fn list_diff_to_json(message: &ListDiffMessage, chain: &ChainType) -> ListDiff {
+    let diff = &message.diff;
+    ListDiff {
+        base_block_hash: hash_to_json(&diff.base_block_hash),
+        block_hash: hash_to_json(&diff.block_hash),
+        cb_tx_merkle_tree: merkle_tree_to_json(message),
+        cb_tx: message.coinbase_transaction.to_hex(),
+        deleted_mns: diff.deleted_masternode_hashes.iter().map(hash_to_json).collect(),
+        mn_list: diff
+            .added_or_modified_masternodes
+            .values()
+            .map(|entry| masternode_to_json(entry, chain))
+            .collect(),
+        deleted_quorums: diff
+            .deleted_quorums
+            .iter()
+            .flat_map(|(&llmq_type, hashes)| {
+                let llmq_type: u8 = llmq_type.into();
+                hashes.iter().map(move |hash| DeletedQuorum {
+                    llmq_type: llmq_type as i64,
+                    quorum_hash: hash_to_json(hash),
+                })
+            })
+            .collect(),
+        new_quorums: diff
+            .added_quorums
+            .values()
+            .flat_map(|quorums_of_type| quorums_of_type.values())
+            .map(quorum_to_json)
+            .collect(),
+        merkle_root_mnlist: hash_to_json(&diff.coinbase_transaction.merkle_root_mn_list),
+        merkle_root_quorums: diff
+            .coinbase_transaction
+            .merkle_root_llmq_list
+            .as_ref()
+            .map(hash_to_json)
+            .unwrap_or_default(),
+    }
+}
+
+fn message_snapshot_to_json(snapshot: &SnapshotMessage) -> Snapshot {
+    snapshot_to_json(&snapshot.snapshot, snapshot.member_count)
+}
+
+/// Emits QRINFO as 'quorum rotationinfo' shows it
+pub fn qr_info_to_json(message: &QRInfoMessage, chain: &ChainType) -> QRInfo {
+    QRInfo {
+        extra_share: message.extra_share.is_some(),
+        quorum_snapshot_at_hminus_c: message_snapshot_to_json(&message.snapshot_at_h_c),
+        quorum_snapshot_at_hminus2c: message_snapshot_to_json(&message.snapshot_at_h_2c),
+        quorum_snapshot_at_hminus3c: message_snapshot_to_json(&message.snapshot_at_h_3c),
+        mn_list_diff_tip: list_diff_to_json(&message.diff_tip, chain),
+        mn_list_diff_h: list_diff_to_json(&message.diff_h, chain),
+        mn_list_diff_at_hminus_c: list_diff_to_json(&message.diff_h_c, chain),
+        mn_list_diff_at_hminus2c: list_diff_to_json(&message.diff_h_2c, chain),
+        mn_list_diff_at_hminus3c: list_diff_to_json(&message.diff_h_3c, chain),
+        quorum_snapshot_at_hminus4c: message
+            .extra_share
+            .as_ref()
+            .map(|(snapshot, _)| message_snapshot_to_json(snapshot)),
+        mn_list_diff_at_hminus4c: message
+            .extra_share
+            .as_ref()
+            .map(|(_, diff)| list_diff_to_json(diff, chain)),
+        last_commitment_per_index: message.last_quorum_per_index.iter().map(quorum_to_json).collect(),
+        quorum_snapshot_list: message.quorum_snapshot_list.iter().map(message_snapshot_to_json).collect(),
+        mn_list_diff_list: message
+            .mn_list_diff_list
+            .iter()
+            .map(|diff| list_diff_to_json(diff, chain))
+            .collect(),
+    }
+}
@@ -2,6 +2,12 @@
 #![allow(unused_variables)]
 #[macro_use]
 pub mod processing;
+pub mod codec;
+#[cfg(any(test, feature = "core-json"))]
+pub mod core_json;
+#[cfg(feature = "mock-peer")]
+pub mod mock_peer;
+pub mod store;
 extern crate dash_spv_ffi;
 extern crate dash_spv_models;
 
@@ -11,8 +17,10 @@ mod macros;
 #[cfg(test)]
 mod tests;
 
-use crate::processing::{MasternodeProcessor, MasternodeProcessorCache, ProcessingError};
-use byte::BytesExt;
+use crate::processing::{CacheStats, LLMQRotationConfig, MasternodeProcessor, MasternodeProcessorCache, ProcessingError, ProcessorSession, QRInfoResult, QRInfoValidationResult, SessionChain, SharedMasternodeList};
+use crate::codec::network::{MNLISTDIFF_COMMAND, QRINFO_COMMAND};
+use crate::codec::{GetMNListDiff, GetQRInfo, NetworkMessage, QRInfoMessage};
+use crate::store::MasternodeCheckpoint;
 use dash_spv_ffi::ffi::boxer::{boxed, boxed_vec};
 use dash_spv_ffi::ffi::callbacks::{
     AddInsightBlockingLookup, GetBlockHashByHeight, GetBlockHeightByHash,
@@ -24,15 +32,22 @@ use dash_spv_ffi::ffi::callbacks::{
 use dash_spv_ffi::ffi::to::ToFFI;
 use dash_spv_ffi::ffi::unboxer::{
     unbox_any, unbox_block, unbox_llmq_snapshot, unbox_llmq_validation_data, unbox_masternode_list,
-    unbox_mn_list_diff_result, unbox_qr_info_result,
+    unbox_mn_list_diff_result, unbox_qr_info_result, unbox_vec_ptr,
 };
 use dash_spv_ffi::types;
+use dash_spv_models::common::LLMQType;
 use dash_spv_models::llmq;
-use dash_spv_models::masternode::LLMQEntry;
-use dash_spv_primitives::consensus::encode;
-use dash_spv_primitives::crypto::byte_util::BytesDecodable;
+use dash_spv_models::tx::CoinbaseTransaction;
+use dash_spv_primitives::crypto::byte_util::{BytesDecodable, ConstDecodable};
+use dash_spv_primitives::crypto::UInt256;
 use std::ptr::null_mut;
 use std::slice;
+#[cfg(feature = "sqlite")]
+use crate::store::{MasternodeStore, SqliteStore};
+#[cfg(feature = "sqlite")]
+use dash_spv_ffi::ffi::from::FromFFI;
+#[cfg(feature = "sqlite")]
+use std::sync::Arc;
 
 /// Destroys anonymous internal holder for UInt256
 #[no_mangle]
@@ -119,6 +134,56 @@ pub unsafe extern "C" fn register_processor(
     boxed(processor)
 }
 
+/// Set LLMQ types which use quorum rotation for the chain processor works with.
+/// By default the rotating types of mainnet or testnet are taken from the genesis given for processing,
+/// on devnets and regtest they must be set, otherwise the rotating types of all known chains are used
+#[no_mangle]
+pub unsafe extern "C" fn processor_set_rotating_llmq_types(
+    processor: *mut MasternodeProcessor,
+    llmq_types: *const u8,
+    count: usize,
+) {
+    let processor = &mut *processor;
+    let types = slice::from_raw_parts(llmq_types, count)
+        .iter()
+        .map(|&llmq_type| LLMQType::from(llmq_type))
+        .collect::<Vec<LLMQType>>();
+    processor.rotation_config = LLMQRotationConfig::from_types(&types);
+    processor.rotation_config_is_set = true;
+    processor.log(format!("processor_set_rotating_llmq_types: {:?}", processor.rotation_config.types()));
+}
+
+/// Bootstraps the cache from the checkpoint bytes (see store::MasternodeCheckpoint)
+/// along with the coinbase transaction of the checkpoint block, which roots the checkpoint must match.
+/// Returns false if the checkpoint is malformed, its integrity check fails or it doesn't match the coinbase
+#[no_mangle]
+pub unsafe extern "C" fn processor_load_checkpoint(
+    checkpoint: *const u8,
+    checkpoint_length: usize,
+    coinbase_transaction: *const u8,
+    coinbase_transaction_length: usize,
+    processor: *mut MasternodeProcessor,
+    cache: *mut MasternodeProcessorCache,
+) -> bool {
+    let processor = &mut *processor;
+    let bytes = slice::from_raw_parts(checkpoint, checkpoint_length);
+    let coinbase_bytes = slice::from_raw_parts(coinbase_transaction, coinbase_transaction_length);
+    let coinbase = match CoinbaseTransaction::from_bytes(coinbase_bytes, &mut 0) {
+        Some(coinbase) => coinbase,
+        None => {
+            processor.log("processor_load_checkpoint: malformed coinbase transaction".to_string());
+            return false;
+        }
+    };
+    match MasternodeCheckpoint::from_bytes(bytes).and_then(|checkpoint| processor.load_checkpoint(&checkpoint, &coinbase, &mut *cache)) {
+        Ok(()) => true,
+        Err(err) => {
+            processor.log(format!("processor_load_checkpoint: {}", err));
+            false
+        }
+    }
+}
+
 /// Unregister all the callbacks for use across FFI
 #[no_mangle]
 pub unsafe extern "C" fn unregister_processor(processor: *mut MasternodeProcessor) {
@@ -127,6 +192,16 @@ pub unsafe extern "C" fn unregister_processor(processor: *mut MasternodeProcesso
     // unbox_any(unboxed.genesis_hash);
 }
 
+/// Enables verification of the lists given by 'MasternodeListLookup':
+/// their roots are recomputed and the corrupted ones are reported with 'CorruptedStorage' error
+#[no_mangle]
+pub unsafe extern "C" fn processor_set_verify_looked_up_lists(
+    processor: *mut MasternodeProcessor,
+    verify: bool,
+) {
+    (*processor).verify_looked_up_lists = verify;
+}
+
 /// Initialize opaque cache to store needed information between FFI calls
 #[no_mangle]
 pub unsafe extern "C" fn processor_create_cache() -> *mut MasternodeProcessorCache {
@@ -142,6 +217,44 @@ pub unsafe extern "C" fn processor_destroy_cache(cache: *mut MasternodeProcessor
     let cache = unbox_any(cache);
 }
 
+/// Counts and heights of what the cache holds along with its approximate memory usage,
+/// must be destroyed with 'processor_destroy_cache_stats'
+#[no_mangle]
+pub unsafe extern "C" fn processor_cache_stats(cache: *const MasternodeProcessorCache) -> *mut CacheStats {
+    boxed((*cache).stats().encode())
+}
+
+/// Destroys CacheStats
+#[no_mangle]
+pub unsafe extern "C" fn processor_destroy_cache_stats(stats: *mut CacheStats) {
+    let stats = unbox_any(stats);
+    stats.destroy();
+}
+
+/// Nearest cached list at or below the height (by its known height),
+/// returned list must be destroyed with 'processor_destroy_masternode_list'
+#[no_mangle]
+pub unsafe extern "C" fn processor_cache_masternode_list_at_or_below(
+    cache: *const MasternodeProcessorCache,
+    block_height: u32,
+) -> *mut types::MasternodeList {
+    match (*cache).masternode_list_at_or_below(block_height) {
+        Some((_, list)) => boxed(list.to_list().encode()),
+        None => null_mut(),
+    }
+}
+
+/// Drops the cached list along with its merkle trees (i.e. the list which won't be a base anymore),
+/// returns false if there is no such list
+#[no_mangle]
+pub unsafe extern "C" fn processor_cache_remove_masternode_list(
+    cache: *mut MasternodeProcessorCache,
+    block_hash: *const u8,
+) -> bool {
+    let block_hash = unwrap_or_return!(UInt256::from_const(block_hash), || false);
+    (*cache).remove_masternode_list(&block_hash).is_some()
+}
+
 /// Read and process message received as a response for 'GETMNLISTDIFF' call
 /// Here we calculate quorums according to Core v0.17
 /// See https://github.com/dashpay/dips/blob/master/dip-0004.md
@@ -161,6 +274,7 @@ pub extern "C" fn process_mnlistdiff_from_message(
     processor.opaque_context = context;
     processor.use_insight_as_backup = use_insight_as_backup;
     processor.genesis_hash = genesis_hash;
+    processor.use_rotation_config_of_genesis();
     processor.log(format!(
         "process_mnlistdiff_from_message.start: {:?} {:?} {:p} {:p} {:?}",
         std::time::Instant::now(),
@@ -194,6 +308,94 @@ pub extern "C" fn process_mnlistdiff_from_message(
     boxed(result)
 }
 
+/// Creates session bound to the chain, which takes ownership of the processor.
+/// 'chain': 0 - mainnet, 1 - testnet, 2 - devnet (with 'devnet_name'), 3 - regtest.
+/// 'genesis_hash' is only needed for devnet and regtest.
+/// Returns null for unknown chain, then the processor is kept by the caller
+#[no_mangle]
+pub unsafe extern "C" fn processor_create_session(
+    processor: *mut MasternodeProcessor,
+    chain: u8,
+    devnet_name: *const std::os::raw::c_char,
+    genesis_hash: *const u8,
+) -> *mut ProcessorSession {
+    let custom_genesis = || UInt256::from_const(genesis_hash);
+    let chain = match chain {
+        0 => Some(SessionChain::MainNet),
+        1 => Some(SessionChain::TestNet),
+        2 => custom_genesis().map(|genesis_hash| SessionChain::DevNet {
+            name: if devnet_name.is_null() {
+                String::new()
+            } else {
+                std::ffi::CStr::from_ptr(devnet_name).to_string_lossy().into_owned()
+            },
+            genesis_hash,
+        }),
+        3 => custom_genesis().map(|genesis_hash| SessionChain::RegTest { genesis_hash }),
+        _ => None,
+    };
+    match chain {
+        Some(chain) => boxed(ProcessorSession::new(chain, *unbox_any(processor))),
+        None => null_mut(),
+    }
+}
+
+/// Destroys the session along with its processor and cache
+#[no_mangle]
+pub unsafe extern "C" fn processor_destroy_session(session: *mut ProcessorSession) {
+    let session = unbox_any(session);
+}
+
+/// Cache of the session, to be used with the functions taking the cache (i.e. 'processor_load_checkpoint')
+#[no_mangle]
+pub unsafe extern "C" fn processor_session_cache(session: *mut ProcessorSession) -> *mut MasternodeProcessorCache {
+    (*session).cache_mut()
+}
+
+/// Same as 'process_mnlistdiff_from_message' but within the session:
+/// diffs which don't belong to the chain of the session are rejected with 'InvalidChain' error
+#[no_mangle]
+pub unsafe extern "C" fn process_mnlistdiff_from_message_in_session(
+    message_arr: *const u8,
+    message_length: usize,
+    use_insight_as_backup: bool,
+    is_from_snapshot: bool,
+    session: *mut ProcessorSession,
+    context: *const std::ffi::c_void,
+) -> *mut types::MNListDiffResult {
+    let session = &mut *session;
+    let message: &[u8] = slice::from_raw_parts(message_arr, message_length);
+    let result = session.process_mnlistdiff(message, use_insight_as_backup, is_from_snapshot, context);
+    session.processor().log(format!(
+        "process_mnlistdiff_from_message_in_session.finish: {:?} {:?}",
+        session.chain(),
+        result.error_status
+    ));
+    boxed(result.encode())
+}
+
+/// Same as 'process_qrinfo_from_message' but within the session:
+/// QRINFO with any of the diffs which don't belong to the chain of the session is rejected with 'InvalidChain' error
+#[no_mangle]
+pub unsafe extern "C" fn process_qrinfo_from_message_in_session(
+    message: *const u8,
+    message_length: usize,
+    use_insight_as_backup: bool,
+    is_from_snapshot: bool,
+    session: *mut ProcessorSession,
+    context: *const std::ffi::c_void,
+) -> *mut types::QRInfoResult {
+    let session = &mut *session;
+    let message: &[u8] = slice::from_raw_parts(message, message_length);
+    let result = session.process_qrinfo(message, use_insight_as_backup, is_from_snapshot, context);
+    session.processor().log(format!(
+        "process_qrinfo_from_message_in_session.finish: {:?} {:?}",
+        session.chain(),
+        result.error_status
+    ));
+    boxed(result.encode())
+}
+
 /// Here we read & calculate quorums according to Core v0.18
 /// See https://github.com/dashpay/dips/blob/master/dip-0024.md
 /// The reason behind we have multiple methods for this is that:
@@ -209,11 +411,69 @@ pub extern "C" fn process_qrinfo_from_message(
     cache: *mut MasternodeProcessorCache,
     context: *const std::ffi::c_void,
 ) -> *mut types::QRInfoResult {
+    let result = qr_info_result_from_message(
+        message,
+        message_length,
+        use_insight_as_backup,
+        is_from_snapshot,
+        genesis_hash,
+        processor,
+        cache,
+        context,
+    );
+    boxed(result.encode())
+}
+
+/// Same as 'process_qrinfo_from_message' but the result also has rotated quorums at h-3c
+/// with their validation statuses, must be destroyed with 'processor_destroy_qr_info_validation_result'
+#[no_mangle]
+pub extern "C" fn process_qrinfo_from_message_with_validation(
+    message: *const u8,
+    message_length: usize,
+    use_insight_as_backup: bool,
+    is_from_snapshot: bool,
+    genesis_hash: *const u8,
+    processor: *mut MasternodeProcessor,
+    cache: *mut MasternodeProcessorCache,
+    context: *const std::ffi::c_void,
+) -> *mut QRInfoValidationResult {
+    let result = qr_info_result_from_message(
+        message,
+        message_length,
+        use_insight_as_backup,
+        is_from_snapshot,
+        genesis_hash,
+        processor,
+        cache,
+        context,
+    );
+    boxed(result.encode_with_validation())
+}
+
+/// Destroys QRInfoValidationResult
+#[no_mangle]
+pub unsafe extern "C" fn processor_destroy_qr_info_validation_result(result: *mut QRInfoValidationResult) {
+    let result = unbox_any(result);
+    result.destroy();
+}
+
+#[allow(clippy::too_many_arguments)]
+fn qr_info_result_from_message(
+    message: *const u8,
+    message_length: usize,
+    use_insight_as_backup: bool,
+    is_from_snapshot: bool,
+    genesis_hash: *const u8,
+    processor: *mut MasternodeProcessor,
+    cache: *mut MasternodeProcessorCache,
+    context: *const std::ffi::c_void,
+) -> QRInfoResult {
     let message: &[u8] = unsafe { slice::from_raw_parts(message, message_length as usize) };
     let processor = unsafe { &mut *processor };
     processor.opaque_context = context;
     processor.use_insight_as_backup = use_insight_as_backup;
     processor.genesis_hash = genesis_hash;
+    processor.use_rotation_config_of_genesis();
     processor.log(format!(
         "process_qrinfo_from_message.start: {:?} {:?} {:?} {:?} {:?}",
         std::time::Instant::now(),
@@ -223,24 +483,17 @@ pub extern "C" fn process_qrinfo_from_message(
         context
     ));
     let cache = unsafe { &mut *cache };
-    let offset = &mut 0;
-    let mut process_list_diff = |list_diff: llmq::MNListDiff| {
-        processor.get_list_diff_result_with_base_lookup(list_diff, cache)
+    let message = match QRInfoMessage::from_bytes(message, &mut 0, |hash| {
+        processor.lookup_block_height_by_hash(hash)
+    }) {
+        Some(message) => message,
+        None => return QRInfoResult::default_with_error(ProcessingError::ParseError),
     };
-    let read_list_diff =
-        |offset: &mut usize| processor.read_list_diff_from_message(message, offset);
-    let read_snapshot = |offset: &mut usize| llmq::LLMQSnapshot::from_bytes(message, offset);
-    let read_var_int = |offset: &mut usize| encode::VarInt::from_bytes(message, offset);
-    let mut get_list_diff_result =
-        |list_diff: llmq::MNListDiff| boxed(process_list_diff(list_diff));
-
-    let snapshot_at_h_c = unwrap_or_qr_result_failure!(read_snapshot(offset));
-    let snapshot_at_h_2c = unwrap_or_qr_result_failure!(read_snapshot(offset));
-    let snapshot_at_h_3c = unwrap_or_qr_result_failure!(read_snapshot(offset));
-    let diff_tip = unwrap_or_qr_result_failure!(read_list_diff(offset));
     if !is_from_snapshot {
-        let error =
-            processor.should_process_diff_with_range(diff_tip.base_block_hash, diff_tip.block_hash);
+        let error = processor.should_process_diff_with_range(
+            message.diff_tip.diff.base_block_hash,
+            message.diff_tip.diff.block_hash,
+        );
         let none_error: u8 = ProcessingError::None.into();
         if error != none_error {
             processor.log(format!(
@@ -248,108 +501,256 @@ pub extern "C" fn process_qrinfo_from_message(
                 std::time::Instant::now(),
                 error
             ));
-            return boxed(types::QRInfoResult::default_with_error(error));
+            return QRInfoResult::default_with_error(ProcessingError::from(error));
         }
     }
-    let diff_h = unwrap_or_qr_result_failure!(read_list_diff(offset));
-    let diff_h_c = unwrap_or_qr_result_failure!(read_list_diff(offset));
-    let diff_h_2c = unwrap_or_qr_result_failure!(read_list_diff(offset));
-    let diff_h_3c = unwrap_or_qr_result_failure!(read_list_diff(offset));
-    let extra_share = message.read_with::<bool>(offset, {}).unwrap_or(false);
-    let snapshot_at_h_4c = if extra_share {
-        Some(unwrap_or_qr_result_failure!(read_snapshot(offset)))
-    } else {
-        None
-    };
-    let diff_h_4c = if extra_share {
-        Some(unwrap_or_qr_result_failure!(read_list_diff(offset)))
-    } else {
-        None
-    };
+    let result = processor.get_qr_info_result_internal(message, cache);
+    processor.log(format!(
+        "process_qrinfo_from_message.finish: {:?} {:#?}",
+        std::time::Instant::now(),
+        result
+    ));
+    result
+}
 
-    processor.save_snapshot(diff_h_c.block_hash, snapshot_at_h_c.clone());
-    processor.save_snapshot(diff_h_2c.block_hash, snapshot_at_h_2c.clone());
-    processor.save_snapshot(diff_h_3c.block_hash, snapshot_at_h_3c.clone());
-    if extra_share {
-        processor.save_snapshot(
-            diff_h_4c.as_ref().unwrap().block_hash,
-            snapshot_at_h_4c.clone().unwrap(),
-        );
+/// Payload of the P2P message if its envelope is valid for the network and it's of the command
+fn network_message_payload<'a>(
+    bytes: &'a [u8],
+    magic: u32,
+    command: &str,
+    processor: &MasternodeProcessor,
+) -> Option<&'a [u8]> {
+    match NetworkMessage::from_bytes(bytes, magic) {
+        Ok(Some((message, _))) if message.command == command => Some(message.payload),
+        Ok(Some((message, _))) => {
+            processor.log(format!("network message: '{}' instead of '{}'", message.command, command));
+            None
+        }
+        Ok(None) => {
+            processor.log(format!("network message: '{}' is incomplete", command));
+            None
+        }
+        Err(err) => {
+            processor.log(format!("network message: {}", err));
+            None
+        }
+    }
+}
+
+/// Same as 'process_mnlistdiff_from_message' but the message comes with P2P envelope,
+/// which is verified for the network given by 'magic' (see codec::network)
+#[no_mangle]
+pub extern "C" fn process_mnlistdiff_from_network_message(
+    message_arr: *const u8,
+    message_length: usize,
+    magic: u32,
+    use_insight_as_backup: bool,
+    is_from_snapshot: bool,
+    genesis_hash: *const u8,
+    processor: *mut MasternodeProcessor,
+    cache: *mut MasternodeProcessorCache,
+    context: *const std::ffi::c_void,
+) -> *mut types::MNListDiffResult {
+    let bytes = unsafe { slice::from_raw_parts(message_arr, message_length) };
+    match network_message_payload(bytes, magic, MNLISTDIFF_COMMAND, unsafe { &*processor }) {
+        Some(payload) => process_mnlistdiff_from_message(
+            payload.as_ptr(),
+            payload.len(),
+            use_insight_as_backup,
+            is_from_snapshot,
+            genesis_hash,
+            processor,
+            cache,
+            context,
+        ),
+        None => boxed(types::MNListDiffResult::default_with_error(ProcessingError::ParseError.into())),
+    }
+}
+
+/// Same as 'process_qrinfo_from_message' but the message comes with P2P envelope
+#[no_mangle]
+pub extern "C" fn process_qrinfo_from_network_message(
+    message: *const u8,
+    message_length: usize,
+    magic: u32,
+    use_insight_as_backup: bool,
+    is_from_snapshot: bool,
+    genesis_hash: *const u8,
+    processor: *mut MasternodeProcessor,
+    cache: *mut MasternodeProcessorCache,
+    context: *const std::ffi::c_void,
+) -> *mut types::QRInfoResult {
+    let bytes = unsafe { slice::from_raw_parts(message, message_length) };
+    match network_message_payload(bytes, magic, QRINFO_COMMAND, unsafe { &*processor }) {
+        Some(payload) => process_qrinfo_from_message(
+            payload.as_ptr(),
+            payload.len(),
+            use_insight_as_backup,
+            is_from_snapshot,
+            genesis_hash,
+            processor,
+            cache,
+            context,
+        ),
+        None => boxed(types::QRInfoResult::default_with_error(ProcessingError::ParseError.into())),
+    }
+}
+
+fn boxed_message(message: Vec<u8>, message_length: *mut usize) -> *mut u8 {
+    unsafe { *message_length = message.len() };
+    boxed_vec(message)
+}
+
+/// 'getmnlistdiff' message with P2P envelope for the network given by 'magic',
+/// its length is written into 'message_length'. Returns null if the hashes are missing
+#[no_mangle]
+pub unsafe extern "C" fn processor_getmnlistdiff_message(
+    magic: u32,
+    base_block_hash: *const u8,
+    block_hash: *const u8,
+    message_length: *mut usize,
+) -> *mut u8 {
+    match (UInt256::from_const(base_block_hash), UInt256::from_const(block_hash)) {
+        (Some(base_block_hash), Some(block_hash)) => boxed_message(
+            GetMNListDiff { base_block_hash, block_hash }.to_network_message(magic),
+            message_length,
+        ),
+        _ => null_mut(),
     }
+}
 
-    let result_at_tip = get_list_diff_result(diff_tip);
-    let result_at_h = get_list_diff_result(diff_h);
-    let result_at_h_c = get_list_diff_result(diff_h_c);
-    let result_at_h_2c = get_list_diff_result(diff_h_2c);
-    let result_at_h_3c = get_list_diff_result(diff_h_3c);
-    let result_at_h_4c = if extra_share {
-        get_list_diff_result(diff_h_4c.unwrap())
+/// 'getqrinfo' message with P2P envelope, 'base_block_hashes' are the blocks of the lists
+/// the client already has
+#[no_mangle]
+pub unsafe extern "C" fn processor_getqrinfo_message(
+    magic: u32,
+    base_block_hashes: *const *const u8,
+    base_block_hashes_count: usize,
+    block_request_hash: *const u8,
+    extra_share: bool,
+    message_length: *mut usize,
+) -> *mut u8 {
+    let base_block_hashes = if base_block_hashes_count == 0 {
+        Some(vec![])
     } else {
-        null_mut()
+        slice::from_raw_parts(base_block_hashes, base_block_hashes_count)
+            .iter()
+            .map(|&hash| UInt256::from_const(hash))
+            .collect::<Option<Vec<UInt256>>>()
     };
+    match (base_block_hashes, UInt256::from_const(block_request_hash)) {
+        (Some(base_block_hashes), Some(block_request_hash)) => boxed_message(
+            GetQRInfo { base_block_hashes, block_request_hash, extra_share }.to_network_message(magic),
+            message_length,
+        ),
+        _ => null_mut(),
+    }
+}
+
+/// Destroys the message built with 'processor_getmnlistdiff_message' or 'processor_getqrinfo_message'
+#[no_mangle]
+pub unsafe extern "C" fn processor_destroy_message(message: *mut u8, message_length: usize) {
+    unbox_vec_ptr(message, message_length);
+}
 
-    let last_quorum_per_index_count = 0; //unwrap_or_qr_result_failure!(read_var_int(offset)).0 as usize;
-    let mut last_quorum_per_index_vec: Vec<*mut types::LLMQEntry> =
-        Vec::with_capacity(last_quorum_per_index_count);
-    for _i in 0..last_quorum_per_index_count {
-        last_quorum_per_index_vec.push(boxed(
-            unwrap_or_qr_result_failure!(LLMQEntry::from_bytes(message, offset)).encode(),
-        ));
+/// Opens (or creates) embedded database store at the path,
+/// returns null if the database can't be opened or migrated (the error goes to the processor log)
+#[cfg(feature = "sqlite")]
+#[no_mangle]
+pub unsafe extern "C" fn processor_create_sqlite_store(
+    path: *const std::os::raw::c_char,
+    processor: *mut MasternodeProcessor,
+) -> *mut SqliteStore {
+    let path = std::ffi::CStr::from_ptr(path).to_string_lossy().into_owned();
+    match SqliteStore::open(&path) {
+        Ok(store) => boxed(store),
+        Err(err) => {
+            (*processor).log(format!("processor_create_sqlite_store: {}: {}", path, err));
+            null_mut()
+        }
     }
-    let quorum_snapshot_list_count = 0; //unwrap_or_qr_result_failure!(read_var_int(offset)).0 as usize;
-    let mut quorum_snapshot_list_vec: Vec<*mut types::LLMQSnapshot> =
-        Vec::with_capacity(quorum_snapshot_list_count);
-    let mut snapshots: Vec<llmq::LLMQSnapshot> = Vec::with_capacity(quorum_snapshot_list_count);
-    for _i in 0..quorum_snapshot_list_count {
-        let snapshot = unwrap_or_qr_result_failure!(read_snapshot(offset));
-        snapshots.push(snapshot.clone());
+}
+
+/// Attaches the store to the session, which takes its ownership:
+/// each diff processed within the session is saved to the store in one transaction
+#[cfg(feature = "sqlite")]
+#[no_mangle]
+pub unsafe extern "C" fn processor_session_attach_sqlite_store(
+    session: *mut ProcessorSession,
+    store: *mut SqliteStore,
+) {
+    (*session).set_store(unbox_any(store));
+}
+
+/// Destroys embedded database store
+#[cfg(feature = "sqlite")]
+#[no_mangle]
+pub unsafe extern "C" fn processor_destroy_sqlite_store(store: *mut SqliteStore) {
+    unbox_any(store);
+}
+
+/// Saves the list passed to 'MasternodeListSave' callback
+#[cfg(feature = "sqlite")]
+#[no_mangle]
+pub unsafe extern "C" fn processor_sqlite_store_save_masternode_list(
+    store: *mut SqliteStore,
+    block_hash: *const u8,
+    list: *const types::MasternodeList,
+) -> bool {
+    let store = &mut *store;
+    let block_hash = unwrap_or_return!(UInt256::from_const(block_hash), || false);
+    let list = (*list).decode();
+    store.save_masternode_list(block_hash, Arc::new(SharedMasternodeList::from_list(&list))).is_ok()
+}
+
+/// Looks up the list for 'MasternodeListLookup' callback,
+/// returned list must be destroyed with 'processor_destroy_masternode_list'
+#[cfg(feature = "sqlite")]
+#[no_mangle]
+pub unsafe extern "C" fn processor_sqlite_store_lookup_masternode_list(
+    store: *mut SqliteStore,
+    block_hash: *const u8,
+) -> *mut types::MasternodeList {
+    let store = &*store;
+    let block_hash = unwrap_or_return!(UInt256::from_const(block_hash), null_mut);
+    match store.masternode_list(&block_hash) {
+        Ok(Some(list)) => boxed(list.to_list().encode()),
+        _ => null_mut(),
     }
-    let mn_list_diff_list_count = 0; //unwrap_or_qr_result_failure!(read_var_int(offset)).0 as usize;
-    let mut mn_list_diff_list_vec: Vec<*mut types::MNListDiffResult> =
-        Vec::with_capacity(mn_list_diff_list_count);
-    assert_eq!(
-        quorum_snapshot_list_count, mn_list_diff_list_count,
-        "'quorum_snapshot_list_count' must be equal 'mn_list_diff_list_count'"
-    );
-    for i in 0..mn_list_diff_list_count {
-        let list_diff = unwrap_or_qr_result_failure!(read_list_diff(offset));
-        let block_hash = list_diff.block_hash.clone();
-        mn_list_diff_list_vec.push(get_list_diff_result(list_diff));
-        let snapshot = snapshots.get(i).unwrap();
-        quorum_snapshot_list_vec.push(boxed(snapshot.encode()));
-        processor.save_snapshot(block_hash, snapshot.clone());
+}
+
+/// Looks up the nearest stored list at or below the height,
+/// returned list must be destroyed with 'processor_destroy_masternode_list'
+#[cfg(feature = "sqlite")]
+#[no_mangle]
+pub unsafe extern "C" fn processor_sqlite_store_lookup_masternode_list_at_or_below(
+    store: *mut SqliteStore,
+    block_height: u32,
+) -> *mut types::MasternodeList {
+    match (*store).masternode_list_at_or_below(block_height) {
+        Ok(Some(list)) => boxed(list.to_list().encode()),
+        _ => null_mut(),
     }
+}
 
-    let result = types::QRInfoResult {
-        error_status: ProcessingError::None.into(),
-        result_at_tip,
-        result_at_h,
-        result_at_h_c,
-        result_at_h_2c,
-        result_at_h_3c,
-        result_at_h_4c,
-        snapshot_at_h_c: boxed(snapshot_at_h_c.encode()),
-        snapshot_at_h_2c: boxed(snapshot_at_h_2c.encode()),
-        snapshot_at_h_3c: boxed(snapshot_at_h_3c.encode()),
-        snapshot_at_h_4c: if extra_share {
-            boxed(snapshot_at_h_4c.unwrap().encode())
-        } else {
-            null_mut()
-        },
-        extra_share,
-        last_quorum_per_index: boxed_vec(last_quorum_per_index_vec),
-        last_quorum_per_index_count,
-        quorum_snapshot_list: boxed_vec(quorum_snapshot_list_vec),
-        quorum_snapshot_list_count,
-        mn_list_diff_list: boxed_vec(mn_list_diff_list_vec),
-        mn_list_diff_list_count,
-    };
-    processor.log(format!(
-        "process_qrinfo_from_message.finish: {:?} {:#?}",
-        std::time::Instant::now(),
-        result
-    ));
-    boxed(result)
+/// Stores everything from the processing session in one transaction
+#[cfg(feature = "sqlite")]
+#[no_mangle]
+pub unsafe extern "C" fn processor_sqlite_store_save_cache(
+    store: *mut SqliteStore,
+    cache: *mut MasternodeProcessorCache,
+) -> bool {
+    (*store).save_cache(&*cache).is_ok()
+}
+
+/// Restores the cache from the store to continue processing from the stored state
+#[cfg(feature = "sqlite")]
+#[no_mangle]
+pub unsafe extern "C" fn processor_sqlite_store_load_cache(
+    store: *mut SqliteStore,
+    cache: *mut MasternodeProcessorCache,
+) -> bool {
+    (*store).load_cache(&mut *cache).is_ok()
 }
 
 // #[no_mangle]
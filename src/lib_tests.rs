@@ -2,11 +2,12 @@
 pub mod tests {
     extern crate libc;
     extern crate reqwest;
+    use crate::codec::QRInfoMessage;
     use crate::processing::processor_cache::MasternodeProcessorCache;
-    use crate::processing::{MNListDiffResult, QRInfoResult};
+    use crate::processing::{MNListDiffResult, QRInfoResult, SharedMasternodeList};
     use crate::{
         process_mnlistdiff_from_message, processor_create_cache, register_processor,
-        unwrap_or_diff_processing_failure, unwrap_or_qr_processing_failure, unwrap_or_return,
+        unwrap_or_diff_processing_failure, unwrap_or_return,
         MasternodeProcessor, ProcessingError,
     };
     use byte::BytesExt;
@@ -17,8 +18,7 @@ pub mod tests {
     use dash_spv_ffi::types;
     use dash_spv_models::common::chain_type::{ChainType, IHaveChainSettings};
     use dash_spv_models::common::LLMQType;
-    use dash_spv_models::{llmq, masternode};
-    use dash_spv_primitives::consensus::encode;
+    use dash_spv_models::llmq;
     use dash_spv_primitives::crypto::byte_util::{
         BytesDecodable, Reversable, UInt256, UInt384, UInt768,
     };
@@ -26,6 +26,7 @@ pub mod tests {
     use serde::{Deserialize, Serialize};
     use std::io::Read;
     use std::ptr::null_mut;
+    use std::sync::Arc;
     use std::{env, fs, slice};
     use crate::tests::block_store::init_testnet_store;
 
@@ -152,6 +153,7 @@ pub mod tests {
         processor.opaque_context = context;
         processor.use_insight_as_backup = use_insight_as_backup;
         processor.genesis_hash = genesis_hash;
+        processor.use_rotation_config_of_genesis();
         let message: &[u8] = unsafe { slice::from_raw_parts(message_arr, message_length as usize) };
         let list_diff =
             unwrap_or_diff_processing_failure!(llmq::MNListDiff::new(message, &mut 0, |hash| {
@@ -182,99 +184,19 @@ pub mod tests {
         processor.opaque_context = context;
         processor.use_insight_as_backup = use_insight_as_backup;
         processor.genesis_hash = genesis_hash;
+        processor.use_rotation_config_of_genesis();
         let cache = unsafe { &mut *cache };
         println!(
             "process_qrinfo_from_message --: {:?} {:?} {:?}",
             processor, processor.opaque_context, cache
         );
-        let offset = &mut 0;
-        let read_list_diff =
-            |offset: &mut usize| processor.read_list_diff_from_message(message, offset);
-        let mut process_list_diff = |list_diff: llmq::MNListDiff| {
-            processor.get_list_diff_result_internal_with_base_lookup(list_diff, cache)
-        };
-        let read_snapshot = |offset: &mut usize| llmq::LLMQSnapshot::from_bytes(message, offset);
-        let read_var_int = |offset: &mut usize| encode::VarInt::from_bytes(message, offset);
-        let snapshot_at_h_c = unwrap_or_qr_processing_failure!(read_snapshot(offset));
-        let snapshot_at_h_2c = unwrap_or_qr_processing_failure!(read_snapshot(offset));
-        let snapshot_at_h_3c = unwrap_or_qr_processing_failure!(read_snapshot(offset));
-        let diff_tip = unwrap_or_qr_processing_failure!(read_list_diff(offset));
-        let diff_h = unwrap_or_qr_processing_failure!(read_list_diff(offset));
-        let diff_h_c = unwrap_or_qr_processing_failure!(read_list_diff(offset));
-        let diff_h_2c = unwrap_or_qr_processing_failure!(read_list_diff(offset));
-        let diff_h_3c = unwrap_or_qr_processing_failure!(read_list_diff(offset));
-        let extra_share = message.read_with::<bool>(offset, {}).unwrap_or(false);
-        let (snapshot_at_h_4c, diff_h_4c) = if extra_share {
-            (
-                Some(unwrap_or_qr_processing_failure!(read_snapshot(offset))),
-                Some(unwrap_or_qr_processing_failure!(read_list_diff(offset))),
-            )
-        } else {
-            (None, None)
+        let message = match QRInfoMessage::from_bytes(message, &mut 0, |hash| {
+            processor.lookup_block_height_by_hash(hash)
+        }) {
+            Some(message) => message,
+            None => return QRInfoResult::default_with_error(ProcessingError::ParseError),
         };
-        processor.save_snapshot(diff_h_c.block_hash, snapshot_at_h_c.clone());
-        processor.save_snapshot(diff_h_2c.block_hash, snapshot_at_h_2c.clone());
-        processor.save_snapshot(diff_h_3c.block_hash, snapshot_at_h_3c.clone());
-        if extra_share {
-            processor.save_snapshot(
-                diff_h_4c.as_ref().unwrap().block_hash,
-                snapshot_at_h_4c.as_ref().unwrap().clone(),
-            );
-        }
-        let last_quorum_per_index_count =
-            unwrap_or_qr_processing_failure!(read_var_int(offset)).0 as usize;
-        let mut last_quorum_per_index: Vec<masternode::LLMQEntry> =
-            Vec::with_capacity(last_quorum_per_index_count);
-        for _i in 0..last_quorum_per_index_count {
-            let entry = unwrap_or_qr_processing_failure!(masternode::LLMQEntry::from_bytes(
-                message, offset
-            ));
-            last_quorum_per_index.push(entry);
-        }
-        let quorum_snapshot_list_count =
-            unwrap_or_qr_processing_failure!(read_var_int(offset)).0 as usize;
-        let mut quorum_snapshot_list: Vec<llmq::LLMQSnapshot> =
-            Vec::with_capacity(quorum_snapshot_list_count);
-        for _i in 0..quorum_snapshot_list_count {
-            quorum_snapshot_list.push(unwrap_or_qr_processing_failure!(read_snapshot(offset)));
-        }
-        let mn_list_diff_list_count =
-            unwrap_or_qr_processing_failure!(read_var_int(offset)).0 as usize;
-        let mut mn_list_diff_list: Vec<MNListDiffResult> =
-            Vec::with_capacity(mn_list_diff_list_count);
-        for _i in 0..mn_list_diff_list_count {
-            mn_list_diff_list.push(process_list_diff(unwrap_or_qr_processing_failure!(
-                read_list_diff(offset)
-            )));
-        }
-        // The order is important since the each new one dependent on previous
-        let result_at_h_4c = if let Some(diff) = diff_h_4c {
-            Some(process_list_diff(diff))
-        } else {
-            None
-        };
-        let result_at_h_3c = process_list_diff(diff_h_3c);
-        let result_at_h_2c = process_list_diff(diff_h_2c);
-        let result_at_h_c = process_list_diff(diff_h_c);
-        let result_at_h = process_list_diff(diff_h);
-        let result_at_tip = process_list_diff(diff_tip);
-        QRInfoResult {
-            error_status: ProcessingError::None,
-            result_at_tip,
-            result_at_h,
-            result_at_h_c,
-            result_at_h_2c,
-            result_at_h_3c,
-            result_at_h_4c,
-            snapshot_at_h_c,
-            snapshot_at_h_2c,
-            snapshot_at_h_3c,
-            snapshot_at_h_4c,
-            extra_share,
-            last_quorum_per_index,
-            quorum_snapshot_list,
-            mn_list_diff_list,
-        }
+        processor.get_qr_info_result_internal(message, cache)
     }
 
     pub fn get_file_as_byte_vec(filename: &String) -> Vec<u8> {
@@ -404,7 +326,7 @@ pub mod tests {
         let data: &mut FFIContext = &mut *(context as *mut FFIContext);
         if let Some(list) = data.cache.mn_lists.get(&h) {
             println!("get_masternode_list_by_block_hash_from_cache: {}: masternodes: {} quorums: {} mn_merkle_root: {:?}, llmq_merkle_root: {:?}", h, list.masternodes.len(), list.quorums.len(), list.masternode_merkle_root, list.llmq_merkle_root);
-            let encoded = list.encode();
+            let encoded = list.to_list().encode();
             // &encoded as *const types::MasternodeList
             boxed(encoded)
         } else {
@@ -429,7 +351,7 @@ pub mod tests {
         let masternode_list = *masternode_list;
         let masternode_list_decoded = masternode_list.decode();
         println!("masternode_list_save_in_cache: {}", h);
-        data.cache.mn_lists.insert(h, masternode_list_decoded);
+        data.cache.add_masternode_list(h, Arc::new(SharedMasternodeList::from_list(&masternode_list_decoded)));
         true
     }
 
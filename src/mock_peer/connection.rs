@@ -0,0 +1,161 @@
+use crate::codec::network::{GETMNLISTDIFF_COMMAND, GETQRINFO_COMMAND, MNLISTDIFF_COMMAND, QRINFO_COMMAND};
+use crate::codec::{encode_network_message, read_bytes, read_var_int, write_var_int, BytesEncodable, NetworkMessage};
+use crate::processing::SyncRequest;
+use byte::{BytesExt, LE};
+use dash_spv_primitives::crypto::byte_util::BytesDecodable;
+use dash_spv_primitives::crypto::UInt256;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const VERSION_COMMAND: &str = "version";
+pub const VERACK_COMMAND: &str = "verack";
+pub const PING_COMMAND: &str = "ping";
+pub const PONG_COMMAND: &str = "pong";
+
+/// Protocol version which has 'getqrinfo' (DIP-0024)
+pub const PROTOCOL_VERSION: i32 = 70227;
+const NODE_NETWORK: u64 = 1;
+
+/// 'version' message as Dash Core sends it, addresses are written empty
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VersionMessage {
+    pub version: i32,
+    pub services: u64,
+    pub timestamp: i64,
+    pub nonce: u64,
+    pub user_agent: String,
+    pub start_height: i32,
+    pub relay: bool,
+    pub mnauth_challenge: UInt256,
+}
+
+impl VersionMessage {
+    pub fn new(user_agent: &str, start_height: i32) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs() as i64);
+        Self {
+            version: PROTOCOL_VERSION,
+            services: NODE_NETWORK,
+            timestamp,
+            nonce: timestamp as u64,
+            user_agent: user_agent.to_string(),
+            start_height,
+            relay: false,
+            mnauth_challenge: UInt256::MIN,
+        }
+    }
+
+    /// Fields after the start height are optional for older peers
+    pub fn from_bytes(bytes: &[u8], offset: &mut usize) -> Option<Self> {
+        let version = bytes.read_with::<i32>(offset, LE).ok()?;
+        let services = bytes.read_with::<u64>(offset, LE).ok()?;
+        let timestamp = bytes.read_with::<i64>(offset, LE).ok()?;
+        // Receiver and sender: services, IPv6 address and port
+        read_bytes(bytes, offset, 2 * 26)?;
+        let nonce = bytes.read_with::<u64>(offset, LE).ok()?;
+        let user_agent_length = read_var_int(bytes, offset)?;
+        let user_agent = String::from_utf8_lossy(read_bytes(bytes, offset, user_agent_length)?).into_owned();
+        let start_height = bytes.read_with::<i32>(offset, LE).ok()?;
+        let relay = bytes.read_with::<bool>(offset, ()).unwrap_or(true);
+        let mnauth_challenge = UInt256::from_bytes(bytes, offset).unwrap_or(UInt256::MIN);
+        Some(Self { version, services, timestamp, nonce, user_agent, start_height, relay, mnauth_challenge })
+    }
+}
+
+impl BytesEncodable for VersionMessage {
+    fn encode_to(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(&self.version.to_le_bytes());
+        buffer.extend_from_slice(&self.services.to_le_bytes());
+        buffer.extend_from_slice(&self.timestamp.to_le_bytes());
+        buffer.extend_from_slice(&[0u8; 2 * 26]);
+        buffer.extend_from_slice(&self.nonce.to_le_bytes());
+        write_var_int(self.user_agent.len(), buffer);
+        buffer.extend_from_slice(self.user_agent.as_bytes());
+        buffer.extend_from_slice(&self.start_height.to_le_bytes());
+        buffer.push(u8::from(self.relay));
+        buffer.extend_from_slice(&self.mnauth_challenge.0);
+    }
+}
+
+fn invalid_data<E: ToString>(error: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error.to_string())
+}
+
+/// Framed P2P connection: whole messages are sent and received
+/// with the envelope checked for the network given by magic
+#[derive(Debug)]
+pub struct PeerConnection {
+    stream: TcpStream,
+    magic: u32,
+    buffer: Vec<u8>,
+}
+
+impl PeerConnection {
+    pub fn new(stream: TcpStream, magic: u32) -> Self {
+        Self { stream, magic, buffer: vec![] }
+    }
+
+    pub fn connect(address: SocketAddr, magic: u32) -> io::Result<Self> {
+        TcpStream::connect(address).map(|stream| Self::new(stream, magic))
+    }
+
+    pub fn stream(&self) -> &TcpStream {
+        &self.stream
+    }
+
+    pub fn send(&mut self, command: &str, payload: &[u8]) -> io::Result<()> {
+        self.stream.write_all(&encode_network_message(self.magic, command, payload))
+    }
+
+    /// Blocks until the whole message is read (or the read timeout of the stream elapses)
+    pub fn receive(&mut self) -> io::Result<(String, Vec<u8>)> {
+        loop {
+            if let Some((message, size)) = NetworkMessage::from_bytes(&self.buffer, self.magic).map_err(invalid_data)? {
+                let received = (message.command, message.payload.to_vec());
+                self.buffer.drain(..size);
+                return Ok(received);
+            }
+            let mut chunk = [0u8; 8192];
+            match self.stream.read(&mut chunk)? {
+                0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+                read => self.buffer.extend_from_slice(&chunk[..read]),
+            }
+        }
+    }
+
+    /// Client side of the handshake, returns the version of the peer
+    pub fn handshake(&mut self, version: &VersionMessage) -> io::Result<VersionMessage> {
+        self.send(VERSION_COMMAND, &version.to_bytes())?;
+        let (mut peer_version, mut has_verack) = (None, false);
+        while peer_version.is_none() || !has_verack {
+            match self.receive()? {
+                (command, payload) if command == VERSION_COMMAND => {
+                    peer_version = Some(VersionMessage::from_bytes(&payload, &mut 0).ok_or_else(|| invalid_data("invalid 'version' payload"))?);
+                    self.send(VERACK_COMMAND, &[])?;
+                }
+                (command, _) if command == VERACK_COMMAND => has_verack = true,
+                _ => {}
+            }
+        }
+        Ok(peer_version.unwrap())
+    }
+
+    /// Sends the request of the plan and waits for its response payload,
+    /// messages of other commands are skipped
+    pub fn request(&mut self, request: &SyncRequest) -> io::Result<Vec<u8>> {
+        let (command, payload, response_command) = match request {
+            SyncRequest::ListDiff { request, .. } => (GETMNLISTDIFF_COMMAND, request.to_bytes(), MNLISTDIFF_COMMAND),
+            SyncRequest::QRInfo { request, .. } => (GETQRINFO_COMMAND, request.to_bytes(), QRINFO_COMMAND),
+        };
+        self.send(command, &payload)?;
+        loop {
+            match self.receive()? {
+                (command, payload) if command == response_command => return Ok(payload),
+                (command, payload) if command == PING_COMMAND => self.send(PONG_COMMAND, &payload)?,
+                _ => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,5 @@
+pub mod connection;
+pub mod peer;
+
+pub use self::connection::{PeerConnection, VersionMessage};
+pub use self::peer::{MockPeer, PeerFixtures, PeerRequest};
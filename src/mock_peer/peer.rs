@@ -0,0 +1,194 @@
+use crate::codec::network::{GETMNLISTDIFF_COMMAND, GETQRINFO_COMMAND, MNLISTDIFF_COMMAND, QRINFO_COMMAND};
+use crate::codec::{BytesEncodable, GetMNListDiff, GetQRInfo, ListDiffMessage, QRInfoMessage};
+use crate::mock_peer::connection::{PeerConnection, VersionMessage, PING_COMMAND, PONG_COMMAND, VERACK_COMMAND, VERSION_COMMAND};
+use dash_spv_primitives::crypto::UInt256;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+pub const MOCK_PEER_USER_AGENT: &str = "/mock-peer:0.1.0/";
+
+/// Request received by the peer and whether there was a fixture to serve it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PeerRequest {
+    pub command: String,
+    pub block_hash: UInt256,
+    pub served: bool,
+}
+
+/// Fixtures the peer serves: list diffs by their base and block, rotation info by its tip
+#[derive(Clone, Debug, Default)]
+pub struct PeerFixtures {
+    list_diffs: BTreeMap<(UInt256, UInt256), Vec<u8>>,
+    qr_infos: BTreeMap<UInt256, Vec<u8>>,
+    pub start_height: i32,
+}
+
+impl PeerFixtures {
+    /// Takes 'MNL_*.dat' as MNLISTDIFF and 'QRINFO_*.dat' as QRINFO payloads,
+    /// files which can't be read as their messages are skipped
+    pub fn from_dir<P: AsRef<Path>>(dir: P) -> io::Result<Self> {
+        let mut fixtures = Self::default();
+        let mut paths = fs::read_dir(dir)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<io::Result<Vec<_>>>()?;
+        paths.sort();
+        for path in paths {
+            let name = match path.file_name().and_then(|name| name.to_str()) {
+                Some(name) if name.ends_with(".dat") => name.to_string(),
+                _ => continue,
+            };
+            if name.starts_with("MNL_") {
+                fixtures.add_list_diff(fs::read(&path)?);
+            } else if name.starts_with("QRINFO_") {
+                fixtures.add_qr_info(fs::read(&path)?);
+            }
+        }
+        Ok(fixtures)
+    }
+
+    pub fn add_list_diff(&mut self, payload: Vec<u8>) -> bool {
+        match ListDiffMessage::from_bytes(&payload, &mut 0, |_| 0) {
+            Some(message) => {
+                self.list_diffs.insert((message.diff.base_block_hash, message.diff.block_hash), payload);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn add_qr_info(&mut self, payload: Vec<u8>) -> bool {
+        match QRInfoMessage::from_bytes(&payload, &mut 0, |_| 0) {
+            Some(message) => {
+                self.qr_infos.insert(message.diff_tip.diff.block_hash, payload);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn list_diff(&self, request: &GetMNListDiff) -> Option<&Vec<u8>> {
+        self.list_diffs.get(&(request.base_block_hash, request.block_hash))
+    }
+
+    /// Bases aren't matched: fixtures are made for the bases the client had at the time
+    pub fn qr_info(&self, request: &GetQRInfo) -> Option<&Vec<u8>> {
+        self.qr_infos.get(&request.block_request_hash)
+    }
+
+    pub fn len(&self) -> usize {
+        self.list_diffs.len() + self.qr_infos.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Dash peer on the loopback which completes the version handshake and serves
+/// 'getmnlistdiff'/'getqrinfo' from the fixtures, so the whole
+/// request-response-process loop runs without the network.
+/// Requests without a fixture get no response, like Dash Core does for unknown blocks
+pub struct MockPeer {
+    address: SocketAddr,
+    requests: Arc<Mutex<Vec<PeerRequest>>>,
+    is_stopped: Arc<AtomicBool>,
+    listener: Option<JoinHandle<()>>,
+}
+
+impl MockPeer {
+    pub fn start(fixtures: PeerFixtures, magic: u32) -> io::Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", 0))?;
+        let address = listener.local_addr()?;
+        let fixtures = Arc::new(fixtures);
+        let requests = Arc::new(Mutex::new(Vec::<PeerRequest>::new()));
+        let is_stopped = Arc::new(AtomicBool::new(false));
+        let (requests_clone, is_stopped_clone) = (Arc::clone(&requests), Arc::clone(&is_stopped));
+        let listener = thread::spawn(move || {
+            for stream in listener.incoming() {
+                if is_stopped_clone.load(Ordering::SeqCst) {
+                    break;
+                }
+                if let Ok(stream) = stream {
+                    let fixtures = Arc::clone(&fixtures);
+                    let requests = Arc::clone(&requests_clone);
+                    thread::spawn(move || serve(PeerConnection::new(stream, magic), &fixtures, &requests));
+                }
+            }
+        });
+        Ok(Self { address, requests, is_stopped, listener: Some(listener) })
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    pub fn requests(&self) -> Vec<PeerRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Stops accepting connections, the ones already opened are served until the client closes them
+    pub fn stop(&mut self) {
+        if let Some(listener) = self.listener.take() {
+            self.is_stopped.store(true, Ordering::SeqCst);
+            // Wakes the listener up, so it sees the flag
+            let _ = TcpStream::connect(self.address);
+            let _ = listener.join();
+        }
+    }
+}
+
+impl Drop for MockPeer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Serves the connection until it's closed or sends a message of another network
+fn serve(mut connection: PeerConnection, fixtures: &PeerFixtures, requests: &Mutex<Vec<PeerRequest>>) {
+    let mut has_verack = false;
+    while let Ok((command, payload)) = connection.receive() {
+        let sent = match command.as_str() {
+            VERSION_COMMAND => connection
+                .send(VERSION_COMMAND, &VersionMessage::new(MOCK_PEER_USER_AGENT, fixtures.start_height).to_bytes())
+                .and_then(|_| connection.send(VERACK_COMMAND, &[])),
+            VERACK_COMMAND => {
+                has_verack = true;
+                Ok(())
+            }
+            PING_COMMAND => connection.send(PONG_COMMAND, &payload),
+            // Requests aren't served before the handshake
+            _ if !has_verack => Ok(()),
+            GETMNLISTDIFF_COMMAND => match GetMNListDiff::from_bytes(&payload, &mut 0) {
+                Some(request) => {
+                    let response = fixtures.list_diff(&request);
+                    record(requests, &command, request.block_hash, response.is_some());
+                    response.map_or(Ok(()), |response| connection.send(MNLISTDIFF_COMMAND, response))
+                }
+                None => break,
+            },
+            GETQRINFO_COMMAND => match GetQRInfo::from_bytes(&payload, &mut 0) {
+                Some(request) => {
+                    let response = fixtures.qr_info(&request);
+                    record(requests, &command, request.block_request_hash, response.is_some());
+                    response.map_or(Ok(()), |response| connection.send(QRINFO_COMMAND, response))
+                }
+                None => break,
+            },
+            _ => Ok(()),
+        };
+        if sent.is_err() {
+            break;
+        }
+    }
+    let _ = connection.stream().shutdown(Shutdown::Both);
+}
+
+fn record(requests: &Mutex<Vec<PeerRequest>>, command: &str, block_hash: UInt256, served: bool) {
+    requests.lock().unwrap().push(PeerRequest { command: command.to_string(), block_hash, served });
+}
@@ -0,0 +1,111 @@
+use crate::processing::{MNListDiffResult, MasternodeProcessorCache, ProcessingError, ProcessingNeeds, PullMessage, PullProcessor, PullResult, PullStep, QRInfoResult, QuorumValidationData, SessionChain};
+use dash_spv_models::llmq::LLMQSnapshot;
+use dash_spv_models::masternode::MasternodeList;
+use dash_spv_primitives::crypto::UInt256;
+use std::future::Future;
+
+/// Lookups of the host awaited by the processing, None means the host doesn't have it.
+/// Implementations may use 'async fn' for these methods
+pub trait AsyncProcessorLookup {
+    fn block_height(&self, block_hash: UInt256) -> impl Future<Output = Option<u32>> + Send;
+    fn block_hash(&self, block_height: u32) -> impl Future<Output = Option<UInt256>> + Send;
+    fn merkle_root(&self, block_hash: UInt256) -> impl Future<Output = Option<UInt256>> + Send;
+    /// Verification of the quorum signatures (BLS)
+    fn validate_quorum(&self, data: QuorumValidationData) -> impl Future<Output = bool> + Send;
+
+    /// Lists are kept by the processor cache, so only stored ones are asked for
+    fn masternode_list(&self, _block_hash: UInt256) -> impl Future<Output = Option<MasternodeList>> + Send {
+        async { None }
+    }
+
+    fn snapshot(&self, _block_hash: UInt256) -> impl Future<Output = Option<LLMQSnapshot>> + Send {
+        async { None }
+    }
+}
+
+/// Async processing on top of 'PullProcessor': its needs are awaited from the lookup
+/// and the processing is resumed, so executor threads are never blocked by the lookups
+#[derive(Debug)]
+pub struct AsyncProcessor<L: AsyncProcessorLookup> {
+    processor: PullProcessor,
+    lookup: L,
+}
+
+impl<L: AsyncProcessorLookup> AsyncProcessor<L> {
+    pub fn new(processor: PullProcessor, lookup: L) -> Self {
+        Self { processor, lookup }
+    }
+
+    pub fn for_chain(chain: &SessionChain, lookup: L) -> Self {
+        Self::new(PullProcessor::for_chain(chain), lookup)
+    }
+
+    pub fn lookup(&self) -> &L {
+        &self.lookup
+    }
+
+    pub fn cache(&self) -> &MasternodeProcessorCache {
+        self.processor.cache()
+    }
+
+    pub fn cache_mut(&mut self) -> &mut MasternodeProcessorCache {
+        self.processor.cache_mut()
+    }
+
+    pub async fn process_mnlistdiff(&mut self, message: &[u8]) -> MNListDiffResult {
+        match self.process(PullMessage::ListDiff(message.to_vec())).await {
+            PullResult::ListDiff(result) => result,
+            PullResult::QRInfo(_) => MNListDiffResult::default_with_error(ProcessingError::ParseError),
+        }
+    }
+
+    pub async fn process_qrinfo(&mut self, message: &[u8]) -> QRInfoResult {
+        match self.process(PullMessage::QRInfo(message.to_vec())).await {
+            PullResult::QRInfo(result) => result,
+            PullResult::ListDiff(_) => QRInfoResult::default_with_error(ProcessingError::ParseError),
+        }
+    }
+
+    async fn process(&mut self, message: PullMessage) -> PullResult {
+        let mut step = self.processor.start(message);
+        loop {
+            match step {
+                PullStep::Done(result) => return result,
+                PullStep::Needs(needs) => {
+                    self.supply(needs).await;
+                    step = match self.processor.resume() {
+                        Some(step) => step,
+                        None => return PullResult::ListDiff(MNListDiffResult::default_with_error(ProcessingError::ParseError)),
+                    };
+                }
+            }
+        }
+    }
+
+    async fn supply(&mut self, needs: ProcessingNeeds) {
+        for block_hash in needs.block_heights {
+            let block_height = self.lookup.block_height(block_hash).await;
+            self.processor.supply_block_height(block_hash, block_height);
+        }
+        for block_height in needs.block_hashes {
+            let block_hash = self.lookup.block_hash(block_height).await;
+            self.processor.supply_block_hash(block_height, block_hash);
+        }
+        for block_hash in needs.merkle_roots {
+            let merkle_root = self.lookup.merkle_root(block_hash).await;
+            self.processor.supply_merkle_root(block_hash, merkle_root);
+        }
+        for block_hash in needs.masternode_lists {
+            let list = self.lookup.masternode_list(block_hash).await;
+            self.processor.supply_masternode_list(block_hash, list);
+        }
+        for block_hash in needs.snapshots {
+            let snapshot = self.lookup.snapshot(block_hash).await;
+            self.processor.supply_snapshot(block_hash, snapshot);
+        }
+        for (commitment_hash, data) in needs.quorum_validations {
+            let is_valid = self.lookup.validate_quorum(data).await;
+            self.processor.supply_quorum_validation(commitment_hash, is_valid);
+        }
+    }
+}
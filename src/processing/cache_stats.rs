@@ -0,0 +1,201 @@
+use crate::processing::{MasternodeProcessorCache, SharedMasternodeList};
+use dash_spv_ffi::ffi::boxer::{boxed, boxed_vec};
+use dash_spv_ffi::ffi::unboxer::{unbox_any, unbox_vec_ptr};
+use dash_spv_models::common::LLMQType;
+use dash_spv_models::llmq::LLMQSnapshot;
+use dash_spv_models::masternode::{LLMQEntry, MasternodeEntry};
+use dash_spv_primitives::crypto::UInt256;
+use std::collections::{BTreeMap, HashSet};
+use std::mem::size_of;
+use std::sync::Arc;
+
+/// What the cache holds, to display the sync state and to decide when to prune
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MasternodeProcessorCacheStats {
+    /// Known heights of the cached lists in ascending order
+    pub mn_list_heights: Vec<u32>,
+    pub llmq_snapshots_count: usize,
+    /// Count of the computed member sets per LLMQ type
+    pub llmq_members: BTreeMap<LLMQType, usize>,
+    pub llmq_indexed_members: BTreeMap<LLMQType, usize>,
+    pub merkle_trees_count: usize,
+    pub rotated_quorums_count: usize,
+    pub needed_masternode_lists: Vec<UInt256>,
+    /// Rough estimate in bytes: sizes of the entries plus their heap-allocated bitsets
+    /// and the merkle trees, without the overhead of the maps.
+    /// Masternodes shared by the lists and tree chunks shared by the trees are counted once
+    pub approximate_memory_usage: usize,
+}
+
+impl MasternodeProcessorCacheStats {
+    pub fn mn_lists_count(&self) -> usize {
+        self.mn_list_heights.len()
+    }
+
+    pub fn lowest_mn_list_height(&self) -> Option<u32> {
+        self.mn_list_heights.first().copied()
+    }
+
+    pub fn highest_mn_list_height(&self) -> Option<u32> {
+        self.mn_list_heights.last().copied()
+    }
+}
+
+fn quorum_memory_usage(entry: &LLMQEntry) -> usize {
+    size_of::<LLMQEntry>() + entry.signers_bitset.len() + entry.valid_members_bitset.len()
+}
+
+fn list_memory_usage(list: &SharedMasternodeList, counted: &mut HashSet<*const MasternodeEntry>) -> usize {
+    size_of::<SharedMasternodeList>()
+        + list.masternodes.len() * (size_of::<UInt256>() + size_of::<Arc<MasternodeEntry>>())
+        + list
+            .masternodes
+            .values()
+            .filter(|entry| counted.insert(Arc::as_ptr(entry)))
+            .count()
+            * size_of::<MasternodeEntry>()
+        + list
+            .quorums
+            .values()
+            .flat_map(|quorums_of_type| quorums_of_type.values())
+            .map(|entry| size_of::<UInt256>() + quorum_memory_usage(entry))
+            .sum::<usize>()
+}
+
+fn snapshot_memory_usage(snapshot: &LLMQSnapshot) -> usize {
+    size_of::<LLMQSnapshot>()
+        + snapshot.member_list.len()
+        + snapshot.skip_list.len() * size_of::<i32>()
+}
+
+fn members_memory_usage<K>(members: &BTreeMap<LLMQType, BTreeMap<K, Vec<MasternodeEntry>>>) -> usize {
+    members
+        .values()
+        .flat_map(|members_of_type| members_of_type.values())
+        .map(|entries| size_of::<K>() + entries.len() * size_of::<MasternodeEntry>())
+        .sum()
+}
+
+fn count_per_type<K, V>(map: &BTreeMap<LLMQType, BTreeMap<K, V>>) -> BTreeMap<LLMQType, usize> {
+    map.iter()
+        .map(|(llmq_type, map_of_type)| (*llmq_type, map_of_type.len()))
+        .collect()
+}
+
+impl MasternodeProcessorCache {
+    pub fn stats(&self) -> MasternodeProcessorCacheStats {
+        let mut mn_list_heights = self
+            .mn_lists
+            .values()
+            .map(|list| list.known_height)
+            .collect::<Vec<u32>>();
+        mn_list_heights.sort_unstable();
+        let mut counted_entries = HashSet::new();
+        let mut counted_chunks = HashSet::new();
+        let approximate_memory_usage = self
+            .mn_lists
+            .values()
+            .map(|list| list_memory_usage(list, &mut counted_entries))
+            .sum::<usize>()
+            + self
+                .llmq_snapshots
+                .values()
+                .map(|snapshot| size_of::<UInt256>() + snapshot_memory_usage(snapshot))
+                .sum::<usize>()
+            + members_memory_usage(&self.llmq_members)
+            + members_memory_usage(&self.llmq_indexed_members)
+            + self
+                .rotated_quorums
+                .iter()
+                .map(|(_, entry)| quorum_memory_usage(entry))
+                .sum::<usize>()
+            + self
+                .merkle_trees
+                .values()
+                .map(|trees| size_of::<UInt256>() + trees.memory_usage(&mut counted_chunks))
+                .sum::<usize>()
+            + self.needed_masternode_lists.len() * size_of::<UInt256>();
+        MasternodeProcessorCacheStats {
+            mn_list_heights,
+            llmq_snapshots_count: self.llmq_snapshots.len(),
+            llmq_members: count_per_type(&self.llmq_members),
+            llmq_indexed_members: count_per_type(&self.llmq_indexed_members),
+            merkle_trees_count: self.merkle_trees.len(),
+            rotated_quorums_count: self.rotated_quorums.len(),
+            needed_masternode_lists: self.needed_masternode_lists.clone(),
+            approximate_memory_usage,
+        }
+    }
+}
+
+/// FFI representation of 'MasternodeProcessorCacheStats',
+/// member sets per type are given as parallel arrays of types and counts
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct CacheStats {
+    pub mn_lists_count: usize,
+    pub mn_list_heights: *mut u32,
+    pub llmq_snapshots_count: usize,
+    pub llmq_members_count: usize,
+    pub llmq_members_types: *mut u8,
+    pub llmq_members_counts: *mut usize,
+    pub llmq_indexed_members_count: usize,
+    pub llmq_indexed_members_types: *mut u8,
+    pub llmq_indexed_members_counts: *mut usize,
+    pub merkle_trees_count: usize,
+    pub rotated_quorums_count: usize,
+    pub needed_masternode_lists_count: usize,
+    pub needed_masternode_lists: *mut *mut [u8; 32],
+    pub approximate_memory_usage: usize,
+}
+
+fn encode_count_per_type(counts: &BTreeMap<LLMQType, usize>) -> (usize, *mut u8, *mut usize) {
+    let types = counts.keys().map(|&llmq_type| llmq_type.into()).collect::<Vec<u8>>();
+    let counts = counts.values().copied().collect::<Vec<usize>>();
+    (types.len(), boxed_vec(types), boxed_vec(counts))
+}
+
+impl MasternodeProcessorCacheStats {
+    pub fn encode(&self) -> CacheStats {
+        let (llmq_members_count, llmq_members_types, llmq_members_counts) =
+            encode_count_per_type(&self.llmq_members);
+        let (llmq_indexed_members_count, llmq_indexed_members_types, llmq_indexed_members_counts) =
+            encode_count_per_type(&self.llmq_indexed_members);
+        CacheStats {
+            mn_lists_count: self.mn_lists_count(),
+            mn_list_heights: boxed_vec(self.mn_list_heights.clone()),
+            llmq_snapshots_count: self.llmq_snapshots_count,
+            llmq_members_count,
+            llmq_members_types,
+            llmq_members_counts,
+            llmq_indexed_members_count,
+            llmq_indexed_members_types,
+            llmq_indexed_members_counts,
+            merkle_trees_count: self.merkle_trees_count,
+            rotated_quorums_count: self.rotated_quorums_count,
+            needed_masternode_lists_count: self.needed_masternode_lists.len(),
+            needed_masternode_lists: boxed_vec(
+                self.needed_masternode_lists
+                    .iter()
+                    .map(|hash| boxed(hash.0))
+                    .collect(),
+            ),
+            approximate_memory_usage: self.approximate_memory_usage,
+        }
+    }
+}
+
+impl CacheStats {
+    pub unsafe fn destroy(self) {
+        unbox_vec_ptr(self.mn_list_heights, self.mn_lists_count);
+        unbox_vec_ptr(self.llmq_members_types, self.llmq_members_count);
+        unbox_vec_ptr(self.llmq_members_counts, self.llmq_members_count);
+        unbox_vec_ptr(self.llmq_indexed_members_types, self.llmq_indexed_members_count);
+        unbox_vec_ptr(self.llmq_indexed_members_counts, self.llmq_indexed_members_count);
+        unbox_vec_ptr(self.needed_masternode_lists, self.needed_masternode_lists_count)
+            .into_iter()
+            .for_each(|hash| {
+                unbox_any(hash);
+            });
+    }
+}
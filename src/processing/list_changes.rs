@@ -0,0 +1,201 @@
+use dash_spv_models::common::LLMQType;
+use dash_spv_models::masternode::{LLMQEntry, MasternodeEntry, MasternodeList};
+use dash_spv_primitives::crypto::UInt256;
+use std::collections::BTreeMap;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MasternodeField {
+    ConfirmedHash,
+    SocketAddress,
+    OperatorPublicKey,
+    KeyIdVoting,
+    IsValid,
+    UpdateHeight,
+    /// Stored hash of the entry, it differs alone when the entry is corrupted
+    EntryHash,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum QuorumField {
+    Version,
+    Index,
+    Signers,
+    ValidMembers,
+    PublicKey,
+    VerificationVectorHash,
+    ThresholdSignature,
+    AllCommitmentAggregatedSignature,
+    EntryHash,
+}
+
+/// Entry present in both lists along with the fields it differs in
+#[derive(Clone, Debug)]
+pub struct MasternodeChange {
+    pub old: MasternodeEntry,
+    pub new: MasternodeEntry,
+    pub fields: Vec<MasternodeField>,
+}
+
+#[derive(Clone, Debug)]
+pub struct QuorumChange {
+    pub old: LLMQEntry,
+    pub new: LLMQEntry,
+    pub fields: Vec<QuorumField>,
+}
+
+/// Difference between two arbitrary lists (not necessarily adjacent ones):
+/// what should be applied to the old list to get the new one
+#[derive(Clone, Debug, Default)]
+pub struct MasternodeListChanges {
+    pub old_block_hash: UInt256,
+    pub new_block_hash: UInt256,
+    pub added_masternodes: BTreeMap<UInt256, MasternodeEntry>,
+    pub removed_masternodes: BTreeMap<UInt256, MasternodeEntry>,
+    pub modified_masternodes: BTreeMap<UInt256, MasternodeChange>,
+    pub added_quorums: BTreeMap<LLMQType, BTreeMap<UInt256, LLMQEntry>>,
+    pub removed_quorums: BTreeMap<LLMQType, BTreeMap<UInt256, LLMQEntry>>,
+    pub modified_quorums: BTreeMap<LLMQType, BTreeMap<UInt256, QuorumChange>>,
+}
+
+impl MasternodeListChanges {
+    pub fn is_empty(&self) -> bool {
+        self.added_masternodes.is_empty()
+            && self.removed_masternodes.is_empty()
+            && self.modified_masternodes.is_empty()
+            && self.added_quorums.is_empty()
+            && self.removed_quorums.is_empty()
+            && self.modified_quorums.is_empty()
+    }
+}
+
+pub fn masternode_changed_fields(old: &MasternodeEntry, new: &MasternodeEntry) -> Vec<MasternodeField> {
+    let mut fields = Vec::<MasternodeField>::new();
+    if old.confirmed_hash != new.confirmed_hash {
+        fields.push(MasternodeField::ConfirmedHash);
+    }
+    if old.socket_address.ip_address != new.socket_address.ip_address
+        || old.socket_address.port != new.socket_address.port
+    {
+        fields.push(MasternodeField::SocketAddress);
+    }
+    if old.operator_public_key != new.operator_public_key {
+        fields.push(MasternodeField::OperatorPublicKey);
+    }
+    if old.key_id_voting != new.key_id_voting {
+        fields.push(MasternodeField::KeyIdVoting);
+    }
+    if old.is_valid != new.is_valid {
+        fields.push(MasternodeField::IsValid);
+    }
+    if old.update_height != new.update_height {
+        fields.push(MasternodeField::UpdateHeight);
+    }
+    if old.entry_hash != new.entry_hash {
+        fields.push(MasternodeField::EntryHash);
+    }
+    fields
+}
+
+pub fn quorum_changed_fields(old: &LLMQEntry, new: &LLMQEntry) -> Vec<QuorumField> {
+    let mut fields = Vec::<QuorumField>::new();
+    if old.version != new.version {
+        fields.push(QuorumField::Version);
+    }
+    if old.index != new.index {
+        fields.push(QuorumField::Index);
+    }
+    if old.signers_count.0 != new.signers_count.0 || old.signers_bitset != new.signers_bitset {
+        fields.push(QuorumField::Signers);
+    }
+    if old.valid_members_count.0 != new.valid_members_count.0
+        || old.valid_members_bitset != new.valid_members_bitset
+    {
+        fields.push(QuorumField::ValidMembers);
+    }
+    if old.public_key != new.public_key {
+        fields.push(QuorumField::PublicKey);
+    }
+    if old.verification_vector_hash != new.verification_vector_hash {
+        fields.push(QuorumField::VerificationVectorHash);
+    }
+    if old.threshold_signature != new.threshold_signature {
+        fields.push(QuorumField::ThresholdSignature);
+    }
+    if old.all_commitment_aggregated_signature != new.all_commitment_aggregated_signature {
+        fields.push(QuorumField::AllCommitmentAggregatedSignature);
+    }
+    if old.entry_hash != new.entry_hash {
+        fields.push(QuorumField::EntryHash);
+    }
+    fields
+}
+
+/// Splits the entries of two maps into added, removed and modified ones,
+/// entries with no changed fields are left out
+fn compare_maps<V: Clone, C, F: Fn(&V, &V) -> Option<C>>(
+    old: &BTreeMap<UInt256, V>,
+    new: &BTreeMap<UInt256, V>,
+    compare: F,
+) -> (BTreeMap<UInt256, V>, BTreeMap<UInt256, V>, BTreeMap<UInt256, C>) {
+    let added = new
+        .iter()
+        .filter(|(hash, _)| !old.contains_key(hash))
+        .map(|(hash, entry)| (*hash, entry.clone()))
+        .collect();
+    let removed = old
+        .iter()
+        .filter(|(hash, _)| !new.contains_key(hash))
+        .map(|(hash, entry)| (*hash, entry.clone()))
+        .collect();
+    let modified = old
+        .iter()
+        .filter_map(|(hash, old_entry)| {
+            new.get(hash)
+                .and_then(|new_entry| compare(old_entry, new_entry))
+                .map(|change| (*hash, change))
+        })
+        .collect();
+    (added, removed, modified)
+}
+
+fn insert_non_empty<V>(map: &mut BTreeMap<LLMQType, BTreeMap<UInt256, V>>, llmq_type: LLMQType, entries: BTreeMap<UInt256, V>) {
+    if !entries.is_empty() {
+        map.insert(llmq_type, entries);
+    }
+}
+
+/// Compares the lists entry by entry, masternodes are matched by their keys in the list,
+/// quorums by type and quorum hash
+pub fn compare_masternode_lists(old: &MasternodeList, new: &MasternodeList) -> MasternodeListChanges {
+    let (added_masternodes, removed_masternodes, modified_masternodes) =
+        compare_maps(&old.masternodes, &new.masternodes, |old, new| {
+            let fields = masternode_changed_fields(old, new);
+            (!fields.is_empty()).then(|| MasternodeChange { old: old.clone(), new: new.clone(), fields })
+        });
+    let mut changes = MasternodeListChanges {
+        old_block_hash: old.block_hash,
+        new_block_hash: new.block_hash,
+        added_masternodes,
+        removed_masternodes,
+        modified_masternodes,
+        ..Default::default()
+    };
+    let empty = BTreeMap::<UInt256, LLMQEntry>::new();
+    let mut llmq_types = old.quorums.keys().chain(new.quorums.keys()).copied().collect::<Vec<LLMQType>>();
+    llmq_types.sort();
+    llmq_types.dedup();
+    for llmq_type in llmq_types {
+        let (added, removed, modified) = compare_maps(
+            old.quorums.get(&llmq_type).unwrap_or(&empty),
+            new.quorums.get(&llmq_type).unwrap_or(&empty),
+            |old, new| {
+                let fields = quorum_changed_fields(old, new);
+                (!fields.is_empty()).then(|| QuorumChange { old: old.clone(), new: new.clone(), fields })
+            },
+        );
+        insert_non_empty(&mut changes.added_quorums, llmq_type, added);
+        insert_non_empty(&mut changes.removed_quorums, llmq_type, removed);
+        insert_non_empty(&mut changes.modified_quorums, llmq_type, modified);
+    }
+    changes
+}
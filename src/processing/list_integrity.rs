@@ -0,0 +1,101 @@
+use crate::codec::{read_llmq_entry, read_masternode_entry, BytesEncodable};
+use crate::processing::{MasternodeListMerkleTrees, SharedMasternodeList};
+use crate::store::MasternodeCheckpoint;
+use dash_spv_models::masternode::{LLMQEntry, MasternodeEntry};
+use dash_spv_primitives::crypto::UInt256;
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ListIntegrityError {
+    /// List is stored under the hash of another block
+    BlockHashMismatch,
+    /// List has no roots to be verified against
+    MissingMerkleRoots,
+    /// Stored entry hash doesn't match the entry
+    EntryHashMismatch,
+    MasternodeMerkleRootMismatch,
+    LLMQMerkleRootMismatch,
+    /// Roots of the list aren't the ones the checkpoint is committed to
+    CheckpointMismatch,
+}
+
+impl fmt::Display for ListIntegrityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ListIntegrityError::BlockHashMismatch => write!(f, "list is stored for another block"),
+            ListIntegrityError::MissingMerkleRoots => write!(f, "list has no merkle roots"),
+            ListIntegrityError::EntryHashMismatch => write!(f, "entry hash doesn't match the entry"),
+            ListIntegrityError::MasternodeMerkleRootMismatch => write!(f, "masternode merkle root mismatch"),
+            ListIntegrityError::LLMQMerkleRootMismatch => write!(f, "quorum merkle root mismatch"),
+            ListIntegrityError::CheckpointMismatch => write!(f, "list doesn't match the checkpoint"),
+        }
+    }
+}
+
+impl std::error::Error for ListIntegrityError {}
+
+/// Entry hash calculated from the fields, regardless of the stored one
+fn masternode_entry_hash(entry: &MasternodeEntry) -> Option<UInt256> {
+    let mut buffer = Vec::<u8>::new();
+    entry.encode_to(&mut buffer);
+    read_masternode_entry(&buffer, &mut 0).map(|entry| entry.entry_hash)
+}
+
+fn llmq_entry_hash(entry: &LLMQEntry) -> Option<UInt256> {
+    let mut buffer = Vec::<u8>::new();
+    entry.encode_to(&mut buffer);
+    read_llmq_entry(&buffer, &mut 0).map(|entry| entry.entry_hash)
+}
+
+/// Recomputes entry hashes and merkle roots of the list and compares them
+/// with the roots stored along with the list (which are the roots from the coinbase
+/// of the block the list was validated with).
+/// Returns the recomputed trees, so they could be reused for the diffs based on the list
+pub fn verify_masternode_list(
+    block_hash: UInt256,
+    list: &SharedMasternodeList,
+) -> Result<MasternodeListMerkleTrees, ListIntegrityError> {
+    if list.block_hash != block_hash {
+        return Err(ListIntegrityError::BlockHashMismatch);
+    }
+    let has_valid_entries = list
+        .masternode_entries()
+        .all(|entry| masternode_entry_hash(entry) == Some(entry.entry_hash))
+        && list
+            .quorums
+            .values()
+            .flat_map(|quorums_of_type| quorums_of_type.values())
+            .all(|entry| llmq_entry_hash(entry) == Some(entry.entry_hash));
+    if !has_valid_entries {
+        return Err(ListIntegrityError::EntryHashMismatch);
+    }
+    let masternode_merkle_root = list
+        .masternode_merkle_root
+        .ok_or(ListIntegrityError::MissingMerkleRoots)?;
+    if list.llmq_merkle_root.is_none() && !list.quorums.is_empty() {
+        return Err(ListIntegrityError::MissingMerkleRoots);
+    }
+    let merkle_trees = MasternodeListMerkleTrees::from_shared_list(list);
+    if merkle_trees.masternode_merkle_root() != Some(masternode_merkle_root) {
+        return Err(ListIntegrityError::MasternodeMerkleRootMismatch);
+    }
+    if list.llmq_merkle_root.is_some() && merkle_trees.llmq_merkle_root() != list.llmq_merkle_root {
+        return Err(ListIntegrityError::LLMQMerkleRootMismatch);
+    }
+    Ok(merkle_trees)
+}
+
+/// Same as 'verify_masternode_list' but the roots are also checked with the checkpoint
+/// (whose integrity hash covers them) instead of being trusted as stored
+pub fn verify_masternode_list_with_checkpoint(
+    list: &SharedMasternodeList,
+    checkpoint: &MasternodeCheckpoint,
+) -> Result<MasternodeListMerkleTrees, ListIntegrityError> {
+    let merkle_trees = verify_masternode_list(checkpoint.block_hash, list)?;
+    if list.masternode_merkle_root != Some(checkpoint.masternode_merkle_root)
+        || list.llmq_merkle_root != checkpoint.llmq_merkle_root
+    {
+        return Err(ListIntegrityError::CheckpointMismatch);
+    }
+    Ok(merkle_trees)
+}
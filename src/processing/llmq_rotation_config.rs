@@ -0,0 +1,85 @@
+use dash_spv_models::common::chain_type::{ChainType, IHaveChainSettings};
+use dash_spv_models::common::LLMQType;
+use dash_spv_primitives::crypto::UInt256;
+
+/// Set of LLMQ types which are using quorum rotation (DIP-0024).
+/// Kept as a plain bitmask over llmq type ids
+/// so the processor which holds it stays representable in C
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LLMQRotationConfig {
+    mask: [u64; 4],
+}
+
+impl Default for LLMQRotationConfig {
+    /// Rotating types of all known chains. The FFI processor replaces it with the types of mainnet
+    /// or testnet once it processes with their genesis, on devnets and regtest the host must set them
+    fn default() -> Self {
+        Self::empty()
+            .with_type(LLMQType::Llmqtype60_75)
+            .with_type(LLMQType::LlmqtypeDevnetDIP0024)
+            .with_type(LLMQType::LlmqtypeTestDIP0024)
+    }
+}
+
+impl LLMQRotationConfig {
+    pub fn empty() -> Self {
+        Self { mask: [0; 4] }
+    }
+
+    /// Rotating type of the chain, chains other than mainnet may also run the quorums
+    /// of the rotating test type
+    pub fn for_chain(chain: &ChainType) -> Self {
+        let config = Self::empty().with_type(chain.isd_llmq_type());
+        match chain {
+            ChainType::MainNet => config,
+            _ => config.with_type(LLMQType::LlmqtypeTestDIP0024),
+        }
+    }
+
+    /// Rotating types of the public network with the genesis, None for devnets and regtest
+    pub fn for_genesis(genesis_hash: UInt256) -> Option<Self> {
+        [ChainType::MainNet, ChainType::TestNet]
+            .iter()
+            .find(|chain| chain.genesis_hash() == genesis_hash)
+            .map(Self::for_chain)
+    }
+
+    pub fn from_types(types: &[LLMQType]) -> Self {
+        types
+            .iter()
+            .fold(Self::empty(), |config, &llmq_type| config.with_type(llmq_type))
+    }
+
+    pub fn with_type(mut self, llmq_type: LLMQType) -> Self {
+        self.add(llmq_type);
+        self
+    }
+
+    pub fn add(&mut self, llmq_type: LLMQType) {
+        let (word, bit) = Self::position(llmq_type);
+        self.mask[word] |= 1 << bit;
+    }
+
+    pub fn remove(&mut self, llmq_type: LLMQType) {
+        let (word, bit) = Self::position(llmq_type);
+        self.mask[word] &= !(1 << bit);
+    }
+
+    pub fn is_rotating(&self, llmq_type: LLMQType) -> bool {
+        let (word, bit) = Self::position(llmq_type);
+        self.mask[word] & (1 << bit) != 0
+    }
+
+    pub fn types(&self) -> Vec<LLMQType> {
+        (0..=u8::MAX)
+            .filter(|id| self.mask[(id / 64) as usize] & (1 << (id % 64)) != 0)
+            .map(LLMQType::from)
+            .collect()
+    }
+
+    fn position(llmq_type: LLMQType) -> (usize, u32) {
+        let id: u8 = llmq_type.into();
+        ((id / 64) as usize, (id % 64) as u32)
+    }
+}
@@ -0,0 +1,30 @@
+/// Outcome of the quorum validation.
+/// 'Unknown' means the members couldn't be determined (i.e. the list or snapshot of one of
+/// the rotation cycles is missing), so the quorum is neither valid nor invalid yet
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Hash, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LLMQValidationStatus {
+    Valid = 0,
+    Invalid = 1,
+    Unknown = 2,
+    /// Type isn't processed (see 'should_process_llmq_of_type')
+    Skipped = 3,
+}
+
+impl From<u8> for LLMQValidationStatus {
+    fn from(orig: u8) -> Self {
+        match orig {
+            0 => LLMQValidationStatus::Valid,
+            1 => LLMQValidationStatus::Invalid,
+            3 => LLMQValidationStatus::Skipped,
+            _ => LLMQValidationStatus::Unknown,
+        }
+    }
+}
+
+impl From<LLMQValidationStatus> for u8 {
+    fn from(status: LLMQValidationStatus) -> Self {
+        status as u8
+    }
+}
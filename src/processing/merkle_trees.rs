@@ -0,0 +1,356 @@
+use crate::processing::shared_masternode_list::{SharedMasternodeList, SharedMasternodes};
+use dash_spv_models::common::LLMQType;
+use dash_spv_models::masternode::{LLMQEntry, MasternodeEntry, MasternodeList};
+use dash_spv_primitives::crypto::byte_util::Reversable;
+use dash_spv_primitives::crypto::UInt256;
+use dash_spv_primitives::hashes::{sha256d, Hash};
+use std::cmp::min;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::mem::size_of;
+use std::sync::Arc;
+
+const HASHES_CHUNK_SIZE: usize = 64;
+
+/// Hashes split into chunks which are shared by the clones,
+/// so a tree derived from the base one copies only the chunks its changes touch.
+/// All the chunks but the last one are full
+#[derive(Clone, Debug, Default)]
+struct SharedHashes {
+    chunks: Vec<Arc<Vec<UInt256>>>,
+    len: usize,
+}
+
+impl SharedHashes {
+    fn from_vec(hashes: Vec<UInt256>) -> Self {
+        let len = hashes.len();
+        Self {
+            chunks: hashes
+                .chunks(HASHES_CHUNK_SIZE)
+                .map(|chunk| Arc::new(chunk.to_vec()))
+                .collect(),
+            len,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn get(&self, index: usize) -> Option<&UInt256> {
+        (index < self.len).then(|| &self.chunks[index / HASHES_CHUNK_SIZE][index % HASHES_CHUNK_SIZE])
+    }
+
+    fn set(&mut self, index: usize, hash: UInt256) {
+        Arc::make_mut(&mut self.chunks[index / HASHES_CHUNK_SIZE])[index % HASHES_CHUNK_SIZE] = hash;
+    }
+
+    fn binary_search(&self, hash: &UInt256) -> Result<usize, usize> {
+        let chunk_index = self
+            .chunks
+            .partition_point(|chunk| chunk.last().map_or(false, |last| last < hash));
+        match self.chunks.get(chunk_index) {
+            Some(chunk) => chunk
+                .binary_search(hash)
+                .map(|index| chunk_index * HASHES_CHUNK_SIZE + index)
+                .map_err(|index| chunk_index * HASHES_CHUNK_SIZE + index),
+            None => Err(self.len),
+        }
+    }
+
+    /// Chunks starting from the one with the index are rebuilt, the ones before it stay shared
+    fn splice<F: FnOnce(&mut Vec<UInt256>, usize)>(&mut self, index: usize, change: F) {
+        let chunk_index = index / HASHES_CHUNK_SIZE;
+        let mut tail = self
+            .chunks
+            .split_off(chunk_index)
+            .iter()
+            .flat_map(|chunk| chunk.iter().cloned())
+            .collect::<Vec<UInt256>>();
+        change(&mut tail, index - chunk_index * HASHES_CHUNK_SIZE);
+        self.len = chunk_index * HASHES_CHUNK_SIZE + tail.len();
+        self.chunks.extend(tail.chunks(HASHES_CHUNK_SIZE).map(|chunk| Arc::new(chunk.to_vec())));
+    }
+
+    fn insert(&mut self, index: usize, hash: UInt256) {
+        self.splice(index, |tail, index| tail.insert(index, hash));
+    }
+
+    fn remove(&mut self, index: usize) {
+        self.splice(index, |tail, index| {
+            tail.remove(index);
+        });
+    }
+
+    fn resize(&mut self, len: usize, hash: UInt256) {
+        let start = min(len, self.len);
+        self.splice(start, |tail, index| tail.resize(index + len - start, hash));
+    }
+}
+
+/// Merkle tree which keeps all of its levels, so changing a few leaves
+/// only rehashes the paths from these leaves to the root.
+/// Leaves are ordered by key, odd levels duplicate their last hash (as in Core).
+/// Clones share the unchanged parts of the levels (see 'SharedHashes')
+#[derive(Clone, Debug)]
+pub struct IncrementalMerkleTree {
+    keys: SharedHashes,
+    levels: Vec<SharedHashes>,
+    dirty: BTreeSet<usize>,
+    shifted_from: Option<usize>,
+}
+
+impl Default for IncrementalMerkleTree {
+    fn default() -> Self {
+        Self {
+            keys: SharedHashes::default(),
+            levels: vec![SharedHashes::default()],
+            dirty: BTreeSet::new(),
+            shifted_from: None,
+        }
+    }
+}
+
+impl IncrementalMerkleTree {
+    pub fn from_leaves(mut leaves: Vec<(UInt256, UInt256)>) -> Self {
+        leaves.sort_by(|(k1, _), (k2, _)| k1.cmp(k2));
+        let (keys, hashes): (Vec<UInt256>, Vec<UInt256>) = leaves.into_iter().unzip();
+        let mut tree = Self {
+            keys: SharedHashes::from_vec(keys),
+            levels: vec![SharedHashes::from_vec(hashes)],
+            dirty: BTreeSet::new(),
+            shifted_from: Some(0),
+        };
+        tree.commit();
+        tree
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.len() == 0
+    }
+
+    /// Root of the tree, uncommitted changes are not taken into account
+    pub fn root(&self) -> Option<UInt256> {
+        self.levels.last().and_then(|level| level.get(0)).cloned()
+    }
+
+    pub fn leaf(&self, key: &UInt256) -> Option<UInt256> {
+        self.keys
+            .binary_search(key)
+            .ok()
+            .and_then(|index| self.levels[0].get(index).cloned())
+    }
+
+    /// Inserts new leaf or replaces the hash of existing one
+    pub fn update(&mut self, key: UInt256, hash: UInt256) {
+        match self.keys.binary_search(&key) {
+            Ok(index) => {
+                if self.levels[0].get(index) != Some(&hash) {
+                    self.levels[0].set(index, hash);
+                    self.dirty.insert(index);
+                }
+            }
+            Err(index) => {
+                self.keys.insert(index, key);
+                self.levels[0].insert(index, hash);
+                self.mark_shifted(index);
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key: &UInt256) -> bool {
+        match self.keys.binary_search(key) {
+            Ok(index) => {
+                self.keys.remove(index);
+                self.levels[0].remove(index);
+                self.mark_shifted(index);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    fn mark_shifted(&mut self, index: usize) {
+        self.shifted_from = Some(self.shifted_from.map_or(index, |from| min(from, index)));
+    }
+
+    /// Recalculates the nodes affected by the changes since last commit.
+    /// Modified leaves cost one hash per level, insertions and removals
+    /// rehash everything to the right of the changed position
+    pub fn commit(&mut self) {
+        let mut dirty = std::mem::take(&mut self.dirty);
+        let mut shifted_from = self.shifted_from.take();
+        let mut depth = 0;
+        loop {
+            let len = self.levels[depth].len();
+            if len <= 1 {
+                self.levels.truncate(depth + 1);
+                break;
+            }
+            if dirty.is_empty() && shifted_from.is_none() && self.levels.len() > depth + 1 {
+                break;
+            }
+            let parent_len = (len + 1) / 2;
+            let mut parent_shifted_from = shifted_from.map(|from| from / 2);
+            if self.levels.len() == depth + 1 {
+                self.levels.push(SharedHashes::default());
+            }
+            let old_parent_len = self.levels[depth + 1].len();
+            if old_parent_len != parent_len {
+                self.levels[depth + 1].resize(parent_len, UInt256::MIN);
+                parent_shifted_from = Some(
+                    parent_shifted_from.map_or(old_parent_len, |from| min(from, old_parent_len)),
+                );
+            }
+            let shifted = parent_shifted_from.unwrap_or(parent_len);
+            let parent_dirty: BTreeSet<usize> = dirty
+                .into_iter()
+                .map(|index| index / 2)
+                .filter(|&index| index < shifted)
+                .collect();
+            let (children, parents) = self.levels.split_at_mut(depth + 1);
+            let children = &children[depth];
+            let parents = &mut parents[0];
+            parent_dirty
+                .iter()
+                .cloned()
+                .chain(shifted..parent_len)
+                .for_each(|index| {
+                    let left = children.get(2 * index).unwrap();
+                    let right = children.get(2 * index + 1).unwrap_or(left);
+                    parents.set(index, Self::hash_pair(left, right));
+                });
+            dirty = parent_dirty;
+            shifted_from = parent_shifted_from;
+            depth += 1;
+        }
+    }
+
+    /// Size of the chunks which aren't in 'counted' yet, so the chunks shared by the trees are counted once
+    pub fn memory_usage(&self, counted: &mut HashSet<*const Vec<UInt256>>) -> usize {
+        size_of::<Self>()
+            + self.dirty.len() * size_of::<usize>()
+            + std::iter::once(&self.keys)
+                .chain(self.levels.iter())
+                .flat_map(|hashes| hashes.chunks.iter())
+                .filter(|chunk| counted.insert(Arc::as_ptr(chunk)))
+                .map(|chunk| size_of::<Vec<UInt256>>() + chunk.len() * size_of::<UInt256>())
+                .sum::<usize>()
+    }
+
+    fn hash_pair(left: &UInt256, right: &UInt256) -> UInt256 {
+        let mut buffer: Vec<u8> = Vec::with_capacity(64);
+        buffer.extend_from_slice(&left.0);
+        buffer.extend_from_slice(&right.0);
+        UInt256(sha256d::Hash::hash(&buffer).into_inner())
+    }
+}
+
+/// Trees for masternode and quorum merkle roots of the masternode list
+/// which are stored alongside the list and updated with each diff.
+/// Trees of the list share everything the diff didn't touch with the trees of its base
+#[derive(Clone, Debug, Default)]
+pub struct MasternodeListMerkleTrees {
+    pub masternodes: IncrementalMerkleTree,
+    pub quorums: IncrementalMerkleTree,
+}
+
+impl MasternodeListMerkleTrees {
+    pub fn from_list(list: &MasternodeList) -> Self {
+        Self::new(&list.masternodes, &list.quorums)
+    }
+
+    pub fn from_shared_list(list: &SharedMasternodeList) -> Self {
+        Self::new(
+            list.masternodes.iter().map(|(hash, entry)| (hash, entry.as_ref())),
+            &list.quorums,
+        )
+    }
+
+    pub fn new<'a, I: IntoIterator<Item = (&'a UInt256, &'a MasternodeEntry)>>(
+        masternodes: I,
+        quorums: &BTreeMap<LLMQType, BTreeMap<UInt256, LLMQEntry>>,
+    ) -> Self {
+        Self {
+            masternodes: IncrementalMerkleTree::from_leaves(
+                masternodes
+                    .into_iter()
+                    .map(|(hash, entry)| (Self::masternode_leaf_key(hash), entry.entry_hash))
+                    .collect(),
+            ),
+            quorums: IncrementalMerkleTree::from_leaves(
+                quorums
+                    .values()
+                    .flat_map(|map| map.values().map(|entry| (entry.entry_hash, entry.entry_hash)))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Entries in SML are ordered by their pro_reg_tx_hash,
+    /// while masternodes in the list are keyed with its reversed form
+    fn masternode_leaf_key(hash: &UInt256) -> UInt256 {
+        hash.clone().reversed()
+    }
+
+    /// Updates the trees of the base list with the changes of the diff.
+    /// 'deleted_masternode_hashes' are in the form they came with diff message,
+    /// changed masternodes and quorums are taken from the resulting list
+    pub fn apply_diff(
+        &mut self,
+        base_quorums: &BTreeMap<LLMQType, BTreeMap<UInt256, LLMQEntry>>,
+        masternodes: &SharedMasternodes,
+        deleted_masternode_hashes: &[UInt256],
+        changed_masternode_hashes: Vec<UInt256>,
+        deleted_quorums: &BTreeMap<LLMQType, Vec<UInt256>>,
+        added_quorums: &BTreeMap<LLMQType, BTreeMap<UInt256, LLMQEntry>>,
+    ) {
+        deleted_masternode_hashes.iter().for_each(|hash| {
+            self.masternodes.remove(hash);
+        });
+        changed_masternode_hashes.iter().for_each(|hash| {
+            if let Some(entry) = masternodes.get(hash) {
+                self.masternodes
+                    .update(Self::masternode_leaf_key(hash), entry.entry_hash);
+            }
+        });
+        deleted_quorums.iter().for_each(|(llmq_type, hashes)| {
+            if let Some(base_quorums_of_type) = base_quorums.get(llmq_type) {
+                hashes.iter().for_each(|llmq_hash| {
+                    if let Some(entry) = base_quorums_of_type.get(llmq_hash) {
+                        self.quorums.remove(&entry.entry_hash);
+                    }
+                });
+            }
+        });
+        added_quorums.iter().for_each(|(llmq_type, entries)| {
+            entries.iter().for_each(|(llmq_hash, entry)| {
+                // Commitment for the same quorum could be replaced
+                if let Some(replaced) = base_quorums
+                    .get(llmq_type)
+                    .and_then(|base_quorums_of_type| base_quorums_of_type.get(llmq_hash))
+                {
+                    self.quorums.remove(&replaced.entry_hash);
+                }
+                self.quorums.update(entry.entry_hash, entry.entry_hash);
+            });
+        });
+        self.masternodes.commit();
+        self.quorums.commit();
+    }
+
+    pub fn masternode_merkle_root(&self) -> Option<UInt256> {
+        self.masternodes.root()
+    }
+
+    pub fn llmq_merkle_root(&self) -> Option<UInt256> {
+        self.quorums.root()
+    }
+
+    pub fn memory_usage(&self, counted: &mut HashSet<*const Vec<UInt256>>) -> usize {
+        self.masternodes.memory_usage(counted) + self.quorums.memory_usage(counted)
+    }
+}
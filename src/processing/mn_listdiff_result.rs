@@ -1,26 +1,38 @@
-use crate::processing::ProcessingError;
+use crate::processing::{LLMQValidationStatus, ProcessingError, SharedMasternodeList};
 use dash_spv_ffi::ffi::boxer::{boxed, boxed_vec};
 use dash_spv_ffi::ffi::to::{encode_masternodes_map, encode_quorums_map, ToFFI};
 use dash_spv_ffi::types;
 use dash_spv_models::common::LLMQType;
-use dash_spv_models::masternode::{LLMQEntry, MasternodeEntry, MasternodeList};
+use dash_spv_models::masternode::{LLMQEntry, MasternodeEntry};
 use dash_spv_primitives::crypto::UInt256;
 use std::collections::BTreeMap;
+use std::sync::Arc;
 
 // #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MNListDiffResult {
     pub error_status: ProcessingError,
+    #[cfg_attr(feature = "serde", serde(with = "crate::processing::serialization::mirrored"))]
     pub base_block_hash: UInt256,
+    #[cfg_attr(feature = "serde", serde(with = "crate::processing::serialization::mirrored"))]
     pub block_hash: UInt256,
     pub has_found_coinbase: bool,       //1 byte
     pub has_valid_coinbase: bool,       //1 byte
     pub has_valid_mn_list_root: bool,   //1 byte
     pub has_valid_llmq_list_root: bool, //1 byte
     pub has_valid_quorums: bool,        //1 byte
-    pub masternode_list: MasternodeList,
+    #[cfg_attr(feature = "serde", serde(with = "crate::processing::serialization::mirrored"))]
+    pub masternode_list: Arc<SharedMasternodeList>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::processing::serialization::mirrored"))]
     pub added_masternodes: BTreeMap<UInt256, MasternodeEntry>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::processing::serialization::mirrored"))]
     pub modified_masternodes: BTreeMap<UInt256, MasternodeEntry>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::processing::serialization::mirrored"))]
     pub added_quorums: BTreeMap<LLMQType, BTreeMap<UInt256, LLMQEntry>>,
+    /// Validation status of each of the added quorums
+    #[cfg_attr(feature = "serde", serde(with = "crate::processing::serialization::mirrored"))]
+    pub quorum_statuses: BTreeMap<LLMQType, BTreeMap<UInt256, LLMQValidationStatus>>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::processing::serialization::mirrored"))]
     pub needed_masternode_lists: Vec<UInt256>,
 }
 
@@ -65,6 +77,7 @@ impl Default for MNListDiffResult {
             added_masternodes: Default::default(),
             modified_masternodes: Default::default(),
             added_quorums: Default::default(),
+            quorum_statuses: Default::default(),
             needed_masternode_lists: vec![],
         }
     }
@@ -76,6 +89,13 @@ impl MNListDiffResult {
         result.error_status = error;
         result
     }
+
+    pub fn quorum_status(&self, llmq_type: LLMQType, llmq_hash: UInt256) -> Option<LLMQValidationStatus> {
+        self.quorum_statuses
+            .get(&llmq_type)
+            .and_then(|statuses| statuses.get(&llmq_hash))
+            .cloned()
+    }
 }
 
 impl MNListDiffResult {
@@ -89,7 +109,7 @@ impl MNListDiffResult {
             has_valid_mn_list_root: self.has_valid_mn_list_root,
             has_valid_llmq_list_root: self.has_valid_llmq_list_root,
             has_valid_quorums: self.has_valid_quorums,
-            masternode_list: boxed(self.masternode_list.encode()),
+            masternode_list: boxed(self.masternode_list.to_list().encode()),
             added_masternodes: encode_masternodes_map(&self.added_masternodes),
             added_masternodes_count: self.added_masternodes.len(),
             modified_masternodes: encode_masternodes_map(&self.modified_masternodes),
@@ -1,11 +1,37 @@
+pub mod async_processor;
+pub mod cache_stats;
+pub mod list_changes;
+pub mod list_integrity;
+pub mod llmq_rotation_config;
+pub mod llmq_validation_status;
+pub mod merkle_trees;
 pub mod mn_listdiff_result;
 pub mod processing_error;
 pub mod processor;
 pub mod processor_cache;
+pub mod processor_session;
+pub mod pull_processor;
 pub mod qr_info_result;
+pub mod rotated_quorum_tracker;
+pub mod shared_masternode_list;
+pub mod sync_planner;
+#[cfg(feature = "serde")]
+pub mod serialization;
 
+pub use self::async_processor::{AsyncProcessor, AsyncProcessorLookup};
+pub use self::cache_stats::{CacheStats, MasternodeProcessorCacheStats};
+pub use self::list_changes::{compare_masternode_lists, MasternodeChange, MasternodeField, MasternodeListChanges, QuorumChange, QuorumField};
+pub use self::list_integrity::{verify_masternode_list, verify_masternode_list_with_checkpoint, ListIntegrityError};
+pub use self::llmq_rotation_config::LLMQRotationConfig;
+pub use self::llmq_validation_status::LLMQValidationStatus;
+pub use self::merkle_trees::{IncrementalMerkleTree, MasternodeListMerkleTrees};
 pub use self::mn_listdiff_result::MNListDiffResult;
 pub use self::processing_error::ProcessingError;
 pub use self::processor::MasternodeProcessor;
 pub use self::processor_cache::MasternodeProcessorCache;
-pub use self::qr_info_result::QRInfoResult;
+pub use self::processor_session::{ProcessorSession, SessionChain};
+pub use self::pull_processor::{ProcessingNeeds, PullMessage, PullProcessor, PullResult, PullStep, QuorumValidationData};
+pub use self::qr_info_result::{QRInfoResult, QRInfoValidationResult};
+pub use self::rotated_quorum_tracker::RotatedQuorumTracker;
+pub use self::shared_masternode_list::{SharedMasternodeList, SharedMasternodes};
+pub use self::sync_planner::{SyncPlanner, SyncRequest};
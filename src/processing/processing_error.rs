@@ -1,12 +1,19 @@
 #[warn(non_camel_case_types)]
 #[repr(u8)]
 #[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Hash, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ProcessingError {
     None = 0,
     PersistInRetrieval = 1,
     LocallyStored = 2,
     ParseError = 3,
     HasNoBaseBlockHash = 4,
+    /// Diff doesn't belong to the chain of the session
+    InvalidChain = 5,
+    /// List given by 'MasternodeListLookup' failed verification
+    CorruptedStorage = 6,
+    /// Diff is processed, but the session store failed to save it
+    StoreFailed = 7,
 }
 
 impl From<u8> for ProcessingError {
@@ -17,6 +24,9 @@ impl From<u8> for ProcessingError {
             2 => ProcessingError::LocallyStored,
             3 => ProcessingError::ParseError,
             4 => ProcessingError::HasNoBaseBlockHash,
+            5 => ProcessingError::InvalidChain,
+            6 => ProcessingError::CorruptedStorage,
+            7 => ProcessingError::StoreFailed,
             _ => ProcessingError::None,
         }
     }
@@ -30,6 +40,9 @@ impl Into<u8> for ProcessingError {
             ProcessingError::LocallyStored => 2,
             ProcessingError::ParseError => 3,
             ProcessingError::HasNoBaseBlockHash => 4,
+            ProcessingError::InvalidChain => 5,
+            ProcessingError::CorruptedStorage => 6,
+            ProcessingError::StoreFailed => 7,
         }
     }
 }
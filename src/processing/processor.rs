@@ -1,5 +1,12 @@
+use crate::processing::list_integrity::{verify_masternode_list, ListIntegrityError};
+use crate::processing::llmq_rotation_config::LLMQRotationConfig;
+use crate::processing::merkle_trees::MasternodeListMerkleTrees;
 use crate::processing::processor_cache::MasternodeProcessorCache;
-use crate::processing::{MNListDiffResult, ProcessingError};
+use crate::processing::rotated_quorum_tracker::RotatedQuorumTracker;
+use crate::processing::shared_masternode_list::{SharedMasternodeList, SharedMasternodes};
+use crate::codec::{ListDiffMessage, QRInfoMessage, SnapshotMessage};
+use crate::processing::{LLMQValidationStatus, MNListDiffResult, ProcessingError, QRInfoResult};
+use crate::store::{CheckpointError, MasternodeCheckpoint};
 use dash_spv_ffi::ffi::boxer::{boxed, boxed_vec};
 use dash_spv_ffi::ffi::callbacks;
 use dash_spv_ffi::ffi::callbacks::{
@@ -12,15 +19,17 @@ use dash_spv_ffi::ffi::callbacks::{
 use dash_spv_ffi::ffi::to::ToFFI;
 use dash_spv_ffi::types;
 use dash_spv_models::common::{LLMQParams, LLMQType};
+use dash_spv_models::tx::CoinbaseTransaction;
 use dash_spv_models::{common, llmq, masternode};
 use dash_spv_primitives::consensus::{encode, Encodable};
 use dash_spv_primitives::crypto::byte_util::{ConstDecodable, Reversable, Zeroable};
-use dash_spv_primitives::crypto::data_ops::{inplace_intersection, Data};
+use dash_spv_primitives::crypto::data_ops::Data;
 use dash_spv_primitives::crypto::UInt256;
 use dash_spv_primitives::hashes::{sha256d, Hash};
 use std::cmp::min;
-use std::collections::{BTreeMap, HashSet};
+use std::collections::BTreeMap;
 use std::ptr::null;
+use std::sync::Arc;
 
 // https://github.com/rust-lang/rfcs/issues/2770
 #[repr(C)]
@@ -29,6 +38,13 @@ pub struct MasternodeProcessor {
     pub opaque_context: *const std::ffi::c_void,
     pub genesis_hash: *const u8,
     pub use_insight_as_backup: bool,
+    /// Recompute the roots of the lists given by 'MasternodeListLookup'
+    /// and treat the corrupted ones as missing
+    pub verify_looked_up_lists: bool,
+    /// LLMQ types which members are determined by quorum rotation
+    pub rotation_config: LLMQRotationConfig,
+    /// Rotating types are set by the host, so they aren't taken from the genesis
+    pub rotation_config_is_set: bool,
     pub get_block_height_by_hash: GetBlockHeightByHash,
     pub get_merkle_root_by_hash: MerkleRootLookup,
     get_block_hash_by_height: GetBlockHashByHeight,
@@ -91,15 +107,32 @@ impl MasternodeProcessor {
             opaque_context: null(),
             genesis_hash: null(),
             use_insight_as_backup: false,
+            verify_looked_up_lists: false,
+            rotation_config: LLMQRotationConfig::default(),
+            rotation_config_is_set: false,
         }
     }
 
+    /// Until the host sets the rotating types, the ones of the chain of the genesis are used
+    pub fn use_rotation_config_of_genesis(&mut self) {
+        if self.rotation_config_is_set || self.genesis_hash.is_null() {
+            return;
+        }
+        if let Some(config) = UInt256::from_const(self.genesis_hash).and_then(LLMQRotationConfig::for_genesis) {
+            self.rotation_config = config;
+        }
+    }
+
+    /// The list from 'MasternodeListLookup' is verified when 'verify_looked_up_lists' is set:
+    /// its recomputed trees are kept in 'merkle_trees' and the one which failed verification
+    /// is reported (it's also added to 'unknown_lists' to be requested again)
     pub(crate) fn find_masternode_list(
         &self,
         block_hash: UInt256,
-        cached_lists: &BTreeMap<UInt256, masternode::MasternodeList>,
+        cached_lists: &BTreeMap<UInt256, Arc<SharedMasternodeList>>,
+        merkle_trees: &mut BTreeMap<UInt256, MasternodeListMerkleTrees>,
         unknown_lists: &mut Vec<UInt256>,
-    ) -> Option<masternode::MasternodeList> {
+    ) -> Result<Option<Arc<SharedMasternodeList>>, ListIntegrityError> {
         let genesis_hash = UInt256::from_const(self.genesis_hash).unwrap();
         if block_hash.is_zero() {
             // If it's a zero block we don't expect masternode list here
@@ -108,7 +141,7 @@ impl MasternodeProcessor {
                 UInt256::MAX,
                 block_hash
             ));
-            None
+            Ok(None)
         } else if block_hash.eq(&genesis_hash) {
             // If it's a genesis block we don't expect masternode list here
             self.log(format!(
@@ -116,16 +149,17 @@ impl MasternodeProcessor {
                 self.lookup_block_height_by_hash(block_hash),
                 block_hash
             ));
-            Some(masternode::MasternodeList::new(BTreeMap::default(), BTreeMap::default(), block_hash, self.lookup_block_height_by_hash(block_hash), false))
+            Ok(Some(Arc::new(SharedMasternodeList::empty(block_hash, self.lookup_block_height_by_hash(block_hash)))))
             // None
         } else if let Some(cached) = cached_lists.get(&block_hash) {
             // Getting it from local cache stored as opaque in FFI context
+            // (only the reference is cloned here, the list itself is shared)
             self.log(format!(
                 "find_masternode_list: (Cached) {}: {}",
                 self.lookup_block_height_by_hash(block_hash),
                 block_hash
             ));
-            Some(cached.clone())
+            Ok(Some(Arc::clone(cached)))
         } else if let Some(looked) = self.lookup_masternode_list(block_hash) {
             // Getting it from FFI directly
             self.log(format!(
@@ -133,7 +167,25 @@ impl MasternodeProcessor {
                 self.lookup_block_height_by_hash(block_hash),
                 block_hash
             ));
-            Some(looked)
+            let looked = SharedMasternodeList::from_list(&looked);
+            if self.verify_looked_up_lists {
+                match verify_masternode_list(block_hash, &looked) {
+                    Ok(trees) => {
+                        merkle_trees.insert(block_hash, trees);
+                    }
+                    Err(error) => {
+                        self.log(format!(
+                            "find_masternode_list: (Corrupted: {}) {}: {}",
+                            error,
+                            self.lookup_block_height_by_hash(block_hash),
+                            block_hash
+                        ));
+                        unknown_lists.push(block_hash);
+                        return Err(error);
+                    }
+                }
+            }
+            Ok(Some(Arc::new(looked)))
         } else {
             //self.log(format!("find_masternode_list: (None) {}: {}", self.lookup_block_height_by_hash(block_hash), block_hash));
             if self.lookup_block_height_by_hash(block_hash) != u32::MAX {
@@ -144,9 +196,22 @@ impl MasternodeProcessor {
                     unknown_lists.push(block_hash);
                 }
             }
-            None
+            Ok(None)
         }
     }
+    /// Hash of the block at the height: it's taken from the height index of the cached lists
+    /// (so the host isn't asked for the header) or looked up by the host
+    pub(crate) fn block_hash_for_height(
+        &self,
+        block_height: u32,
+        cached_list_heights: &BTreeMap<u32, UInt256>,
+    ) -> Option<UInt256> {
+        cached_list_heights
+            .get(&block_height)
+            .copied()
+            .or_else(|| self.lookup_block_hash_by_height(block_height))
+    }
+
     pub(crate) fn find_snapshot(
         &self,
         block_hash: UInt256,
@@ -172,11 +237,15 @@ impl MasternodeProcessor {
         cache: &mut MasternodeProcessorCache,
     ) -> types::MNListDiffResult {
         let base_block_hash = list_diff.base_block_hash;
-        let base_list = self.find_masternode_list(
+        let base_list = match self.find_masternode_list(
             base_block_hash,
             &cache.mn_lists,
+            &mut cache.merkle_trees,
             &mut cache.needed_masternode_lists,
-        );
+        ) {
+            Ok(base_list) => base_list,
+            Err(_) => return types::MNListDiffResult::default_with_error(ProcessingError::CorruptedStorage.into()),
+        };
         self.get_list_diff_result(base_list, list_diff, cache)
     }
 
@@ -185,17 +254,21 @@ impl MasternodeProcessor {
         list_diff: llmq::MNListDiff,
         cache: &mut MasternodeProcessorCache,
     ) -> MNListDiffResult {
-        let base_list = self.find_masternode_list(
+        let base_list = match self.find_masternode_list(
             list_diff.base_block_hash,
             &cache.mn_lists,
+            &mut cache.merkle_trees,
             &mut cache.needed_masternode_lists,
-        );
+        ) {
+            Ok(base_list) => base_list,
+            Err(_) => return MNListDiffResult::default_with_error(ProcessingError::CorruptedStorage),
+        };
         self.get_list_diff_result_internal(base_list, list_diff, cache)
     }
 
     pub(crate) fn get_list_diff_result(
         &self,
-        base_list: Option<masternode::MasternodeList>,
+        base_list: Option<Arc<SharedMasternodeList>>,
         list_diff: llmq::MNListDiff,
         cache: &mut MasternodeProcessorCache,
     ) -> types::MNListDiffResult {
@@ -206,7 +279,7 @@ impl MasternodeProcessor {
     fn cache_masternode_list(
         &self,
         block_hash: UInt256,
-        list: masternode::MasternodeList,
+        list: Arc<SharedMasternodeList>,
         cache: &mut MasternodeProcessorCache,
     ) {
         // It's good to cache lists to use it inside processing session
@@ -218,45 +291,82 @@ impl MasternodeProcessor {
 
     pub(crate) fn get_list_diff_result_internal(
         &self,
-        base_list: Option<masternode::MasternodeList>,
+        base_list: Option<Arc<SharedMasternodeList>>,
         list_diff: llmq::MNListDiff,
         cache: &mut MasternodeProcessorCache,
     ) -> MNListDiffResult {
         let base_block_hash = list_diff.base_block_hash;
         let block_hash = list_diff.block_hash;
         let block_height = list_diff.block_height;
-        let (base_masternodes, base_quorums) = match base_list {
-            Some(list) => (list.masternodes, list.quorums),
-            None => (BTreeMap::new(), BTreeMap::new()),
-        };
+        let empty_list = SharedMasternodeList::default();
+        let base_list = base_list.as_deref().unwrap_or(&empty_list);
         let mut coinbase_transaction = list_diff.coinbase_transaction;
         let quorums_active = coinbase_transaction.coinbase_transaction_version >= 2;
-        let (added_masternodes, modified_masternodes, masternodes) = self.classify_masternodes(
-            base_masternodes,
+        let deleted_masternode_hashes = list_diff.deleted_masternode_hashes.clone();
+        let deleted_quorums = list_diff.deleted_quorums.clone();
+        let (added_masternodes, modified_masternodes, masternodes) = Self::classify_masternodes(
+            &base_list.masternodes,
             list_diff.added_or_modified_masternodes,
             list_diff.deleted_masternode_hashes,
             block_height,
             block_hash,
         );
-        let (added_quorums, quorums, has_valid_quorums) = self.classify_quorums(
-            base_quorums,
+        let (added_quorums, quorums, has_valid_quorums, quorum_statuses) = match self.classify_quorums(
+            &base_list.quorums,
             list_diff.added_quorums,
-            list_diff.deleted_quorums,
+            deleted_quorums.clone(),
             cache,
+        ) {
+            Ok(classified) => classified,
+            Err(_) => return MNListDiffResult::default_with_error(ProcessingError::CorruptedStorage),
+        };
+        let merkle_tree = common::MerkleTree {
+            tree_element_count: list_diff.total_transactions,
+            hashes: list_diff.merkle_hashes.1,
+            flags: list_diff.merkle_flags.as_slice(),
+        };
+        // Only the leaves touched by the diff are rehashed here,
+        // the rest of the trees is shared with the trees of the base list
+        let mut merkle_trees = cache
+            .merkle_trees
+            .get(&base_block_hash)
+            .cloned()
+            .unwrap_or_else(|| MasternodeListMerkleTrees::from_shared_list(base_list));
+        merkle_trees.apply_diff(
+            &base_list.quorums,
+            &masternodes,
+            &deleted_masternode_hashes,
+            added_masternodes
+                .keys()
+                .chain(modified_masternodes.keys())
+                .cloned()
+                .collect(),
+            &deleted_quorums,
+            &added_quorums,
         );
-        let masternode_list = masternode::MasternodeList::new(
+        let masternode_merkle_root = merkle_trees.masternode_merkle_root();
+        let llmq_merkle_root = merkle_trees.llmq_merkle_root();
+        cache.merkle_trees.insert(block_hash, merkle_trees);
+        // Entries which the diff doesn't touch are shared with the base list
+        let masternode_list = Arc::new(SharedMasternodeList::new(
             masternodes,
             quorums,
             block_hash,
             block_height,
-            quorums_active,
+            masternode_merkle_root,
+            llmq_merkle_root.filter(|_| quorums_active),
+        ));
+        self.track_rotated_quorums(
+            added_quorums.values().flat_map(|quorums| quorums.values()),
+            &mut cache.rotated_quorums,
         );
-        let merkle_tree = common::MerkleTree {
-            tree_element_count: list_diff.total_transactions,
-            hashes: list_diff.merkle_hashes.1,
-            flags: list_diff.merkle_flags.as_slice(),
-        };
-        self.cache_masternode_list(block_hash, masternode_list.clone(), cache);
+        deleted_quorums.iter().for_each(|(&llmq_type, llmq_hashes)| {
+            llmq_hashes.iter().for_each(|&llmq_hash| {
+                cache.rotated_quorums.close_quorum(llmq_type, llmq_hash, block_height);
+            })
+        });
+        // The result and the cache share the same list
+        self.cache_masternode_list(block_hash, Arc::clone(&masternode_list), cache);
         let needed_masternode_lists = cache.needed_masternode_lists.clone();
         cache.needed_masternode_lists.clear();
         MNListDiffResult {
@@ -268,21 +378,128 @@ impl MasternodeProcessor {
                 self.lookup_merkle_root_by_hash(block_hash)
                     .unwrap_or(UInt256::MIN),
             ),
-            has_valid_mn_list_root: masternode_list.has_valid_mn_list_root(&coinbase_transaction),
+            has_valid_mn_list_root: masternode_merkle_root
+                == Some(coinbase_transaction.merkle_root_mn_list),
             has_valid_llmq_list_root: !quorums_active
-                || masternode_list.has_valid_llmq_list_root(&coinbase_transaction),
+                || (llmq_merkle_root.is_some()
+                    && llmq_merkle_root == coinbase_transaction.merkle_root_llmq_list),
             has_valid_quorums,
             masternode_list,
             added_masternodes,
             modified_masternodes,
             added_quorums,
+            quorum_statuses,
             needed_masternode_lists,
         }
     }
 
-    pub fn classify_masternodes(
+    /// Processes decoded QRINFO against the cache, its snapshots are cached along with the lists
+    pub(crate) fn get_qr_info_result_internal(
         &self,
-        base_masternodes: BTreeMap<UInt256, masternode::MasternodeEntry>,
+        message: QRInfoMessage,
+        cache: &mut MasternodeProcessorCache,
+    ) -> QRInfoResult {
+        let QRInfoMessage {
+            snapshot_at_h_c,
+            snapshot_at_h_2c,
+            snapshot_at_h_3c,
+            diff_tip,
+            diff_h,
+            diff_h_c,
+            diff_h_2c,
+            diff_h_3c,
+            extra_share,
+            last_quorum_per_index,
+            quorum_snapshot_list,
+            mn_list_diff_list,
+            has_tail: _,
+        } = message;
+        let mut save_snapshot = |block_hash: UInt256, snapshot: &SnapshotMessage| {
+            cache.llmq_snapshots.insert(block_hash, snapshot.snapshot.clone());
+            self.save_snapshot(block_hash, snapshot.snapshot.clone());
+        };
+        save_snapshot(diff_h_c.diff.block_hash, &snapshot_at_h_c);
+        save_snapshot(diff_h_2c.diff.block_hash, &snapshot_at_h_2c);
+        save_snapshot(diff_h_3c.diff.block_hash, &snapshot_at_h_3c);
+        if let Some((snapshot, diff)) = &extra_share {
+            save_snapshot(diff.diff.block_hash, snapshot);
+        }
+        quorum_snapshot_list
+            .iter()
+            .zip(mn_list_diff_list.iter())
+            .for_each(|(snapshot, diff)| save_snapshot(diff.diff.block_hash, snapshot));
+        let mut process_list_diff = |message: ListDiffMessage| {
+            self.get_list_diff_result_internal_with_base_lookup(message.diff, cache)
+        };
+        let mn_list_diff_list = mn_list_diff_list
+            .into_iter()
+            .map(&mut process_list_diff)
+            .collect::<Vec<MNListDiffResult>>();
+        // The order is important since the each new one dependent on previous:
+        // with extra share quorums at h-3c are validated using the list and snapshot at h-4c
+        let (snapshot_at_h_4c, result_at_h_4c) = match extra_share {
+            Some((snapshot, diff)) => (Some(snapshot.snapshot), Some(process_list_diff(diff))),
+            None => (None, None),
+        };
+        let result_at_h_3c = process_list_diff(diff_h_3c);
+        let result_at_h_2c = process_list_diff(diff_h_2c);
+        let result_at_h_c = process_list_diff(diff_h_c);
+        let result_at_h = process_list_diff(diff_h);
+        let result_at_tip = process_list_diff(diff_tip);
+        // The message is rejected if any of its diffs is based on the corrupted stored list
+        let is_corrupted = [&result_at_tip, &result_at_h, &result_at_h_c, &result_at_h_2c, &result_at_h_3c]
+            .into_iter()
+            .chain(result_at_h_4c.iter())
+            .chain(mn_list_diff_list.iter())
+            .any(|result| result.error_status == ProcessingError::CorruptedStorage);
+        if is_corrupted {
+            return QRInfoResult::default_with_error(ProcessingError::CorruptedStorage);
+        }
+        // Commitments of the message aren't validated by themselves,
+        // so they're tracked as the tip list has them (i.e. with their validation status)
+        self.track_rotated_quorums(
+            last_quorum_per_index.iter().filter_map(|quorum| {
+                result_at_tip
+                    .masternode_list
+                    .quorums
+                    .get(&quorum.llmq_type)
+                    .and_then(|quorums| quorums.get(&quorum.llmq_hash))
+            }),
+            &mut cache.rotated_quorums,
+        );
+        let rotated_quorums_at_h_3c = if result_at_h_4c.is_some() {
+            self.rotated_quorums_of(&result_at_h_3c)
+        } else {
+            vec![]
+        };
+        QRInfoResult {
+            error_status: ProcessingError::None,
+            result_at_tip,
+            result_at_h,
+            result_at_h_c,
+            result_at_h_2c,
+            result_at_h_3c,
+            extra_share: result_at_h_4c.is_some(),
+            result_at_h_4c,
+            snapshot_at_h_c: snapshot_at_h_c.snapshot,
+            snapshot_at_h_2c: snapshot_at_h_2c.snapshot,
+            snapshot_at_h_3c: snapshot_at_h_3c.snapshot,
+            snapshot_at_h_4c,
+            rotated_quorums_at_h_3c,
+            last_quorum_per_index,
+            quorum_snapshot_list: quorum_snapshot_list
+                .into_iter()
+                .map(|snapshot| snapshot.snapshot)
+                .collect(),
+            mn_list_diff_list,
+        }
+    }
+
+    /// Classifies the diff entries against the base list.
+    /// Only the entries of the diff are inspected: the resulting map shares the untouched
+    /// entries with the base one, so it costs the size of the diff rather than the list
+    pub fn classify_masternodes(
+        base_masternodes: &SharedMasternodes,
         added_or_modified_masternodes: BTreeMap<UInt256, masternode::MasternodeEntry>,
         deleted_masternode_hashes: Vec<UInt256>,
         block_height: u32,
@@ -290,105 +507,98 @@ impl MasternodeProcessor {
     ) -> (
         BTreeMap<UInt256, masternode::MasternodeEntry>,
         BTreeMap<UInt256, masternode::MasternodeEntry>,
-        BTreeMap<UInt256, masternode::MasternodeEntry>,
+        SharedMasternodes,
     ) {
-        let added_or_modified_masternodes = added_or_modified_masternodes;
-        let deleted_masternode_hashes = deleted_masternode_hashes;
-        let mut added_masternodes = added_or_modified_masternodes.clone();
-        let mut modified_masternode_keys: HashSet<UInt256> = HashSet::new();
-        if base_masternodes.len() > 0 {
-            let base_masternodes = base_masternodes.clone();
-            base_masternodes.iter().for_each(|(h, _e)| {
-                added_masternodes.remove(h);
-            });
-            let mut new_mn_keys: HashSet<UInt256> =
-                added_or_modified_masternodes.keys().cloned().collect();
-            let mut old_mn_keys: HashSet<UInt256> = base_masternodes.keys().cloned().collect();
-            modified_masternode_keys = inplace_intersection(&mut new_mn_keys, &mut old_mn_keys);
-        }
-        let mut modified_masternodes: BTreeMap<UInt256, masternode::MasternodeEntry> =
-            modified_masternode_keys
-                .clone()
+        let (added_masternodes, mut modified_masternodes): (BTreeMap<_, _>, BTreeMap<_, _>) =
+            added_or_modified_masternodes
                 .into_iter()
-                .fold(BTreeMap::new(), |mut acc, hash| {
-                    acc.insert(hash, added_or_modified_masternodes[&hash].clone());
-                    acc
-                });
-        let mut masternodes = if base_masternodes.len() > 0 {
-            let mut old_mnodes = base_masternodes.clone();
-            for hash in deleted_masternode_hashes {
-                old_mnodes.remove(&hash.clone().reversed());
-            }
-            old_mnodes.extend(added_masternodes.clone());
-            old_mnodes
-        } else {
-            added_masternodes.clone()
-        };
+                .partition(|(hash, _)| !base_masternodes.contains_key(hash));
+        let mut masternodes = base_masternodes.clone();
+        for hash in deleted_masternode_hashes {
+            masternodes.remove(&hash.clone().reversed());
+        }
+        added_masternodes.iter().for_each(|(hash, added)| {
+            masternodes.insert(*hash, Arc::new(added.clone()));
+        });
         modified_masternodes
             .iter_mut()
             .for_each(|(hash, modified)| {
-                if let Some(mut old) = masternodes.get_mut(hash) {
-                    if (*old).update_height < (*modified).update_height {
+                if let Some(old) = masternodes.get(hash) {
+                    // The old entry is shared with the base list, so it's never changed in place
+                    let mut old = old.as_ref().clone();
+                    if old.update_height < (*modified).update_height {
                         if (*modified).provider_registration_transaction_hash
-                            == (*old).provider_registration_transaction_hash
+                            == old.provider_registration_transaction_hash
                         {
                             (*modified).update_with_previous_entry(
-                                old,
+                                &mut old,
                                 common::Block {
                                     height: block_height,
                                     hash: block_hash,
                                 },
                             );
                         }
-                        if !(*old).confirmed_hash.is_zero()
-                            && (*old).known_confirmed_at_height.is_some()
-                            && (*old).known_confirmed_at_height.unwrap() > block_height
-                        {
-                            (*old).known_confirmed_at_height = Some(block_height);
-                        }
                     }
-                    masternodes.insert((*hash).clone(), (*modified).clone());
+                    masternodes.insert(*hash, Arc::new((*modified).clone()));
                 }
             });
         (added_masternodes, modified_masternodes, masternodes)
     }
 
+    /// Validates the added quorums and applies the diff to the base quorums.
+    /// 'has_valid_quorums' is true unless one of the added quorums is 'Invalid':
+    /// quorums of the types skipped by 'should_process_llmq_of_type' (and the ones whose
+    /// members can't be determined yet) aren't validated, so they don't affect it and stay unverified.
+    /// Status of each added quorum is given along with them.
+    /// Fails if a list needed for validation is looked up corrupted
+    #[allow(clippy::type_complexity)]
     pub fn classify_quorums(
         &self,
-        base_quorums: BTreeMap<LLMQType, BTreeMap<UInt256, masternode::LLMQEntry>>,
+        base_quorums: &BTreeMap<LLMQType, BTreeMap<UInt256, masternode::LLMQEntry>>,
         added_quorums: BTreeMap<LLMQType, BTreeMap<UInt256, masternode::LLMQEntry>>,
         deleted_quorums: BTreeMap<LLMQType, Vec<UInt256>>,
         cache: &mut MasternodeProcessorCache,
-    ) -> (
+    ) -> Result<(
         BTreeMap<LLMQType, BTreeMap<UInt256, masternode::LLMQEntry>>,
         BTreeMap<LLMQType, BTreeMap<UInt256, masternode::LLMQEntry>>,
         bool,
-    ) {
-        let has_valid_quorums = true;
+        BTreeMap<LLMQType, BTreeMap<UInt256, LLMQValidationStatus>>,
+    ), ListIntegrityError> {
+        let mut statuses = BTreeMap::<LLMQType, BTreeMap<UInt256, LLMQValidationStatus>>::new();
         let mut added = added_quorums.clone();
-        added.iter_mut().for_each(|(&llmq_type, llmqs_of_type)| {
-            if self.should_process_quorum(llmq_type) {
-                (*llmqs_of_type)
-                    .iter_mut()
-                    .for_each(|(&llmq_block_hash, quorum)| {
-                        if let Some(masternode::MasternodeList { masternodes, .. }) = self
-                            .find_masternode_list(
-                                llmq_block_hash,
-                                &cache.mn_lists,
-                                &mut cache.needed_masternode_lists,
-                            )
-                        {
-                            self.validate_quorum(
-                                quorum,
-                                has_valid_quorums,
-                                llmq_block_hash,
-                                masternodes,
-                                cache,
-                            )
-                        }
-                    });
+        for (&llmq_type, llmqs_of_type) in added.iter_mut() {
+            let should_process = self.should_process_quorum(llmq_type);
+            let statuses_of_type = statuses.entry(llmq_type).or_insert_with(BTreeMap::new);
+            for (&llmq_block_hash, quorum) in llmqs_of_type.iter_mut() {
+                let status = if !should_process {
+                    LLMQValidationStatus::Skipped
+                } else if let Some(list) = self.find_masternode_list(
+                    llmq_block_hash,
+                    &cache.mn_lists,
+                    &mut cache.merkle_trees,
+                    &mut cache.needed_masternode_lists,
+                )? {
+                    self.validate_quorum(quorum, llmq_block_hash, &list.masternodes, cache)?
+                } else {
+                    LLMQValidationStatus::Unknown
+                };
+                statuses_of_type.insert(llmq_block_hash, status);
             }
-        });
+        }
+        let has_valid_quorums = statuses
+            .values()
+            .flat_map(BTreeMap::values)
+            .all(|&status| status != LLMQValidationStatus::Invalid);
+        let quorums = Self::apply_quorums_diff(base_quorums, &added, &deleted_quorums);
+        Ok((added, quorums, has_valid_quorums, statuses))
+    }
+
+    /// Quorums of the base list with the deleted ones removed and the added ones inserted
+    pub fn apply_quorums_diff(
+        base_quorums: &BTreeMap<LLMQType, BTreeMap<UInt256, masternode::LLMQEntry>>,
+        added: &BTreeMap<LLMQType, BTreeMap<UInt256, masternode::LLMQEntry>>,
+        deleted_quorums: &BTreeMap<LLMQType, Vec<UInt256>>,
+    ) -> BTreeMap<LLMQType, BTreeMap<UInt256, masternode::LLMQEntry>> {
         let mut quorums = base_quorums.clone();
         quorums.extend(
             added
@@ -409,36 +619,45 @@ impl MasternodeProcessor {
                 });
             }
         });
-        (added, quorums, has_valid_quorums)
+        quorums
     }
 
+    /// Quorum is 'Unknown' if its members can't be determined: members of rotated quorums
+    /// are reconstructed from the lists and snapshots of the previous cycles
     pub fn validate_quorum(
         &self,
         quorum: &mut masternode::LLMQEntry,
-        has_valid_quorums: bool,
         block_hash: UInt256,
-        masternodes: BTreeMap<UInt256, masternode::MasternodeEntry>,
+        masternodes: &SharedMasternodes,
         cache: &mut MasternodeProcessorCache,
-    ) {
+    ) -> Result<LLMQValidationStatus, ListIntegrityError> {
         let block_height = self.lookup_block_height_by_hash(block_hash);
-        println!("validate_quorum: {}: {:?}", block_height, quorum);
         let quorum_modifier = quorum.llmq_quorum_hash();
         let quorum_count = quorum.llmq_type.size();
-        let valid_masternodes = if quorum.index.is_some() {
-            self.get_rotated_masternodes_for_quorum(
+        let valid_masternodes = if self.rotation_config.is_rotating(quorum.llmq_type) {
+            match self.get_rotated_masternodes_for_quorum(
                 quorum.llmq_type,
                 block_hash,
                 block_height,
                 &mut cache.llmq_members,
                 &mut cache.llmq_indexed_members,
                 &cache.mn_lists,
+                &cache.mn_list_height_index,
                 &cache.llmq_snapshots,
+                &mut cache.merkle_trees,
                 &mut cache.needed_masternode_lists
-            )
+            )? {
+                Some(members) => members,
+                None => return Ok(LLMQValidationStatus::Unknown),
+            }
         } else {
             Self::valid_masternodes_for(masternodes, quorum_modifier, quorum_count, block_height)
         };
-        self.validate_signature(valid_masternodes, quorum, block_height, has_valid_quorums);
+        if self.validate_signature(valid_masternodes, quorum, block_height) {
+            Ok(LLMQValidationStatus::Valid)
+        } else {
+            Ok(LLMQValidationStatus::Invalid)
+        }
     }
 
     pub fn score_masternodes(
@@ -465,20 +684,20 @@ impl MasternodeProcessor {
             })
     }
     pub fn score_masternodes_map(
-        masternodes: BTreeMap<UInt256, masternode::MasternodeEntry>,
+        masternodes: &SharedMasternodes,
         quorum_modifier: UInt256,
         block_height: u32,
     ) -> BTreeMap<UInt256, masternode::MasternodeEntry> {
         masternodes
-            .clone()
-            .into_iter()
-            .filter_map(|(_, entry)| {
+            .values()
+            .filter_map(|entry| {
                 if entry.confirmed_hash.is_zero() || !entry.is_valid {
                     return None;
                 }
+                let entry = entry.as_ref();
                 let score = masternode::MasternodeList::masternode_score(entry.clone(), quorum_modifier, block_height);
                 if score.is_some() && !score.unwrap().0.is_empty() {
-                    Some((score.unwrap(), entry))
+                    Some((score.unwrap(), entry.clone()))
                 } else {
                     None
                 }
@@ -527,7 +746,7 @@ impl MasternodeProcessor {
     }
 
     pub fn valid_masternodes_for(
-        masternodes: BTreeMap<UInt256, masternode::MasternodeEntry>,
+        masternodes: &SharedMasternodes,
         quorum_modifier: UInt256,
         quorum_count: u32,
         block_height: u32,
@@ -560,7 +779,7 @@ impl MasternodeProcessor {
     }
 
     pub fn valid_masternodes_for_rotated_quorum(
-        masternodes: BTreeMap<UInt256, masternode::MasternodeEntry>,
+        masternodes: &SharedMasternodes,
         quorum_modifier: UInt256,
         quorum_count: u32,
         block_height: u32,
@@ -581,34 +800,40 @@ impl MasternodeProcessor {
         UInt256(sha256d::Hash::hash(&buffer).into_inner())
     }
 
-    // Reconstruct quorum members at index from snapshot
+    // Reconstruct quorum members at index from snapshot,
+    // None if the block, the list or the snapshot at the work block is unknown
     pub fn quorum_quarter_members_by_snapshot(
         &self,
         llmq_params: LLMQParams,
         quorum_base_block_height: u32,
-        cached_lists: &BTreeMap<UInt256, masternode::MasternodeList>,
+        cached_lists: &BTreeMap<UInt256, Arc<SharedMasternodeList>>,
+        cached_list_heights: &BTreeMap<u32, UInt256>,
         cached_snapshots: &BTreeMap<UInt256, llmq::LLMQSnapshot>,
+        merkle_trees: &mut BTreeMap<UInt256, MasternodeListMerkleTrees>,
         unknown_lists: &mut Vec<UInt256>,
-    ) -> Vec<Vec<masternode::MasternodeEntry>> {
+    ) -> Result<Option<Vec<Vec<masternode::MasternodeEntry>>>, ListIntegrityError> {
         let work_block_height = quorum_base_block_height - 8;
         let llmq_type = llmq_params.r#type;
         let quorum_count = llmq_params.signing_active_quorum_count;
         let quorum_size = llmq_params.size;
         let quarter_size = (quorum_size / 4) as usize;
         // Quorum members dichotomy in snapshot
-        match self.lookup_block_hash_by_height(work_block_height) {
-            None => panic!("missing block for height: {}", work_block_height),
+        match self.block_hash_for_height(work_block_height, cached_list_heights) {
+            None => {
+                self.log(format!("missing block for height: {}", work_block_height));
+                Ok(None)
+            }
             Some(work_block_hash) => {
                 //println!("quorum_quarter_members_by_snapshot: find masternode list for: {}: {} (cached_snapshots: {:#?})", work_block_height, work_block_hash.clone().reversed(), cached_snapshots);
                 if let Some(masternode_list) =
-                    self.find_masternode_list(work_block_hash, &cached_lists, unknown_lists)
+                    self.find_masternode_list(work_block_hash, cached_lists, merkle_trees, unknown_lists)?
                 {
                     if let Some(snapshot) = self.find_snapshot(work_block_hash, &cached_snapshots) {
                         let mut i: u32 = 0;
                         // TODO: partition with enumeration doesn't work here, so need to change
                         // nodes.into_iter().enumerate().partition(|&(i, _)| snapshot.member_list.bit_is_true_at_le_index(i as u32))
                         let quorum_modifier = Self::build_llmq_modifier(llmq_type, work_block_hash);
-                        let scored_masternodes = Self::score_masternodes_map(masternode_list.masternodes, quorum_modifier, work_block_height);
+                        let scored_masternodes = Self::score_masternodes_map(&masternode_list.masternodes, quorum_modifier, work_block_height);
                         let scored_sorted_masternodes = Self::sort_scored_masternodes(scored_masternodes);
                         let (used_at_h, unused_at_h) = scored_sorted_masternodes
                         .into_iter()
@@ -635,38 +860,41 @@ impl MasternodeProcessor {
                         let mut sorted_combined_mns_list = sorted_unused_at_h;
                         sorted_combined_mns_list.extend(sorted_used_at_h);
                         // println!("sorted_combined_mns_list: {:#?}", sorted_combined_mns_list.iter().map(|m|m.provider_registration_transaction_hash.clone().reversed()).collect::<Vec<UInt256>>());
-                        snapshot.apply_skip_strategy(
+                        Ok(Some(snapshot.apply_skip_strategy(
                             sorted_combined_mns_list,
                             quorum_count as usize,
                             quarter_size,
-                        )
+                        )))
                     } else {
                         self.log(format!(
                             "missing snapshot for block at height: {}: {}",
                             work_block_height, work_block_hash
                         ));
-                        vec![]
+                        Ok(None)
                     }
                 } else {
                     self.log(format!(
                         "missing masternode_list for block at height: {}: {}",
                         work_block_height, work_block_hash.clone().reversed()
                     ));
-                    vec![]
+                    Ok(None)
                 }
             }
         }
     }
 
-    // Determine quorum members at new index
+    // Determine quorum members at new index,
+    // None if the block or the list at the work block is unknown
     pub fn new_quorum_quarter_members(
         &self,
         params: LLMQParams,
         quorum_base_block_height: u32,
         previous_quarters: [Vec<Vec<masternode::MasternodeEntry>>; 3],
-        cached_lists: &BTreeMap<UInt256, masternode::MasternodeList>,
+        cached_lists: &BTreeMap<UInt256, Arc<SharedMasternodeList>>,
+        cached_list_heights: &BTreeMap<u32, UInt256>,
+        merkle_trees: &mut BTreeMap<UInt256, MasternodeListMerkleTrees>,
         unknown_lists: &mut Vec<UInt256>,
-    ) -> Vec<Vec<masternode::MasternodeEntry>> {
+    ) -> Result<Option<Vec<Vec<masternode::MasternodeEntry>>>, ListIntegrityError> {
         let quorum_count = params.signing_active_quorum_count;
         let num_quorums = quorum_count as usize;
         let mut quarter_quorum_members =
@@ -674,15 +902,18 @@ impl MasternodeProcessor {
         let quorum_size = params.size as usize;
         let quarter_size = quorum_size / 4;
         let work_block_height = quorum_base_block_height - 8;
-        match self.lookup_block_hash_by_height(work_block_height) {
-            None => panic!("missing block for height: {}", work_block_height),
+        match self.block_hash_for_height(work_block_height, cached_list_heights) {
+            None => {
+                self.log(format!("missing block for height: {}", work_block_height));
+                Ok(None)
+            }
             Some(work_block_hash) => {
                 if let Some(masternode_list) =
-                    self.find_masternode_list(work_block_hash, cached_lists, unknown_lists)
+                    self.find_masternode_list(work_block_hash, cached_lists, merkle_trees, unknown_lists)?
                 {
                     if masternode_list.masternodes.len() < quarter_size {
                         self.log(format!("masternode list at {}: {} has less masternodes ({}) then required for quarter size: ({})", work_block_height, work_block_hash, masternode_list.masternodes.len(), quarter_size));
-                        quarter_quorum_members
+                        Ok(Some(quarter_quorum_members))
                     } else {
                         let mut masternodes_used_at_h = Vec::<masternode::MasternodeEntry>::new();
                         let mut masternodes_unused_at_h = Vec::<masternode::MasternodeEntry>::new();
@@ -704,7 +935,7 @@ impl MasternodeProcessor {
                                 }
                             });
                         });
-                        masternode_list.masternodes.into_values().for_each(|mn| {
+                        masternode_list.masternode_entries().for_each(|mn| {
                             if mn.is_valid
                                 && masternodes_used_at_h
                                     .iter()
@@ -715,7 +946,7 @@ impl MasternodeProcessor {
                                     .count()
                                     == 0
                             {
-                                masternodes_unused_at_h.push(mn);
+                                masternodes_unused_at_h.push(mn.clone());
                             }
                         });
                         let modifier = Self::build_llmq_modifier(params.r#type, work_block_hash);
@@ -769,14 +1000,14 @@ impl MasternodeProcessor {
                                 }
                             }
                         });
-                        quarter_quorum_members
+                        Ok(Some(quarter_quorum_members))
                     }
                 } else {
                     self.log(format!(
                         "missing masternode list for height: {}: {}",
                         work_block_height, work_block_hash
                     ));
-                    quarter_quorum_members
+                    Ok(None)
                 }
             }
         }
@@ -798,63 +1029,101 @@ impl MasternodeProcessor {
         }
     }
 
+    /// Members of all the quorums of the cycle, None if any of the quarters can't be determined
     fn rotate_members(
         &self,
         cycle_quorum_base_block_height: u32,
         llmq_params: LLMQParams,
-        cached_lists: &BTreeMap<UInt256, masternode::MasternodeList>,
+        cached_lists: &BTreeMap<UInt256, Arc<SharedMasternodeList>>,
+        cached_list_heights: &BTreeMap<u32, UInt256>,
         cached_snapshots: &BTreeMap<UInt256, llmq::LLMQSnapshot>,
+        merkle_trees: &mut BTreeMap<UInt256, MasternodeListMerkleTrees>,
         unknown_lists: &mut Vec<UInt256>,
-    ) -> Vec<Vec<masternode::MasternodeEntry>> {
+    ) -> Result<Option<Vec<Vec<masternode::MasternodeEntry>>>, ListIntegrityError> {
         let num_quorums = llmq_params.signing_active_quorum_count as usize;
         let cycle_length = llmq_params.dkg_params.interval;
         let prev_q_h_m_c = self.quorum_quarter_members_by_snapshot(
             llmq_params,
             cycle_quorum_base_block_height - cycle_length,
             cached_lists,
+            cached_list_heights,
             cached_snapshots,
+            merkle_trees,
             unknown_lists,
-        );
+        )?;
         let prev_q_h_m_2c = self.quorum_quarter_members_by_snapshot(
             llmq_params,
             cycle_quorum_base_block_height - 2 * cycle_length,
             cached_lists,
+            cached_list_heights,
             cached_snapshots,
+            merkle_trees,
             unknown_lists,
-        );
+        )?;
         let prev_q_h_m_3c = self.quorum_quarter_members_by_snapshot(
             llmq_params,
             cycle_quorum_base_block_height - 3 * cycle_length,
             cached_lists,
+            cached_list_heights,
             cached_snapshots,
+            merkle_trees,
             unknown_lists,
-        );
+        )?;
+        // All the quarters are looked for before giving up, so each missing list is requested
+        let (prev_q_h_m_c, prev_q_h_m_2c, prev_q_h_m_3c) = match (prev_q_h_m_c, prev_q_h_m_2c, prev_q_h_m_3c) {
+            (Some(h_c), Some(h_2c), Some(h_3c)) => (h_c, h_2c, h_3c),
+            _ => return Ok(None),
+        };
         // println!("INFO: Quarter H-C {:#?}", prev_q_h_m_c.iter().flat_map(|v| v.iter().map(|m| m.provider_registration_transaction_hash.clone().reversed())).collect::<Vec<UInt256>>());
         // println!("INFO: Quarter H-2C {:#?}", prev_q_h_m_2c.iter().flat_map(|v| v.iter().map(|m| m.provider_registration_transaction_hash.clone().reversed())).collect::<Vec<UInt256>>());
         // println!("INFO: Quarter H-3C {:#?}", prev_q_h_m_3c.iter().flat_map(|v| v.iter().map(|m| m.provider_registration_transaction_hash.clone().reversed())).collect::<Vec<UInt256>>());
         let mut rotated_members =
             Vec::<Vec<masternode::MasternodeEntry>>::with_capacity(num_quorums);
-        let new_quarter_members = self.new_quorum_quarter_members(
-            llmq_params,
-            cycle_quorum_base_block_height,
-            [
-                prev_q_h_m_c.clone(),
-                prev_q_h_m_2c.clone(),
-                prev_q_h_m_3c.clone(),
-            ],
-            cached_lists,
-            unknown_lists,
-        );
+        // The snapshot of the cycle itself is known when the cycle isn't the latest one
+        // (i.e. h-3c with its predecessors at h-4c given by extra share):
+        // its quarter is reconstructed by the snapshot just like the quarters of the previous cycles
+        let quarter_members_by_snapshot = self
+            .quorum_quarter_members_by_snapshot(
+                llmq_params,
+                cycle_quorum_base_block_height,
+                cached_lists,
+                cached_list_heights,
+                cached_snapshots,
+                merkle_trees,
+                &mut vec![],
+            )?
+            .filter(|quarter| quarter.len() == num_quorums);
+        let new_quarter_members = match quarter_members_by_snapshot {
+            Some(quarter) => quarter,
+            None => match self.new_quorum_quarter_members(
+                llmq_params,
+                cycle_quorum_base_block_height,
+                [
+                    prev_q_h_m_c.clone(),
+                    prev_q_h_m_2c.clone(),
+                    prev_q_h_m_3c.clone(),
+                ],
+                cached_lists,
+                cached_list_heights,
+                merkle_trees,
+                unknown_lists,
+            )? {
+                Some(quarter) => quarter,
+                None => return Ok(None),
+            },
+        };
         (0..num_quorums).for_each(|i| {
             Self::add_quorum_members_from_quarter(&mut rotated_members, &prev_q_h_m_3c, i);
             Self::add_quorum_members_from_quarter(&mut rotated_members, &prev_q_h_m_2c, i);
             Self::add_quorum_members_from_quarter(&mut rotated_members, &prev_q_h_m_c, i);
             Self::add_quorum_members_from_quarter(&mut rotated_members, &new_quarter_members, i);
         });
-        rotated_members
+        Ok(Some(rotated_members))
     }
 
-    /// Determine masternodes which is responsible for signing at this quorum index
+    /// Determine masternodes which is responsible for signing at this quorum index,
+    /// None if they can't be determined with the lists and snapshots at hand.
+    /// Fails if one of the lists is looked up corrupted
     pub fn get_rotated_masternodes_for_quorum(
         &self,
         llmq_type: LLMQType,
@@ -862,14 +1131,16 @@ impl MasternodeProcessor {
         block_height: u32,
         cached_llmq_members: &mut BTreeMap<LLMQType, BTreeMap<UInt256, Vec<masternode::MasternodeEntry>>>,
         cached_llmq_indexed_members: &mut BTreeMap<LLMQType, BTreeMap<llmq::LLMQIndexedHash, Vec<masternode::MasternodeEntry>>>,
-        cached_mn_lists: &BTreeMap<UInt256, masternode::MasternodeList>,
+        cached_mn_lists: &BTreeMap<UInt256, Arc<SharedMasternodeList>>,
+        cached_mn_list_heights: &BTreeMap<u32, UInt256>,
         cached_llmq_snapshots: &BTreeMap<UInt256, llmq::LLMQSnapshot>,
+        cached_merkle_trees: &mut BTreeMap<UInt256, MasternodeListMerkleTrees>,
         cached_needed_masternode_lists: &mut Vec<UInt256>,
-    ) -> Vec<masternode::MasternodeEntry> {
+    ) -> Result<Option<Vec<masternode::MasternodeEntry>>, ListIntegrityError> {
         let map_by_type_opt = cached_llmq_members.get_mut(&llmq_type);
         if map_by_type_opt.is_some() {
             if let Some(members) = map_by_type_opt.as_ref().unwrap().get(&block_hash) {
-                return members.clone();
+                return Ok(Some(members.clone()));
             }
         } else {
             cached_llmq_members.insert(llmq_type, BTreeMap::new());
@@ -878,8 +1149,11 @@ impl MasternodeProcessor {
         let llmq_params = llmq_type.params();
         let quorum_index = block_height % llmq_params.dkg_params.interval;
         let cycle_base_height = block_height - quorum_index;
-        match self.lookup_block_hash_by_height(cycle_base_height) {
-            None => panic!("missing hash for block at height: {}", cycle_base_height),
+        match self.block_hash_for_height(cycle_base_height, cached_mn_list_heights) {
+            None => {
+                self.log(format!("missing hash for block at height: {}", cycle_base_height));
+                Ok(None)
+            }
             Some(cycle_base_hash) => {
                 let map_by_type_indexed_opt = cached_llmq_indexed_members.get_mut(&llmq_type);
                 if map_by_type_indexed_opt.is_some() {
@@ -889,19 +1163,29 @@ impl MasternodeProcessor {
                         .get(&llmq::LLMQIndexedHash::new(cycle_base_hash, quorum_index))
                     {
                         map_by_type.insert(block_hash, members.clone());
-                        return members.clone();
+                        return Ok(Some(members.clone()));
                     }
                 } else {
                     cached_llmq_indexed_members
                         .insert(llmq_type, BTreeMap::new());
                 }
-                let rotated_members = self.rotate_members(
+                let rotated_members = match self.rotate_members(
                     cycle_base_height,
                     llmq_params,
                     cached_mn_lists,
+                    cached_mn_list_heights,
                     cached_llmq_snapshots,
+                    cached_merkle_trees,
                     cached_needed_masternode_lists,
-                );
+                )? {
+                    Some(rotated_members) => rotated_members,
+                    // Members aren't cached, so they're recalculated when missing lists
+                    // or snapshots arrive (i.e. with extra share)
+                    None => {
+                        self.log(format!("get_rotated_masternodes_for_quorum: unknown members for cycle at {}", cycle_base_height));
+                        return Ok(None);
+                    }
+                };
                 let map_indexed_quorum_members_of_type =
                     cached_llmq_indexed_members.get_mut(&llmq_type).unwrap();
                 rotated_members.iter().enumerate().for_each(|(i, members)| {
@@ -910,15 +1194,58 @@ impl MasternodeProcessor {
                         members.clone(),
                     );
                 });
-                if let Some(members) = rotated_members.get(quorum_index as usize) {
-                    map_by_type.insert(block_hash, members.clone());
-                    return members.clone();
-                }
-                vec![]
+                let members = match rotated_members.get(quorum_index as usize) {
+                    Some(members) => members,
+                    None => return Ok(None),
+                };
+                map_by_type.insert(block_hash, members.clone());
+                Ok(Some(members.clone()))
             }
         }
     }
 
+    /// Commitments of rotating types added with the diff
+    pub fn rotated_quorums_of(&self, result: &MNListDiffResult) -> Vec<masternode::LLMQEntry> {
+        result
+            .added_quorums
+            .iter()
+            .filter(|(&llmq_type, _)| self.rotation_config.is_rotating(llmq_type))
+            .flat_map(|(_, quorums)| quorums.values().cloned())
+            .collect()
+    }
+
+    /// Feeds the tracker with commitments of rotating types,
+    /// they come from processed diffs and from 'last_commitment_per_index' of QRINFO.
+    /// Only the ones which passed 'validate_quorum' (i.e. 'verified') are tracked
+    pub fn track_rotated_quorums<'a, I: IntoIterator<Item = &'a masternode::LLMQEntry>>(
+        &self,
+        quorums: I,
+        tracker: &mut RotatedQuorumTracker,
+    ) {
+        quorums
+            .into_iter()
+            .filter(|quorum| quorum.verified && self.rotation_config.is_rotating(quorum.llmq_type))
+            .for_each(|quorum| {
+                let height = self.lookup_block_height_by_hash(quorum.llmq_hash);
+                if height != u32::MAX {
+                    tracker.add_quorum(height, quorum.clone());
+                } else {
+                    self.log(format!("track_rotated_quorums: unknown height for {}", quorum.llmq_hash));
+                }
+            });
+    }
+
+    /// Bootstraps the session from the checkpoint instead of the diff from genesis.
+    /// The checkpoint is rejected unless its roots match the coinbase of the checkpoint block
+    pub fn load_checkpoint(&self, checkpoint: &MasternodeCheckpoint, coinbase: &CoinbaseTransaction, cache: &mut MasternodeProcessorCache) -> Result<(), CheckpointError> {
+        cache.load_checkpoint(checkpoint, coinbase)?;
+        self.track_rotated_quorums(
+            checkpoint.list.quorums.values().flat_map(|quorums| quorums.values()),
+            &mut cache.rotated_quorums,
+        );
+        Ok(())
+    }
+
     ///////////////////////////////////////////////////////////////////////////////////////////
     /// FFI-callbacks
     ///////////////////////////////////////////////////////////////////////////////////////////
@@ -1024,58 +1351,55 @@ impl MasternodeProcessor {
     }
 
     /// Calls c++ BLS lib via FFI
+    /// Members of rotated quorums are verified the same way,
+    /// they're just determined differently (see 'get_rotated_masternodes_for_quorum')
     fn validate_signature(
         &self,
         valid_masternodes: Vec<masternode::MasternodeEntry>,
         quorum: &mut masternode::LLMQEntry,
         block_height: u32,
-        mut has_valid_quorums: bool,
-    ) {
-        if quorum.llmq_type == LLMQType::Llmqtype60_75 {
-            has_valid_quorums &= true;
-        } else {
-            let operator_pks: Vec<*mut [u8; 48]> = (0..valid_masternodes.len())
-                .into_iter()
-                .filter_map(|i| {
-                    match quorum
-                        .signers_bitset
-                        .as_slice()
-                        .bit_is_true_at_le_index(i as u32)
-                    {
-                        true => Some(boxed(
-                            valid_masternodes[i].operator_public_key_at(block_height).0,
-                        )),
-                        false => None,
-                    }
-                })
-                .collect();
-            println!(
-                "validate_signature: {:?} {:?} {:?}",
-                valid_masternodes, quorum, operator_pks
-            );
-            let operator_public_keys_count = operator_pks.len();
-            let is_valid_signature = unsafe {
-                (self.validate_llmq)(
-                    boxed(types::LLMQValidationData {
-                        items: boxed_vec(operator_pks),
-                        count: operator_public_keys_count,
-                        commitment_hash: boxed(quorum.generate_commitment_hash().0),
-                        all_commitment_aggregated_signature: boxed(
-                            quorum.all_commitment_aggregated_signature.0,
-                        ),
-                        threshold_signature: boxed(quorum.threshold_signature.0),
-                        public_key: boxed(quorum.public_key.0),
-                    }),
-                    self.opaque_context,
-                )
-            };
-            let is_valid_payload = quorum.validate_payload();
-            has_valid_quorums &= is_valid_payload && is_valid_signature;
-        }
-
-        if has_valid_quorums {
+    ) -> bool {
+        let operator_pks: Vec<*mut [u8; 48]> = (0..valid_masternodes.len())
+            .into_iter()
+            .filter_map(|i| {
+                match quorum
+                    .signers_bitset
+                    .as_slice()
+                    .bit_is_true_at_le_index(i as u32)
+                {
+                    true => Some(boxed(
+                        valid_masternodes[i].operator_public_key_at(block_height).0,
+                    )),
+                    false => None,
+                }
+            })
+            .collect();
+        println!(
+            "validate_signature: {:?} {:?} {:?}",
+            valid_masternodes, quorum, operator_pks
+        );
+        let operator_public_keys_count = operator_pks.len();
+        let is_valid_signature = unsafe {
+            (self.validate_llmq)(
+                boxed(types::LLMQValidationData {
+                    items: boxed_vec(operator_pks),
+                    count: operator_public_keys_count,
+                    commitment_hash: boxed(quorum.generate_commitment_hash().0),
+                    all_commitment_aggregated_signature: boxed(
+                        quorum.all_commitment_aggregated_signature.0,
+                    ),
+                    threshold_signature: boxed(quorum.threshold_signature.0),
+                    public_key: boxed(quorum.public_key.0),
+                }),
+                self.opaque_context,
+            )
+        };
+        let is_valid_payload = quorum.validate_payload();
+        let has_valid_quorum = is_valid_payload && is_valid_signature;
+        if has_valid_quorum {
             quorum.verified = true;
         }
+        has_valid_quorum
     }
 
     pub fn read_list_diff_from_message<'a>(
@@ -1,15 +1,37 @@
+use crate::processing::merkle_trees::MasternodeListMerkleTrees;
+use crate::processing::rotated_quorum_tracker::RotatedQuorumTracker;
+use crate::processing::shared_masternode_list::SharedMasternodeList;
+use crate::store::{CheckpointError, MasternodeCheckpoint};
 use dash_spv_models::common::LLMQType;
 use dash_spv_models::llmq::{LLMQIndexedHash, LLMQSnapshot};
-use dash_spv_models::masternode::{MasternodeEntry, MasternodeList};
+use dash_spv_models::masternode::MasternodeEntry;
+use dash_spv_models::tx::CoinbaseTransaction;
 use dash_spv_primitives::crypto::UInt256;
 use std::collections::BTreeMap;
+use std::sync::Arc;
 
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MasternodeProcessorCache {
+    #[cfg_attr(feature = "serde", serde(with = "crate::processing::serialization::mirrored"))]
     pub llmq_members: BTreeMap<LLMQType, BTreeMap<UInt256, Vec<MasternodeEntry>>>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::processing::serialization::mirrored"))]
     pub llmq_indexed_members: BTreeMap<LLMQType, BTreeMap<LLMQIndexedHash, Vec<MasternodeEntry>>>,
-    pub mn_lists: BTreeMap<UInt256, MasternodeList>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::processing::serialization::mirrored"))]
+    pub mn_lists: BTreeMap<UInt256, Arc<SharedMasternodeList>>,
+    /// Block hashes of the lists by their known height, it's kept by 'add_masternode_list'
+    /// and 'remove_masternode_list'. Of the lists at the same height (e.g. of a fork)
+    /// the last added one is indexed, the other one takes its place once it's removed
+    #[cfg_attr(feature = "serde", serde(with = "crate::processing::serialization::mirrored"))]
+    pub mn_list_height_index: BTreeMap<u32, UInt256>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::processing::serialization::mirrored"))]
     pub llmq_snapshots: BTreeMap<UInt256, LLMQSnapshot>,
+    /// Trees are rebuilt from the lists when needed
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub merkle_trees: BTreeMap<UInt256, MasternodeListMerkleTrees>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::processing::serialization::mirrored"))]
+    pub rotated_quorums: RotatedQuorumTracker,
+    #[cfg_attr(feature = "serde", serde(with = "crate::processing::serialization::mirrored"))]
     pub needed_masternode_lists: Vec<UInt256>,
 }
 impl Default for MasternodeProcessorCache {
@@ -19,6 +41,9 @@ impl Default for MasternodeProcessorCache {
             llmq_indexed_members: BTreeMap::new(),
             llmq_snapshots: BTreeMap::new(),
             mn_lists: BTreeMap::new(),
+            mn_list_height_index: BTreeMap::new(),
+            merkle_trees: BTreeMap::new(),
+            rotated_quorums: RotatedQuorumTracker::default(),
             needed_masternode_lists: vec![],
         }
     }
@@ -31,15 +56,66 @@ impl std::fmt::Debug for MasternodeProcessorCache {
             .field("llmq_indexed_members", &self.llmq_indexed_members)
             .field("llmq_snapshots", &self.llmq_snapshots)
             .field("mn_lists", &self.mn_lists)
+            .field("merkle_trees", &self.merkle_trees.len())
+            .field("rotated_quorums", &self.rotated_quorums.len())
             .field("needed_masternode_lists", &self.needed_masternode_lists)
             .finish()
     }
 }
 
 impl MasternodeProcessorCache {
-    pub fn add_masternode_list(&mut self, block_hash: UInt256, list: MasternodeList) {
+    pub fn add_masternode_list(&mut self, block_hash: UInt256, list: Arc<SharedMasternodeList>) {
+        if list.known_height != u32::MAX {
+            self.mn_list_height_index.insert(list.known_height, block_hash);
+        }
         self.mn_lists.insert(block_hash, list);
     }
+
+    /// Drops the list along with its merkle trees
+    pub fn remove_masternode_list(&mut self, block_hash: &UInt256) -> Option<Arc<SharedMasternodeList>> {
+        self.merkle_trees.remove(block_hash);
+        let list = self.mn_lists.remove(block_hash)?;
+        if self.mn_list_height_index.get(&list.known_height) == Some(block_hash) {
+            match self.mn_lists.iter().find(|(_, other)| other.known_height == list.known_height) {
+                Some((other_hash, _)) => self.mn_list_height_index.insert(list.known_height, *other_hash),
+                None => self.mn_list_height_index.remove(&list.known_height),
+            };
+        }
+        Some(list)
+    }
+
+    /// Nearest list at or below the height (by its known height) along with its block hash
+    pub fn masternode_list_at_or_below(&self, block_height: u32) -> Option<(UInt256, Arc<SharedMasternodeList>)> {
+        self.mn_list_height_index
+            .range(..=block_height)
+            .next_back()
+            .and_then(|(_, block_hash)| self.mn_lists.get(block_hash).map(|list| (*block_hash, Arc::clone(list))))
+    }
+
+    pub fn masternode_list_at_height(&self, block_height: u32) -> Option<(UInt256, Arc<SharedMasternodeList>)> {
+        self.masternode_list_at_or_below(block_height)
+            .filter(|(_, list)| list.known_height == block_height)
+    }
+
+    /// Uses the checkpoint as a trusted base: its list, merkle trees and snapshots
+    /// are added as if they were processed. The checkpoint is only trusted
+    /// if its roots match the coinbase of the checkpoint block
+    pub fn load_checkpoint(&mut self, checkpoint: &MasternodeCheckpoint, coinbase: &CoinbaseTransaction) -> Result<(), CheckpointError> {
+        checkpoint.verify_coinbase(coinbase)?;
+        self.merkle_trees.insert(
+            checkpoint.block_hash,
+            MasternodeListMerkleTrees::from_shared_list(&checkpoint.list),
+        );
+        self.add_masternode_list(
+            checkpoint.block_hash,
+            Arc::clone(&checkpoint.list),
+        );
+        checkpoint.snapshots.iter().for_each(|(block_hash, snapshot)| {
+            self.llmq_snapshots.insert(*block_hash, snapshot.clone());
+        });
+        Ok(())
+    }
+
     pub fn get_quorum_members_of_type(
         &mut self,
         r#type: LLMQType,
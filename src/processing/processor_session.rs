@@ -0,0 +1,253 @@
+use crate::codec::{network, QRInfoMessage};
+use crate::processing::{LLMQRotationConfig, MNListDiffResult, MasternodeProcessor, MasternodeProcessorCache, ProcessingError, QRInfoResult};
+use crate::store::MasternodeStore;
+use dash_spv_models::common::chain_type::IHaveChainSettings;
+use dash_spv_models::common::{ChainType, LLMQType};
+use dash_spv_models::llmq;
+use dash_spv_primitives::crypto::byte_util::Zeroable;
+use dash_spv_primitives::crypto::UInt256;
+
+/// Chain the session is bound to.
+/// Devnets and regtest have their own genesis, so it's provided along with them
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SessionChain {
+    MainNet,
+    TestNet,
+    DevNet { name: String, genesis_hash: UInt256 },
+    RegTest { genesis_hash: UInt256 },
+}
+
+impl SessionChain {
+    pub fn genesis_hash(&self) -> UInt256 {
+        match self {
+            SessionChain::MainNet => ChainType::MainNet.genesis_hash(),
+            SessionChain::TestNet => ChainType::TestNet.genesis_hash(),
+            SessionChain::DevNet { genesis_hash, .. } | SessionChain::RegTest { genesis_hash } => *genesis_hash,
+        }
+    }
+
+    pub fn network_magic(&self) -> u32 {
+        match self {
+            SessionChain::MainNet => network::MAINNET_MAGIC,
+            SessionChain::TestNet => network::TESTNET_MAGIC,
+            SessionChain::DevNet { .. } => network::DEVNET_MAGIC,
+            SessionChain::RegTest { .. } => network::REGTEST_MAGIC,
+        }
+    }
+
+    /// Rotating types the chain runs: devnets have their own rotating type
+    /// along with the test one and regtest runs the test one only (as in Dash Core)
+    pub fn rotation_config(&self) -> LLMQRotationConfig {
+        match self {
+            SessionChain::MainNet => LLMQRotationConfig::for_chain(&ChainType::MainNet),
+            SessionChain::TestNet => LLMQRotationConfig::for_chain(&ChainType::TestNet),
+            SessionChain::DevNet { .. } => LLMQRotationConfig::from_types(&[LLMQType::LlmqtypeDevnetDIP0024, LLMQType::LlmqtypeTestDIP0024]),
+            SessionChain::RegTest { .. } => LLMQRotationConfig::from_types(&[LLMQType::LlmqtypeTestDIP0024]),
+        }
+    }
+
+    /// Genesis of another chain: the genesis of any of the public networks,
+    /// or any block the chain knows at height 0 (i.e. the genesis of a devnet or regtest)
+    fn is_foreign_genesis(&self, block_hash: &UInt256, block_height: u32) -> bool {
+        *block_hash != self.genesis_hash()
+            && !block_hash.is_zero()
+            && (block_height == 0
+                || *block_hash == ChainType::MainNet.genesis_hash()
+                || *block_hash == ChainType::TestNet.genesis_hash())
+    }
+}
+
+/// Processor bound to the chain: owns its cache, genesis and LLMQ configuration,
+/// so lists of different chains are never mixed within one cache.
+/// Diffs which don't belong to the chain are rejected with 'ProcessingError::InvalidChain'.
+/// With the store attached, each processed diff is saved to it right away
+pub struct ProcessorSession {
+    chain: SessionChain,
+    /// Boxed, so the pointer given to the processor stays valid when the session is moved
+    genesis_hash: Box<UInt256>,
+    processor: MasternodeProcessor,
+    cache: MasternodeProcessorCache,
+    store: Option<Box<dyn MasternodeStore>>,
+}
+
+impl std::fmt::Debug for ProcessorSession {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProcessorSession")
+            .field("chain", &self.chain)
+            .field("mn_lists", &self.cache.mn_lists.len())
+            .field("has_store", &self.store.is_some())
+            .finish()
+    }
+}
+
+impl ProcessorSession {
+    pub fn new(chain: SessionChain, mut processor: MasternodeProcessor) -> Self {
+        let genesis_hash = Box::new(chain.genesis_hash());
+        processor.rotation_config = chain.rotation_config();
+        processor.genesis_hash = genesis_hash.0.as_ptr();
+        Self {
+            chain,
+            genesis_hash,
+            processor,
+            cache: MasternodeProcessorCache::default(),
+            store: None,
+        }
+    }
+
+    pub fn with_store(mut self, store: Box<dyn MasternodeStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    pub fn set_store(&mut self, store: Box<dyn MasternodeStore>) {
+        self.store = Some(store);
+    }
+
+    pub fn store(&self) -> Option<&dyn MasternodeStore> {
+        self.store.as_deref()
+    }
+
+    pub fn chain(&self) -> &SessionChain {
+        &self.chain
+    }
+
+    pub fn genesis_hash(&self) -> UInt256 {
+        *self.genesis_hash
+    }
+
+    pub fn processor(&self) -> &MasternodeProcessor {
+        &self.processor
+    }
+
+    pub fn cache(&self) -> &MasternodeProcessorCache {
+        &self.cache
+    }
+
+    pub fn cache_mut(&mut self) -> &mut MasternodeProcessorCache {
+        &mut self.cache
+    }
+
+    fn bind(&mut self, context: *const std::ffi::c_void, use_insight_as_backup: bool) {
+        self.processor.opaque_context = context;
+        self.processor.use_insight_as_backup = use_insight_as_backup;
+        self.processor.genesis_hash = self.genesis_hash.0.as_ptr();
+    }
+
+    /// The diff belongs to the chain if its base is the genesis of the chain
+    /// or a block known to the chain, and its block is known to the chain
+    pub fn check_chain(&self, list_diff: &llmq::MNListDiff) -> Result<(), ProcessingError> {
+        let base_block_hash = list_diff.base_block_hash;
+        let base_block_height = self.processor.lookup_block_height_by_hash(base_block_hash);
+        if self.chain.is_foreign_genesis(&base_block_hash, base_block_height) {
+            self.processor.log(format!("session: diff is based on the genesis of another chain {}", base_block_hash));
+            return Err(ProcessingError::InvalidChain);
+        }
+        if list_diff.block_height == u32::MAX {
+            self.processor.log(format!("session: block {} is unknown to {:?}", list_diff.block_hash, self.chain));
+            return Err(ProcessingError::InvalidChain);
+        }
+        let is_known_base = base_block_hash.is_zero()
+            || base_block_hash == *self.genesis_hash
+            || self.cache.mn_lists.contains_key(&base_block_hash)
+            || base_block_height != u32::MAX;
+        if !is_known_base {
+            self.processor.log(format!("session: base block {} is unknown to {:?}", base_block_hash, self.chain));
+            return Err(ProcessingError::InvalidChain);
+        }
+        Ok(())
+    }
+
+    /// Reads and processes MNLISTDIFF message against the session cache
+    pub fn process_mnlistdiff(
+        &mut self,
+        message: &[u8],
+        use_insight_as_backup: bool,
+        is_from_snapshot: bool,
+        context: *const std::ffi::c_void,
+    ) -> MNListDiffResult {
+        self.bind(context, use_insight_as_backup);
+        let processor = &self.processor;
+        let list_diff = match llmq::MNListDiff::new(message, &mut 0, |hash| processor.lookup_block_height_by_hash(hash)) {
+            Some(list_diff) => list_diff,
+            None => return MNListDiffResult::default_with_error(ProcessingError::ParseError),
+        };
+        if let Err(error) = self.check_chain(&list_diff) {
+            return MNListDiffResult::default_with_error(error);
+        }
+        if !is_from_snapshot {
+            let error = self
+                .processor
+                .should_process_diff_with_range(list_diff.base_block_hash, list_diff.block_hash);
+            let none_error: u8 = ProcessingError::None.into();
+            if error != none_error {
+                return MNListDiffResult::default_with_error(ProcessingError::from(error));
+            }
+        }
+        let mut result = self
+            .processor
+            .get_list_diff_result_internal_with_base_lookup(list_diff, &mut self.cache);
+        if result.error_status == ProcessingError::None {
+            if let Err(error) = self.save_result(&result) {
+                result.error_status = error;
+            }
+        }
+        result
+    }
+
+    fn save_result(&mut self, result: &MNListDiffResult) -> Result<(), ProcessingError> {
+        match self.store.as_mut() {
+            Some(store) => store.save_list_diff_result(result, &self.cache).map_err(|err| {
+                self.processor.log(format!("session: failed to save list at {}: {}", result.block_hash, err));
+                ProcessingError::StoreFailed
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Reads and processes QRINFO message against the session cache,
+    /// it's rejected if any of its diffs doesn't belong to the chain.
+    /// With the store attached, lists of all its diffs are saved
+    pub fn process_qrinfo(
+        &mut self,
+        message: &[u8],
+        use_insight_as_backup: bool,
+        is_from_snapshot: bool,
+        context: *const std::ffi::c_void,
+    ) -> QRInfoResult {
+        self.bind(context, use_insight_as_backup);
+        let processor = &self.processor;
+        let message = match QRInfoMessage::from_bytes(message, &mut 0, |hash| processor.lookup_block_height_by_hash(hash)) {
+            Some(message) => message,
+            None => return QRInfoResult::default_with_error(ProcessingError::ParseError),
+        };
+        if let Err(error) = message.diffs().into_iter().try_for_each(|diff| self.check_chain(&diff.diff)) {
+            return QRInfoResult::default_with_error(error);
+        }
+        if !is_from_snapshot {
+            let error = self
+                .processor
+                .should_process_diff_with_range(message.diff_tip.diff.base_block_hash, message.diff_tip.diff.block_hash);
+            let none_error: u8 = ProcessingError::None.into();
+            if error != none_error {
+                return QRInfoResult::default_with_error(ProcessingError::from(error));
+            }
+        }
+        let mut result = self
+            .processor
+            .get_qr_info_result_internal(message, &mut self.cache);
+        if result.error_status == ProcessingError::None {
+            let diff_results = result
+                .result_at_h_4c
+                .iter()
+                .chain([&result.result_at_h_3c, &result.result_at_h_2c, &result.result_at_h_c, &result.result_at_h, &result.result_at_tip])
+                .chain(result.mn_list_diff_list.iter());
+            let saved = diff_results
+                .into_iter()
+                .try_for_each(|diff_result| self.save_result(diff_result));
+            if let Err(error) = saved {
+                result.error_status = error;
+            }
+        }
+        result
+    }
+}
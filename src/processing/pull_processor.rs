@@ -0,0 +1,403 @@
+use crate::codec::QRInfoMessage;
+use crate::processing::{LLMQRotationConfig, MNListDiffResult, MasternodeProcessor, MasternodeProcessorCache, ProcessingError, QRInfoResult, RotatedQuorumTracker, SessionChain, SharedMasternodeList};
+use dash_spv_ffi::ffi::boxer::boxed;
+use dash_spv_ffi::ffi::to::ToFFI;
+use dash_spv_ffi::ffi::unboxer::{unbox_any, unbox_llmq_snapshot, unbox_llmq_validation_data, unbox_masternode_list};
+use dash_spv_ffi::types;
+use dash_spv_models::llmq::{self, LLMQSnapshot};
+use dash_spv_models::masternode::MasternodeList;
+use dash_spv_primitives::crypto::byte_util::Zeroable;
+use dash_spv_primitives::crypto::{UInt256, UInt384, UInt768};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ffi::c_void;
+use std::ptr::null_mut;
+use std::sync::Arc;
+
+/// Data of the quorum commitment the host should verify (BLS signatures)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QuorumValidationData {
+    pub operator_public_keys: Vec<UInt384>,
+    pub commitment_hash: UInt256,
+    pub all_commitment_aggregated_signature: UInt768,
+    pub threshold_signature: UInt768,
+    pub public_key: UInt384,
+}
+
+/// What the processing is missing, everything is keyed by block hash except the hashes
+/// of the blocks at heights. Quorum validations are keyed by commitment hash
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProcessingNeeds {
+    pub block_heights: BTreeSet<UInt256>,
+    pub block_hashes: BTreeSet<u32>,
+    pub merkle_roots: BTreeSet<UInt256>,
+    pub masternode_lists: BTreeSet<UInt256>,
+    pub snapshots: BTreeSet<UInt256>,
+    pub quorum_validations: BTreeMap<UInt256, QuorumValidationData>,
+}
+
+impl ProcessingNeeds {
+    pub fn is_empty(&self) -> bool {
+        self.block_heights.is_empty()
+            && self.block_hashes.is_empty()
+            && self.merkle_roots.is_empty()
+            && self.masternode_lists.is_empty()
+            && self.snapshots.is_empty()
+            && self.quorum_validations.is_empty()
+    }
+}
+
+/// Answers of the host, None is the answer as well: the host doesn't have it
+#[derive(Debug, Default)]
+struct PullContext {
+    block_heights: BTreeMap<UInt256, Option<u32>>,
+    block_hashes: BTreeMap<u32, Option<UInt256>>,
+    merkle_roots: BTreeMap<UInt256, Option<UInt256>>,
+    masternode_lists: BTreeMap<UInt256, Option<MasternodeList>>,
+    snapshots: BTreeMap<UInt256, Option<LLMQSnapshot>>,
+    quorum_validations: BTreeMap<UInt256, bool>,
+    needs: ProcessingNeeds,
+}
+
+impl PullContext {
+    unsafe fn from_ptr<'a>(context: *const c_void) -> std::cell::RefMut<'a, PullContext> {
+        (*(context as *const RefCell<PullContext>)).borrow_mut()
+    }
+}
+
+unsafe extern "C" fn get_merkle_root_by_hash(block_hash: *mut [u8; 32], context: *const c_void) -> *mut u8 {
+    let mut context = PullContext::from_ptr(context);
+    let block_hash = UInt256(*unbox_any(block_hash));
+    match context.merkle_roots.get(&block_hash) {
+        Some(Some(merkle_root)) => boxed(merkle_root.0) as *mut u8,
+        Some(None) => null_mut(),
+        None => {
+            context.needs.merkle_roots.insert(block_hash);
+            null_mut()
+        }
+    }
+}
+
+unsafe extern "C" fn get_block_height_by_hash(block_hash: *mut [u8; 32], context: *const c_void) -> u32 {
+    let mut context = PullContext::from_ptr(context);
+    let block_hash = UInt256(*unbox_any(block_hash));
+    match context.block_heights.get(&block_hash) {
+        Some(height) => height.unwrap_or(u32::MAX),
+        None if block_hash.is_zero() => u32::MAX,
+        None => {
+            context.needs.block_heights.insert(block_hash);
+            u32::MAX
+        }
+    }
+}
+
+unsafe extern "C" fn get_block_hash_by_height(block_height: u32, context: *const c_void) -> *mut u8 {
+    let mut context = PullContext::from_ptr(context);
+    match context.block_hashes.get(&block_height) {
+        Some(Some(block_hash)) => boxed(block_hash.0) as *mut u8,
+        Some(None) => null_mut(),
+        None => {
+            context.needs.block_hashes.insert(block_height);
+            null_mut()
+        }
+    }
+}
+
+unsafe extern "C" fn get_llmq_snapshot_by_block_hash(block_hash: *mut [u8; 32], context: *const c_void) -> *mut types::LLMQSnapshot {
+    let mut context = PullContext::from_ptr(context);
+    let block_hash = UInt256(*unbox_any(block_hash));
+    match context.snapshots.get(&block_hash) {
+        Some(Some(snapshot)) => boxed(snapshot.encode()),
+        Some(None) => null_mut(),
+        None => {
+            context.needs.snapshots.insert(block_hash);
+            null_mut()
+        }
+    }
+}
+
+/// Snapshots of QRINFO are kept by the cache
+unsafe extern "C" fn save_llmq_snapshot(block_hash: *mut [u8; 32], snapshot: *mut types::LLMQSnapshot, _context: *const c_void) -> bool {
+    unbox_any(block_hash);
+    unbox_llmq_snapshot(snapshot);
+    true
+}
+
+unsafe extern "C" fn get_masternode_list_by_block_hash(block_hash: *mut [u8; 32], context: *const c_void) -> *mut types::MasternodeList {
+    let mut context = PullContext::from_ptr(context);
+    let block_hash = UInt256(*unbox_any(block_hash));
+    match context.masternode_lists.get(&block_hash) {
+        Some(Some(list)) => boxed(list.encode()),
+        Some(None) => null_mut(),
+        None => {
+            context.needs.masternode_lists.insert(block_hash);
+            null_mut()
+        }
+    }
+}
+
+/// Lists are kept by the cache
+unsafe extern "C" fn save_masternode_list(block_hash: *mut [u8; 32], masternode_list: *mut types::MasternodeList, _context: *const c_void) -> bool {
+    unbox_any(block_hash);
+    unbox_masternode_list(masternode_list);
+    true
+}
+
+unsafe extern "C" fn destroy_masternode_list(masternode_list: *mut types::MasternodeList) {
+    if !masternode_list.is_null() {
+        unbox_masternode_list(masternode_list);
+    }
+}
+
+/// Unknown blocks are reported as needs instead
+unsafe extern "C" fn add_insight(block_hash: *mut [u8; 32], _context: *const c_void) {
+    unbox_any(block_hash);
+}
+
+unsafe extern "C" fn should_process_llmq_of_type(_llmq_type: u8, _context: *const c_void) -> bool {
+    true
+}
+
+unsafe extern "C" fn validate_llmq(data: *mut types::LLMQValidationData, context: *const c_void) -> bool {
+    let mut context = PullContext::from_ptr(context);
+    let validation = {
+        let data = &*data;
+        QuorumValidationData {
+            operator_public_keys: (0..data.count).map(|i| UInt384(**data.items.add(i))).collect(),
+            commitment_hash: UInt256(*data.commitment_hash),
+            all_commitment_aggregated_signature: UInt768(*data.all_commitment_aggregated_signature),
+            threshold_signature: UInt768(*data.threshold_signature),
+            public_key: UInt384(*data.public_key),
+        }
+    };
+    unbox_llmq_validation_data(data);
+    match context.quorum_validations.get(&validation.commitment_hash) {
+        Some(is_valid) => *is_valid,
+        None => {
+            context.needs.quorum_validations.insert(validation.commitment_hash, validation);
+            false
+        }
+    }
+}
+
+unsafe extern "C" fn destroy_hash(hash: *mut u8) {
+    if !hash.is_null() {
+        unbox_any(hash as *mut [u8; 32]);
+    }
+}
+
+unsafe extern "C" fn destroy_snapshot(snapshot: *mut types::LLMQSnapshot) {
+    if !snapshot.is_null() {
+        unbox_llmq_snapshot(snapshot);
+    }
+}
+
+/// The host decides which diffs to request before the processing
+unsafe extern "C" fn should_process_diff_with_range(base_block_hash: *mut [u8; 32], block_hash: *mut [u8; 32], _context: *const c_void) -> u8 {
+    unbox_any(base_block_hash);
+    unbox_any(block_hash);
+    ProcessingError::None.into()
+}
+
+unsafe extern "C" fn log_message(_message: *const libc::c_char, _context: *const c_void) {}
+
+/// Part of the cache the suspended processing must leave untouched. Lists are shared,
+/// so they're cheap to copy. Quorum members and merkle trees are derived from the lists,
+/// so they're kept, except the trees of the lists made by the suspended processing
+struct CacheCheckpoint {
+    mn_lists: BTreeMap<UInt256, Arc<SharedMasternodeList>>,
+    mn_list_height_index: BTreeMap<u32, UInt256>,
+    llmq_snapshots: BTreeMap<UInt256, LLMQSnapshot>,
+    rotated_quorums: RotatedQuorumTracker,
+    needed_masternode_lists: Vec<UInt256>,
+}
+
+impl CacheCheckpoint {
+    fn new(cache: &MasternodeProcessorCache) -> Self {
+        Self {
+            mn_lists: cache.mn_lists.clone(),
+            mn_list_height_index: cache.mn_list_height_index.clone(),
+            llmq_snapshots: cache.llmq_snapshots.clone(),
+            rotated_quorums: cache.rotated_quorums.clone(),
+            needed_masternode_lists: cache.needed_masternode_lists.clone(),
+        }
+    }
+
+    fn restore(self, cache: &mut MasternodeProcessorCache) {
+        let made_lists = cache
+            .mn_lists
+            .iter()
+            .filter(|(block_hash, list)| self.mn_lists.get(block_hash).map_or(true, |kept| !Arc::ptr_eq(kept, list)))
+            .map(|(block_hash, _)| *block_hash)
+            .collect::<Vec<UInt256>>();
+        made_lists.iter().for_each(|block_hash| {
+            cache.merkle_trees.remove(block_hash);
+        });
+        cache.mn_lists = self.mn_lists;
+        cache.mn_list_height_index = self.mn_list_height_index;
+        cache.llmq_snapshots = self.llmq_snapshots;
+        cache.rotated_quorums = self.rotated_quorums;
+        cache.needed_masternode_lists = self.needed_masternode_lists;
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PullMessage {
+    ListDiff(Vec<u8>),
+    QRInfo(Vec<u8>),
+}
+
+#[derive(Debug)]
+pub enum PullResult {
+    ListDiff(MNListDiffResult),
+    QRInfo(QRInfoResult),
+}
+
+#[derive(Debug)]
+pub enum PullStep {
+    /// Processing is suspended until the host supplies all of these
+    Needs(ProcessingNeeds),
+    Done(PullResult),
+}
+
+/// Processing without callbacks into the host: the message is processed with the data
+/// supplied so far and what's missing is returned as needs. Once the host supplies them
+/// (in any way, e.g. from async storage or network) the processing is resumed.
+/// Each attempt runs against the cache with a checkpoint of what it changes, which is restored
+/// when the attempt is suspended, so the suspended processing leaves no trace. Answers are kept between messages
+pub struct PullProcessor {
+    /// Boxed, so the pointers given to the processor stay valid when it's moved
+    genesis_hash: Box<UInt256>,
+    context: Box<RefCell<PullContext>>,
+    processor: MasternodeProcessor,
+    cache: MasternodeProcessorCache,
+    pending: Option<PullMessage>,
+}
+
+/// Pointers of the processor point only into the boxes owned by this struct,
+/// so it's safe to move it to another thread (e.g. with the future processing it)
+unsafe impl Send for PullProcessor {}
+
+impl std::fmt::Debug for PullProcessor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PullProcessor")
+            .field("genesis_hash", &self.genesis_hash)
+            .field("mn_lists", &self.cache.mn_lists.len())
+            .field("pending", &self.pending.is_some())
+            .finish()
+    }
+}
+
+impl PullProcessor {
+    pub fn new(genesis_hash: UInt256, rotation_config: LLMQRotationConfig) -> Self {
+        let mut processor = MasternodeProcessor::new(
+            get_merkle_root_by_hash,
+            get_block_height_by_hash,
+            get_block_hash_by_height,
+            get_llmq_snapshot_by_block_hash,
+            save_llmq_snapshot,
+            get_masternode_list_by_block_hash,
+            save_masternode_list,
+            destroy_masternode_list,
+            add_insight,
+            should_process_llmq_of_type,
+            validate_llmq,
+            destroy_hash,
+            destroy_snapshot,
+            should_process_diff_with_range,
+            log_message,
+        );
+        let genesis_hash = Box::new(genesis_hash);
+        let context = Box::new(RefCell::new(PullContext::default()));
+        context.borrow_mut().block_heights.insert(*genesis_hash, Some(0));
+        processor.genesis_hash = genesis_hash.0.as_ptr();
+        processor.opaque_context = &*context as *const RefCell<PullContext> as *const c_void;
+        processor.rotation_config = rotation_config;
+        Self { genesis_hash, context, processor, cache: MasternodeProcessorCache::default(), pending: None }
+    }
+
+    pub fn for_chain(chain: &SessionChain) -> Self {
+        Self::new(chain.genesis_hash(), chain.rotation_config())
+    }
+
+    pub fn cache(&self) -> &MasternodeProcessorCache {
+        &self.cache
+    }
+
+    pub fn cache_mut(&mut self) -> &mut MasternodeProcessorCache {
+        &mut self.cache
+    }
+
+    pub fn pending(&self) -> Option<&PullMessage> {
+        self.pending.as_ref()
+    }
+
+    pub fn supply_block_height(&mut self, block_hash: UInt256, block_height: Option<u32>) {
+        self.context.borrow_mut().block_heights.insert(block_hash, block_height);
+    }
+
+    pub fn supply_block_hash(&mut self, block_height: u32, block_hash: Option<UInt256>) {
+        self.context.borrow_mut().block_hashes.insert(block_height, block_hash);
+    }
+
+    pub fn supply_merkle_root(&mut self, block_hash: UInt256, merkle_root: Option<UInt256>) {
+        self.context.borrow_mut().merkle_roots.insert(block_hash, merkle_root);
+    }
+
+    pub fn supply_masternode_list(&mut self, block_hash: UInt256, list: Option<MasternodeList>) {
+        self.context.borrow_mut().masternode_lists.insert(block_hash, list);
+    }
+
+    pub fn supply_snapshot(&mut self, block_hash: UInt256, snapshot: Option<LLMQSnapshot>) {
+        self.context.borrow_mut().snapshots.insert(block_hash, snapshot);
+    }
+
+    pub fn supply_quorum_validation(&mut self, commitment_hash: UInt256, is_valid: bool) {
+        self.context.borrow_mut().quorum_validations.insert(commitment_hash, is_valid);
+    }
+
+    /// Starts processing of the message, the one suspended before is dropped
+    pub fn start(&mut self, message: PullMessage) -> PullStep {
+        self.pending = Some(message);
+        self.run()
+    }
+
+    /// Continues the suspended processing, None if there is nothing to resume
+    pub fn resume(&mut self) -> Option<PullStep> {
+        self.pending.is_some().then(|| self.run())
+    }
+
+    fn run(&mut self) -> PullStep {
+        let message = match &self.pending {
+            Some(message) => message,
+            None => return PullStep::Done(PullResult::ListDiff(MNListDiffResult::default_with_error(ProcessingError::ParseError))),
+        };
+        self.context.borrow_mut().needs = ProcessingNeeds::default();
+        let checkpoint = CacheCheckpoint::new(&self.cache);
+        let cache = &mut self.cache;
+        let processor = &self.processor;
+        let block_height_lookup = |hash: UInt256| processor.lookup_block_height_by_hash(hash);
+        let result = match message {
+            PullMessage::ListDiff(bytes) => PullResult::ListDiff(
+                match llmq::MNListDiff::new(bytes, &mut 0, block_height_lookup) {
+                    Some(list_diff) => processor.get_list_diff_result_internal_with_base_lookup(list_diff, cache),
+                    None => MNListDiffResult::default_with_error(ProcessingError::ParseError),
+                },
+            ),
+            PullMessage::QRInfo(bytes) => PullResult::QRInfo(
+                match QRInfoMessage::from_bytes(bytes, &mut 0, block_height_lookup) {
+                    Some(message) => processor.get_qr_info_result_internal(message, cache),
+                    None => QRInfoResult::default_with_error(ProcessingError::ParseError),
+                },
+            ),
+        };
+        let needs = std::mem::take(&mut self.context.borrow_mut().needs);
+        if needs.is_empty() {
+            self.pending = None;
+            PullStep::Done(result)
+        } else {
+            checkpoint.restore(&mut self.cache);
+            PullStep::Needs(needs)
+        }
+    }
+}
@@ -1,8 +1,14 @@
-use crate::processing::{MNListDiffResult, ProcessingError};
+use crate::processing::{LLMQValidationStatus, MNListDiffResult, ProcessingError};
+use dash_spv_ffi::ffi::boxer::{boxed, boxed_vec};
+use dash_spv_ffi::ffi::to::ToFFI;
+use dash_spv_ffi::ffi::unboxer::{unbox_llmq_entry, unbox_qr_info_result, unbox_vec_ptr};
+use dash_spv_ffi::types;
 use dash_spv_models::llmq::LLMQSnapshot;
 use dash_spv_models::masternode::LLMQEntry;
+use std::ptr::null_mut;
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct QRInfoResult {
     pub error_status: ProcessingError,
     pub result_at_tip: MNListDiffResult,
@@ -12,13 +18,23 @@ pub struct QRInfoResult {
     pub result_at_h_3c: MNListDiffResult,
     pub result_at_h_4c: Option<MNListDiffResult>,
 
+    #[cfg_attr(feature = "serde", serde(with = "crate::processing::serialization::mirrored"))]
     pub snapshot_at_h_c: LLMQSnapshot,
+    #[cfg_attr(feature = "serde", serde(with = "crate::processing::serialization::mirrored"))]
     pub snapshot_at_h_2c: LLMQSnapshot,
+    #[cfg_attr(feature = "serde", serde(with = "crate::processing::serialization::mirrored"))]
     pub snapshot_at_h_3c: LLMQSnapshot,
+    #[cfg_attr(feature = "serde", serde(with = "crate::processing::serialization::mirrored"))]
     pub snapshot_at_h_4c: Option<LLMQSnapshot>,
 
     pub extra_share: bool,
+    /// Rotated quorums mined at h-3c, which can be fully validated only with extra share,
+    /// their statuses are in 'result_at_h_3c' (see 'rotated_quorum_statuses_at_h_3c')
+    #[cfg_attr(feature = "serde", serde(with = "crate::processing::serialization::mirrored"))]
+    pub rotated_quorums_at_h_3c: Vec<LLMQEntry>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::processing::serialization::mirrored"))]
     pub last_quorum_per_index: Vec<LLMQEntry>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::processing::serialization::mirrored"))]
     pub quorum_snapshot_list: Vec<LLMQSnapshot>,
     pub mn_list_diff_list: Vec<MNListDiffResult>,
 }
@@ -37,6 +53,7 @@ impl Default for QRInfoResult {
             snapshot_at_h_3c: Default::default(),
             snapshot_at_h_4c: None,
             extra_share: false,
+            rotated_quorums_at_h_3c: vec![],
             last_quorum_per_index: vec![],
             quorum_snapshot_list: vec![],
             mn_list_diff_list: vec![],
@@ -50,4 +67,104 @@ impl QRInfoResult {
         result.error_status = error;
         result
     }
+
+    /// Statuses of 'rotated_quorums_at_h_3c' in the same order
+    pub fn rotated_quorum_statuses_at_h_3c(&self) -> Vec<LLMQValidationStatus> {
+        self.rotated_quorums_at_h_3c
+            .iter()
+            .map(|quorum| {
+                self.result_at_h_3c
+                    .quorum_status(quorum.llmq_type, quorum.llmq_hash)
+                    .unwrap_or(LLMQValidationStatus::Unknown)
+            })
+            .collect()
+    }
+}
+
+impl QRInfoResult {
+    pub fn encode(&self) -> types::QRInfoResult {
+        types::QRInfoResult {
+            error_status: self.error_status.into(),
+            result_at_tip: boxed(self.result_at_tip.encode()),
+            result_at_h: boxed(self.result_at_h.encode()),
+            result_at_h_c: boxed(self.result_at_h_c.encode()),
+            result_at_h_2c: boxed(self.result_at_h_2c.encode()),
+            result_at_h_3c: boxed(self.result_at_h_3c.encode()),
+            result_at_h_4c: self
+                .result_at_h_4c
+                .as_ref()
+                .map_or(null_mut(), |result| boxed(result.encode())),
+            snapshot_at_h_c: boxed(self.snapshot_at_h_c.encode()),
+            snapshot_at_h_2c: boxed(self.snapshot_at_h_2c.encode()),
+            snapshot_at_h_3c: boxed(self.snapshot_at_h_3c.encode()),
+            snapshot_at_h_4c: self
+                .snapshot_at_h_4c
+                .as_ref()
+                .map_or(null_mut(), |snapshot| boxed(snapshot.encode())),
+            extra_share: self.extra_share,
+            last_quorum_per_index: boxed_vec(
+                self.last_quorum_per_index
+                    .iter()
+                    .map(|quorum| boxed(quorum.encode()))
+                    .collect(),
+            ),
+            last_quorum_per_index_count: self.last_quorum_per_index.len(),
+            quorum_snapshot_list: boxed_vec(
+                self.quorum_snapshot_list
+                    .iter()
+                    .map(|snapshot| boxed(snapshot.encode()))
+                    .collect(),
+            ),
+            quorum_snapshot_list_count: self.quorum_snapshot_list.len(),
+            mn_list_diff_list: boxed_vec(
+                self.mn_list_diff_list
+                    .iter()
+                    .map(|result| boxed(result.encode()))
+                    .collect(),
+            ),
+            mn_list_diff_list_count: self.mn_list_diff_list.len(),
+        }
+    }
+}
+
+/// FFI result of QRINFO along with what 'types::QRInfoResult' doesn't have:
+/// rotated quorums at h-3c and their validation statuses as parallel arrays
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct QRInfoValidationResult {
+    pub result: *mut types::QRInfoResult,
+    pub rotated_quorums_at_h_3c_count: usize,
+    pub rotated_quorums_at_h_3c: *mut *mut types::LLMQEntry,
+    pub rotated_quorum_statuses_at_h_3c: *mut u8,
+}
+
+impl QRInfoResult {
+    pub fn encode_with_validation(&self) -> QRInfoValidationResult {
+        QRInfoValidationResult {
+            result: boxed(self.encode()),
+            rotated_quorums_at_h_3c_count: self.rotated_quorums_at_h_3c.len(),
+            rotated_quorums_at_h_3c: boxed_vec(
+                self.rotated_quorums_at_h_3c
+                    .iter()
+                    .map(|quorum| boxed(quorum.encode()))
+                    .collect(),
+            ),
+            rotated_quorum_statuses_at_h_3c: boxed_vec(
+                self.rotated_quorum_statuses_at_h_3c()
+                    .into_iter()
+                    .map(u8::from)
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl QRInfoValidationResult {
+    pub unsafe fn destroy(self) {
+        unbox_qr_info_result(self.result);
+        unbox_vec_ptr(self.rotated_quorums_at_h_3c, self.rotated_quorums_at_h_3c_count)
+            .into_iter()
+            .for_each(|quorum| unbox_llmq_entry(quorum));
+        unbox_vec_ptr(self.rotated_quorum_statuses_at_h_3c, self.rotated_quorums_at_h_3c_count);
+    }
 }
@@ -0,0 +1,123 @@
+use dash_spv_models::common::LLMQType;
+use dash_spv_models::masternode::LLMQEntry;
+use dash_spv_primitives::crypto::UInt256;
+use std::collections::BTreeMap;
+
+/// Keeps track of which commitment is active at each index of rotated quorums.
+/// Commitments are stored by height of their quorum block,
+/// so the one active at any height is the latest mined at or below it
+/// unless it was deleted from the list at or below that height
+#[derive(Clone, Debug, Default)]
+pub struct RotatedQuorumTracker {
+    history: BTreeMap<(LLMQType, u16), BTreeMap<u32, LLMQEntry>>,
+    /// Heights of the lists the commitments were deleted from
+    closed: BTreeMap<(LLMQType, UInt256), u32>,
+}
+
+impl RotatedQuorumTracker {
+    /// Commitments without quorum index are not rotated ones and are ignored
+    pub fn add_quorum(&mut self, height: u32, quorum: LLMQEntry) -> bool {
+        match quorum.index {
+            Some(index) => {
+                self.history
+                    .entry((quorum.llmq_type, index))
+                    .or_insert_with(BTreeMap::new)
+                    .insert(height, quorum);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Closes the history entry of the commitment deleted from the list at the height,
+    /// it's not active from there on. Returns false if the commitment isn't tracked
+    pub fn close_quorum(&mut self, llmq_type: LLMQType, llmq_hash: UInt256, height: u32) -> bool {
+        let is_tracked = self
+            .history
+            .iter()
+            .filter(|((r#type, _), _)| *r#type == llmq_type)
+            .any(|(_, quorums)| quorums.values().any(|quorum| quorum.llmq_hash == llmq_hash));
+        if is_tracked {
+            self.closed.insert((llmq_type, llmq_hash), height);
+        }
+        is_tracked
+    }
+
+    /// Height of the list the commitment was deleted from
+    pub fn closed_at(&self, llmq_type: LLMQType, llmq_hash: UInt256) -> Option<u32> {
+        self.closed.get(&(llmq_type, llmq_hash)).cloned()
+    }
+
+    fn is_open_at(&self, quorum: &LLMQEntry, height: u32) -> bool {
+        self.closed_at(quorum.llmq_type, quorum.llmq_hash)
+            .map_or(true, |closed_at| closed_at > height)
+    }
+
+    fn active_in<'a>(&self, quorums: &'a BTreeMap<u32, LLMQEntry>, height: u32) -> Option<&'a LLMQEntry> {
+        quorums
+            .range(..=height)
+            .next_back()
+            .map(|(_, quorum)| quorum)
+            .filter(|quorum| self.is_open_at(quorum, height))
+    }
+
+    pub fn active_quorum(&self, llmq_type: LLMQType, index: u16, height: u32) -> Option<&LLMQEntry> {
+        self.history
+            .get(&(llmq_type, index))
+            .and_then(|quorums| self.active_in(quorums, height))
+    }
+
+    /// Active commitments of the type at the height ordered by quorum index
+    pub fn active_quorums(&self, llmq_type: LLMQType, height: u32) -> Vec<&LLMQEntry> {
+        self.history
+            .iter()
+            .filter(|((r#type, _), _)| *r#type == llmq_type)
+            .filter_map(|(_, quorums)| self.active_in(quorums, height))
+            .collect()
+    }
+
+    /// All known commitments at the quorum index with heights of their quorum blocks
+    pub fn history(&self, llmq_type: LLMQType, index: u16) -> Option<&BTreeMap<u32, LLMQEntry>> {
+        self.history.get(&(llmq_type, index))
+    }
+
+    /// All known commitments with heights of their quorum blocks
+    pub fn iter(&self) -> impl Iterator<Item = (u32, &LLMQEntry)> + '_ {
+        self.history
+            .values()
+            .flat_map(|quorums| quorums.iter().map(|(&height, quorum)| (height, quorum)))
+    }
+
+    pub fn indexes(&self, llmq_type: LLMQType) -> Vec<u16> {
+        self.history
+            .keys()
+            .filter(|(r#type, _)| *r#type == llmq_type)
+            .map(|&(_, index)| index)
+            .collect()
+    }
+
+    /// Drops commitments which are no longer active at the height
+    pub fn prune(&mut self, height: u32) {
+        let closed = &self.closed;
+        self.history.values_mut().for_each(|quorums| {
+            if let Some(&active_height) = quorums.range(..=height).next_back().map(|(h, _)| h) {
+                *quorums = quorums.split_off(&active_height);
+            }
+            quorums.retain(|_, quorum| {
+                closed
+                    .get(&(quorum.llmq_type, quorum.llmq_hash))
+                    .map_or(true, |&closed_at| closed_at > height)
+            });
+        });
+        self.history.retain(|_, quorums| !quorums.is_empty());
+        self.closed.retain(|_, closed_at| *closed_at > height);
+    }
+
+    pub fn len(&self) -> usize {
+        self.history.values().map(BTreeMap::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.history.is_empty()
+    }
+}
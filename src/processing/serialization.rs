@@ -0,0 +1,390 @@
+//! Serialization of the processing results and the cache (available with 'serde' feature).
+//! Models of dash-spv-models and dash-spv-primitives don't implement serde,
+//! so each of them is mirrored with a plain struct: hashes, keys and signatures are hex strings.
+//! Commitments and addresses are in the form of Dash Core RPC (see core_json)
+use crate::core_json::{quorum_from_json, quorum_to_json, socket_address_from_json, socket_address_to_json, LLMQ};
+use crate::processing::{LLMQValidationStatus, RotatedQuorumTracker, SharedMasternodeList};
+use dash_spv_models::common::{Block, LLMQSnapshotSkipMode, LLMQType};
+use dash_spv_models::llmq::{LLMQIndexedHash, LLMQSnapshot};
+use dash_spv_models::masternode::{LLMQEntry, MasternodeEntry, MasternodeList};
+use dash_spv_primitives::crypto::byte_util::Reversable;
+use dash_spv_primitives::crypto::{UInt128, UInt160, UInt256, UInt384, UInt768};
+use dash_spv_primitives::hashes::hex::{FromHex, ToHex};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Conversion into the serializable form and back
+pub trait SerdeMirror: Sized {
+    type Mirror: Serialize + DeserializeOwned;
+    fn to_mirror(&self) -> Self::Mirror;
+    fn from_mirror(mirror: Self::Mirror) -> Option<Self>;
+}
+
+/// To be used as '#[serde(with = "mirrored")]' for the fields of the mirrored types
+pub mod mirrored {
+    use super::SerdeMirror;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<T: SerdeMirror, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+        value.to_mirror().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, T: SerdeMirror, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+        let mirror = T::Mirror::deserialize(deserializer)?;
+        T::from_mirror(mirror).ok_or_else(|| D::Error::custom("invalid value"))
+    }
+}
+
+macro_rules! impl_hex_mirror {
+    ($($t:ty),*) => {
+        $(impl SerdeMirror for $t {
+            type Mirror = String;
+            fn to_mirror(&self) -> Self::Mirror {
+                self.0.to_hex()
+            }
+            fn from_mirror(mirror: Self::Mirror) -> Option<Self> {
+                <$t>::from_hex(&mirror).ok()
+            }
+        })*
+    };
+}
+
+impl_hex_mirror!(UInt128, UInt160, UInt384, UInt768);
+
+impl SerdeMirror for u32 {
+    type Mirror = u32;
+    fn to_mirror(&self) -> Self::Mirror {
+        *self
+    }
+    fn from_mirror(mirror: Self::Mirror) -> Option<Self> {
+        Some(mirror)
+    }
+}
+
+/// Hashes are shown reversed as Dash Core does
+impl SerdeMirror for UInt256 {
+    type Mirror = String;
+    fn to_mirror(&self) -> Self::Mirror {
+        self.clone().reversed().0.to_hex()
+    }
+    fn from_mirror(mirror: Self::Mirror) -> Option<Self> {
+        UInt256::from_hex(&mirror).ok().map(|hash| hash.clone().reversed())
+    }
+}
+
+impl SerdeMirror for LLMQType {
+    type Mirror = u8;
+    fn to_mirror(&self) -> Self::Mirror {
+        (*self).into()
+    }
+    fn from_mirror(mirror: Self::Mirror) -> Option<Self> {
+        Some(LLMQType::from(mirror))
+    }
+}
+
+impl SerdeMirror for LLMQValidationStatus {
+    type Mirror = u8;
+    fn to_mirror(&self) -> Self::Mirror {
+        (*self).into()
+    }
+    fn from_mirror(mirror: Self::Mirror) -> Option<Self> {
+        Some(LLMQValidationStatus::from(mirror))
+    }
+}
+
+impl<T: SerdeMirror> SerdeMirror for Arc<T> {
+    type Mirror = T::Mirror;
+    fn to_mirror(&self) -> Self::Mirror {
+        T::to_mirror(self)
+    }
+    fn from_mirror(mirror: Self::Mirror) -> Option<Self> {
+        T::from_mirror(mirror).map(Arc::new)
+    }
+}
+
+impl<T: SerdeMirror> SerdeMirror for Option<T> {
+    type Mirror = Option<T::Mirror>;
+    fn to_mirror(&self) -> Self::Mirror {
+        self.as_ref().map(T::to_mirror)
+    }
+    fn from_mirror(mirror: Self::Mirror) -> Option<Self> {
+        match mirror {
+            Some(mirror) => T::from_mirror(mirror).map(Some),
+            None => Some(None),
+        }
+    }
+}
+
+impl<T: SerdeMirror> SerdeMirror for Vec<T> {
+    type Mirror = Vec<T::Mirror>;
+    fn to_mirror(&self) -> Self::Mirror {
+        self.iter().map(T::to_mirror).collect()
+    }
+    fn from_mirror(mirror: Self::Mirror) -> Option<Self> {
+        mirror.into_iter().map(T::from_mirror).collect()
+    }
+}
+
+impl<K: SerdeMirror + Ord, V: SerdeMirror> SerdeMirror for BTreeMap<K, V>
+where
+    K::Mirror: Ord,
+{
+    type Mirror = BTreeMap<K::Mirror, V::Mirror>;
+    fn to_mirror(&self) -> Self::Mirror {
+        self.iter().map(|(key, value)| (key.to_mirror(), value.to_mirror())).collect()
+    }
+    fn from_mirror(mirror: Self::Mirror) -> Option<Self> {
+        mirror
+            .into_iter()
+            .map(|(key, value)| Some((K::from_mirror(key)?, V::from_mirror(value)?)))
+            .collect()
+    }
+}
+
+/// Rendered as "hash:index", so it could be a key of JSON object
+impl SerdeMirror for LLMQIndexedHash {
+    type Mirror = String;
+    fn to_mirror(&self) -> Self::Mirror {
+        format!("{}:{}", self.hash.to_mirror(), self.index)
+    }
+    fn from_mirror(mirror: Self::Mirror) -> Option<Self> {
+        let (hash, index) = mirror.split_once(':')?;
+        Some(LLMQIndexedHash::new(UInt256::from_mirror(hash.to_string())?, index.parse().ok()?))
+    }
+}
+
+/// Value the entry had before the block
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HistoryMirror<T> {
+    pub height: u32,
+    pub block_hash: String,
+    pub value: T,
+}
+
+fn history_to_mirror<T, M, F: Fn(&T) -> M>(history: &BTreeMap<Block, T>, to_mirror: F) -> Vec<HistoryMirror<M>> {
+    history
+        .iter()
+        .map(|(block, value)| HistoryMirror { height: block.height, block_hash: block.hash.to_mirror(), value: to_mirror(value) })
+        .collect()
+}
+
+fn history_from_mirror<T, M, F: Fn(M) -> Option<T>>(mirror: Vec<HistoryMirror<M>>, from_mirror: F) -> Option<BTreeMap<Block, T>> {
+    mirror
+        .into_iter()
+        .map(|item| Some((Block { height: item.height, hash: UInt256::from_mirror(item.block_hash)? }, from_mirror(item.value)?)))
+        .collect()
+}
+
+/// The whole entry: as it comes with MNLISTDIFF along with the state the processor keeps for it.
+/// Voting key is kept as key id, since the address form depends on the chain
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MasternodeEntryMirror {
+    pub provider_registration_transaction_hash: String,
+    pub confirmed_hash: String,
+    /// "ip:port" as Dash Core shows it
+    pub service: String,
+    pub operator_public_key: String,
+    pub key_id_voting: String,
+    pub is_valid: bool,
+    pub entry_hash: String,
+    pub update_height: u32,
+    #[serde(default)]
+    pub known_confirmed_at_height: Option<u32>,
+    #[serde(default)]
+    pub previous_operator_public_keys: Vec<HistoryMirror<String>>,
+    #[serde(default)]
+    pub previous_entry_hashes: Vec<HistoryMirror<String>>,
+    #[serde(default)]
+    pub previous_validity: Vec<HistoryMirror<bool>>,
+}
+
+impl SerdeMirror for MasternodeEntry {
+    type Mirror = MasternodeEntryMirror;
+    fn to_mirror(&self) -> Self::Mirror {
+        MasternodeEntryMirror {
+            provider_registration_transaction_hash: self.provider_registration_transaction_hash.to_mirror(),
+            confirmed_hash: self.confirmed_hash.to_mirror(),
+            service: socket_address_to_json(&self.socket_address),
+            operator_public_key: self.operator_public_key.to_mirror(),
+            key_id_voting: self.key_id_voting.to_mirror(),
+            is_valid: self.is_valid,
+            entry_hash: self.entry_hash.to_mirror(),
+            update_height: self.update_height,
+            known_confirmed_at_height: self.known_confirmed_at_height,
+            previous_operator_public_keys: history_to_mirror(&self.previous_operator_public_keys, UInt384::to_mirror),
+            previous_entry_hashes: history_to_mirror(&self.previous_entry_hashes, UInt256::to_mirror),
+            previous_validity: history_to_mirror(&self.previous_validity, |is_valid| *is_valid),
+        }
+    }
+    /// Entry hash is recalculated
+    fn from_mirror(mirror: Self::Mirror) -> Option<Self> {
+        let mut entry = MasternodeEntry::new(
+            UInt256::from_mirror(mirror.provider_registration_transaction_hash)?,
+            UInt256::from_mirror(mirror.confirmed_hash)?,
+            socket_address_from_json(&mirror.service)?,
+            UInt160::from_mirror(mirror.key_id_voting)?,
+            UInt384::from_mirror(mirror.operator_public_key)?,
+            u8::from(mirror.is_valid),
+        );
+        entry.update_height = mirror.update_height;
+        entry.known_confirmed_at_height = mirror.known_confirmed_at_height;
+        entry.previous_operator_public_keys = history_from_mirror(mirror.previous_operator_public_keys, UInt384::from_mirror)?;
+        entry.previous_entry_hashes = history_from_mirror(mirror.previous_entry_hashes, UInt256::from_mirror)?;
+        entry.previous_validity = history_from_mirror(mirror.previous_validity, Some)?;
+        Some(entry)
+    }
+}
+
+/// Commitment as it comes within 'newQuorums' of 'protx diff' along with whether it's verified
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LLMQEntryMirror {
+    #[serde(flatten)]
+    pub commitment: LLMQ,
+    pub verified: bool,
+    pub entry_hash: String,
+}
+
+impl SerdeMirror for LLMQEntry {
+    type Mirror = LLMQEntryMirror;
+    fn to_mirror(&self) -> Self::Mirror {
+        LLMQEntryMirror {
+            commitment: quorum_to_json(self),
+            verified: self.verified,
+            entry_hash: self.entry_hash.to_mirror(),
+        }
+    }
+    /// Entry hash is recalculated
+    fn from_mirror(mirror: Self::Mirror) -> Option<Self> {
+        let mut entry = quorum_from_json(&mirror.commitment)?;
+        entry.verified = mirror.verified;
+        Some(entry)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LLMQSnapshotMirror {
+    pub member_list: String,
+    pub skip_list: Vec<i32>,
+    pub skip_list_mode: u32,
+}
+
+impl SerdeMirror for LLMQSnapshot {
+    type Mirror = LLMQSnapshotMirror;
+    fn to_mirror(&self) -> Self::Mirror {
+        LLMQSnapshotMirror {
+            member_list: self.member_list.to_hex(),
+            skip_list: self.skip_list.clone(),
+            skip_list_mode: self.skip_list_mode.into(),
+        }
+    }
+    fn from_mirror(mirror: Self::Mirror) -> Option<Self> {
+        Some(LLMQSnapshot::new(
+            Vec::from_hex(&mirror.member_list).ok()?,
+            mirror.skip_list,
+            LLMQSnapshotSkipMode::from(mirror.skip_list_mode),
+        ))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MasternodeListMirror {
+    pub block_hash: String,
+    pub known_height: u32,
+    pub quorums_active: bool,
+    /// Roots are informational, they're recalculated on deserialization
+    #[serde(default)]
+    pub masternode_merkle_root: Option<String>,
+    #[serde(default)]
+    pub llmq_merkle_root: Option<String>,
+    pub masternodes: Vec<MasternodeEntryMirror>,
+    pub quorums: Vec<LLMQEntryMirror>,
+}
+
+impl SerdeMirror for MasternodeList {
+    type Mirror = MasternodeListMirror;
+    fn to_mirror(&self) -> Self::Mirror {
+        MasternodeListMirror {
+            block_hash: self.block_hash.to_mirror(),
+            known_height: self.known_height,
+            quorums_active: self.llmq_merkle_root.is_some(),
+            masternode_merkle_root: self.masternode_merkle_root.to_mirror(),
+            llmq_merkle_root: self.llmq_merkle_root.to_mirror(),
+            masternodes: self.masternodes.values().map(MasternodeEntry::to_mirror).collect(),
+            quorums: self
+                .quorums
+                .values()
+                .flat_map(|quorums_of_type| quorums_of_type.values())
+                .map(LLMQEntry::to_mirror)
+                .collect(),
+        }
+    }
+    fn from_mirror(mirror: Self::Mirror) -> Option<Self> {
+        let masternodes = Vec::<MasternodeEntry>::from_mirror(mirror.masternodes)?
+            .into_iter()
+            .map(|entry| (entry.provider_registration_transaction_hash.clone().reversed(), entry))
+            .collect();
+        let mut quorums = BTreeMap::<LLMQType, BTreeMap<UInt256, LLMQEntry>>::new();
+        for entry in Vec::<LLMQEntry>::from_mirror(mirror.quorums)? {
+            quorums
+                .entry(entry.llmq_type)
+                .or_default()
+                .insert(entry.llmq_hash, entry);
+        }
+        Some(MasternodeList::new(
+            masternodes,
+            quorums,
+            UInt256::from_mirror(mirror.block_hash)?,
+            mirror.known_height,
+            mirror.quorums_active,
+        ))
+    }
+}
+
+/// Same form as 'MasternodeList', entries aren't shared after deserialization
+impl SerdeMirror for SharedMasternodeList {
+    type Mirror = MasternodeListMirror;
+    fn to_mirror(&self) -> Self::Mirror {
+        self.to_list().to_mirror()
+    }
+    fn from_mirror(mirror: Self::Mirror) -> Option<Self> {
+        MasternodeList::from_mirror(mirror).map(|list| SharedMasternodeList::from_list(&list))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TrackedQuorumMirror {
+    pub height: u32,
+    pub quorum: LLMQEntryMirror,
+    #[serde(default)]
+    pub closed_at: Option<u32>,
+}
+
+/// Tracked commitments with the heights of their quorum blocks
+/// and of the lists they were deleted from
+impl SerdeMirror for RotatedQuorumTracker {
+    type Mirror = Vec<TrackedQuorumMirror>;
+    fn to_mirror(&self) -> Self::Mirror {
+        self.iter()
+            .map(|(height, quorum)| TrackedQuorumMirror {
+                height,
+                quorum: quorum.to_mirror(),
+                closed_at: self.closed_at(quorum.llmq_type, quorum.llmq_hash),
+            })
+            .collect()
+    }
+    fn from_mirror(mirror: Self::Mirror) -> Option<Self> {
+        let mut tracker = RotatedQuorumTracker::default();
+        for tracked in mirror {
+            let quorum = LLMQEntry::from_mirror(tracked.quorum)?;
+            let (llmq_type, llmq_hash) = (quorum.llmq_type, quorum.llmq_hash);
+            tracker.add_quorum(tracked.height, quorum);
+            if let Some(closed_at) = tracked.closed_at {
+                tracker.close_quorum(llmq_type, llmq_hash, closed_at);
+            }
+        }
+        Some(tracker)
+    }
+}
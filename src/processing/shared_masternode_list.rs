@@ -0,0 +1,111 @@
+use dash_spv_models::common::LLMQType;
+use dash_spv_models::masternode::{LLMQEntry, MasternodeEntry, MasternodeList};
+use dash_spv_primitives::crypto::UInt256;
+use im::OrdMap;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Masternodes keyed the same way as in 'MasternodeList' (by reversed pro_reg_tx_hash).
+/// Cloning the map is O(1) and each change copies only the nodes on its path,
+/// so the lists derived from each other share everything the diffs didn't touch
+pub type SharedMasternodes = OrdMap<UInt256, Arc<MasternodeEntry>>;
+
+/// Processing form of the masternode list: the one held by the cache and the results.
+/// 'MasternodeList' is still used for FFI and the network messages (see 'to_list' and 'from_list')
+#[derive(Clone, Debug, Default)]
+pub struct SharedMasternodeList {
+    pub block_hash: UInt256,
+    pub known_height: u32,
+    pub masternode_merkle_root: Option<UInt256>,
+    pub llmq_merkle_root: Option<UInt256>,
+    pub masternodes: SharedMasternodes,
+    /// Quorums are few (hundreds at most), so they're kept by value
+    pub quorums: BTreeMap<LLMQType, BTreeMap<UInt256, LLMQEntry>>,
+}
+
+impl SharedMasternodeList {
+    pub fn new(
+        masternodes: SharedMasternodes,
+        quorums: BTreeMap<LLMQType, BTreeMap<UInt256, LLMQEntry>>,
+        block_hash: UInt256,
+        known_height: u32,
+        masternode_merkle_root: Option<UInt256>,
+        llmq_merkle_root: Option<UInt256>,
+    ) -> Self {
+        Self {
+            block_hash,
+            known_height,
+            masternode_merkle_root,
+            llmq_merkle_root,
+            masternodes,
+            quorums,
+        }
+    }
+
+    /// List without entries (i.e. the one at genesis)
+    pub fn empty(block_hash: UInt256, known_height: u32) -> Self {
+        Self {
+            block_hash,
+            known_height,
+            ..Default::default()
+        }
+    }
+
+    /// Entries are copied once here, the lists derived from this one will share them
+    pub fn from_list(list: &MasternodeList) -> Self {
+        Self {
+            block_hash: list.block_hash,
+            known_height: list.known_height,
+            masternode_merkle_root: list.masternode_merkle_root,
+            llmq_merkle_root: list.llmq_merkle_root,
+            masternodes: list
+                .masternodes
+                .iter()
+                .map(|(hash, entry)| (*hash, Arc::new(entry.clone())))
+                .collect(),
+            quorums: list.quorums.clone(),
+        }
+    }
+
+    /// Copy of the list for FFI and the stores, the roots are the ones of this list
+    pub fn to_list(&self) -> MasternodeList {
+        let mut list = MasternodeList::new(
+            self.masternodes
+                .iter()
+                .map(|(hash, entry)| (*hash, entry.as_ref().clone()))
+                .collect(),
+            self.quorums.clone(),
+            self.block_hash,
+            self.known_height,
+            self.llmq_merkle_root.is_some(),
+        );
+        list.masternode_merkle_root = self.masternode_merkle_root;
+        list.llmq_merkle_root = self.llmq_merkle_root;
+        list
+    }
+
+    pub fn masternode(&self, hash: &UInt256) -> Option<&MasternodeEntry> {
+        self.masternodes.get(hash).map(Arc::as_ref)
+    }
+
+    /// Entries in the order of their keys
+    pub fn masternode_entries(&self) -> impl Iterator<Item = &MasternodeEntry> + '_ {
+        self.masternodes.values().map(Arc::as_ref)
+    }
+
+    pub fn quorums_count(&self) -> usize {
+        self.quorums.values().map(BTreeMap::len).sum()
+    }
+}
+
+impl From<&MasternodeList> for SharedMasternodeList {
+    fn from(list: &MasternodeList) -> Self {
+        Self::from_list(list)
+    }
+}
+
+impl From<&SharedMasternodeList> for MasternodeList {
+    fn from(list: &SharedMasternodeList) -> Self {
+        list.to_list()
+    }
+}
@@ -0,0 +1,204 @@
+use crate::codec::{GetMNListDiff, GetQRInfo};
+use crate::processing::{LLMQRotationConfig, MNListDiffResult, MasternodeProcessorCache, ProcessingError, QRInfoResult, SessionChain};
+use dash_spv_models::common::LLMQType;
+use dash_spv_primitives::crypto::UInt256;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Dash Core rejects 'getqrinfo' with more base blocks
+pub const MAX_QR_INFO_BASE_BLOCK_HASHES: usize = 4;
+
+/// Request of the plan along with the height of the block it's made for
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SyncRequest {
+    ListDiff { block_height: u32, request: GetMNListDiff },
+    QRInfo { block_height: u32, request: GetQRInfo },
+}
+
+impl SyncRequest {
+    pub fn block_height(&self) -> u32 {
+        match self {
+            SyncRequest::ListDiff { block_height, .. } | SyncRequest::QRInfo { block_height, .. } => *block_height,
+        }
+    }
+
+    pub fn block_hash(&self) -> UInt256 {
+        match self {
+            SyncRequest::ListDiff { request, .. } => request.block_hash,
+            SyncRequest::QRInfo { request, .. } => request.block_request_hash,
+        }
+    }
+
+    /// Request with P2P envelope for the network given by magic
+    pub fn to_network_message(&self, magic: u32) -> Vec<u8> {
+        match self {
+            SyncRequest::ListDiff { request, .. } => request.to_network_message(magic),
+            SyncRequest::QRInfo { request, .. } => request.to_network_message(magic),
+        }
+    }
+}
+
+/// Decides which diffs should be requested to get from the cached state to the chain tip:
+/// - QRINFO once quorums of a new rotation cycle are mined (it brings the list at the tip as well),
+/// - MNLISTDIFF for every list reported as needed by the results,
+/// - MNLISTDIFF to the tip if there is no list there yet.
+/// Each diff is based on the nearest cached list below its block (or the genesis).
+/// The plan is recomputed from the cache, so it reflects the results as soon as they're processed:
+/// the cycle is synced once the lists and snapshots its rotation is made of are cached
+#[derive(Clone, Debug)]
+pub struct SyncPlanner {
+    genesis_hash: UInt256,
+    rotation_config: LLMQRotationConfig,
+    pub extra_share: bool,
+    /// Needed lists reported by the results, until their diffs are processed
+    needed_lists: BTreeSet<UInt256>,
+}
+
+impl SyncPlanner {
+    pub fn new(genesis_hash: UInt256, rotation_config: LLMQRotationConfig) -> Self {
+        Self {
+            genesis_hash,
+            rotation_config,
+            extra_share: true,
+            needed_lists: BTreeSet::new(),
+        }
+    }
+
+    pub fn for_chain(chain: &SessionChain) -> Self {
+        Self::new(chain.genesis_hash(), chain.rotation_config())
+    }
+
+    pub fn needed_lists(&self) -> &BTreeSet<UInt256> {
+        &self.needed_lists
+    }
+
+    /// Height of the latest cycle of the type which quorums are mined by the tip
+    pub fn mined_cycle_height(llmq_type: LLMQType, tip_height: u32) -> Option<u32> {
+        let dkg_params = llmq_type.params().dkg_params;
+        let cycle_height = tip_height - tip_height % dkg_params.interval;
+        if tip_height - cycle_height >= dkg_params.mining_window_end {
+            Some(cycle_height)
+        } else {
+            cycle_height.checked_sub(dkg_params.interval)
+        }
+    }
+
+    /// Whether the cache has what the rotation of the cycle is made of: the list at its work block
+    /// and both the list and the snapshot at the work blocks of the 3 cycles before.
+    /// Work blocks below the genesis have nothing to be synced
+    pub fn is_cycle_synced(llmq_type: LLMQType, cycle_height: u32, cache: &MasternodeProcessorCache) -> bool {
+        let interval = llmq_type.params().dkg_params.interval;
+        let work_block_height = |cycles: u32| cycle_height.checked_sub(cycles * interval + 8);
+        let has_list = |height: u32| cache.masternode_list_at_height(height).is_some();
+        let has_snapshot = |height: u32| {
+            cache
+                .masternode_list_at_height(height)
+                .map_or(false, |(block_hash, _)| cache.llmq_snapshots.contains_key(&block_hash))
+        };
+        work_block_height(0).map_or(true, has_list) && (1..=3).filter_map(work_block_height).all(has_snapshot)
+    }
+
+    /// Rotating types which have a mined cycle not synced yet
+    fn unsynced_types(&self, tip_height: u32, cache: &MasternodeProcessorCache) -> Vec<(LLMQType, u32)> {
+        self.rotation_config
+            .types()
+            .into_iter()
+            .filter_map(|llmq_type| {
+                Self::mined_cycle_height(llmq_type, tip_height)
+                    .filter(|&height| !Self::is_cycle_synced(llmq_type, height, cache))
+                    .map(|height| (llmq_type, height))
+            })
+            .collect()
+    }
+
+    fn list_diff_request(&self, block_hash: UInt256, block_height: u32, cache: &MasternodeProcessorCache) -> SyncRequest {
+        let base_block_hash = block_height
+            .checked_sub(1)
+            .and_then(|height| cache.masternode_list_at_or_below(height))
+            .map_or(self.genesis_hash, |(base_block_hash, _)| base_block_hash);
+        SyncRequest::ListDiff { block_height, request: GetMNListDiff { base_block_hash, block_hash } }
+    }
+
+    fn qr_info_request(&self, block_request_hash: UInt256, block_height: u32, cache: &MasternodeProcessorCache) -> SyncRequest {
+        let mut lists = cache
+            .mn_lists
+            .iter()
+            .filter(|(_, list)| list.known_height < block_height)
+            .map(|(block_hash, list)| (list.known_height, *block_hash))
+            .collect::<Vec<(u32, UInt256)>>();
+        lists.sort_by(|(height1, _), (height2, _)| height2.cmp(height1));
+        let base_block_hashes = lists
+            .into_iter()
+            .take(MAX_QR_INFO_BASE_BLOCK_HASHES)
+            .map(|(_, block_hash)| block_hash)
+            .collect();
+        SyncRequest::QRInfo {
+            block_height,
+            request: GetQRInfo { base_block_hashes, block_request_hash, extra_share: self.extra_share },
+        }
+    }
+
+    /// Ordered requests to sync up to the tip: rotation info goes first since its lists
+    /// are the bases for the rest, then lists from the lowest to the highest.
+    /// Blocks the lookups don't know yet are left for the next plan
+    pub fn plan<BHL: Fn(UInt256) -> u32, BHH: Fn(u32) -> Option<UInt256>>(
+        &self,
+        tip_height: u32,
+        cache: &MasternodeProcessorCache,
+        block_height_lookup: BHL,
+        block_hash_lookup: BHH,
+    ) -> Vec<SyncRequest> {
+        let mut requests = Vec::<SyncRequest>::new();
+        let tip_hash = match block_hash_lookup(tip_height) {
+            Some(tip_hash) => tip_hash,
+            None => return requests,
+        };
+        let has_tip_list = cache.masternode_list_at_height(tip_height).is_some();
+        let needs_qr_info = !self.unsynced_types(tip_height, cache).is_empty();
+        if needs_qr_info {
+            requests.push(self.qr_info_request(tip_hash, tip_height, cache));
+        }
+        let mut list_diffs = BTreeMap::<u32, SyncRequest>::new();
+        self.needed_lists
+            .iter()
+            .chain(cache.needed_masternode_lists.iter())
+            .filter(|block_hash| !cache.mn_lists.contains_key(block_hash))
+            .for_each(|&block_hash| match block_height_lookup(block_hash) {
+                u32::MAX => {}
+                block_height if block_height <= tip_height => {
+                    list_diffs.insert(block_height, self.list_diff_request(block_hash, block_height, cache));
+                }
+                _ => {}
+            });
+        if !needs_qr_info && !has_tip_list {
+            list_diffs.insert(tip_height, self.list_diff_request(tip_hash, tip_height, cache));
+        }
+        requests.extend(list_diffs.into_values());
+        requests
+    }
+
+    /// Takes the needed lists of the processed diff
+    pub fn on_list_diff_result(&mut self, result: &MNListDiffResult) {
+        if result.error_status != ProcessingError::None {
+            return;
+        }
+        self.needed_lists.remove(&result.block_hash);
+        self.needed_lists.extend(result.needed_masternode_lists.iter().copied());
+    }
+
+    /// Takes the needed lists of all the diffs, the synced cycles are known from the cache
+    pub fn on_qr_info_result(&mut self, result: &QRInfoResult) {
+        if result.error_status != ProcessingError::None {
+            return;
+        }
+        let mut results = vec![
+            &result.result_at_tip,
+            &result.result_at_h,
+            &result.result_at_h_c,
+            &result.result_at_h_2c,
+            &result.result_at_h_3c,
+        ];
+        results.extend(result.result_at_h_4c.as_ref());
+        results.extend(result.mn_list_diff_list.iter());
+        results.into_iter().for_each(|result| self.on_list_diff_result(result));
+    }
+}
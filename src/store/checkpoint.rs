@@ -0,0 +1,174 @@
+use crate::codec::{read_bytes, read_stored_masternode_list, read_stored_snapshot, read_var_int, write_stored_masternode_list, write_stored_snapshot, write_var_int};
+use crate::processing::SharedMasternodeList;
+use byte::{BytesExt, LE};
+use dash_spv_models::llmq::LLMQSnapshot;
+use dash_spv_models::tx::CoinbaseTransaction;
+use dash_spv_primitives::crypto::byte_util::BytesDecodable;
+use dash_spv_primitives::crypto::UInt256;
+use dash_spv_primitives::hashes::{sha256d, Hash};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+pub const CHECKPOINT_MAGIC: [u8; 4] = *b"MNCP";
+/// Version 2 keeps the history of the entries and the verification status of the quorums
+pub const CHECKPOINT_VERSION: u16 = 2;
+/// magic (4) + version (2) + payload length (4)
+const CHECKPOINT_HEADER_SIZE: usize = 10;
+const CHECKPOINT_HASH_SIZE: usize = 32;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CheckpointError {
+    UnknownFormat,
+    UnsupportedVersion(u16),
+    /// Integrity hash doesn't match the content
+    Corrupted,
+    /// Roots of the stored list aren't the ones the checkpoint is committed to
+    InvalidMerkleRoots,
+    /// Coinbase isn't the one of the checkpoint block or its roots aren't the ones of the checkpoint
+    CoinbaseMismatch,
+}
+
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckpointError::UnknownFormat => write!(f, "not a masternode checkpoint"),
+            CheckpointError::UnsupportedVersion(version) => write!(f, "unsupported checkpoint version {}", version),
+            CheckpointError::Corrupted => write!(f, "checkpoint integrity hash mismatch"),
+            CheckpointError::InvalidMerkleRoots => write!(f, "checkpoint list doesn't match its merkle roots"),
+            CheckpointError::CoinbaseMismatch => write!(f, "checkpoint merkle roots don't match the coinbase"),
+        }
+    }
+}
+
+impl std::error::Error for CheckpointError {}
+
+/// Trusted base to bootstrap from instead of the diff from genesis.
+/// Format: magic, version (u16), payload length (u32), payload and sha256d of the payload.
+/// Payload: block hash, height, coinbase roots (merkleRootMNList and optional merkleRootQuorums),
+/// the list with its quorums and the snapshots needed for quorum rotation.
+/// Since the roots are a part of the hashed payload and the list is checked against them,
+/// the integrity hash is tied to the coinbase of the checkpoint block
+#[derive(Clone, Debug)]
+pub struct MasternodeCheckpoint {
+    pub block_hash: UInt256,
+    pub height: u32,
+    pub masternode_merkle_root: UInt256,
+    pub llmq_merkle_root: Option<UInt256>,
+    pub list: Arc<SharedMasternodeList>,
+    pub snapshots: BTreeMap<UInt256, LLMQSnapshot>,
+}
+
+impl MasternodeCheckpoint {
+    pub fn new(list: Arc<SharedMasternodeList>, snapshots: BTreeMap<UInt256, LLMQSnapshot>) -> Option<Self> {
+        Some(Self {
+            block_hash: list.block_hash,
+            height: list.known_height,
+            masternode_merkle_root: list.masternode_merkle_root?,
+            llmq_merkle_root: list.llmq_merkle_root,
+            list,
+            snapshots,
+        })
+    }
+
+    pub fn has_valid_merkle_roots(&self) -> bool {
+        self.list.block_hash == self.block_hash
+            && self.list.known_height == self.height
+            && self.list.masternode_merkle_root == Some(self.masternode_merkle_root)
+            && self.list.llmq_merkle_root == self.llmq_merkle_root
+    }
+
+    /// Checks the roots against the coinbase of the checkpoint block
+    pub fn verify_coinbase(&self, coinbase: &CoinbaseTransaction) -> Result<(), CheckpointError> {
+        if coinbase.height == self.height
+            && coinbase.merkle_root_mn_list == self.masternode_merkle_root
+            && coinbase.merkle_root_llmq_list == self.llmq_merkle_root
+        {
+            Ok(())
+        } else {
+            Err(CheckpointError::CoinbaseMismatch)
+        }
+    }
+
+    pub fn integrity_hash(payload: &[u8]) -> [u8; CHECKPOINT_HASH_SIZE] {
+        sha256d::Hash::hash(payload).into_inner()
+    }
+
+    fn encode_payload(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(&self.block_hash.0);
+        buffer.extend_from_slice(&self.height.to_le_bytes());
+        buffer.extend_from_slice(&self.masternode_merkle_root.0);
+        match self.llmq_merkle_root {
+            Some(root) => {
+                buffer.push(1);
+                buffer.extend_from_slice(&root.0);
+            }
+            None => buffer.push(0),
+        }
+        write_stored_masternode_list(&self.list, buffer);
+        write_var_int(self.snapshots.len(), buffer);
+        self.snapshots.iter().for_each(|(block_hash, snapshot)| {
+            buffer.extend_from_slice(&block_hash.0);
+            write_stored_snapshot(snapshot, buffer);
+        });
+    }
+
+    fn decode_payload(payload: &[u8]) -> Option<Self> {
+        let offset = &mut 0;
+        let block_hash = UInt256::from_bytes(payload, offset)?;
+        let height = payload.read_with::<u32>(offset, LE).ok()?;
+        let masternode_merkle_root = UInt256::from_bytes(payload, offset)?;
+        let llmq_merkle_root = match read_bytes(payload, offset, 1)?[0] {
+            0 => None,
+            _ => Some(UInt256::from_bytes(payload, offset)?),
+        };
+        let list = Arc::new(read_stored_masternode_list(payload, offset)?);
+        let snapshots_count = read_var_int(payload, offset)?;
+        let mut snapshots = BTreeMap::new();
+        for _i in 0..snapshots_count {
+            let snapshot_block_hash = UInt256::from_bytes(payload, offset)?;
+            snapshots.insert(snapshot_block_hash, read_stored_snapshot(payload, offset)?);
+        }
+        if *offset != payload.len() {
+            return None;
+        }
+        Some(Self { block_hash, height, masternode_merkle_root, llmq_merkle_root, list, snapshots })
+    }
+
+    /// Decodes the checkpoint, checking its integrity hash and the roots of the list
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CheckpointError> {
+        if bytes.len() < CHECKPOINT_HEADER_SIZE || bytes[..4] != CHECKPOINT_MAGIC {
+            return Err(CheckpointError::UnknownFormat);
+        }
+        let offset = &mut 4;
+        let version = bytes.read_with::<u16>(offset, LE).map_err(|_| CheckpointError::UnknownFormat)?;
+        if version != CHECKPOINT_VERSION {
+            return Err(CheckpointError::UnsupportedVersion(version));
+        }
+        let len = bytes.read_with::<u32>(offset, LE).map_err(|_| CheckpointError::UnknownFormat)? as usize;
+        if bytes.len() != CHECKPOINT_HEADER_SIZE + len + CHECKPOINT_HASH_SIZE {
+            return Err(CheckpointError::Corrupted);
+        }
+        let payload = &bytes[CHECKPOINT_HEADER_SIZE..CHECKPOINT_HEADER_SIZE + len];
+        if bytes[CHECKPOINT_HEADER_SIZE + len..] != Self::integrity_hash(payload) {
+            return Err(CheckpointError::Corrupted);
+        }
+        let checkpoint = Self::decode_payload(payload).ok_or(CheckpointError::Corrupted)?;
+        if !checkpoint.has_valid_merkle_roots() {
+            return Err(CheckpointError::InvalidMerkleRoots);
+        }
+        Ok(checkpoint)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut payload = Vec::<u8>::new();
+        self.encode_payload(&mut payload);
+        let mut bytes = Vec::<u8>::with_capacity(CHECKPOINT_HEADER_SIZE + payload.len() + CHECKPOINT_HASH_SIZE);
+        bytes.extend_from_slice(&CHECKPOINT_MAGIC);
+        bytes.extend_from_slice(&CHECKPOINT_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&payload);
+        bytes.extend_from_slice(&Self::integrity_hash(&payload));
+        bytes
+    }
+}
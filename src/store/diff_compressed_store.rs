@@ -0,0 +1,360 @@
+use crate::codec::stored::{write_stored_llmq_entry, write_stored_masternode_entry};
+use crate::processing::{MasternodeProcessor, SharedMasternodeList};
+use crate::store::{MasternodeStore, MemoryStore, StoreError};
+use dash_spv_models::common::LLMQType;
+use dash_spv_models::llmq::{LLMQIndexedHash, LLMQSnapshot};
+use dash_spv_models::masternode::{LLMQEntry, MasternodeEntry};
+use dash_spv_primitives::crypto::byte_util::Reversable;
+use dash_spv_primitives::crypto::UInt256;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Changes of the list against the previous stored one, in the same form as they come with MNLISTDIFF
+#[derive(Clone, Debug)]
+pub struct ListDelta {
+    pub block_hash: UInt256,
+    pub base_height: u32,
+    /// Roots of the list, so they don't have to be recomputed on replay
+    pub masternode_merkle_root: Option<UInt256>,
+    pub llmq_merkle_root: Option<UInt256>,
+    pub added_or_modified_masternodes: BTreeMap<UInt256, MasternodeEntry>,
+    pub deleted_masternode_hashes: Vec<UInt256>,
+    pub added_quorums: BTreeMap<LLMQType, BTreeMap<UInt256, LLMQEntry>>,
+    pub deleted_quorums: BTreeMap<LLMQType, Vec<UInt256>>,
+}
+
+fn stored_masternode_entry(entry: &MasternodeEntry) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    write_stored_masternode_entry(entry, &mut buffer);
+    buffer
+}
+
+fn stored_llmq_entry(entry: &LLMQEntry) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    write_stored_llmq_entry(entry, &mut buffer);
+    buffer
+}
+
+/// Entries are equal if their whole state is, not only the part committed by the entry hash
+fn is_same_masternode_entry(base: &Arc<MasternodeEntry>, entry: &Arc<MasternodeEntry>) -> bool {
+    Arc::ptr_eq(base, entry) || stored_masternode_entry(base) == stored_masternode_entry(entry)
+}
+
+fn is_same_llmq_entry(base: &LLMQEntry, entry: &LLMQEntry) -> bool {
+    stored_llmq_entry(base) == stored_llmq_entry(entry)
+}
+
+fn is_same_list(list: &SharedMasternodeList, other: &SharedMasternodeList) -> bool {
+    list.masternodes.len() == other.masternodes.len()
+        && list.masternodes.iter().all(|(hash, entry)| {
+            other.masternodes.get(hash).map_or(false, |other_entry| is_same_masternode_entry(entry, other_entry))
+        })
+        && list.quorums.len() == other.quorums.len()
+        && list.quorums.iter().all(|(llmq_type, quorums_of_type)| {
+            other.quorums.get(llmq_type).map_or(false, |other_quorums| {
+                quorums_of_type.len() == other_quorums.len()
+                    && quorums_of_type.iter().all(|(hash, entry)| {
+                        other_quorums.get(hash).map_or(false, |other_entry| is_same_llmq_entry(entry, other_entry))
+                    })
+            })
+        })
+}
+
+impl ListDelta {
+    pub fn between(base: &SharedMasternodeList, list: &SharedMasternodeList, base_height: u32) -> Self {
+        let added_or_modified_masternodes = list
+            .masternodes
+            .iter()
+            .filter(|(hash, entry)| {
+                base.masternodes
+                    .get(hash)
+                    .map_or(true, |base_entry| !is_same_masternode_entry(base_entry, entry))
+            })
+            .map(|(hash, entry)| (*hash, entry.as_ref().clone()))
+            .collect();
+        // Deleted hashes are kept in the form of the diff message
+        let deleted_masternode_hashes = base
+            .masternodes
+            .keys()
+            .filter(|hash| !list.masternodes.contains_key(hash))
+            .map(|hash| hash.clone().reversed())
+            .collect();
+        let mut added_quorums = BTreeMap::<LLMQType, BTreeMap<UInt256, LLMQEntry>>::new();
+        list.quorums.iter().for_each(|(&llmq_type, quorums_of_type)| {
+            let base_quorums_of_type = base.quorums.get(&llmq_type);
+            quorums_of_type
+                .iter()
+                .filter(|(hash, entry)| {
+                    base_quorums_of_type
+                        .and_then(|base_quorums| base_quorums.get(hash))
+                        .map_or(true, |base_entry| !is_same_llmq_entry(base_entry, entry))
+                })
+                .for_each(|(hash, entry)| {
+                    added_quorums
+                        .entry(llmq_type)
+                        .or_default()
+                        .insert(*hash, entry.clone());
+                });
+        });
+        let mut deleted_quorums = BTreeMap::<LLMQType, Vec<UInt256>>::new();
+        base.quorums.iter().for_each(|(&llmq_type, base_quorums_of_type)| {
+            let quorums_of_type = list.quorums.get(&llmq_type);
+            base_quorums_of_type
+                .keys()
+                .filter(|hash| quorums_of_type.map_or(true, |quorums| !quorums.contains_key(hash)))
+                .for_each(|hash| deleted_quorums.entry(llmq_type).or_default().push(*hash));
+        });
+        Self {
+            block_hash: list.block_hash,
+            base_height,
+            masternode_merkle_root: list.masternode_merkle_root,
+            llmq_merkle_root: list.llmq_merkle_root,
+            added_or_modified_masternodes,
+            deleted_masternode_hashes,
+            added_quorums,
+            deleted_quorums,
+        }
+    }
+
+    /// Delta against the base, if replaying it gives exactly the list:
+    /// the processor rebuilds the history of modified entries against their base entries,
+    /// so it doesn't hold for the lists which aren't stored one after another
+    pub fn reproducing(base: &SharedMasternodeList, list: &SharedMasternodeList, base_height: u32) -> Option<Self> {
+        let delta = Self::between(base, list, base_height);
+        is_same_list(&delta.apply(base, list.known_height), list).then_some(delta)
+    }
+
+    /// Applies the delta the same way the processor applies the diff: masternodes are
+    /// classified by 'classify_masternodes' and quorums are applied as 'classify_quorums' does,
+    /// with no validation since the stored entries keep whether they're verified
+    pub fn apply(&self, base: &SharedMasternodeList, height: u32) -> SharedMasternodeList {
+        let (_, _, masternodes) = MasternodeProcessor::classify_masternodes(
+            &base.masternodes,
+            self.added_or_modified_masternodes.clone(),
+            self.deleted_masternode_hashes.clone(),
+            height,
+            self.block_hash,
+        );
+        let quorums = MasternodeProcessor::apply_quorums_diff(
+            &base.quorums,
+            &self.added_quorums,
+            &self.deleted_quorums,
+        );
+        SharedMasternodeList::new(
+            masternodes,
+            quorums,
+            self.block_hash,
+            height,
+            self.masternode_merkle_root,
+            self.llmq_merkle_root,
+        )
+    }
+
+    pub fn entries_count(&self) -> usize {
+        self.added_or_modified_masternodes.len()
+            + self.deleted_masternode_hashes.len()
+            + self.added_quorums.values().map(BTreeMap::len).sum::<usize>()
+            + self.deleted_quorums.values().map(Vec::len).sum::<usize>()
+    }
+}
+
+/// Keeps full lists only at the first height stored within each interval (DKG interval)
+/// and the rest as deltas against the previous stored list.
+/// Lookups replay deltas from the checkpoint, so at most one interval of deltas is applied
+#[derive(Clone, Debug)]
+pub struct DiffCompressedStore {
+    interval: u32,
+    checkpoints: BTreeMap<u32, Arc<SharedMasternodeList>>,
+    deltas: BTreeMap<u32, ListDelta>,
+    heights: BTreeMap<UInt256, u32>,
+    /// Latest stored list is kept in full to compute the next delta
+    tip: Option<(u32, Arc<SharedMasternodeList>)>,
+    other: MemoryStore,
+}
+
+impl DiffCompressedStore {
+    pub fn new(interval: u32) -> Self {
+        Self {
+            interval: interval.max(1),
+            checkpoints: BTreeMap::new(),
+            deltas: BTreeMap::new(),
+            heights: BTreeMap::new(),
+            tip: None,
+            other: MemoryStore::default(),
+        }
+    }
+
+    pub fn for_llmq_type(llmq_type: LLMQType) -> Self {
+        Self::new(llmq_type.params().dkg_params.interval)
+    }
+
+    pub fn checkpoints_count(&self) -> usize {
+        self.checkpoints.len()
+    }
+
+    pub fn deltas_count(&self) -> usize {
+        self.deltas.len()
+    }
+
+    /// Count of masternode and quorum entries actually kept
+    pub fn stored_entries_count(&self) -> usize {
+        self.checkpoints
+            .values()
+            .map(|list| list.masternodes.len() + list.quorums.values().map(BTreeMap::len).sum::<usize>())
+            .sum::<usize>()
+            + self.deltas.values().map(ListDelta::entries_count).sum::<usize>()
+    }
+
+    pub fn masternode_list_at_height(&self, height: u32) -> Option<Arc<SharedMasternodeList>> {
+        if let Some((tip_height, tip)) = &self.tip {
+            if *tip_height == height {
+                return Some(Arc::clone(tip));
+            }
+        }
+        if let Some(list) = self.checkpoints.get(&height) {
+            return Some(Arc::clone(list));
+        }
+        let mut chain = Vec::<(u32, &ListDelta)>::new();
+        let mut current = height;
+        let checkpoint = loop {
+            if let Some(list) = self.checkpoints.get(&current) {
+                break list;
+            }
+            let delta = self.deltas.get(&current)?;
+            chain.push((current, delta));
+            current = delta.base_height;
+        };
+        let list = chain
+            .into_iter()
+            .rev()
+            .fold(SharedMasternodeList::clone(checkpoint), |base, (height, delta)| delta.apply(&base, height));
+        Some(Arc::new(list))
+    }
+
+    fn is_checkpoint_needed(&self, height: u32) -> bool {
+        match &self.tip {
+            Some((tip_height, _)) => {
+                *tip_height >= height || *tip_height / self.interval != height / self.interval
+            }
+            None => true,
+        }
+    }
+}
+
+impl MasternodeStore for DiffCompressedStore {
+    fn save_masternode_list(&mut self, block_hash: UInt256, list: Arc<SharedMasternodeList>) -> Result<(), StoreError> {
+        let height = list.known_height;
+        if self.heights.get(&block_hash) == Some(&height)
+            && self.masternode_list_at_height(height).map_or(false, |stored| is_same_list(&stored, &list))
+        {
+            return Ok(());
+        }
+        // Lists based on the replaced one are rebased onto the new one,
+        // so they're reconstructed before anything is changed
+        let dependents = self
+            .deltas
+            .iter()
+            .filter(|(&delta_height, delta)| delta.base_height == height && delta_height != height)
+            .map(|(&delta_height, _)| {
+                self.masternode_list_at_height(delta_height)
+                    .map(|dependent| (delta_height, dependent))
+                    .ok_or(StoreError::Corrupted(delta_height as u64))
+            })
+            .collect::<Result<Vec<_>, StoreError>>()?;
+        let base = if let Some(delta) = self.deltas.get(&height) {
+            // Replaced delta stays against its own base
+            let base_height = delta.base_height;
+            self.masternode_list_at_height(base_height)
+                .map(|base| (base_height, base))
+        } else if self.checkpoints.contains_key(&height) || self.is_checkpoint_needed(height) {
+            None
+        } else {
+            self.tip.clone()
+        };
+        let delta = base.and_then(|(base_height, base)| ListDelta::reproducing(&base, &list, base_height));
+        match delta {
+            Some(delta) => {
+                self.checkpoints.remove(&height);
+                self.deltas.insert(height, delta);
+            }
+            None => {
+                self.deltas.remove(&height);
+                self.checkpoints.insert(height, Arc::clone(&list));
+            }
+        }
+        for (dependent_height, dependent) in dependents {
+            match ListDelta::reproducing(&list, &dependent, height) {
+                Some(delta) => {
+                    self.deltas.insert(dependent_height, delta);
+                }
+                None => {
+                    self.deltas.remove(&dependent_height);
+                    self.checkpoints.insert(dependent_height, dependent);
+                }
+            }
+        }
+        // Only one list is kept per height
+        self.heights.retain(|_, stored_height| *stored_height != height);
+        self.heights.insert(block_hash, height);
+        if self.tip.as_ref().map_or(true, |(tip_height, _)| *tip_height <= height) {
+            self.tip = Some((height, list));
+        }
+        Ok(())
+    }
+
+    fn masternode_list(&self, block_hash: &UInt256) -> Result<Option<Arc<SharedMasternodeList>>, StoreError> {
+        Ok(self
+            .heights
+            .get(block_hash)
+            .and_then(|&height| self.masternode_list_at_height(height)))
+    }
+
+    fn masternode_list_hashes(&self) -> Result<Vec<UInt256>, StoreError> {
+        Ok(self.heights.keys().cloned().collect())
+    }
+
+    fn masternode_list_at_or_below(&self, block_height: u32) -> Result<Option<Arc<SharedMasternodeList>>, StoreError> {
+        Ok(self
+            .heights
+            .values()
+            .filter(|&&height| height <= block_height)
+            .max()
+            .and_then(|&height| self.masternode_list_at_height(height)))
+    }
+
+    fn save_snapshot(&mut self, block_hash: UInt256, snapshot: LLMQSnapshot) -> Result<(), StoreError> {
+        self.other.save_snapshot(block_hash, snapshot)
+    }
+
+    fn snapshot(&self, block_hash: &UInt256) -> Result<Option<LLMQSnapshot>, StoreError> {
+        self.other.snapshot(block_hash)
+    }
+
+    fn snapshot_hashes(&self) -> Result<Vec<UInt256>, StoreError> {
+        self.other.snapshot_hashes()
+    }
+
+    fn save_quorum_members(&mut self, llmq_type: LLMQType, block_hash: UInt256, members: Vec<MasternodeEntry>) -> Result<(), StoreError> {
+        self.other.save_quorum_members(llmq_type, block_hash, members)
+    }
+
+    fn quorum_members(&self, llmq_type: LLMQType, block_hash: &UInt256) -> Result<Option<Vec<MasternodeEntry>>, StoreError> {
+        self.other.quorum_members(llmq_type, block_hash)
+    }
+
+    fn quorum_members_keys(&self) -> Result<Vec<(LLMQType, UInt256)>, StoreError> {
+        self.other.quorum_members_keys()
+    }
+
+    fn save_indexed_quorum_members(&mut self, llmq_type: LLMQType, indexed_hash: LLMQIndexedHash, members: Vec<MasternodeEntry>) -> Result<(), StoreError> {
+        self.other.save_indexed_quorum_members(llmq_type, indexed_hash, members)
+    }
+
+    fn indexed_quorum_members(&self, llmq_type: LLMQType, indexed_hash: &LLMQIndexedHash) -> Result<Option<Vec<MasternodeEntry>>, StoreError> {
+        self.other.indexed_quorum_members(llmq_type, indexed_hash)
+    }
+
+    fn indexed_quorum_members_keys(&self) -> Result<Vec<(LLMQType, LLMQIndexedHash)>, StoreError> {
+        self.other.indexed_quorum_members_keys()
+    }
+}
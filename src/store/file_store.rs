@@ -0,0 +1,225 @@
+use crate::codec::{read_bytes, read_stored_masternode_entries, read_stored_masternode_list, read_stored_snapshot, write_stored_masternode_entries, write_stored_masternode_list, write_stored_snapshot};
+use crate::processing::{MasternodeProcessorCache, SharedMasternodeList};
+use crate::store::{MasternodeStore, MemoryStore, StoreError};
+use byte::{BytesExt, LE};
+use dash_spv_models::common::LLMQType;
+use dash_spv_models::llmq::{LLMQIndexedHash, LLMQSnapshot};
+use dash_spv_models::masternode::MasternodeEntry;
+use dash_spv_primitives::crypto::byte_util::BytesDecodable;
+use dash_spv_primitives::crypto::UInt256;
+use dash_spv_primitives::hashes::{sha256d, Hash};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+const RECORD_MASTERNODE_LIST: u8 = 1;
+const RECORD_SNAPSHOT: u8 = 2;
+const RECORD_QUORUM_MEMBERS: u8 = 3;
+const RECORD_INDEXED_QUORUM_MEMBERS: u8 = 4;
+/// kind (1) + payload length (4)
+const RECORD_HEADER_SIZE: usize = 5;
+const RECORD_CHECKSUM_SIZE: usize = 4;
+
+/// Append-only file store.
+/// Each save appends a record: kind, payload length, payload and the first 4 bytes of its sha256d.
+/// The file is replayed on open (latest record for the key wins) and lookups are served from memory.
+/// Incomplete record at the end of the file (i.e. interrupted write) is cut off on open
+#[derive(Debug)]
+pub struct FileStore {
+    path: PathBuf,
+    file: File,
+    index: MemoryStore,
+}
+
+impl FileStore {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, StoreError> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&path)?;
+        let mut bytes = Vec::<u8>::new();
+        file.read_to_end(&mut bytes)?;
+        let mut index = MemoryStore::default();
+        let valid_len = Self::replay(&bytes, &mut index)?;
+        if valid_len < bytes.len() {
+            file.set_len(valid_len as u64)?;
+        }
+        Ok(Self { path, file, index })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the length of the file part which consists of complete records
+    fn replay(bytes: &[u8], index: &mut MemoryStore) -> Result<usize, StoreError> {
+        let mut offset = 0usize;
+        while bytes.len() - offset >= RECORD_HEADER_SIZE {
+            let record_offset = offset;
+            let kind = bytes[offset];
+            offset += 1;
+            let len = bytes.read_with::<u32>(&mut offset, LE).unwrap() as usize;
+            if bytes.len() - offset < len + RECORD_CHECKSUM_SIZE {
+                return Ok(record_offset);
+            }
+            let payload = &bytes[offset..offset + len];
+            offset += len;
+            if bytes[offset..offset + RECORD_CHECKSUM_SIZE] != Self::checksum(payload) {
+                return Err(StoreError::Corrupted(record_offset as u64));
+            }
+            offset += RECORD_CHECKSUM_SIZE;
+            Self::apply_record(kind, payload, index)
+                .ok_or(StoreError::Corrupted(record_offset as u64))?;
+        }
+        Ok(offset)
+    }
+
+    fn apply_record(kind: u8, payload: &[u8], index: &mut MemoryStore) -> Option<()> {
+        let offset = &mut 0;
+        let block_hash = UInt256::from_bytes(payload, offset)?;
+        match kind {
+            RECORD_MASTERNODE_LIST => {
+                let list = read_stored_masternode_list(payload, offset)?;
+                index.save_masternode_list(block_hash, Arc::new(list)).ok()?;
+            }
+            RECORD_SNAPSHOT => {
+                let snapshot = read_stored_snapshot(payload, offset)?;
+                index.llmq_snapshots.insert(block_hash, snapshot);
+            }
+            RECORD_QUORUM_MEMBERS => {
+                let llmq_type = LLMQType::from(read_bytes(payload, offset, 1)?[0]);
+                let members = read_stored_masternode_entries(payload, offset)?;
+                index.llmq_members.insert((llmq_type, block_hash), members);
+            }
+            RECORD_INDEXED_QUORUM_MEMBERS => {
+                let llmq_type = LLMQType::from(read_bytes(payload, offset, 1)?[0]);
+                let index_of_quorum = payload.read_with::<u32>(offset, LE).ok()?;
+                let members = read_stored_masternode_entries(payload, offset)?;
+                index
+                    .llmq_indexed_members
+                    .insert((llmq_type, LLMQIndexedHash::new(block_hash, index_of_quorum)), members);
+            }
+            _ => return None,
+        }
+        Some(())
+    }
+
+    fn checksum(payload: &[u8]) -> [u8; RECORD_CHECKSUM_SIZE] {
+        let hash = sha256d::Hash::hash(payload).into_inner();
+        [hash[0], hash[1], hash[2], hash[3]]
+    }
+
+    fn append(&mut self, kind: u8, payload: Vec<u8>) -> Result<(), StoreError> {
+        let mut record = Vec::<u8>::with_capacity(RECORD_HEADER_SIZE + payload.len() + RECORD_CHECKSUM_SIZE);
+        record.push(kind);
+        record.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        record.extend_from_slice(&payload);
+        record.extend_from_slice(&Self::checksum(&payload));
+        self.file.write_all(&record)?;
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+impl MasternodeStore for FileStore {
+    fn save_masternode_list(&mut self, block_hash: UInt256, list: Arc<SharedMasternodeList>) -> Result<(), StoreError> {
+        let mut payload = block_hash.0.to_vec();
+        write_stored_masternode_list(&list, &mut payload);
+        self.append(RECORD_MASTERNODE_LIST, payload)?;
+        self.index.save_masternode_list(block_hash, list)
+    }
+
+    fn masternode_list(&self, block_hash: &UInt256) -> Result<Option<Arc<SharedMasternodeList>>, StoreError> {
+        self.index.masternode_list(block_hash)
+    }
+
+    fn masternode_list_hashes(&self) -> Result<Vec<UInt256>, StoreError> {
+        self.index.masternode_list_hashes()
+    }
+
+    fn masternode_list_at_or_below(&self, block_height: u32) -> Result<Option<Arc<SharedMasternodeList>>, StoreError> {
+        self.index.masternode_list_at_or_below(block_height)
+    }
+
+    fn save_snapshot(&mut self, block_hash: UInt256, snapshot: LLMQSnapshot) -> Result<(), StoreError> {
+        let mut payload = block_hash.0.to_vec();
+        write_stored_snapshot(&snapshot, &mut payload);
+        self.append(RECORD_SNAPSHOT, payload)?;
+        self.index.save_snapshot(block_hash, snapshot)
+    }
+
+    fn snapshot(&self, block_hash: &UInt256) -> Result<Option<LLMQSnapshot>, StoreError> {
+        self.index.snapshot(block_hash)
+    }
+
+    fn snapshot_hashes(&self) -> Result<Vec<UInt256>, StoreError> {
+        self.index.snapshot_hashes()
+    }
+
+    fn save_quorum_members(&mut self, llmq_type: LLMQType, block_hash: UInt256, members: Vec<MasternodeEntry>) -> Result<(), StoreError> {
+        let mut payload = block_hash.0.to_vec();
+        payload.push(llmq_type.into());
+        write_stored_masternode_entries(&members, &mut payload);
+        self.append(RECORD_QUORUM_MEMBERS, payload)?;
+        self.index.save_quorum_members(llmq_type, block_hash, members)
+    }
+
+    fn quorum_members(&self, llmq_type: LLMQType, block_hash: &UInt256) -> Result<Option<Vec<MasternodeEntry>>, StoreError> {
+        self.index.quorum_members(llmq_type, block_hash)
+    }
+
+    fn quorum_members_keys(&self) -> Result<Vec<(LLMQType, UInt256)>, StoreError> {
+        self.index.quorum_members_keys()
+    }
+
+    fn save_indexed_quorum_members(&mut self, llmq_type: LLMQType, indexed_hash: LLMQIndexedHash, members: Vec<MasternodeEntry>) -> Result<(), StoreError> {
+        let mut payload = indexed_hash.hash.0.to_vec();
+        payload.push(llmq_type.into());
+        payload.extend_from_slice(&indexed_hash.index.to_le_bytes());
+        write_stored_masternode_entries(&members, &mut payload);
+        self.append(RECORD_INDEXED_QUORUM_MEMBERS, payload)?;
+        self.index.save_indexed_quorum_members(llmq_type, indexed_hash, members)
+    }
+
+    fn indexed_quorum_members(&self, llmq_type: LLMQType, indexed_hash: &LLMQIndexedHash) -> Result<Option<Vec<MasternodeEntry>>, StoreError> {
+        self.index.indexed_quorum_members(llmq_type, indexed_hash)
+    }
+
+    fn indexed_quorum_members_keys(&self) -> Result<Vec<(LLMQType, LLMQIndexedHash)>, StoreError> {
+        self.index.indexed_quorum_members_keys()
+    }
+
+    /// Records are only appended for the keys which aren't stored yet:
+    /// the list, snapshot and members of the block never change, so saving the same cache
+    /// again (i.e. at the end of each session) doesn't grow the file
+    fn save_cache(&mut self, cache: &MasternodeProcessorCache) -> Result<(), StoreError> {
+        for (block_hash, list) in &cache.mn_lists {
+            if !self.index.mn_lists.contains_key(block_hash) {
+                self.save_masternode_list(*block_hash, Arc::clone(list))?;
+            }
+        }
+        for (block_hash, snapshot) in &cache.llmq_snapshots {
+            if !self.index.llmq_snapshots.contains_key(block_hash) {
+                self.save_snapshot(*block_hash, snapshot.clone())?;
+            }
+        }
+        for (&llmq_type, members_of_type) in &cache.llmq_members {
+            for (block_hash, members) in members_of_type {
+                if !self.index.llmq_members.contains_key(&(llmq_type, *block_hash)) {
+                    self.save_quorum_members(llmq_type, *block_hash, members.clone())?;
+                }
+            }
+        }
+        for (&llmq_type, members_of_type) in &cache.llmq_indexed_members {
+            for (indexed_hash, members) in members_of_type {
+                if !self.index.llmq_indexed_members.contains_key(&(llmq_type, indexed_hash.clone())) {
+                    self.save_indexed_quorum_members(llmq_type, indexed_hash.clone(), members.clone())?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,81 @@
+use crate::processing::SharedMasternodeList;
+use crate::store::{MasternodeStore, StoreError};
+use dash_spv_models::common::LLMQType;
+use dash_spv_models::llmq::{LLMQIndexedHash, LLMQSnapshot};
+use dash_spv_models::masternode::MasternodeEntry;
+use dash_spv_primitives::crypto::UInt256;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+#[derive(Clone, Debug, Default)]
+pub struct MemoryStore {
+    pub mn_lists: BTreeMap<UInt256, Arc<SharedMasternodeList>>,
+    /// Block hash of the latest list saved for the height
+    pub mn_list_heights: BTreeMap<u32, UInt256>,
+    pub llmq_snapshots: BTreeMap<UInt256, LLMQSnapshot>,
+    pub llmq_members: BTreeMap<(LLMQType, UInt256), Vec<MasternodeEntry>>,
+    pub llmq_indexed_members: BTreeMap<(LLMQType, LLMQIndexedHash), Vec<MasternodeEntry>>,
+}
+
+impl MasternodeStore for MemoryStore {
+    fn save_masternode_list(&mut self, block_hash: UInt256, list: Arc<SharedMasternodeList>) -> Result<(), StoreError> {
+        self.mn_list_heights.insert(list.known_height, block_hash);
+        self.mn_lists.insert(block_hash, list);
+        Ok(())
+    }
+
+    fn masternode_list(&self, block_hash: &UInt256) -> Result<Option<Arc<SharedMasternodeList>>, StoreError> {
+        Ok(self.mn_lists.get(block_hash).cloned())
+    }
+
+    fn masternode_list_hashes(&self) -> Result<Vec<UInt256>, StoreError> {
+        Ok(self.mn_lists.keys().cloned().collect())
+    }
+
+    fn masternode_list_at_or_below(&self, block_height: u32) -> Result<Option<Arc<SharedMasternodeList>>, StoreError> {
+        Ok(self
+            .mn_list_heights
+            .range(..=block_height)
+            .next_back()
+            .and_then(|(_, block_hash)| self.mn_lists.get(block_hash).cloned()))
+    }
+
+    fn save_snapshot(&mut self, block_hash: UInt256, snapshot: LLMQSnapshot) -> Result<(), StoreError> {
+        self.llmq_snapshots.insert(block_hash, snapshot);
+        Ok(())
+    }
+
+    fn snapshot(&self, block_hash: &UInt256) -> Result<Option<LLMQSnapshot>, StoreError> {
+        Ok(self.llmq_snapshots.get(block_hash).cloned())
+    }
+
+    fn snapshot_hashes(&self) -> Result<Vec<UInt256>, StoreError> {
+        Ok(self.llmq_snapshots.keys().cloned().collect())
+    }
+
+    fn save_quorum_members(&mut self, llmq_type: LLMQType, block_hash: UInt256, members: Vec<MasternodeEntry>) -> Result<(), StoreError> {
+        self.llmq_members.insert((llmq_type, block_hash), members);
+        Ok(())
+    }
+
+    fn quorum_members(&self, llmq_type: LLMQType, block_hash: &UInt256) -> Result<Option<Vec<MasternodeEntry>>, StoreError> {
+        Ok(self.llmq_members.get(&(llmq_type, *block_hash)).cloned())
+    }
+
+    fn quorum_members_keys(&self) -> Result<Vec<(LLMQType, UInt256)>, StoreError> {
+        Ok(self.llmq_members.keys().cloned().collect())
+    }
+
+    fn save_indexed_quorum_members(&mut self, llmq_type: LLMQType, indexed_hash: LLMQIndexedHash, members: Vec<MasternodeEntry>) -> Result<(), StoreError> {
+        self.llmq_indexed_members.insert((llmq_type, indexed_hash), members);
+        Ok(())
+    }
+
+    fn indexed_quorum_members(&self, llmq_type: LLMQType, indexed_hash: &LLMQIndexedHash) -> Result<Option<Vec<MasternodeEntry>>, StoreError> {
+        Ok(self.llmq_indexed_members.get(&(llmq_type, indexed_hash.clone())).cloned())
+    }
+
+    fn indexed_quorum_members_keys(&self) -> Result<Vec<(LLMQType, LLMQIndexedHash)>, StoreError> {
+        Ok(self.llmq_indexed_members.keys().cloned().collect())
+    }
+}
@@ -0,0 +1,129 @@
+pub mod checkpoint;
+pub mod diff_compressed_store;
+pub mod file_store;
+pub mod memory_store;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_store;
+pub mod store_error;
+
+pub use self::checkpoint::{CheckpointError, MasternodeCheckpoint};
+pub use self::diff_compressed_store::{DiffCompressedStore, ListDelta};
+pub use self::file_store::FileStore;
+pub use self::memory_store::MemoryStore;
+#[cfg(feature = "sqlite")]
+pub use self::sqlite_store::SqliteStore;
+pub use self::store_error::StoreError;
+
+use crate::processing::{MNListDiffResult, MasternodeProcessorCache, SharedMasternodeList};
+use dash_spv_models::common::LLMQType;
+use dash_spv_models::llmq::{LLMQIndexedHash, LLMQSnapshot};
+use dash_spv_models::masternode::MasternodeEntry;
+use dash_spv_primitives::crypto::UInt256;
+use std::sync::Arc;
+
+/// Persistence of the processing state: masternode lists, quorum snapshots
+/// and computed quorum members (rotated ones by their cycle and index),
+/// keyed the same way as in MasternodeProcessorCache
+pub trait MasternodeStore {
+    fn save_masternode_list(&mut self, block_hash: UInt256, list: Arc<SharedMasternodeList>) -> Result<(), StoreError>;
+    fn masternode_list(&self, block_hash: &UInt256) -> Result<Option<Arc<SharedMasternodeList>>, StoreError>;
+    fn masternode_list_hashes(&self) -> Result<Vec<UInt256>, StoreError>;
+    /// Nearest list at or below the height (by its known height).
+    /// Stores which index lists by height should override it, by default all the lists are scanned
+    fn masternode_list_at_or_below(&self, block_height: u32) -> Result<Option<Arc<SharedMasternodeList>>, StoreError> {
+        let mut nearest: Option<Arc<SharedMasternodeList>> = None;
+        for block_hash in self.masternode_list_hashes()? {
+            if let Some(list) = self.masternode_list(&block_hash)? {
+                if list.known_height <= block_height
+                    && nearest.as_ref().map_or(true, |nearest| nearest.known_height < list.known_height)
+                {
+                    nearest = Some(list);
+                }
+            }
+        }
+        Ok(nearest)
+    }
+
+    fn save_snapshot(&mut self, block_hash: UInt256, snapshot: LLMQSnapshot) -> Result<(), StoreError>;
+    fn snapshot(&self, block_hash: &UInt256) -> Result<Option<LLMQSnapshot>, StoreError>;
+    fn snapshot_hashes(&self) -> Result<Vec<UInt256>, StoreError>;
+
+    fn save_quorum_members(&mut self, llmq_type: LLMQType, block_hash: UInt256, members: Vec<MasternodeEntry>) -> Result<(), StoreError>;
+    fn quorum_members(&self, llmq_type: LLMQType, block_hash: &UInt256) -> Result<Option<Vec<MasternodeEntry>>, StoreError>;
+    fn quorum_members_keys(&self) -> Result<Vec<(LLMQType, UInt256)>, StoreError>;
+
+    fn save_indexed_quorum_members(&mut self, llmq_type: LLMQType, indexed_hash: LLMQIndexedHash, members: Vec<MasternodeEntry>) -> Result<(), StoreError>;
+    fn indexed_quorum_members(&self, llmq_type: LLMQType, indexed_hash: &LLMQIndexedHash) -> Result<Option<Vec<MasternodeEntry>>, StoreError>;
+    fn indexed_quorum_members_keys(&self) -> Result<Vec<(LLMQType, LLMQIndexedHash)>, StoreError>;
+
+    /// Writes the outcome of a single processed diff: the resulting list, the snapshot
+    /// at its block and members of the added quorums, if the cache has them
+    fn save_list_diff_result(&mut self, result: &MNListDiffResult, cache: &MasternodeProcessorCache) -> Result<(), StoreError> {
+        self.save_masternode_list(result.block_hash, Arc::clone(&result.masternode_list))?;
+        if let Some(snapshot) = cache.llmq_snapshots.get(&result.block_hash) {
+            self.save_snapshot(result.block_hash, snapshot.clone())?;
+        }
+        for (&llmq_type, quorums_of_type) in &result.added_quorums {
+            for llmq_hash in quorums_of_type.keys() {
+                if let Some(members) = cache.llmq_members.get(&llmq_type).and_then(|members| members.get(llmq_hash)) {
+                    self.save_quorum_members(llmq_type, *llmq_hash, members.clone())?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes everything the cache has accumulated during the processing session
+    fn save_cache(&mut self, cache: &MasternodeProcessorCache) -> Result<(), StoreError> {
+        for (block_hash, list) in &cache.mn_lists {
+            self.save_masternode_list(*block_hash, Arc::clone(list))?;
+        }
+        for (block_hash, snapshot) in &cache.llmq_snapshots {
+            self.save_snapshot(*block_hash, snapshot.clone())?;
+        }
+        for (&llmq_type, members_of_type) in &cache.llmq_members {
+            for (block_hash, members) in members_of_type {
+                self.save_quorum_members(llmq_type, *block_hash, members.clone())?;
+            }
+        }
+        for (&llmq_type, members_of_type) in &cache.llmq_indexed_members {
+            for (indexed_hash, members) in members_of_type {
+                self.save_indexed_quorum_members(llmq_type, indexed_hash.clone(), members.clone())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Fills the cache with the stored state, so processing could continue from it
+    fn load_cache(&self, cache: &mut MasternodeProcessorCache) -> Result<(), StoreError> {
+        for block_hash in self.masternode_list_hashes()? {
+            if let Some(list) = self.masternode_list(&block_hash)? {
+                cache.add_masternode_list(block_hash, list);
+            }
+        }
+        for block_hash in self.snapshot_hashes()? {
+            if let Some(snapshot) = self.snapshot(&block_hash)? {
+                cache.llmq_snapshots.insert(block_hash, snapshot);
+            }
+        }
+        for (llmq_type, block_hash) in self.quorum_members_keys()? {
+            if let Some(members) = self.quorum_members(llmq_type, &block_hash)? {
+                cache
+                    .llmq_members
+                    .entry(llmq_type)
+                    .or_default()
+                    .insert(block_hash, members);
+            }
+        }
+        for (llmq_type, indexed_hash) in self.indexed_quorum_members_keys()? {
+            if let Some(members) = self.indexed_quorum_members(llmq_type, &indexed_hash)? {
+                cache
+                    .llmq_indexed_members
+                    .entry(llmq_type)
+                    .or_default()
+                    .insert(indexed_hash, members);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,333 @@
+use crate::codec::{read_stored_masternode_entries, read_stored_masternode_list, read_stored_snapshot, write_stored_masternode_entries, write_stored_masternode_list, write_stored_snapshot};
+use crate::processing::{MNListDiffResult, MasternodeProcessorCache, SharedMasternodeList};
+use crate::store::{MasternodeStore, StoreError};
+use dash_spv_models::common::LLMQType;
+use dash_spv_models::llmq::{LLMQIndexedHash, LLMQSnapshot};
+use dash_spv_models::masternode::MasternodeEntry;
+use dash_spv_primitives::crypto::UInt256;
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Schema changes, each one is applied once in its own transaction.
+/// Applied count is kept in 'user_version', so new migrations are only appended
+const MIGRATIONS: [&str; 3] = [
+    "CREATE TABLE masternode_lists (
+        block_hash BLOB PRIMARY KEY NOT NULL,
+        height INTEGER NOT NULL,
+        data BLOB NOT NULL
+    );
+    CREATE INDEX masternode_lists_height ON masternode_lists (height);
+    CREATE TABLE llmq_snapshots (
+        block_hash BLOB PRIMARY KEY NOT NULL,
+        data BLOB NOT NULL
+    );",
+    "CREATE TABLE quorum_members (
+        llmq_type INTEGER NOT NULL,
+        block_hash BLOB NOT NULL,
+        data BLOB NOT NULL,
+        PRIMARY KEY (llmq_type, block_hash)
+    );",
+    "CREATE TABLE indexed_quorum_members (
+        llmq_type INTEGER NOT NULL,
+        block_hash BLOB NOT NULL,
+        quorum_index INTEGER NOT NULL,
+        data BLOB NOT NULL,
+        PRIMARY KEY (llmq_type, block_hash, quorum_index)
+    );",
+];
+
+/// Embedded database store (available with 'sqlite' feature).
+/// Lists are indexed by height and all the writes of 'save_cache' are done in one transaction
+#[derive(Debug)]
+pub struct SqliteStore {
+    connection: Connection,
+}
+
+impl From<rusqlite::Error> for StoreError {
+    fn from(err: rusqlite::Error) -> Self {
+        StoreError::Database(err.to_string())
+    }
+}
+
+impl SqliteStore {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, StoreError> {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self, StoreError> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(mut connection: Connection) -> Result<Self, StoreError> {
+        Self::migrate(&mut connection)?;
+        Ok(Self { connection })
+    }
+
+    fn migrate(connection: &mut Connection) -> Result<(), StoreError> {
+        let version: i64 = connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        for (index, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+            let tx = connection.transaction()?;
+            tx.execute_batch(migration)?;
+            tx.pragma_update(None, "user_version", (index + 1) as i64)?;
+            tx.commit()?;
+        }
+        Ok(())
+    }
+
+    pub fn schema_version(&self) -> Result<usize, StoreError> {
+        let version: i64 = self.connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        Ok(version as usize)
+    }
+
+    /// Block hashes of the stored lists within the heights range (inclusive) ordered by height
+    pub fn masternode_list_hashes_in_range(&self, from: u32, to: u32) -> Result<Vec<UInt256>, StoreError> {
+        let mut statement = self.connection.prepare(
+            "SELECT block_hash FROM masternode_lists WHERE height BETWEEN ?1 AND ?2 ORDER BY height",
+        )?;
+        let hashes = statement
+            .query_map(params![from, to], |row| row.get::<_, Vec<u8>>(0))?
+            .collect::<Result<Vec<Vec<u8>>, rusqlite::Error>>()?;
+        hashes.into_iter().map(|hash| Self::block_hash(hash)).collect()
+    }
+
+    pub fn masternode_list_at_height(&self, height: u32) -> Result<Option<Arc<SharedMasternodeList>>, StoreError> {
+        self.connection
+            .query_row(
+                "SELECT data FROM masternode_lists WHERE height = ?1",
+                params![height],
+                |row| row.get::<_, Vec<u8>>(0),
+            )
+            .optional()?
+            .map(|data| Self::decode_list(&data))
+            .transpose()
+    }
+
+    fn block_hash(bytes: Vec<u8>) -> Result<UInt256, StoreError> {
+        <[u8; 32]>::try_from(bytes.as_slice())
+            .map(UInt256)
+            .map_err(|_| StoreError::Database("invalid block hash".to_string()))
+    }
+
+    fn decode_list(data: &[u8]) -> Result<Arc<SharedMasternodeList>, StoreError> {
+        read_stored_masternode_list(data, &mut 0)
+            .map(Arc::new)
+            .ok_or_else(|| StoreError::Database("can't decode masternode list".to_string()))
+    }
+
+    fn insert_masternode_list(tx: &Transaction, block_hash: UInt256, list: &SharedMasternodeList) -> Result<(), StoreError> {
+        tx.execute(
+            "INSERT OR REPLACE INTO masternode_lists (block_hash, height, data) VALUES (?1, ?2, ?3)",
+            params![&block_hash.0[..], list.known_height, Self::encode(|data| write_stored_masternode_list(list, data))],
+        )?;
+        Ok(())
+    }
+
+    fn insert_snapshot(tx: &Transaction, block_hash: UInt256, snapshot: &LLMQSnapshot) -> Result<(), StoreError> {
+        tx.execute(
+            "INSERT OR REPLACE INTO llmq_snapshots (block_hash, data) VALUES (?1, ?2)",
+            params![&block_hash.0[..], Self::encode(|data| write_stored_snapshot(snapshot, data))],
+        )?;
+        Ok(())
+    }
+
+    fn insert_quorum_members(tx: &Transaction, llmq_type: LLMQType, block_hash: UInt256, members: &[MasternodeEntry]) -> Result<(), StoreError> {
+        let llmq_type: u8 = llmq_type.into();
+        tx.execute(
+            "INSERT OR REPLACE INTO quorum_members (llmq_type, block_hash, data) VALUES (?1, ?2, ?3)",
+            params![llmq_type, &block_hash.0[..], Self::encode(|data| write_stored_masternode_entries(members, data))],
+        )?;
+        Ok(())
+    }
+
+    fn insert_indexed_quorum_members(tx: &Transaction, llmq_type: LLMQType, indexed_hash: &LLMQIndexedHash, members: &[MasternodeEntry]) -> Result<(), StoreError> {
+        let llmq_type: u8 = llmq_type.into();
+        tx.execute(
+            "INSERT OR REPLACE INTO indexed_quorum_members (llmq_type, block_hash, quorum_index, data) VALUES (?1, ?2, ?3, ?4)",
+            params![llmq_type, &indexed_hash.hash.0[..], indexed_hash.index, Self::encode(|data| write_stored_masternode_entries(members, data))],
+        )?;
+        Ok(())
+    }
+
+    fn encode<W: FnOnce(&mut Vec<u8>)>(write: W) -> Vec<u8> {
+        let mut data = Vec::<u8>::new();
+        write(&mut data);
+        data
+    }
+
+    fn decode_members(data: &[u8]) -> Result<Vec<MasternodeEntry>, StoreError> {
+        read_stored_masternode_entries(data, &mut 0)
+            .ok_or_else(|| StoreError::Database("can't decode quorum members".to_string()))
+    }
+
+    fn select_hashes(&self, sql: &str) -> Result<Vec<UInt256>, StoreError> {
+        let mut statement = self.connection.prepare(sql)?;
+        let hashes = statement
+            .query_map([], |row| row.get::<_, Vec<u8>>(0))?
+            .collect::<Result<Vec<Vec<u8>>, rusqlite::Error>>()?;
+        hashes.into_iter().map(|hash| Self::block_hash(hash)).collect()
+    }
+}
+
+impl MasternodeStore for SqliteStore {
+    fn save_masternode_list(&mut self, block_hash: UInt256, list: Arc<SharedMasternodeList>) -> Result<(), StoreError> {
+        let tx = self.connection.transaction()?;
+        Self::insert_masternode_list(&tx, block_hash, &list)?;
+        Ok(tx.commit()?)
+    }
+
+    fn masternode_list(&self, block_hash: &UInt256) -> Result<Option<Arc<SharedMasternodeList>>, StoreError> {
+        self.connection
+            .query_row(
+                "SELECT data FROM masternode_lists WHERE block_hash = ?1",
+                params![&block_hash.0[..]],
+                |row| row.get::<_, Vec<u8>>(0),
+            )
+            .optional()?
+            .map(|data| Self::decode_list(&data))
+            .transpose()
+    }
+
+    fn masternode_list_hashes(&self) -> Result<Vec<UInt256>, StoreError> {
+        self.select_hashes("SELECT block_hash FROM masternode_lists ORDER BY height")
+    }
+
+    fn masternode_list_at_or_below(&self, block_height: u32) -> Result<Option<Arc<SharedMasternodeList>>, StoreError> {
+        self.connection
+            .query_row(
+                "SELECT data FROM masternode_lists WHERE height <= ?1 ORDER BY height DESC LIMIT 1",
+                params![block_height],
+                |row| row.get::<_, Vec<u8>>(0),
+            )
+            .optional()?
+            .map(|data| Self::decode_list(&data))
+            .transpose()
+    }
+
+    fn save_snapshot(&mut self, block_hash: UInt256, snapshot: LLMQSnapshot) -> Result<(), StoreError> {
+        let tx = self.connection.transaction()?;
+        Self::insert_snapshot(&tx, block_hash, &snapshot)?;
+        Ok(tx.commit()?)
+    }
+
+    fn snapshot(&self, block_hash: &UInt256) -> Result<Option<LLMQSnapshot>, StoreError> {
+        self.connection
+            .query_row(
+                "SELECT data FROM llmq_snapshots WHERE block_hash = ?1",
+                params![&block_hash.0[..]],
+                |row| row.get::<_, Vec<u8>>(0),
+            )
+            .optional()?
+            .map(|data| {
+                read_stored_snapshot(&data, &mut 0)
+                    .ok_or_else(|| StoreError::Database("can't decode snapshot".to_string()))
+            })
+            .transpose()
+    }
+
+    fn snapshot_hashes(&self) -> Result<Vec<UInt256>, StoreError> {
+        self.select_hashes("SELECT block_hash FROM llmq_snapshots")
+    }
+
+    fn save_quorum_members(&mut self, llmq_type: LLMQType, block_hash: UInt256, members: Vec<MasternodeEntry>) -> Result<(), StoreError> {
+        let tx = self.connection.transaction()?;
+        Self::insert_quorum_members(&tx, llmq_type, block_hash, &members)?;
+        Ok(tx.commit()?)
+    }
+
+    fn quorum_members(&self, llmq_type: LLMQType, block_hash: &UInt256) -> Result<Option<Vec<MasternodeEntry>>, StoreError> {
+        let llmq_type: u8 = llmq_type.into();
+        self.connection
+            .query_row(
+                "SELECT data FROM quorum_members WHERE llmq_type = ?1 AND block_hash = ?2",
+                params![llmq_type, &block_hash.0[..]],
+                |row| row.get::<_, Vec<u8>>(0),
+            )
+            .optional()?
+            .map(|data| Self::decode_members(&data))
+            .transpose()
+    }
+
+    fn quorum_members_keys(&self) -> Result<Vec<(LLMQType, UInt256)>, StoreError> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT llmq_type, block_hash FROM quorum_members")?;
+        let keys = statement
+            .query_map([], |row| Ok((row.get::<_, u8>(0)?, row.get::<_, Vec<u8>>(1)?)))?
+            .collect::<Result<Vec<(u8, Vec<u8>)>, rusqlite::Error>>()?;
+        keys.into_iter()
+            .map(|(llmq_type, hash)| Ok((LLMQType::from(llmq_type), Self::block_hash(hash)?)))
+            .collect()
+    }
+
+    fn save_indexed_quorum_members(&mut self, llmq_type: LLMQType, indexed_hash: LLMQIndexedHash, members: Vec<MasternodeEntry>) -> Result<(), StoreError> {
+        let tx = self.connection.transaction()?;
+        Self::insert_indexed_quorum_members(&tx, llmq_type, &indexed_hash, &members)?;
+        Ok(tx.commit()?)
+    }
+
+    fn indexed_quorum_members(&self, llmq_type: LLMQType, indexed_hash: &LLMQIndexedHash) -> Result<Option<Vec<MasternodeEntry>>, StoreError> {
+        let llmq_type: u8 = llmq_type.into();
+        self.connection
+            .query_row(
+                "SELECT data FROM indexed_quorum_members WHERE llmq_type = ?1 AND block_hash = ?2 AND quorum_index = ?3",
+                params![llmq_type, &indexed_hash.hash.0[..], indexed_hash.index],
+                |row| row.get::<_, Vec<u8>>(0),
+            )
+            .optional()?
+            .map(|data| Self::decode_members(&data))
+            .transpose()
+    }
+
+    fn indexed_quorum_members_keys(&self) -> Result<Vec<(LLMQType, LLMQIndexedHash)>, StoreError> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT llmq_type, block_hash, quorum_index FROM indexed_quorum_members")?;
+        let keys = statement
+            .query_map([], |row| Ok((row.get::<_, u8>(0)?, row.get::<_, Vec<u8>>(1)?, row.get::<_, u32>(2)?)))?
+            .collect::<Result<Vec<(u8, Vec<u8>, u32)>, rusqlite::Error>>()?;
+        keys.into_iter()
+            .map(|(llmq_type, hash, index)| Ok((LLMQType::from(llmq_type), LLMQIndexedHash::new(Self::block_hash(hash)?, index))))
+            .collect()
+    }
+
+    /// List, snapshot and quorum members of the diff are committed together,
+    /// each diff of the session is committed on its own
+    fn save_list_diff_result(&mut self, result: &MNListDiffResult, cache: &MasternodeProcessorCache) -> Result<(), StoreError> {
+        let tx = self.connection.transaction()?;
+        Self::insert_masternode_list(&tx, result.block_hash, &result.masternode_list)?;
+        if let Some(snapshot) = cache.llmq_snapshots.get(&result.block_hash) {
+            Self::insert_snapshot(&tx, result.block_hash, snapshot)?;
+        }
+        for (&llmq_type, quorums_of_type) in &result.added_quorums {
+            for llmq_hash in quorums_of_type.keys() {
+                if let Some(members) = cache.llmq_members.get(&llmq_type).and_then(|members| members.get(llmq_hash)) {
+                    Self::insert_quorum_members(&tx, llmq_type, *llmq_hash, members)?;
+                }
+            }
+        }
+        Ok(tx.commit()?)
+    }
+
+    /// Either the whole cache is stored or nothing
+    fn save_cache(&mut self, cache: &MasternodeProcessorCache) -> Result<(), StoreError> {
+        let tx = self.connection.transaction()?;
+        for (block_hash, list) in &cache.mn_lists {
+            Self::insert_masternode_list(&tx, *block_hash, list)?;
+        }
+        for (block_hash, snapshot) in &cache.llmq_snapshots {
+            Self::insert_snapshot(&tx, *block_hash, snapshot)?;
+        }
+        for (&llmq_type, members_of_type) in &cache.llmq_members {
+            for (block_hash, members) in members_of_type {
+                Self::insert_quorum_members(&tx, llmq_type, *block_hash, members)?;
+            }
+        }
+        for (&llmq_type, members_of_type) in &cache.llmq_indexed_members {
+            for (indexed_hash, members) in members_of_type {
+                Self::insert_indexed_quorum_members(&tx, llmq_type, indexed_hash, members)?;
+            }
+        }
+        Ok(tx.commit()?)
+    }
+}
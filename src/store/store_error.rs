@@ -0,0 +1,28 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum StoreError {
+    Io(std::io::Error),
+    /// Record at the offset can't be decoded or its checksum doesn't match
+    Corrupted(u64),
+    /// Error reported by the database backend
+    Database(String),
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::Io(err) => write!(f, "store io error: {}", err),
+            StoreError::Corrupted(offset) => write!(f, "store record at {} is corrupted", offset),
+            StoreError::Database(message) => write!(f, "store database error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {}
+
+impl From<std::io::Error> for StoreError {
+    fn from(err: std::io::Error) -> Self {
+        StoreError::Io(err)
+    }
+}
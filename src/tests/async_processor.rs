@@ -0,0 +1,118 @@
+use crate::codec::QRInfoMessage;
+use crate::lib_tests::tests::{message_from_file, FFIContext, MerkleBlock};
+use crate::processing::{AsyncProcessor, AsyncProcessorLookup, LLMQRotationConfig, ProcessingError, ProcessorSession, PullProcessor, QuorumValidationData, SessionChain};
+use crate::tests::block_store::init_mainnet_store;
+use crate::tests::processor_session::default_processor;
+use dash_spv_models::common::chain_type::{ChainType, IHaveChainSettings};
+use dash_spv_primitives::crypto::UInt256;
+use std::future::Future;
+use std::pin::{pin, Pin};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::task::{Context, Poll, Waker};
+
+/// Future which is pending once, as the storage or the network would be
+struct YieldOnce(bool);
+
+impl Future for YieldOnce {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            Poll::Ready(())
+        } else {
+            self.0 = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
+
+/// Executor is enough to drive the futures of the test, all of them wake themselves up
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let mut cx = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+}
+
+fn assert_send<T: Send>(_: &T) {}
+
+#[derive(Debug, Default)]
+struct StoreLookup {
+    blocks: Vec<MerkleBlock>,
+    lookups: AtomicUsize,
+    validations: AtomicUsize,
+}
+
+impl AsyncProcessorLookup for StoreLookup {
+    async fn block_height(&self, block_hash: UInt256) -> Option<u32> {
+        YieldOnce(false).await;
+        self.lookups.fetch_add(1, Ordering::SeqCst);
+        self.blocks.iter().find(|block| block.hash == block_hash).map(|block| block.height)
+    }
+
+    async fn block_hash(&self, block_height: u32) -> Option<UInt256> {
+        YieldOnce(false).await;
+        self.lookups.fetch_add(1, Ordering::SeqCst);
+        self.blocks.iter().find(|block| block.height == block_height).map(|block| block.hash)
+    }
+
+    async fn merkle_root(&self, _block_hash: UInt256) -> Option<UInt256> {
+        None
+    }
+
+    async fn validate_quorum(&self, _data: QuorumValidationData) -> bool {
+        self.validations.fetch_add(1, Ordering::SeqCst);
+        true
+    }
+}
+
+#[test]
+fn test_async_processor_list_diffs() {
+    let lookup = StoreLookup { blocks: init_mainnet_store(), ..Default::default() };
+    let mut processor = AsyncProcessor::for_chain(&SessionChain::MainNet, lookup);
+    // Same messages processed with the callbacks give the reference results
+    let context = &mut (FFIContext {
+        chain: ChainType::MainNet,
+        cache: &mut Default::default(),
+        blocks: init_mainnet_store()
+    });
+    let mut session = ProcessorSession::new(SessionChain::MainNet, default_processor());
+    for file in ["MNL_0_1090944.dat", "MNL_1090944_1091520.dat"] {
+        let bytes = message_from_file(file.to_string());
+        let expected = session.process_mnlistdiff(&bytes, false, false, context as *mut _ as *mut std::ffi::c_void);
+        let future = processor.process_mnlistdiff(&bytes);
+        assert_send(&future);
+        let result = block_on(future);
+        assert_eq!(result.error_status, ProcessingError::None, "{}", file);
+        assert_eq!(result.block_hash, expected.block_hash, "{}", file);
+        assert_eq!(result.has_found_coinbase, expected.has_found_coinbase, "{}", file);
+        assert_eq!(result.has_valid_mn_list_root, expected.has_valid_mn_list_root, "{}", file);
+        assert_eq!(
+            result.masternode_list.masternodes.keys().collect::<Vec<_>>(),
+            expected.masternode_list.masternodes.keys().collect::<Vec<_>>(),
+            "{}: list must be the same", file
+        );
+    }
+    assert!(processor.lookup().lookups.load(Ordering::SeqCst) > 0, "Blocks are awaited from the lookup");
+    assert_eq!(processor.cache().mn_lists.len(), 2);
+}
+
+#[test]
+fn test_async_processor_qr_info() {
+    let bytes = message_from_file("QRINFO_0_1739226.dat".to_string());
+    let message = QRInfoMessage::from_bytes(&bytes, &mut 0, |_| 0).unwrap();
+    let processor = PullProcessor::new(ChainType::MainNet.genesis_hash(), LLMQRotationConfig::default());
+    let mut processor = AsyncProcessor::new(processor, StoreLookup::default());
+    let result = block_on(processor.process_qrinfo(&bytes));
+    assert_eq!(result.error_status, ProcessingError::None);
+    assert_eq!(result.result_at_tip.block_hash, message.diff_tip.diff.block_hash);
+    assert!(processor.cache().mn_lists.contains_key(&message.diff_h.diff.block_hash));
+    assert!(processor.cache().llmq_snapshots.contains_key(&message.diff_h_2c.diff.block_hash));
+
+    let result = block_on(processor.process_mnlistdiff(&[0; 10]));
+    assert_eq!(result.error_status, ProcessingError::ParseError);
+}
@@ -0,0 +1,60 @@
+use crate::processing::MasternodeProcessorCache;
+use crate::tests::mainnet_reload::process_mainnet_reload;
+use crate::{processor_cache_stats, processor_destroy_cache_stats};
+use std::collections::HashSet;
+
+#[test]
+fn test_cache_stats() {
+    let mut cache = MasternodeProcessorCache::default();
+    let empty = cache.stats();
+    assert_eq!(empty.mn_lists_count(), 0);
+    assert_eq!(empty.lowest_mn_list_height(), None);
+    assert_eq!(empty.approximate_memory_usage, 0);
+
+    process_mainnet_reload(&mut cache);
+    let stats = cache.stats();
+    assert_eq!(stats.mn_lists_count(), 29, "There should be 29 masternode lists");
+    assert_eq!(stats.lowest_mn_list_height(), Some(1090944));
+    assert_eq!(stats.highest_mn_list_height(), Some(1092916));
+    assert!(stats.mn_list_heights.windows(2).all(|pair| pair[0] <= pair[1]), "Heights should be sorted");
+    assert_eq!(stats.needed_masternode_lists, cache.needed_masternode_lists);
+    assert_eq!(stats.llmq_members.values().sum::<usize>(), cache.llmq_members.values().map(|members| members.len()).sum::<usize>());
+    let entries: usize = cache.mn_lists.values().map(|list| list.masternodes.len()).sum();
+    assert!(stats.approximate_memory_usage > entries, "Memory usage should account for the entries");
+
+    let ffi_stats = unsafe { processor_cache_stats(&cache) };
+    let encoded = unsafe { *ffi_stats };
+    assert_eq!(encoded.mn_lists_count, 29);
+    assert_eq!(unsafe { *encoded.mn_list_heights.add(28) }, 1092916);
+    assert_eq!(encoded.llmq_members_count, stats.llmq_members.len());
+    assert_eq!(encoded.approximate_memory_usage, stats.approximate_memory_usage);
+    unsafe { processor_destroy_cache_stats(ffi_stats) };
+}
+
+#[test]
+fn test_cache_stats_merkle_trees() {
+    let mut cache = MasternodeProcessorCache::default();
+    process_mainnet_reload(&mut cache);
+    let stats = cache.stats();
+    assert_eq!(stats.merkle_trees_count, stats.mn_lists_count(), "Each processed list should have its trees");
+    let separate_usage = cache
+        .merkle_trees
+        .values()
+        .map(|trees| trees.memory_usage(&mut HashSet::new()))
+        .sum::<usize>();
+    let mut counted = HashSet::new();
+    let shared_usage = cache
+        .merkle_trees
+        .values()
+        .map(|trees| trees.memory_usage(&mut counted))
+        .sum::<usize>();
+    assert!(shared_usage < separate_usage, "Trees of the consecutive lists should share their chunks");
+
+    let block_hash = *cache.mn_lists.keys().next().unwrap();
+    assert!(cache.remove_masternode_list(&block_hash).is_some());
+    assert!(!cache.merkle_trees.contains_key(&block_hash), "Trees should be dropped along with the list");
+    let pruned = cache.stats();
+    assert_eq!(pruned.merkle_trees_count, pruned.mn_lists_count());
+    assert!(pruned.approximate_memory_usage < stats.approximate_memory_usage);
+    assert!(cache.remove_masternode_list(&block_hash).is_none());
+}
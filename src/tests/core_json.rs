@@ -0,0 +1,104 @@
+use crate::codec::{ListDiffMessage, QRInfoMessage};
+use crate::core_json::from_json::{masternode_from_json, quorum_from_json};
+use crate::core_json::{hash_to_json, list_diff_from_json, list_diff_to_json, qr_info_to_json, masternode_list_from_json, masternode_list_to_json, quorum_to_json, snapshot_from_json, snapshot_to_json, ListDiff, Node, QRInfo, LLMQ};
+use crate::lib_tests::tests::message_from_file;
+use dash_spv_models::common::ChainType;
+
+#[test]
+fn test_core_json_masternode_list_round_trip() {
+    let chain = ChainType::MainNet;
+    let diff: ListDiff = serde_json::from_slice(&message_from_file("1738792_diff.txt".to_string())).unwrap();
+    let list = masternode_list_from_json(&diff, 1738792).unwrap();
+    assert_eq!(list.masternodes.len(), diff.mn_list.len());
+    assert_eq!(hash_to_json(&list.block_hash), diff.block_hash);
+    // Roots computed from imported entries should be the same as Core has
+    assert_eq!(list.masternode_merkle_root.as_ref().map(hash_to_json), Some(diff.merkle_root_mnlist.clone()), "Masternode root should match Core's one");
+    let exported = masternode_list_to_json(&list, &chain);
+    let mut expected_nodes = diff.mn_list.clone();
+    let mut exported_nodes = exported.mn_list.clone();
+    expected_nodes.sort_by(|a, b| a.pro_reg_tx_hash.cmp(&b.pro_reg_tx_hash));
+    exported_nodes.sort_by(|a, b| a.pro_reg_tx_hash.cmp(&b.pro_reg_tx_hash));
+    assert_eq!(expected_nodes, exported_nodes, "Exported masternodes should be the same as Core's ones");
+    let mut expected_quorums = diff.new_quorums.clone();
+    let mut exported_quorums = exported.new_quorums.clone();
+    expected_quorums.sort_by(|a, b| (a.llmq_type, &a.quorum_hash).cmp(&(b.llmq_type, &b.quorum_hash)));
+    exported_quorums.sort_by(|a, b| (a.llmq_type, &a.quorum_hash).cmp(&(b.llmq_type, &b.quorum_hash)));
+    assert_eq!(expected_quorums, exported_quorums, "Exported quorums should be the same as Core's ones");
+    // and the exported list is loaded back into the same one
+    let json = serde_json::to_string(&exported).unwrap();
+    let restored = masternode_list_from_json(&serde_json::from_str(&json).unwrap(), 1738792).unwrap();
+    assert_eq!(list.masternode_merkle_root, restored.masternode_merkle_root);
+    assert_eq!(list.llmq_merkle_root, restored.llmq_merkle_root);
+}
+
+#[test]
+fn test_core_json_snapshot_round_trip() {
+    let qrinfo: QRInfo = serde_json::from_slice(&message_from_file("1738792.txt".to_string())).unwrap();
+    [&qrinfo.quorum_snapshot_at_hminus_c, &qrinfo.quorum_snapshot_at_hminus2c, &qrinfo.quorum_snapshot_at_hminus3c]
+        .into_iter()
+        .chain(qrinfo.quorum_snapshot_list.iter())
+        .for_each(|snapshot| {
+            let imported = snapshot_from_json(snapshot);
+            let exported = snapshot_to_json(&imported, snapshot.active_quorum_members.len());
+            assert_eq!(snapshot, &exported, "Snapshot should be exported as it was loaded");
+        });
+}
+
+#[test]
+fn test_core_json_malformed_entries() {
+    let node: Node = serde_json::from_str(r#"{"proRegTxHash": "b2ec60a27f88e4be90e26673053e4f942938826dcde77cd880cdfe2df8284440", "confirmedHash": "000000000000001acdbf3eaaf148cfb257c8db84edbf1565040928322d05dc98", "service": "95.179.229.181:9999", "pubKeyOperator": "145860e681f8b96d77bf4e26352ad20a7b01674776b77404027794b36c2805ec534cda8f0d052afe23e94b6ccd48f3c8", "votingAddress": "Xovz9qpk4FkiuJsdbsbtHTYnNivkYWLxQH", "isValid": true}"#).unwrap();
+    assert!(masternode_from_json(&node).is_some());
+    let broken_address = Node { voting_address: "Xovz9qpk4FkiuJsdbsbtHTYnNivkYWLxQ1".to_string(), ..node.clone() };
+    assert!(masternode_from_json(&broken_address).is_none(), "Address with broken checksum should be rejected");
+    let broken_service = Node { service: "95.179.229.181".to_string(), ..node };
+    assert!(masternode_from_json(&broken_service).is_none(), "Service without port should be rejected");
+    // Non-rotated quorums come without index
+    let signature = "15".repeat(96);
+    let llmq = LLMQ {
+        version: 1,
+        llmq_type: 1,
+        quorum_hash: "000000000000002052e2f922d3d474271acf7b72cdfa180eef57a449a3ea4101".to_string(),
+        quorum_index: None,
+        signers_count: 48,
+        signers: "ffffbf7fffff03".to_string(),
+        valid_members_count: 48,
+        valid_members: "ffffbf7fffff03".to_string(),
+        quorum_public_key: "8e3b657d1cd297351f5fc4198fb088c5b40eeb0b4a3875c72a3889f7a29f1e9434888b84e35eecb5981aba861e217735".to_string(),
+        quorum_vvec_hash: "d8b2c77aee95b6c91ba89d2bfacb2ac20ffa87eaa2d33c1cec72a7a17aa899c3".to_string(),
+        quorum_sig: signature.clone(),
+        members_sig: signature,
+    };
+    let entry = quorum_from_json(&llmq).unwrap();
+    assert_eq!(entry.index, None);
+    assert!(!serde_json::to_string(&quorum_to_json(&entry)).unwrap().contains("quorumIndex"));
+    assert_eq!(quorum_to_json(&entry), llmq);
+}
+
+#[test]
+fn test_core_json_message_export() {
+    let chain = ChainType::MainNet;
+    let bytes = message_from_file("MNL_0_1090944.dat".to_string());
+    let message = ListDiffMessage::from_bytes(&bytes, &mut 0, |_| 1090944).unwrap();
+    assert!(!message.coinbase_transaction.is_empty());
+    let json = list_diff_to_json(&message, &chain);
+    assert_eq!(json.mn_list.len(), message.diff.added_or_modified_masternodes.len());
+    // Exported diff is the same message as 'protx diff' would give
+    let imported = list_diff_from_json(&json, |_| 1090944).unwrap();
+    assert_eq!(imported.block_hash, message.diff.block_hash);
+    assert_eq!(imported.total_transactions, message.diff.total_transactions);
+    assert_eq!(imported.merkle_hashes.1, message.diff.merkle_hashes.1);
+    assert_eq!(imported.merkle_flags, message.diff.merkle_flags);
+    assert_eq!(imported.coinbase_transaction.merkle_root_mn_list, message.diff.coinbase_transaction.merkle_root_mn_list);
+    assert_eq!(
+        imported.added_or_modified_masternodes.keys().collect::<Vec<_>>(),
+        message.diff.added_or_modified_masternodes.keys().collect::<Vec<_>>()
+    );
+
+    let bytes = message_from_file("QRINFO_1_107966.dat".to_string());
+    let message = QRInfoMessage::from_bytes(&bytes, &mut 0, |_| u32::MAX).unwrap();
+    let json = qr_info_to_json(&message, &ChainType::TestNet);
+    assert_eq!(json.extra_share, message.extra_share.is_some());
+    assert_eq!(json.mn_list_diff_at_hminus4c.is_some(), json.extra_share);
+    assert_eq!(json.mn_list_diff_tip.block_hash, hash_to_json(&message.diff_tip.diff.block_hash));
+    assert_eq!(message.diffs().len(), 5 + usize::from(json.extra_share) + message.mn_list_diff_list.len());
+}
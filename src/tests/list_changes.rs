@@ -0,0 +1,91 @@
+use crate::processing::{compare_masternode_lists, MasternodeField, MasternodeProcessorCache, QuorumField};
+use crate::tests::mainnet_reload::process_mainnet_reload;
+use dash_spv_primitives::crypto::UInt256;
+use std::collections::BTreeSet;
+
+#[test]
+fn test_compare_adjacent_masternode_lists() {
+    let mut cache = MasternodeProcessorCache::default();
+    let results = process_mainnet_reload(&mut cache);
+    let mut compared = 0;
+    for (base, result) in results.iter().zip(results.iter().skip(1)) {
+        if result.base_block_hash != base.block_hash {
+            continue;
+        }
+        let changes = compare_masternode_lists(&base.masternode_list.to_list(), &result.masternode_list.to_list());
+        assert_eq!(changes.old_block_hash, base.block_hash);
+        assert_eq!(changes.new_block_hash, result.block_hash);
+        assert_eq!(
+            changes.added_masternodes.keys().collect::<BTreeSet<_>>(),
+            result.added_masternodes.keys().collect::<BTreeSet<_>>(),
+            "Added masternodes must match the ones of the diff"
+        );
+        assert!(
+            changes.modified_masternodes.keys().all(|hash| result.modified_masternodes.contains_key(hash)),
+            "Only masternodes modified by the diff can differ"
+        );
+        assert!(changes.modified_masternodes.values().all(|change| !change.fields.is_empty()));
+        compared += 1;
+    }
+    assert!(compared > 0, "Reload must contain adjacent diffs");
+}
+
+#[test]
+fn test_compare_arbitrary_masternode_lists() {
+    let mut cache = MasternodeProcessorCache::default();
+    process_mainnet_reload(&mut cache);
+    let mut lists = cache.mn_lists.values().map(|list| list.to_list()).collect::<Vec<_>>();
+    lists.sort_by_key(|list| list.known_height);
+    let (first, last) = (lists.first().unwrap(), lists.last().unwrap());
+
+    assert!(compare_masternode_lists(first, first).is_empty(), "List doesn't differ from itself");
+
+    let forward = compare_masternode_lists(first, last);
+    let backward = compare_masternode_lists(last, first);
+    assert!(!forward.is_empty());
+    assert_eq!(
+        forward.added_masternodes.keys().collect::<Vec<_>>(),
+        backward.removed_masternodes.keys().collect::<Vec<_>>()
+    );
+    assert_eq!(
+        forward.removed_masternodes.keys().collect::<Vec<_>>(),
+        backward.added_masternodes.keys().collect::<Vec<_>>()
+    );
+    assert_eq!(
+        forward.modified_masternodes.keys().collect::<Vec<_>>(),
+        backward.modified_masternodes.keys().collect::<Vec<_>>()
+    );
+    assert_eq!(
+        forward.added_quorums.values().map(|quorums| quorums.len()).sum::<usize>(),
+        backward.removed_quorums.values().map(|quorums| quorums.len()).sum::<usize>()
+    );
+}
+
+#[test]
+fn test_compare_masternode_lists_field_changes() {
+    let mut cache = MasternodeProcessorCache::default();
+    process_mainnet_reload(&mut cache);
+    let list = &cache.mn_lists.values().next().unwrap().to_list();
+
+    let mut changed = list.clone();
+    let (&changed_hash, entry) = changed.masternodes.iter_mut().next().unwrap();
+    entry.is_valid = !entry.is_valid;
+    let removed_hash = *changed.masternodes.keys().last().unwrap();
+    changed.masternodes.remove(&removed_hash);
+    let changes = compare_masternode_lists(list, &changed);
+    assert!(changes.added_masternodes.is_empty());
+    assert_eq!(changes.removed_masternodes.keys().collect::<Vec<&UInt256>>(), vec![&removed_hash]);
+    let change = changes.modified_masternodes.get(&changed_hash).unwrap();
+    assert_eq!(change.fields, vec![MasternodeField::IsValid], "Only the changed field must be reported");
+    assert_eq!(change.old.is_valid, !change.new.is_valid);
+
+    let mut changed = list.clone();
+    let (&llmq_type, quorums) = changed.quorums.iter_mut().next().unwrap();
+    let (&changed_hash, quorum) = quorums.iter_mut().next().unwrap();
+    quorum.verification_vector_hash = UInt256::MIN;
+    quorum.index = Some(quorum.index.map_or(1, |index| index + 1));
+    let changes = compare_masternode_lists(list, &changed);
+    assert!(changes.added_quorums.is_empty() && changes.removed_quorums.is_empty());
+    let change = changes.modified_quorums.get(&llmq_type).and_then(|quorums| quorums.get(&changed_hash)).unwrap();
+    assert_eq!(change.fields, vec![QuorumField::Index, QuorumField::VerificationVectorHash]);
+}
@@ -0,0 +1,113 @@
+use crate::codec::{encode_list_diff, BytesEncodable, ListDiffMessage};
+use crate::lib_tests::tests::{add_insight_lookup_default, get_block_hash_by_height_default, get_block_height_by_hash_from_context, get_llmq_snapshot_by_block_hash_default, get_masternode_list_by_block_hash_default, get_merkle_root_by_hash_default, hash_destroy_default, log_default, masternode_list_destroy_default, masternode_list_save_default, message_from_file, process_mnlistdiff_from_message_internal, save_llmq_snapshot_default, should_process_diff_with_range_default, should_process_llmq_of_type, snapshot_destroy_default, validate_llmq_callback, FFIContext};
+use crate::processing::{MasternodeProcessorCache, SharedMasternodeList};
+use crate::register_processor;
+use crate::tests::block_store::init_mainnet_store;
+use crate::tests::mainnet_reload::{mainnet_reload_files, process_mainnet_reload};
+use dash_spv_models::common::chain_type::{ChainType, IHaveChainSettings};
+use dash_spv_models::masternode::MasternodeEntry;
+use std::sync::Arc;
+
+fn masternode_entries(message: &ListDiffMessage) -> Vec<Vec<u8>> {
+    let mut entries = message
+        .diff
+        .added_or_modified_masternodes
+        .values()
+        .map(MasternodeEntry::to_bytes)
+        .collect::<Vec<Vec<u8>>>();
+    entries.sort();
+    entries
+}
+
+#[test]
+fn test_list_diff_message_round_trip() {
+    for file in mainnet_reload_files() {
+        let bytes = message_from_file(file.clone());
+        let message = ListDiffMessage::from_bytes(&bytes, &mut 0, |_| 0).unwrap();
+        let encoded = message.to_bytes();
+        assert_eq!(encoded.len(), bytes.len(), "{}: payload size must be the same", file);
+        assert!(encoded == bytes, "{}: payload must be byte-exact", file);
+        assert_eq!(message.order.masternodes.len(), message.diff.added_or_modified_masternodes.len(), "{}", file);
+        let decoded = ListDiffMessage::from_bytes(&encoded, &mut 0, |_| 0).unwrap();
+        assert_eq!(decoded.diff.deleted_masternode_hashes, message.diff.deleted_masternode_hashes);
+        assert_eq!(masternode_entries(&decoded), masternode_entries(&message), "{}: masternodes must be the same", file);
+        assert_eq!(decoded.diff.deleted_quorums, message.diff.deleted_quorums);
+        assert_eq!(
+            decoded.diff.added_quorums.values().flat_map(|quorums| quorums.values().map(|entry| entry.to_bytes())).collect::<Vec<_>>(),
+            message.diff.added_quorums.values().flat_map(|quorums| quorums.values().map(|entry| entry.to_bytes())).collect::<Vec<_>>(),
+            "{}: quorums must be the same", file
+        );
+        assert_eq!(decoded.to_bytes(), encoded, "{}: encoding must be stable", file);
+    }
+}
+
+#[test]
+fn test_list_diff_from_lists() {
+    let mut original_cache = MasternodeProcessorCache::default();
+    let original_results = process_mainnet_reload(&mut original_cache);
+
+    let context = &mut (FFIContext {
+        chain: ChainType::MainNet,
+        cache: &mut Default::default(),
+        blocks: init_mainnet_store()
+    });
+    let processor = unsafe {
+        register_processor(
+            get_merkle_root_by_hash_default,
+            get_block_height_by_hash_from_context,
+            get_block_hash_by_height_default,
+            get_llmq_snapshot_by_block_hash_default,
+            save_llmq_snapshot_default,
+            get_masternode_list_by_block_hash_default,
+            masternode_list_save_default,
+            masternode_list_destroy_default,
+            add_insight_lookup_default,
+            should_process_llmq_of_type,
+            validate_llmq_callback,
+            hash_destroy_default,
+            snapshot_destroy_default,
+            should_process_diff_with_range_default,
+            log_default,
+        )
+    };
+    let mut cache = MasternodeProcessorCache::default();
+    // Whole list goes against the empty list of the genesis
+    let mut base = Arc::new(SharedMasternodeList::empty(ChainType::MainNet.genesis_hash(), 0));
+    let mut encoded_count = 0;
+    for (file, original) in mainnet_reload_files().into_iter().zip(original_results.iter()) {
+        let message = ListDiffMessage::from_bytes(&message_from_file(file.clone()), &mut 0, |_| 0).unwrap();
+        if message.diff.base_block_hash != base.block_hash {
+            // Only the chain of diffs is re-encoded
+            break;
+        }
+        let bytes = encode_list_diff(
+            &base,
+            &original.masternode_list,
+            &message.merkle_tree(),
+            &message.coinbase_transaction,
+        )
+        .unwrap();
+        let result = process_mnlistdiff_from_message_internal(
+            bytes.as_ptr(),
+            bytes.len(),
+            false,
+            context.genesis_as_ptr(),
+            processor,
+            &mut cache,
+            context as *mut _ as *mut std::ffi::c_void,
+        );
+        assert_eq!(result.error_status, original.error_status, "{}", file);
+        assert_eq!(result.has_found_coinbase, original.has_found_coinbase, "{}", file);
+        assert_eq!(result.has_valid_mn_list_root, original.has_valid_mn_list_root, "{}: masternodes must be committed the same way", file);
+        assert_eq!(result.has_valid_llmq_list_root, original.has_valid_llmq_list_root, "{}: quorums must be committed the same way", file);
+        assert_eq!(result.masternode_list.masternode_merkle_root, original.masternode_list.masternode_merkle_root, "{}", file);
+        assert_eq!(
+            result.masternode_list.masternodes.keys().collect::<Vec<_>>(),
+            original.masternode_list.masternodes.keys().collect::<Vec<_>>(),
+            "{}: list must be the same", file
+        );
+        base = Arc::clone(&original.masternode_list);
+        encoded_count += 1;
+    }
+    assert!(encoded_count > 1, "Diffs from the genesis and from the list must be re-encoded");
+}
@@ -0,0 +1,159 @@
+use crate::lib_tests::tests::{add_insight_lookup_default, get_block_hash_by_height_default, get_block_height_by_hash_from_context, get_llmq_snapshot_by_block_hash_default, get_masternode_list_by_block_hash_from_cache, get_merkle_root_by_hash_default, hash_destroy_default, log_default, masternode_list_destroy_default, masternode_list_save_default, message_from_file, process_mnlistdiff_from_message_internal, save_llmq_snapshot_default, should_process_diff_with_range_default, should_process_llmq_of_type, snapshot_destroy_default, validate_llmq_callback, FFIContext};
+use crate::processing::{verify_masternode_list, verify_masternode_list_with_checkpoint, ListIntegrityError, MasternodeProcessorCache, ProcessingError, SharedMasternodeList};
+use crate::store::MasternodeCheckpoint;
+use crate::tests::block_store::init_mainnet_store;
+use crate::tests::mainnet_reload::process_mainnet_reload;
+use crate::{processor_set_verify_looked_up_lists, register_processor};
+use dash_spv_models::common::chain_type::ChainType;
+use dash_spv_models::common::LLMQType;
+use dash_spv_primitives::crypto::UInt256;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+#[test]
+fn test_verify_masternode_list() {
+    let mut cache = MasternodeProcessorCache::default();
+    process_mainnet_reload(&mut cache);
+    let (&block_hash, list) = cache.mn_lists.iter().next().unwrap();
+    assert!(verify_masternode_list(block_hash, list).is_ok(), "Processed list must be valid");
+    assert_eq!(
+        verify_masternode_list(UInt256::MIN, list).err(),
+        Some(ListIntegrityError::BlockHashMismatch)
+    );
+
+    let mut corrupted = SharedMasternodeList::clone(list);
+    let (&hash, entry) = corrupted.masternodes.iter().next().unwrap();
+    let mut entry = entry.as_ref().clone();
+    entry.is_valid = !entry.is_valid;
+    corrupted.masternodes.insert(hash, Arc::new(entry));
+    assert_eq!(
+        verify_masternode_list(block_hash, &corrupted).err(),
+        Some(ListIntegrityError::EntryHashMismatch),
+        "Entry changed without its hash must be detected"
+    );
+
+    let mut corrupted = SharedMasternodeList::clone(list);
+    let removed = *corrupted.masternodes.keys().next().unwrap();
+    corrupted.masternodes.remove(&removed);
+    assert_eq!(
+        verify_masternode_list(block_hash, &corrupted).err(),
+        Some(ListIntegrityError::MasternodeMerkleRootMismatch),
+        "Missing entry must be detected"
+    );
+
+    let checkpoint = MasternodeCheckpoint::new(Arc::clone(list), BTreeMap::new()).unwrap();
+    assert!(verify_masternode_list_with_checkpoint(list, &checkpoint).is_ok());
+    let other = cache.mn_lists.values().last().unwrap();
+    assert!(verify_masternode_list_with_checkpoint(other, &checkpoint).is_err(), "List of another block must be rejected");
+}
+
+#[test]
+fn test_verify_looked_up_lists() {
+    // Base list is served by 'MasternodeListLookup' from the context
+    let mut stored = MasternodeProcessorCache::default();
+    let context = &mut (FFIContext {
+        chain: ChainType::MainNet,
+        cache: &mut stored,
+        blocks: init_mainnet_store()
+    });
+    let processor = unsafe {
+        register_processor(
+            get_merkle_root_by_hash_default,
+            get_block_height_by_hash_from_context,
+            get_block_hash_by_height_default,
+            get_llmq_snapshot_by_block_hash_default,
+            save_llmq_snapshot_default,
+            get_masternode_list_by_block_hash_from_cache,
+            masternode_list_save_default,
+            masternode_list_destroy_default,
+            add_insight_lookup_default,
+            should_process_llmq_of_type,
+            validate_llmq_callback,
+            hash_destroy_default,
+            snapshot_destroy_default,
+            should_process_diff_with_range_default,
+            log_default,
+        )
+    };
+    unsafe { processor_set_verify_looked_up_lists(processor, true) };
+    let genesis = context.genesis_as_ptr();
+    let context_ptr = context as *mut _ as *mut std::ffi::c_void;
+    let bytes = message_from_file("MNL_0_1090944.dat".to_string());
+    let base = process_mnlistdiff_from_message_internal(bytes.as_ptr(), bytes.len(), false, genesis, processor, context.cache, context_ptr);
+    assert_eq!(base.error_status, ProcessingError::None);
+
+    let bytes = message_from_file("MNL_1090944_1091520.dat".to_string());
+    let cache = &mut MasternodeProcessorCache::default();
+    let result = process_mnlistdiff_from_message_internal(bytes.as_ptr(), bytes.len(), false, genesis, processor, cache, context_ptr);
+    assert_eq!(result.error_status, ProcessingError::None, "Valid stored list must pass verification");
+    assert!(result.has_valid_mn_list_root, "Diff should be applied to the looked up list");
+    assert!(cache.merkle_trees.contains_key(&base.block_hash), "Trees of the verified list should be cached");
+}
+
+#[test]
+fn test_corrupted_looked_up_lists() {
+    // Base list is served by 'MasternodeListLookup' from the context, but it's tampered there
+    let mut stored = MasternodeProcessorCache::default();
+    let context = &mut (FFIContext {
+        chain: ChainType::MainNet,
+        cache: &mut stored,
+        blocks: init_mainnet_store()
+    });
+    let processor = unsafe {
+        register_processor(
+            get_merkle_root_by_hash_default,
+            get_block_height_by_hash_from_context,
+            get_block_hash_by_height_default,
+            get_llmq_snapshot_by_block_hash_default,
+            save_llmq_snapshot_default,
+            get_masternode_list_by_block_hash_from_cache,
+            masternode_list_save_default,
+            masternode_list_destroy_default,
+            add_insight_lookup_default,
+            should_process_llmq_of_type,
+            validate_llmq_callback,
+            hash_destroy_default,
+            snapshot_destroy_default,
+            should_process_diff_with_range_default,
+            log_default,
+        )
+    };
+    unsafe { processor_set_verify_looked_up_lists(processor, true) };
+    let genesis = context.genesis_as_ptr();
+    let context_ptr = context as *mut _ as *mut std::ffi::c_void;
+    let bytes = message_from_file("MNL_0_1090944.dat".to_string());
+    let base = process_mnlistdiff_from_message_internal(bytes.as_ptr(), bytes.len(), false, genesis, processor, context.cache, context_ptr);
+    assert_eq!(base.error_status, ProcessingError::None);
+    let mut tampered = SharedMasternodeList::clone(&base.masternode_list);
+    let (&hash, entry) = tampered.masternodes.iter().next().unwrap();
+    let mut entry = entry.as_ref().clone();
+    entry.is_valid = !entry.is_valid;
+    tampered.masternodes.insert(hash, Arc::new(entry));
+    context.cache.add_masternode_list(base.block_hash, Arc::new(tampered));
+
+    let bytes = message_from_file("MNL_1090944_1091520.dat".to_string());
+    let cache = &mut MasternodeProcessorCache::default();
+    let result = process_mnlistdiff_from_message_internal(bytes.as_ptr(), bytes.len(), false, genesis, processor, cache, context_ptr);
+    assert_eq!(result.error_status, ProcessingError::CorruptedStorage, "Diff must not be applied to the tampered list");
+    assert!(cache.needed_masternode_lists.contains(&base.block_hash), "Tampered list should be requested again");
+    assert!(!cache.merkle_trees.contains_key(&base.block_hash));
+
+    // Quorum validated with the tampered list at its block is reported the same way
+    let mut quorum = base
+        .masternode_list
+        .quorums
+        .get(&LLMQType::Llmqtype400_60)
+        .and_then(|quorums| quorums.values().next())
+        .cloned()
+        .unwrap();
+    quorum.llmq_hash = base.block_hash;
+    let added = BTreeMap::from([(quorum.llmq_type, BTreeMap::from([(quorum.llmq_hash, quorum)]))]);
+    let processor = unsafe { &mut *processor };
+    processor.opaque_context = context_ptr;
+    processor.genesis_hash = genesis;
+    let cache = &mut MasternodeProcessorCache::default();
+    assert_eq!(
+        processor.classify_quorums(&BTreeMap::new(), added, BTreeMap::new(), cache).err(),
+        Some(ListIntegrityError::EntryHashMismatch)
+    );
+}
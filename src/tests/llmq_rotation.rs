@@ -1,8 +1,9 @@
 use bls_signatures::{G1Element, G2Element, Scheme};
 use dash_spv_ffi::ffi::unboxer::unbox_any;
 use dash_spv_ffi::types;
-use crate::lib_tests::tests::{add_insight_lookup_default, get_block_hash_by_height_default, get_llmq_snapshot_by_block_hash_default, get_masternode_list_by_block_hash_default, get_masternode_list_by_block_hash_from_cache, get_merkle_root_by_hash_default, hash_destroy_default, log_default, masternode_list_destroy_default, masternode_list_save_default, masternode_list_save_in_cache, message_from_file, process_mnlistdiff_from_message_internal, process_qrinfo_from_message_internal, save_llmq_snapshot_default, save_llmq_snapshot_in_cache, should_process_diff_with_range_default, should_process_llmq_of_type, snapshot_destroy_default, validate_llmq_callback, FFIContext};
-use crate::{process_qrinfo_from_message, processor_create_cache, register_processor};
+use crate::lib_tests::tests::{add_insight_lookup_default, get_block_height_by_hash_from_context, get_block_hash_by_height_default, get_llmq_snapshot_by_block_hash_default, get_masternode_list_by_block_hash_default, get_masternode_list_by_block_hash_from_cache, get_merkle_root_by_hash_default, hash_destroy_default, log_default, masternode_list_destroy_default, masternode_list_save_default, masternode_list_save_in_cache, message_from_file, process_mnlistdiff_from_message_internal, process_qrinfo_from_message_internal, save_llmq_snapshot_default, save_llmq_snapshot_in_cache, should_process_diff_with_range_default, should_process_llmq_of_type, snapshot_destroy_default, validate_llmq_callback, FFIContext};
+use crate::processing::{LLMQRotationConfig, LLMQValidationStatus};
+use crate::{process_qrinfo_from_message, process_qrinfo_from_message_with_validation, processor_create_cache, processor_destroy_qr_info_validation_result, processor_set_rotating_llmq_types, register_processor};
 use dash_spv_models::common::chain_type::{ChainType, DevnetType, IHaveChainSettings};
 use dash_spv_models::common::LLMQType;
 use dash_spv_primitives::crypto::byte_util::{AsBytes, Reversable, UInt256};
@@ -495,6 +496,27 @@ fn test_devnet_333() {
         cache,
         context,
     );
+    let validation_ptr = process_qrinfo_from_message_with_validation(
+        bytes.as_ptr(),
+        bytes.len(),
+        false,
+        false,
+        chain.genesis_hash().0.as_ptr(),
+        processor,
+        unsafe { processor_create_cache() },
+        context,
+    );
+    let validation = unsafe { &*validation_ptr };
+    let statuses = unsafe {
+        std::slice::from_raw_parts(validation.rotated_quorum_statuses_at_h_3c, validation.rotated_quorums_at_h_3c_count)
+    };
+    assert!(unsafe { (*validation.result).extra_share });
+    assert_eq!(validation.rotated_quorums_at_h_3c_count, 2, "Quorums of both indexes are mined at h-3c");
+    assert!(
+        statuses.iter().all(|&status| LLMQValidationStatus::from(status) != LLMQValidationStatus::Skipped),
+        "Rotated quorums are processed"
+    );
+    unsafe { processor_destroy_qr_info_validation_result(validation_ptr) };
 }
 
 #[test]
@@ -776,6 +798,28 @@ fn test_processor_devnet_333_2() {
         result.result_at_h.has_valid_mn_list_root,
         "Invalid masternodes root"
     );
+    assert!(
+        result.extra_share || result.rotated_quorums_at_h_3c.is_empty(),
+        "Quorums at h-3c are reported only with extra share"
+    );
+    result.rotated_quorums_at_h_3c.iter().for_each(|quorum| {
+        assert!(quorum.index.is_some(), "Rotated quorum at h-3c should have an index");
+    });
+    let statuses = result.rotated_quorum_statuses_at_h_3c();
+    assert_eq!(statuses.len(), result.rotated_quorums_at_h_3c.len());
+    let quorum_hash = UInt256::from_hex("000007756da9e48ef99829c467379e3a873ec9bf90652a9d10483329ef4e222c").unwrap().reversed();
+    let position = result.rotated_quorums_at_h_3c
+        .iter()
+        .position(|quorum| quorum.llmq_hash == quorum_hash)
+        .expect("Quorum at index 0 is mined at h-3c");
+    let quorum = &result.rotated_quorums_at_h_3c[position];
+    assert_eq!(quorum.index, Some(0));
+    assert_ne!(
+        statuses[position],
+        LLMQValidationStatus::Invalid,
+        "With extra share quorum at h-3c is either validated or its members are unknown"
+    );
+    assert_eq!(statuses[position] == LLMQValidationStatus::Valid, quorum.verified);
     // assert!(result.result_at_h.has_valid_llmq_list_root, "Invalid quorums root");
 }
 
@@ -1591,3 +1635,57 @@ pub unsafe extern "C" fn should_process_isd_quorum(
     let data: &mut FFIContext = &mut *(context as *mut FFIContext);
     LLMQType::from(llmq_type) == data.chain.isd_llmq_type()
 }
+
+#[test]
+fn test_llmq_rotation_config() {
+    let mainnet = LLMQRotationConfig::for_chain(&ChainType::MainNet);
+    assert!(mainnet.is_rotating(LLMQType::Llmqtype60_75), "60_75 should rotate on mainnet");
+    assert!(!mainnet.is_rotating(LLMQType::Llmqtype400_60), "400_60 should not rotate");
+    assert!(!mainnet.is_rotating(LLMQType::Llmqtype50_60), "50_60 should not rotate");
+    assert_eq!(mainnet.types(), vec![LLMQType::Llmqtype60_75]);
+    let devnet = LLMQRotationConfig::for_chain(&ChainType::DevNet(DevnetType::JackDaniels));
+    assert!(devnet.is_rotating(LLMQType::LlmqtypeDevnetDIP0024), "DevnetDIP0024 should rotate on devnet");
+    assert!(devnet.is_rotating(LLMQType::LlmqtypeTestDIP0024), "Test type should rotate on devnet");
+    let testnet = LLMQRotationConfig::for_chain(&ChainType::TestNet);
+    assert_eq!(testnet.types(), vec![LLMQType::Llmqtype60_75, LLMQType::LlmqtypeTestDIP0024]);
+    assert!(!mainnet.is_rotating(LLMQType::LlmqtypeTestDIP0024), "There are no test quorums on mainnet");
+    let default = LLMQRotationConfig::default();
+    assert!(default.is_rotating(LLMQType::Llmqtype60_75) && default.is_rotating(LLMQType::LlmqtypeDevnetDIP0024));
+    assert!(default.is_rotating(LLMQType::LlmqtypeTestDIP0024));
+    let mut custom = LLMQRotationConfig::from_types(&[LLMQType::Llmqtype50_60, LLMQType::Llmqtype60_75]);
+    custom.remove(LLMQType::Llmqtype60_75);
+    assert!(custom.is_rotating(LLMQType::Llmqtype50_60));
+    assert!(!custom.is_rotating(LLMQType::Llmqtype60_75));
+    assert_eq!(custom.types(), vec![LLMQType::Llmqtype50_60]);
+    let processor = unsafe {
+        &mut *register_processor(
+            get_merkle_root_by_hash_default,
+            get_block_height_by_hash_from_context,
+            get_block_hash_by_height_default,
+            get_llmq_snapshot_by_block_hash_default,
+            save_llmq_snapshot_default,
+            get_masternode_list_by_block_hash_default,
+            masternode_list_save_default,
+            masternode_list_destroy_default,
+            add_insight_lookup_default,
+            should_process_llmq_of_type,
+            validate_llmq_callback,
+            hash_destroy_default,
+            snapshot_destroy_default,
+            should_process_diff_with_range_default,
+            log_default,
+        )
+    };
+    assert_eq!(processor.rotation_config, LLMQRotationConfig::default());
+    let testnet_genesis = ChainType::TestNet.genesis_hash();
+    processor.genesis_hash = testnet_genesis.0.as_ptr();
+    processor.use_rotation_config_of_genesis();
+    assert_eq!(processor.rotation_config, testnet, "Rotating types should be taken from the genesis until they're set");
+    assert_eq!(LLMQRotationConfig::for_genesis(ChainType::MainNet.genesis_hash()), Some(mainnet));
+    assert_eq!(LLMQRotationConfig::for_genesis(UInt256::MIN), None, "Devnets and regtest aren't known by their genesis");
+    let types: Vec<u8> = vec![LLMQType::Llmqtype60_75.into()];
+    unsafe { processor_set_rotating_llmq_types(processor, types.as_ptr(), types.len()) };
+    assert_eq!(processor.rotation_config, mainnet);
+    processor.use_rotation_config_of_genesis();
+    assert_eq!(processor.rotation_config, mainnet, "Rotating types set by the host should be kept");
+}
@@ -3,10 +3,13 @@ use dash_spv_models::masternode;
 use dash_spv_primitives::crypto::byte_util::Reversable;
 use dash_spv_primitives::crypto::UInt256;
 use dash_spv_primitives::hashes::hex::FromHex;
-use crate::lib_tests::tests::{add_insight_lookup_default, FFIContext, get_block_hash_by_height_from_context, get_block_height_by_hash_from_context, get_llmq_snapshot_by_block_hash_from_context, get_masternode_list_by_block_hash_from_cache, get_merkle_root_by_hash_default, hash_destroy_default, log_default, masternode_list_destroy_default, masternode_list_save_in_cache, message_from_file, process_qrinfo_from_message_internal, save_llmq_snapshot_in_cache, should_process_diff_with_range_default, snapshot_destroy_default};
-use crate::{processor_create_cache, register_processor};
+use std::collections::BTreeMap;
+use crate::lib_tests::tests::{add_insight_lookup_default, FFIContext, get_block_hash_by_height_from_context, get_block_height_by_hash_from_context, get_llmq_snapshot_by_block_hash_from_context, get_masternode_list_by_block_hash_from_cache, get_merkle_root_by_hash_default, hash_destroy_default, log_default, masternode_list_destroy_default, masternode_list_save_in_cache, message_from_file, process_qrinfo_from_message_internal, save_llmq_snapshot_in_cache, should_process_diff_with_range_default, snapshot_destroy_default, validate_llmq_callback};
+use crate::{process_qrinfo_from_message, processor_create_cache, processor_destroy_qr_info_result, register_processor};
+use crate::codec::{QRInfoBlocks, QRInfoMessage};
+use crate::processing::{MasternodeProcessorCache, ProcessingError};
 use crate::tests::block_store::init_mainnet_store;
-use crate::tests::json_from_core_snapshot::{block_hash_to_block_hash, ListDiff, masternode_list_from_genesis_diff, QRInfo, snapshot_to_snapshot};
+use crate::core_json::{hash_from_json, list_diff_from_json, ListDiff, QRInfo, snapshot_from_json};
 use crate::tests::llmq_rotation::{should_process_isd_quorum, validate_llmq_callback_throuh_rust_bls};
 
 #[test]
@@ -24,9 +27,9 @@ fn testnet_quorum_quarters() {
     let diff_8840: ListDiff = serde_json::from_slice(&message_from_file("1738840_diff.txt".to_string())).unwrap();
     let diff_8888: ListDiff = serde_json::from_slice(&message_from_file("1738888_diff.txt".to_string())).unwrap();
 
-    let list_diff_8792 = masternode_list_from_genesis_diff(diff_8792, |_| block_height_8792);
-    let list_diff_8840 = masternode_list_from_genesis_diff(diff_8840, |_| block_height_8840);
-    let list_diff_8888 = masternode_list_from_genesis_diff(diff_8888, |_| block_height_8888);
+    let list_diff_8792 = list_diff_from_json(&diff_8792, |_| block_height_8792).unwrap();
+    let list_diff_8840 = list_diff_from_json(&diff_8840, |_| block_height_8840).unwrap();
+    let list_diff_8888 = list_diff_from_json(&diff_8888, |_| block_height_8888).unwrap();
 
     let block_hash_8792 = list_diff_8792.block_hash;
     let block_hash_8840 = list_diff_8840.block_hash;
@@ -71,31 +74,31 @@ fn testnet_quorum_quarters() {
     processor.save_masternode_list(block_hash_8888, &masternode_list_8888);
 
 
-    let snapshot_8792_h_c = snapshot_to_snapshot(qrinfo_8792.quorum_snapshot_at_hminus_c);
-    let snapshot_8792_h_2c = snapshot_to_snapshot(qrinfo_8792.quorum_snapshot_at_hminus2c);
-    let snapshot_8792_h_3c = snapshot_to_snapshot(qrinfo_8792.quorum_snapshot_at_hminus3c);
-    let snapshot_8840_h_c = snapshot_to_snapshot(qrinfo_8840.quorum_snapshot_at_hminus_c);
-    let snapshot_8840_h_2c = snapshot_to_snapshot(qrinfo_8840.quorum_snapshot_at_hminus2c);
-    let snapshot_8840_h_3c = snapshot_to_snapshot(qrinfo_8840.quorum_snapshot_at_hminus3c);
-    let snapshot_8888_h_c = snapshot_to_snapshot(qrinfo_8888.quorum_snapshot_at_hminus_c);
-    let snapshot_8888_h_2c = snapshot_to_snapshot(qrinfo_8888.quorum_snapshot_at_hminus2c);
-    let snapshot_8888_h_3c = snapshot_to_snapshot(qrinfo_8888.quorum_snapshot_at_hminus3c);
-    let snapshot_8936_h_c = snapshot_to_snapshot(qrinfo_8936.quorum_snapshot_at_hminus_c);
-    let snapshot_8936_h_2c = snapshot_to_snapshot(qrinfo_8936.quorum_snapshot_at_hminus2c);
-    let snapshot_8936_h_3c = snapshot_to_snapshot(qrinfo_8936.quorum_snapshot_at_hminus3c);
+    let snapshot_8792_h_c = snapshot_from_json(&qrinfo_8792.quorum_snapshot_at_hminus_c);
+    let snapshot_8792_h_2c = snapshot_from_json(&qrinfo_8792.quorum_snapshot_at_hminus2c);
+    let snapshot_8792_h_3c = snapshot_from_json(&qrinfo_8792.quorum_snapshot_at_hminus3c);
+    let snapshot_8840_h_c = snapshot_from_json(&qrinfo_8840.quorum_snapshot_at_hminus_c);
+    let snapshot_8840_h_2c = snapshot_from_json(&qrinfo_8840.quorum_snapshot_at_hminus2c);
+    let snapshot_8840_h_3c = snapshot_from_json(&qrinfo_8840.quorum_snapshot_at_hminus3c);
+    let snapshot_8888_h_c = snapshot_from_json(&qrinfo_8888.quorum_snapshot_at_hminus_c);
+    let snapshot_8888_h_2c = snapshot_from_json(&qrinfo_8888.quorum_snapshot_at_hminus2c);
+    let snapshot_8888_h_3c = snapshot_from_json(&qrinfo_8888.quorum_snapshot_at_hminus3c);
+    let snapshot_8936_h_c = snapshot_from_json(&qrinfo_8936.quorum_snapshot_at_hminus_c);
+    let snapshot_8936_h_2c = snapshot_from_json(&qrinfo_8936.quorum_snapshot_at_hminus2c);
+    let snapshot_8936_h_3c = snapshot_from_json(&qrinfo_8936.quorum_snapshot_at_hminus3c);
 
-    let block_hash_8792_h_c = block_hash_to_block_hash(qrinfo_8792.mn_list_diff_at_hminus_c.block_hash).reversed();
-    let block_hash_8792_h_2c = block_hash_to_block_hash(qrinfo_8792.mn_list_diff_at_hminus2c.block_hash).reversed();
-    let block_hash_8792_h_3c = block_hash_to_block_hash(qrinfo_8792.mn_list_diff_at_hminus3c.block_hash).reversed();
-    let block_hash_8840_h_c = block_hash_to_block_hash(qrinfo_8840.mn_list_diff_at_hminus_c.block_hash).reversed();
-    let block_hash_8840_h_2c = block_hash_to_block_hash(qrinfo_8840.mn_list_diff_at_hminus2c.block_hash).reversed();
-    let block_hash_8840_h_3c = block_hash_to_block_hash(qrinfo_8840.mn_list_diff_at_hminus3c.block_hash).reversed();
-    let block_hash_8888_h_c = block_hash_to_block_hash(qrinfo_8888.mn_list_diff_at_hminus_c.block_hash).reversed();
-    let block_hash_8888_h_2c = block_hash_to_block_hash(qrinfo_8888.mn_list_diff_at_hminus2c.block_hash).reversed();
-    let block_hash_8888_h_3c = block_hash_to_block_hash(qrinfo_8888.mn_list_diff_at_hminus3c.block_hash).reversed();
-    let block_hash_8936_h_c = block_hash_to_block_hash(qrinfo_8936.mn_list_diff_at_hminus_c.block_hash).reversed();
-    let block_hash_8936_h_2c = block_hash_to_block_hash(qrinfo_8936.mn_list_diff_at_hminus2c.block_hash).reversed();
-    let block_hash_8936_h_3c = block_hash_to_block_hash(qrinfo_8936.mn_list_diff_at_hminus3c.block_hash).reversed();
+    let block_hash_8792_h_c = hash_from_json(&qrinfo_8792.mn_list_diff_at_hminus_c.block_hash).unwrap();
+    let block_hash_8792_h_2c = hash_from_json(&qrinfo_8792.mn_list_diff_at_hminus2c.block_hash).unwrap();
+    let block_hash_8792_h_3c = hash_from_json(&qrinfo_8792.mn_list_diff_at_hminus3c.block_hash).unwrap();
+    let block_hash_8840_h_c = hash_from_json(&qrinfo_8840.mn_list_diff_at_hminus_c.block_hash).unwrap();
+    let block_hash_8840_h_2c = hash_from_json(&qrinfo_8840.mn_list_diff_at_hminus2c.block_hash).unwrap();
+    let block_hash_8840_h_3c = hash_from_json(&qrinfo_8840.mn_list_diff_at_hminus3c.block_hash).unwrap();
+    let block_hash_8888_h_c = hash_from_json(&qrinfo_8888.mn_list_diff_at_hminus_c.block_hash).unwrap();
+    let block_hash_8888_h_2c = hash_from_json(&qrinfo_8888.mn_list_diff_at_hminus2c.block_hash).unwrap();
+    let block_hash_8888_h_3c = hash_from_json(&qrinfo_8888.mn_list_diff_at_hminus3c.block_hash).unwrap();
+    let block_hash_8936_h_c = hash_from_json(&qrinfo_8936.mn_list_diff_at_hminus_c.block_hash).unwrap();
+    let block_hash_8936_h_2c = hash_from_json(&qrinfo_8936.mn_list_diff_at_hminus2c.block_hash).unwrap();
+    let block_hash_8936_h_3c = hash_from_json(&qrinfo_8936.mn_list_diff_at_hminus3c.block_hash).unwrap();
 
 
     processor.save_snapshot(block_hash_8792_h_c, snapshot_8792_h_c);
@@ -142,8 +145,12 @@ fn testnet_quorum_quarters() {
         &mut context.cache.llmq_members,
         &mut context.cache.llmq_indexed_members,
         &context.cache.mn_lists,
+        &context.cache.mn_list_height_index,
         &context.cache.llmq_snapshots,
-        &mut context.cache.needed_masternode_lists);
+        &mut context.cache.merkle_trees,
+        &mut context.cache.needed_masternode_lists)
+        .unwrap()
+        .unwrap_or_default();
     let node_hashes = nodes.into_iter().map(|m| m.provider_registration_transaction_hash).collect::<Vec<UInt256>>();
 
     println!("{}: {:?}", context.block_for_hash(last_quorum.llmq_hash).unwrap().height, last_quorum);
@@ -295,6 +302,110 @@ fn testnet_quorum_quarters() {
     //UInt256::from_hex("($1)").unwrap().clone().reversed()
 }
 
+/// Processes QRINFO payload on mainnet with the processor which validates the rotated quorums,
+/// gives the error and the count of the last commitments per index
+fn process_mainnet_qrinfo(bytes: &[u8], context: &mut FFIContext) -> (ProcessingError, usize) {
+    let processor = unsafe {
+        &mut *register_processor(
+            get_merkle_root_by_hash_default,
+            get_block_height_by_hash_from_context,
+            get_block_hash_by_height_from_context,
+            get_llmq_snapshot_by_block_hash_from_context,
+            save_llmq_snapshot_in_cache,
+            get_masternode_list_by_block_hash_from_cache,
+            masternode_list_save_in_cache,
+            masternode_list_destroy_default,
+            add_insight_lookup_default,
+            should_process_isd_quorum,
+            validate_llmq_callback,
+            hash_destroy_default,
+            snapshot_destroy_default,
+            should_process_diff_with_range_default,
+            log_default,
+        )
+    };
+    let result = process_qrinfo_from_message(
+        bytes.as_ptr(),
+        bytes.len(),
+        false,
+        false,
+        context.genesis_as_ptr(),
+        processor,
+        context.cache,
+        context as *mut _ as *mut std::ffi::c_void,
+    );
+    let (error_status, last_quorum_per_index_count) =
+        unsafe { ((*result).error_status, (*result).last_quorum_per_index_count) };
+    unsafe { processor_destroy_qr_info_result(result) };
+    (ProcessingError::from(error_status), last_quorum_per_index_count)
+}
+
+#[test]
+fn test_qrinfo_from_message_tracks_rotated_quorums() {
+    let chain = ChainType::MainNet;
+    let cache = unsafe { &mut *processor_create_cache() };
+    let context = &mut (FFIContext { chain, cache, blocks: init_mainnet_store() });
+    let bytes = message_from_file("QRINFO_0_1739226.dat".to_string());
+    let (error, last_quorum_per_index_count) = process_mainnet_qrinfo(&bytes, context);
+    assert_eq!(error, ProcessingError::None);
+    assert!(last_quorum_per_index_count > 0, "The tail of the message must be read");
+    let tracked = &context.cache.rotated_quorums;
+    assert!(!tracked.is_empty(), "Rotated quorums must be tracked after QRINFO");
+    assert!(tracked.iter().all(|(_, quorum)| quorum.verified), "Only validated quorums are tracked");
+}
+
+#[test]
+fn test_generated_qrinfo_tracks_rotated_quorums() {
+    let chain = ChainType::MainNet;
+    let cache = unsafe { &mut *processor_create_cache() };
+    let context = &mut (FFIContext { chain, cache, blocks: init_mainnet_store() });
+    let bytes = message_from_file("QRINFO_0_1739226.dat".to_string());
+    let (error, _) = process_mainnet_qrinfo(&bytes, context);
+    assert_eq!(error, ProcessingError::None);
+    // The same QRINFO is built from the lists and snapshots the received one has left in the cache
+    let original = QRInfoMessage::from_bytes(&bytes, &mut 0, |_| 0).unwrap();
+    let coinbases = original
+        .diffs()
+        .into_iter()
+        .map(|message| (message.diff.block_hash, (message.merkle_tree(), message.coinbase_transaction.clone())))
+        .collect::<BTreeMap<_, _>>();
+    let blocks = QRInfoBlocks {
+        tip: original.diff_tip.diff.block_hash,
+        h: original.diff_h.diff.block_hash,
+        h_c: original.diff_h_c.diff.block_hash,
+        h_2c: original.diff_h_2c.diff.block_hash,
+        h_3c: original.diff_h_3c.diff.block_hash,
+        h_4c: original.extra_share.as_ref().map(|(_, diff)| diff.diff.block_hash),
+        extra: original.mn_list_diff_list.iter().map(|diff| diff.diff.block_hash).collect(),
+    };
+    let generated = QRInfoMessage::from_lists(
+        &blocks,
+        &[],
+        original.diff_tip.diff.base_block_hash,
+        &context.cache.mn_lists,
+        &context.cache.llmq_snapshots,
+        original.last_quorum_per_index.clone(),
+        |block_hash| coinbases.get(&block_hash).cloned(),
+    )
+    .expect("Processed QRINFO leaves all the lists and snapshots it's built from");
+    let generated_cache = unsafe { &mut *processor_create_cache() };
+    let generated_context = &mut (FFIContext { chain, cache: generated_cache, blocks: init_mainnet_store() });
+    let (error, last_quorum_per_index_count) = process_mainnet_qrinfo(&generated.to_bytes(), generated_context);
+    assert_eq!(error, ProcessingError::None);
+    assert_eq!(last_quorum_per_index_count, original.last_quorum_per_index.len());
+    let tracked = |cache: &MasternodeProcessorCache| {
+        cache
+            .rotated_quorums
+            .iter()
+            .map(|(height, quorum)| (height, quorum.llmq_hash, quorum.index, quorum.verified))
+            .collect::<Vec<_>>()
+    };
+    let generated_tracked = tracked(generated_context.cache);
+    assert!(!generated_tracked.is_empty(), "Rotated quorums must be tracked after generated QRINFO");
+    assert!(generated_tracked.iter().all(|&(.., verified)| verified), "Only validated quorums are tracked");
+    assert_eq!(generated_tracked, tracked(context.cache), "Generated QRINFO must give the same rotated quorums");
+}
+
 /*
 20:10:45 26 QuorumRotationState.computeQuorumMembersByQuarterRotation: QuarterComposition h[1738944] i[0]:
 
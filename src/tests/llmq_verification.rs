@@ -1,7 +1,11 @@
 use crate::lib_tests::tests::{add_insight_lookup_default, assert_diff_result, get_block_hash_by_height_default, get_llmq_snapshot_by_block_hash_default, get_masternode_list_by_block_hash_from_cache, get_merkle_root_by_hash_default, hash_destroy_default, log_default, masternode_list_destroy_default, masternode_list_save_in_cache, message_from_file, save_llmq_snapshot_default, should_process_diff_with_range_default, should_process_llmq_of_type, snapshot_destroy_default, validate_llmq_callback, FFIContext, get_block_height_by_hash_from_context};
+use crate::processing::{LLMQRotationConfig, LLMQValidationStatus, MasternodeProcessorCache};
+use crate::tests::mainnet_reload::process_mainnet_reload;
 use crate::{process_mnlistdiff_from_message, processor_create_cache, register_processor};
+use dash_spv_ffi::ffi::callbacks::{ShouldProcessLLMQTypeCallback, ValidateLLMQCallback};
 use dash_spv_ffi::ffi::from::FromFFI;
 use dash_spv_ffi::ffi::to::ToFFI;
+use dash_spv_ffi::ffi::unboxer::unbox_any;
 use dash_spv_ffi::types;
 use dash_spv_models::common::chain_type::ChainType;
 use dash_spv_models::common::LLMQType;
@@ -9,7 +13,8 @@ use dash_spv_models::masternode::LLMQEntry;
 use dash_spv_primitives::crypto::byte_util::UInt256;
 use std::collections::BTreeMap;
 use std::ptr::null_mut;
-use crate::tests::block_store::init_testnet_store;
+use std::sync::Arc;
+use crate::tests::block_store::{init_mainnet_store, init_testnet_store};
 
 #[test]
 fn testnet_llmq_verification() {
@@ -233,3 +238,87 @@ fn testnet_llmq_verification_using_processor_and_cache() {
         //assert!(quorum_to_verify.verified, "Unable to verify quorum");
     }
 }
+
+unsafe extern "C" fn should_process_no_llmq(_llmq_type: u8, _context: *const std::ffi::c_void) -> bool {
+    false
+}
+
+unsafe extern "C" fn should_process_every_llmq(_llmq_type: u8, _context: *const std::ffi::c_void) -> bool {
+    true
+}
+
+unsafe extern "C" fn validate_llmq_never(data: *mut types::LLMQValidationData, _context: *const std::ffi::c_void) -> bool {
+    unbox_any(data);
+    false
+}
+
+fn classify_added_quorum(
+    should_process_llmq_of_type: ShouldProcessLLMQTypeCallback,
+    validate_llmq: ValidateLLMQCallback,
+    quorum: &LLMQEntry,
+    cache: &mut MasternodeProcessorCache,
+) -> (bool, bool, LLMQValidationStatus) {
+    let context = &mut FFIContext {
+        chain: ChainType::MainNet,
+        cache: &mut MasternodeProcessorCache::default(),
+        blocks: init_mainnet_store(),
+    };
+    let processor = unsafe {
+        &mut *register_processor(
+            get_merkle_root_by_hash_default,
+            get_block_height_by_hash_from_context,
+            get_block_hash_by_height_default,
+            get_llmq_snapshot_by_block_hash_default,
+            save_llmq_snapshot_default,
+            get_masternode_list_by_block_hash_from_cache,
+            masternode_list_save_in_cache,
+            masternode_list_destroy_default,
+            add_insight_lookup_default,
+            should_process_llmq_of_type,
+            validate_llmq,
+            hash_destroy_default,
+            snapshot_destroy_default,
+            should_process_diff_with_range_default,
+            log_default,
+        )
+    };
+    processor.opaque_context = context as *mut _ as *mut std::ffi::c_void;
+    processor.genesis_hash = context.genesis_as_ptr();
+    let added = BTreeMap::from([(quorum.llmq_type, BTreeMap::from([(quorum.llmq_hash, quorum.clone())]))]);
+    let (added, _, has_valid_quorums, statuses) = processor.classify_quorums(&BTreeMap::new(), added, BTreeMap::new(), cache).unwrap();
+    (has_valid_quorums, added[&quorum.llmq_type][&quorum.llmq_hash].verified, statuses[&quorum.llmq_type][&quorum.llmq_hash])
+}
+
+#[test]
+fn test_skipped_llmq_types_dont_affect_quorums_validity() {
+    let mut cache = MasternodeProcessorCache::default();
+    process_mainnet_reload(&mut cache);
+    let list = cache.mn_lists.values().max_by_key(|list| list.known_height).cloned().unwrap();
+    let mut quorum = list
+        .quorums
+        .iter()
+        .filter(|(&llmq_type, _)| !LLMQRotationConfig::default().is_rotating(llmq_type))
+        .flat_map(|(_, quorums_of_type)| quorums_of_type.values())
+        .next()
+        .cloned()
+        .unwrap();
+    quorum.verified = false;
+    // Members are taken from the list at the quorum block, so it must be known
+    cache.add_masternode_list(quorum.llmq_hash, Arc::clone(&list));
+    assert_eq!(
+        classify_added_quorum(should_process_no_llmq, validate_llmq_never, &quorum, &mut cache),
+        (true, false, LLMQValidationStatus::Skipped),
+        "Skipped type is neither validated nor makes the quorums invalid"
+    );
+    assert_eq!(
+        classify_added_quorum(should_process_every_llmq, validate_llmq_never, &quorum, &mut cache),
+        (false, false, LLMQValidationStatus::Invalid),
+        "Processed quorum failing validation makes the quorums invalid"
+    );
+    cache.remove_masternode_list(&quorum.llmq_hash);
+    assert_eq!(
+        classify_added_quorum(should_process_every_llmq, validate_llmq_never, &quorum, &mut cache),
+        (true, false, LLMQValidationStatus::Unknown),
+        "Quorum without known list isn't validated"
+    );
+}
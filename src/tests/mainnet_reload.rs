@@ -1,16 +1,16 @@
-use crate::lib_tests::tests::{add_insight_lookup_default, assert_diff_result, get_block_hash_by_height_default, get_llmq_snapshot_by_block_hash_default, get_masternode_list_by_block_hash_from_cache, get_merkle_root_by_hash_default, hash_destroy_default, log_default, masternode_list_destroy_default, masternode_list_save_in_cache, message_from_file, save_llmq_snapshot_default, should_process_diff_with_range_default, should_process_llmq_of_type, snapshot_destroy_default, validate_llmq_callback, FFIContext, get_block_height_by_hash_from_context};
+use crate::lib_tests::tests::{add_insight_lookup_default, process_mnlistdiff_from_message_internal, get_masternode_list_by_block_hash_default, masternode_list_save_default, assert_diff_result, get_block_hash_by_height_default, get_llmq_snapshot_by_block_hash_default, get_masternode_list_by_block_hash_from_cache, get_merkle_root_by_hash_default, hash_destroy_default, log_default, masternode_list_destroy_default, masternode_list_save_in_cache, message_from_file, save_llmq_snapshot_default, should_process_diff_with_range_default, should_process_llmq_of_type, snapshot_destroy_default, validate_llmq_callback, FFIContext, get_block_height_by_hash_from_context};
 use crate::{process_mnlistdiff_from_message, processor_create_cache, register_processor};
 use dash_spv_ffi::ffi::from::FromFFI;
 use dash_spv_models::common::chain_type::ChainType;
 use dash_spv_models::masternode;
 use dash_spv_primitives::crypto::byte_util::UInt256;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+use crate::processing::{MasternodeProcessorCache, MNListDiffResult, SharedMasternodeList};
 use crate::tests::block_store::init_mainnet_store;
 
-#[test]
-fn test_mainnet_reload_with_processor() {
-    let chain = ChainType::MainNet;
-    let files = vec![
+pub fn mainnet_reload_files() -> Vec<String> {
+    vec![
         "MNL_0_1090944.dat".to_string(),
         "MNL_1090944_1091520.dat".to_string(),
         "MNL_1091520_1091808.dat".to_string(),
@@ -40,7 +40,13 @@ fn test_mainnet_reload_with_processor() {
         "MNL_1092840_1092864.dat".to_string(),
         "MNL_1092864_1092888.dat".to_string(),
         "MNL_1092888_1092916.dat".to_string(),
-    ];
+    ]
+}
+
+#[test]
+fn test_mainnet_reload_with_processor() {
+    let chain = ChainType::MainNet;
+    let files = mainnet_reload_files();
     let context = &mut (FFIContext {
         chain,
         cache: &mut Default::default(),
@@ -56,7 +62,7 @@ pub fn load_masternode_lists_for_files(
     files: Vec<String>,
     assert_validity: bool,
     context: &mut FFIContext,
-) -> (bool, BTreeMap<UInt256, masternode::MasternodeList>) {
+) -> (bool, BTreeMap<UInt256, Arc<SharedMasternodeList>>) {
     let cache = unsafe { &mut *processor_create_cache() };
     let processor = unsafe {
         register_processor(
@@ -107,3 +113,124 @@ pub fn load_masternode_lists_for_files(
     let lists = context.cache.mn_lists.clone();
     (true, lists)
 }
+
+/// Processes the whole mainnet reload sequence with the default callbacks into the cache
+pub fn process_mainnet_reload(cache: &mut MasternodeProcessorCache) -> Vec<MNListDiffResult> {
+    let context = &mut (FFIContext {
+        chain: ChainType::MainNet,
+        cache: &mut Default::default(),
+        blocks: init_mainnet_store()
+    });
+    let processor = unsafe {
+        register_processor(
+            get_merkle_root_by_hash_default,
+            get_block_height_by_hash_from_context,
+            get_block_hash_by_height_default,
+            get_llmq_snapshot_by_block_hash_default,
+            save_llmq_snapshot_default,
+            get_masternode_list_by_block_hash_default,
+            masternode_list_save_default,
+            masternode_list_destroy_default,
+            add_insight_lookup_default,
+            should_process_llmq_of_type,
+            validate_llmq_callback,
+            hash_destroy_default,
+            snapshot_destroy_default,
+            should_process_diff_with_range_default,
+            log_default,
+        )
+    };
+    mainnet_reload_files()
+        .into_iter()
+        .map(|file| {
+            let bytes = message_from_file(file);
+            process_mnlistdiff_from_message_internal(
+                bytes.as_ptr(),
+                bytes.len(),
+                false,
+                context.genesis_as_ptr(),
+                processor,
+                cache,
+                context as *mut _ as *mut std::ffi::c_void,
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn test_mainnet_reload_memory_footprint() {
+    let chain = ChainType::MainNet;
+    let context = &mut (FFIContext {
+        chain,
+        cache: &mut Default::default(),
+        blocks: init_mainnet_store()
+    });
+    let processor = unsafe {
+        register_processor(
+            get_merkle_root_by_hash_default,
+            get_block_height_by_hash_from_context,
+            get_block_hash_by_height_default,
+            get_llmq_snapshot_by_block_hash_default,
+            save_llmq_snapshot_default,
+            get_masternode_list_by_block_hash_default,
+            masternode_list_save_default,
+            masternode_list_destroy_default,
+            add_insight_lookup_default,
+            should_process_llmq_of_type,
+            validate_llmq_callback,
+            hash_destroy_default,
+            snapshot_destroy_default,
+            should_process_diff_with_range_default,
+            log_default,
+        )
+    };
+    let cache = unsafe { &mut *processor_create_cache() };
+    let entry_size = std::mem::size_of::<masternode::MasternodeEntry>();
+    let mut diff_entries = 0usize;
+    let mut previous: Option<Arc<SharedMasternodeList>> = None;
+    for file in mainnet_reload_files() {
+        let bytes = message_from_file(file.clone());
+        let result = process_mnlistdiff_from_message_internal(
+            bytes.as_ptr(),
+            bytes.len(),
+            false,
+            context.genesis_as_ptr(),
+            processor,
+            cache,
+            context as *mut _ as *mut std::ffi::c_void,
+        );
+        let cached = cache.mn_lists.get(&result.block_hash).unwrap();
+        // The list from the result and the one in the cache must be the same allocation
+        assert!(Arc::ptr_eq(cached, &result.masternode_list), "List for {} was copied into the cache", file);
+        diff_entries += result.added_masternodes.len() + result.modified_masternodes.len();
+        // Entries untouched by the diff must be shared with the base list rather than copied
+        if let Some(base) = previous.as_ref().filter(|base| base.block_hash == result.base_block_hash) {
+            let mut shared = 0;
+            for (hash, entry) in result.masternode_list.masternodes.iter() {
+                if result.added_masternodes.contains_key(hash) || result.modified_masternodes.contains_key(hash) {
+                    continue;
+                }
+                let base_entry = base.masternodes.get(hash).unwrap();
+                assert!(Arc::ptr_eq(entry, base_entry), "Unchanged entry {} was copied in {}", hash, file);
+                shared += 1;
+            }
+            assert!(shared > 0, "Lists in {} should share entries", file);
+        }
+        previous = Some(Arc::clone(&result.masternode_list));
+        println!("[{}]: list: {} entries (~{} bytes), diff: {} entries (~{} bytes)",
+                 file,
+                 result.masternode_list.masternodes.len(),
+                 result.masternode_list.masternodes.len() * entry_size,
+                 result.added_masternodes.len() + result.modified_masternodes.len(),
+                 (result.added_masternodes.len() + result.modified_masternodes.len()) * entry_size);
+    }
+    let retained = cache
+        .mn_lists
+        .values()
+        .flat_map(|list| list.masternodes.values().map(Arc::as_ptr))
+        .collect::<HashSet<_>>()
+        .len();
+    println!("retained by cache: ~{} bytes in {} lists, diff entries: {}", retained * entry_size, cache.mn_lists.len(), diff_entries);
+    assert!(retained <= diff_entries, "Cache should retain only the entries brought by the diffs");
+    assert_eq!(cache.mn_lists.len(), 29, "There should be 29 masternode lists");
+}
@@ -1,10 +1,14 @@
-use crate::lib_tests::tests::{add_insight_lookup_default, assert_diff_result, get_block_hash_by_height_default, get_llmq_snapshot_by_block_hash_default, get_masternode_list_by_block_hash_default, get_merkle_root_by_hash_default, hash_destroy_default, log_default, masternode_list_destroy_default, masternode_list_save_default, message_from_file, save_llmq_snapshot_default, should_process_diff_with_range_default, should_process_llmq_of_type, snapshot_destroy_default, validate_llmq_callback, FFIContext, get_block_height_by_hash_from_context};
+use crate::lib_tests::tests::{add_insight_lookup_default, process_mnlistdiff_from_message_internal, assert_diff_result, get_block_hash_by_height_default, get_llmq_snapshot_by_block_hash_default, get_masternode_list_by_block_hash_default, get_merkle_root_by_hash_default, hash_destroy_default, log_default, masternode_list_destroy_default, masternode_list_save_default, message_from_file, save_llmq_snapshot_default, should_process_diff_with_range_default, should_process_llmq_of_type, snapshot_destroy_default, validate_llmq_callback, FFIContext, get_block_height_by_hash_from_context};
 use crate::{process_mnlistdiff_from_message, processor_create_cache, register_processor};
 use dash_spv_ffi::ffi::from::FromFFI;
 use dash_spv_models::common::chain_type::ChainType;
 use dash_spv_primitives::crypto::byte_util::UInt256;
 use dash_spv_primitives::hashes::hex::FromHex;
-use crate::tests::block_store::init_testnet_store;
+use crate::processing::{IncrementalMerkleTree, MasternodeListMerkleTrees};
+use crate::tests::block_store::{init_mainnet_store, init_testnet_store};
+use crate::tests::mainnet_reload::mainnet_reload_files;
+use dash_spv_primitives::hashes::{sha256d, Hash};
+use std::collections::BTreeMap;
 
 #[test]
 fn test_mnl_saving_to_disk() {
@@ -59,3 +63,144 @@ fn test_mnl_saving_to_disk() {
     );
     assert_diff_result(context, result);
 }
+
+#[test]
+fn test_mnl_merkle_trees_match_list_roots() {
+    let chain = ChainType::TestNet;
+    let bytes = message_from_file("ML_at_122088.dat".to_string());
+    let context = &mut (FFIContext {
+        chain,
+        cache: &mut Default::default(),
+        blocks: init_testnet_store()
+    });
+    let processor = unsafe {
+        register_processor(
+            get_merkle_root_by_hash_default,
+            get_block_height_by_hash_from_context,
+            get_block_hash_by_height_default,
+            get_llmq_snapshot_by_block_hash_default,
+            save_llmq_snapshot_default,
+            get_masternode_list_by_block_hash_default,
+            masternode_list_save_default,
+            masternode_list_destroy_default,
+            add_insight_lookup_default,
+            should_process_llmq_of_type,
+            validate_llmq_callback,
+            hash_destroy_default,
+            snapshot_destroy_default,
+            should_process_diff_with_range_default,
+            log_default,
+        )
+    };
+    let cache = unsafe { &mut *processor_create_cache() };
+    let result = process_mnlistdiff_from_message_internal(
+        bytes.as_ptr(),
+        bytes.len(),
+        false,
+        context.genesis_as_ptr(),
+        processor,
+        cache,
+        context as *mut _ as *mut std::ffi::c_void,
+    );
+    let list = &result.masternode_list;
+    let trees = MasternodeListMerkleTrees::from_shared_list(list);
+    assert_eq!(
+        UInt256::from_hex("94d0af97187af3b9311c98b1cf40c9c9849df0af55dc63b097b80d4cf6c816c5")
+            .unwrap(),
+        trees.masternode_merkle_root().unwrap(),
+        "MNList merkle root should be valid"
+    );
+    assert_eq!(trees.masternode_merkle_root(), list.masternode_merkle_root, "Masternode roots should be equal");
+    assert_eq!(trees.llmq_merkle_root(), list.llmq_merkle_root, "Quorum roots should be equal");
+    let cached = cache.merkle_trees.get(&result.block_hash).unwrap();
+    assert_eq!(cached.masternode_merkle_root(), trees.masternode_merkle_root(), "Cached masternode root should be equal");
+    assert_eq!(cached.llmq_merkle_root(), trees.llmq_merkle_root(), "Cached quorum root should be equal");
+    assert!(result.has_valid_mn_list_root, "rootMNListValid not valid");
+}
+
+#[test]
+fn test_mnl_incremental_merkle_roots_on_reload() {
+    let chain = ChainType::MainNet;
+    let context = &mut (FFIContext {
+        chain,
+        cache: &mut Default::default(),
+        blocks: init_mainnet_store()
+    });
+    let processor = unsafe {
+        register_processor(
+            get_merkle_root_by_hash_default,
+            get_block_height_by_hash_from_context,
+            get_block_hash_by_height_default,
+            get_llmq_snapshot_by_block_hash_default,
+            save_llmq_snapshot_default,
+            get_masternode_list_by_block_hash_default,
+            masternode_list_save_default,
+            masternode_list_destroy_default,
+            add_insight_lookup_default,
+            should_process_llmq_of_type,
+            validate_llmq_callback,
+            hash_destroy_default,
+            snapshot_destroy_default,
+            should_process_diff_with_range_default,
+            log_default,
+        )
+    };
+    let cache = unsafe { &mut *processor_create_cache() };
+    for file in mainnet_reload_files() {
+        let bytes = message_from_file(file.clone());
+        let result = process_mnlistdiff_from_message_internal(
+            bytes.as_ptr(),
+            bytes.len(),
+            false,
+            context.genesis_as_ptr(),
+            processor,
+            cache,
+            context as *mut _ as *mut std::ffi::c_void,
+        );
+        let incremental = cache.merkle_trees.get(&result.block_hash).unwrap();
+        let rebuilt = MasternodeListMerkleTrees::from_shared_list(&result.masternode_list);
+        assert_eq!(incremental.masternode_merkle_root(), rebuilt.masternode_merkle_root(), "Masternode roots differ for {}", file);
+        assert_eq!(incremental.llmq_merkle_root(), rebuilt.llmq_merkle_root(), "Quorum roots differ for {}", file);
+        assert_eq!(incremental.masternode_merkle_root(), result.masternode_list.masternode_merkle_root, "Masternode root differs from list for {}", file);
+        assert_eq!(incremental.llmq_merkle_root(), result.masternode_list.llmq_merkle_root, "Quorum root differs from list for {}", file);
+        assert!(result.has_valid_mn_list_root, "rootMNListValid not valid for {}", file);
+    }
+}
+
+#[test]
+fn test_incremental_merkle_tree_updates() {
+    let leaf = |i: u32| UInt256(sha256d::Hash::hash(&i.to_le_bytes()).into_inner());
+    let mut leaves: BTreeMap<UInt256, UInt256> = (0..37u32).map(|i| (leaf(i), leaf(i + 1000))).collect();
+    let mut tree = IncrementalMerkleTree::from_leaves(leaves.clone().into_iter().collect());
+    let rebuild = |leaves: &BTreeMap<UInt256, UInt256>| IncrementalMerkleTree::from_leaves(leaves.clone().into_iter().collect()).root();
+    assert_eq!(tree.root(), rebuild(&leaves));
+    // modify a few leaves
+    leaves.keys().cloned().step_by(5).collect::<Vec<UInt256>>().into_iter().for_each(|key| {
+        let hash = leaf(leaves.len() as u32 + 2000);
+        leaves.insert(key, hash);
+        tree.update(key, hash);
+    });
+    tree.commit();
+    assert_eq!(tree.root(), rebuild(&leaves), "Root should be equal after modification");
+    // insert and remove some
+    (37..45u32).for_each(|i| {
+        leaves.insert(leaf(i), leaf(i + 3000));
+        tree.update(leaf(i), leaf(i + 3000));
+    });
+    (0..30u32).step_by(3).for_each(|i| {
+        leaves.remove(&leaf(i));
+        tree.remove(&leaf(i));
+    });
+    tree.commit();
+    assert_eq!(tree.len(), leaves.len());
+    assert_eq!(tree.root(), rebuild(&leaves), "Root should be equal after insertion and removal");
+    // shrink down to the single leaf which is the root itself
+    leaves.clone().keys().skip(1).for_each(|key| {
+        leaves.remove(key);
+        tree.remove(key);
+    });
+    tree.commit();
+    assert_eq!(tree.root(), leaves.values().next().cloned());
+    tree = IncrementalMerkleTree::from_leaves(vec![]);
+    assert_eq!(tree.root(), None);
+}
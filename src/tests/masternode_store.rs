@@ -0,0 +1,332 @@
+use crate::codec::BytesEncodable;
+use crate::processing::{MasternodeProcessorCache, SharedMasternodeList};
+use crate::store::{CheckpointError, DiffCompressedStore, FileStore, MasternodeCheckpoint, MasternodeStore, MemoryStore, StoreError};
+use crate::lib_tests::tests::message_from_file;
+use crate::tests::mainnet_reload::{mainnet_reload_files, process_mainnet_reload};
+use dash_spv_models::common::{Block, LLMQSnapshotSkipMode, LLMQType};
+use dash_spv_models::llmq::{LLMQIndexedHash, LLMQSnapshot, MNListDiff};
+use dash_spv_models::masternode::MasternodeEntry;
+use dash_spv_primitives::crypto::{UInt256, UInt384};
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+pub fn temp_store_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("{}_{}.dat", name, std::process::id()));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+pub fn populated_cache() -> MasternodeProcessorCache {
+    let mut cache = MasternodeProcessorCache::default();
+    process_mainnet_reload(&mut cache);
+    let (&block_hash, list) = cache.mn_lists.iter().next().unwrap();
+    let members = list.masternode_entries().take(5).cloned().collect::<Vec<MasternodeEntry>>();
+    cache.llmq_members.entry(LLMQType::Llmqtype400_60).or_default().insert(block_hash, members.clone());
+    cache.llmq_indexed_members.entry(LLMQType::Llmqtype60_75).or_default().insert(LLMQIndexedHash::new(block_hash, 1), members);
+    cache.llmq_snapshots.insert(block_hash, LLMQSnapshot::new(vec![0b1011, 0b1], vec![1, 3, 5], LLMQSnapshotSkipMode::from(1)));
+    // The state which doesn't come with the messages should be kept as well
+    let mut list = SharedMasternodeList::clone(list);
+    let (&hash, entry) = list.masternodes.iter().next().unwrap();
+    let mut entry = MasternodeEntry::clone(entry);
+    let block = || Block { height: list.known_height - 1, hash: UInt256::MAX };
+    entry.previous_validity.insert(block(), !entry.is_valid);
+    entry.previous_entry_hashes.insert(block(), UInt256::MIN);
+    entry.previous_operator_public_keys.insert(block(), UInt384([1; 48]));
+    entry.known_confirmed_at_height = Some(list.known_height - 10);
+    entry.update_height = list.known_height;
+    list.masternodes.insert(hash, Arc::new(entry));
+    if let Some(quorum) = list.quorums.values_mut().flat_map(|quorums| quorums.values_mut()).next() {
+        quorum.verified = !quorum.verified;
+    }
+    cache.add_masternode_list(block_hash, Arc::new(list));
+    cache
+}
+
+fn assert_same_masternodes(expected: &[&MasternodeEntry], actual: &[&MasternodeEntry]) {
+    assert_eq!(expected.len(), actual.len(), "Masternodes count should be equal");
+    expected.iter().zip(actual.iter()).for_each(|(expected, actual)| {
+        let hash = expected.provider_registration_transaction_hash;
+        assert_eq!(hash, actual.provider_registration_transaction_hash);
+        assert_eq!(expected.confirmed_hash, actual.confirmed_hash, "{}", hash);
+        assert_eq!(
+            expected.confirmed_hash_hashed_with_provider_registration_transaction_hash,
+            actual.confirmed_hash_hashed_with_provider_registration_transaction_hash,
+            "{}", hash);
+        assert_eq!(expected.socket_address.ip_address, actual.socket_address.ip_address, "{}", hash);
+        assert_eq!(expected.socket_address.port, actual.socket_address.port, "{}", hash);
+        assert_eq!(expected.operator_public_key, actual.operator_public_key, "{}", hash);
+        assert_eq!(expected.key_id_voting, actual.key_id_voting, "{}", hash);
+        assert_eq!(expected.is_valid, actual.is_valid, "{}", hash);
+        assert_eq!(expected.entry_hash, actual.entry_hash, "{}", hash);
+        assert_eq!(expected.update_height, actual.update_height, "{}", hash);
+        assert_eq!(expected.known_confirmed_at_height, actual.known_confirmed_at_height, "{}", hash);
+        assert_eq!(expected.previous_operator_public_keys, actual.previous_operator_public_keys, "{}", hash);
+        assert_eq!(expected.previous_entry_hashes, actual.previous_entry_hashes, "{}", hash);
+        assert_eq!(expected.previous_validity, actual.previous_validity, "{}", hash);
+    });
+}
+
+fn assert_same_quorums(expected: &SharedMasternodeList, actual: &SharedMasternodeList) {
+    assert_eq!(expected.quorums_count(), actual.quorums_count(), "Quorums count should be equal");
+    expected.quorums.iter().for_each(|(llmq_type, quorums_of_type)| {
+        quorums_of_type.iter().for_each(|(llmq_hash, quorum)| {
+            let restored = actual.quorums.get(llmq_type).and_then(|quorums| quorums.get(llmq_hash)).unwrap();
+            assert_eq!(quorum.to_bytes(), restored.to_bytes(), "Quorum {} should be equal", llmq_hash);
+            assert_eq!(quorum.entry_hash, restored.entry_hash);
+            assert_eq!(quorum.verified, restored.verified, "Verification of {} should be kept", llmq_hash);
+        });
+    });
+}
+
+pub fn assert_same_state(expected: &MasternodeProcessorCache, actual: &MasternodeProcessorCache) {
+    assert_eq!(expected.mn_lists.len(), actual.mn_lists.len(), "Lists count should be equal");
+    expected.mn_lists.iter().for_each(|(block_hash, list)| {
+        let restored = actual.mn_lists.get(block_hash).unwrap();
+        assert_eq!(list.block_hash, restored.block_hash);
+        assert_eq!(list.known_height, restored.known_height);
+        assert_eq!(list.masternodes.keys().collect::<Vec<&UInt256>>(), restored.masternodes.keys().collect::<Vec<&UInt256>>());
+        assert_same_masternodes(&list.masternode_entries().collect::<Vec<_>>(), &restored.masternode_entries().collect::<Vec<_>>());
+        assert_same_quorums(list, restored);
+        assert_eq!(list.masternode_merkle_root, restored.masternode_merkle_root, "Masternode root should be equal");
+        assert_eq!(list.llmq_merkle_root, restored.llmq_merkle_root, "Quorum root should be equal");
+    });
+    assert_eq!(expected.llmq_snapshots.len(), actual.llmq_snapshots.len());
+    expected.llmq_snapshots.iter().for_each(|(block_hash, snapshot)| {
+        let restored = actual.llmq_snapshots.get(block_hash).unwrap();
+        assert_eq!(snapshot.member_list, restored.member_list);
+        assert_eq!(snapshot.skip_list, restored.skip_list);
+        assert_eq!(snapshot.skip_list_mode, restored.skip_list_mode);
+    });
+    assert_eq!(expected.llmq_members.len(), actual.llmq_members.len());
+    expected.llmq_members.iter().for_each(|(llmq_type, members_of_type)| {
+        members_of_type.iter().for_each(|(block_hash, members)| {
+            let restored = actual.llmq_members.get(llmq_type).unwrap().get(block_hash).unwrap();
+            assert_same_masternodes(&members.iter().collect::<Vec<_>>(), &restored.iter().collect::<Vec<_>>());
+        });
+    });
+    assert_eq!(expected.llmq_indexed_members.len(), actual.llmq_indexed_members.len());
+    expected.llmq_indexed_members.iter().for_each(|(llmq_type, members_of_type)| {
+        members_of_type.iter().for_each(|(indexed_hash, members)| {
+            let restored = actual.llmq_indexed_members.get(llmq_type).unwrap().get(indexed_hash).unwrap();
+            assert_same_masternodes(&members.iter().collect::<Vec<_>>(), &restored.iter().collect::<Vec<_>>());
+        });
+    });
+}
+
+#[test]
+fn test_memory_store_round_trip() {
+    let cache = populated_cache();
+    let mut store = MemoryStore::default();
+    store.save_cache(&cache).unwrap();
+    let mut restored = MasternodeProcessorCache::default();
+    store.load_cache(&mut restored).unwrap();
+    assert_same_state(&cache, &restored);
+    // Memory store shares the lists with the cache
+    cache.mn_lists.iter().for_each(|(block_hash, list)| {
+        assert!(Arc::ptr_eq(list, restored.mn_lists.get(block_hash).unwrap()));
+    });
+}
+
+#[test]
+fn test_file_store_round_trip() {
+    let path = temp_store_path("test_file_store_round_trip");
+    let cache = populated_cache();
+    {
+        let mut store = FileStore::open(&path).unwrap();
+        store.save_cache(&cache).unwrap();
+    }
+    let saved_len = fs::metadata(&path).unwrap().len();
+    let mut store = FileStore::open(&path).unwrap();
+    store.save_cache(&cache).unwrap();
+    assert_eq!(fs::metadata(&path).unwrap().len(), saved_len, "Stored state isn't appended again");
+    let mut restored = MasternodeProcessorCache::default();
+    store.load_cache(&mut restored).unwrap();
+    assert_same_state(&cache, &restored);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_file_store_recovery() {
+    let path = temp_store_path("test_file_store_recovery");
+    let cache = populated_cache();
+    let block_hash = *cache.llmq_snapshots.keys().next().unwrap();
+    let snapshot = cache.llmq_snapshots.get(&block_hash).unwrap().clone();
+    {
+        let mut store = FileStore::open(&path).unwrap();
+        store.save_snapshot(block_hash, snapshot.clone()).unwrap();
+    }
+    let valid_len = fs::metadata(&path).unwrap().len();
+    // Interrupted write leaves an incomplete record which is dropped on open
+    fs::OpenOptions::new().append(true).open(&path).unwrap().write_all(&[1, 200, 0, 0, 0, 1, 2, 3]).unwrap();
+    {
+        let mut store = FileStore::open(&path).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().len(), valid_len, "Incomplete record should be cut off");
+        assert!(store.snapshot(&block_hash).unwrap().is_some());
+        // the latest record for the key wins
+        let replacement = LLMQSnapshot::new(vec![0b111], vec![], LLMQSnapshotSkipMode::from(0));
+        store.save_snapshot(block_hash, replacement).unwrap();
+    }
+    let store = FileStore::open(&path).unwrap();
+    assert_eq!(store.snapshot(&block_hash).unwrap().unwrap().member_list, vec![0b111]);
+    drop(store);
+    // Broken checksum of the complete record is reported
+    let mut bytes = fs::read(&path).unwrap();
+    let last = valid_len as usize - 1;
+    bytes[last] ^= 0xff;
+    fs::write(&path, bytes).unwrap();
+    match FileStore::open(&path) {
+        Err(StoreError::Corrupted(offset)) => assert_eq!(offset, 0),
+        other => panic!("Corrupted store should be reported: {:?}", other.map(|_| ())),
+    }
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_diff_compressed_store() {
+    let cache = populated_cache();
+    let mut lists = cache.mn_lists.iter().collect::<Vec<(&UInt256, &Arc<SharedMasternodeList>)>>();
+    lists.sort_by_key(|(_, list)| list.known_height);
+    // Use rotation cycle as interval so there are several lists within it
+    let mut store = DiffCompressedStore::for_llmq_type(LLMQType::Llmqtype60_75);
+    lists.iter().for_each(|(block_hash, list)| {
+        store.save_masternode_list(**block_hash, Arc::clone(list)).unwrap();
+    });
+    assert!(store.deltas_count() > 0, "Some lists should be stored as deltas");
+    assert_eq!(store.checkpoints_count() + store.deltas_count(), lists.len());
+    let full_entries_count = lists
+        .iter()
+        .map(|(_, list)| list.masternodes.len() + list.quorums.values().map(|q| q.len()).sum::<usize>())
+        .sum::<usize>();
+    println!("diff compressed store keeps {} of {} entries", store.stored_entries_count(), full_entries_count);
+    assert!(store.stored_entries_count() < full_entries_count, "Deltas should take less space than full lists");
+    lists.iter().for_each(|(block_hash, list)| {
+        let restored = store.masternode_list(block_hash).unwrap().unwrap();
+        assert_eq!(restored.known_height, list.known_height);
+        assert_eq!(list.masternodes.keys().collect::<Vec<&UInt256>>(), restored.masternodes.keys().collect::<Vec<&UInt256>>());
+        assert_same_masternodes(&list.masternode_entries().collect::<Vec<_>>(), &restored.masternode_entries().collect::<Vec<_>>());
+        assert_same_quorums(list, &restored);
+        assert_eq!(list.masternode_merkle_root, restored.masternode_merkle_root, "Masternode root at {} should be equal", list.known_height);
+        assert_eq!(list.llmq_merkle_root, restored.llmq_merkle_root, "Quorum root at {} should be equal", list.known_height);
+    });
+    let mut restored = MasternodeProcessorCache::default();
+    store.save_cache(&cache).unwrap();
+    store.load_cache(&mut restored).unwrap();
+    assert_same_state(&cache, &restored);
+}
+
+#[test]
+fn test_diff_compressed_store_replacing_list() {
+    let cache = populated_cache();
+    let mut lists = cache.mn_lists.values().cloned().collect::<Vec<Arc<SharedMasternodeList>>>();
+    lists.sort_by_key(|list| list.known_height);
+    let mut store = DiffCompressedStore::new(u32::MAX);
+    lists.iter().for_each(|list| store.save_masternode_list(list.block_hash, Arc::clone(list)).unwrap());
+    assert!(store.deltas_count() > 0, "Lists after the first one should be kept as deltas");
+    // Replace the list in the middle with the one whose entry differs only in its state, not in its entry hash
+    let replaced = &lists[lists.len() / 2];
+    let mut replacement = SharedMasternodeList::clone(replaced);
+    let (hash, mut entry) = replacement.masternodes.iter().next().map(|(hash, entry)| (*hash, entry.as_ref().clone())).unwrap();
+    entry.known_confirmed_at_height = Some(replaced.known_height);
+    entry.previous_validity.insert(Block { height: replaced.known_height, hash: replaced.block_hash }, !entry.is_valid);
+    replacement.masternodes.insert(hash, Arc::new(entry.clone()));
+    let replacement = Arc::new(replacement);
+    store.save_masternode_list(replacement.block_hash, Arc::clone(&replacement)).unwrap();
+    let restored = store.masternode_list(&replacement.block_hash).unwrap().unwrap();
+    assert_same_masternodes(&[&entry], &[restored.masternodes.get(&hash).unwrap().as_ref()]);
+    // Lists stored after the replaced one are still reconstructed as they were saved
+    lists.iter().filter(|list| list.known_height > replaced.known_height).for_each(|list| {
+        let restored = store.masternode_list(&list.block_hash).unwrap().unwrap();
+        assert_same_masternodes(&list.masternode_entries().collect::<Vec<_>>(), &restored.masternode_entries().collect::<Vec<_>>());
+        assert_same_quorums(list, &restored);
+    });
+}
+
+#[test]
+fn test_masternode_checkpoint() {
+    let cache = populated_cache();
+    let list = cache.mn_lists.values().max_by_key(|list| list.known_height).unwrap();
+    let checkpoint = MasternodeCheckpoint::new(Arc::clone(list), cache.llmq_snapshots.clone()).unwrap();
+    let bytes = checkpoint.to_bytes();
+    let restored = MasternodeCheckpoint::from_bytes(&bytes).unwrap();
+    assert_eq!(restored.block_hash, list.block_hash);
+    assert_eq!(restored.height, list.known_height);
+    assert_eq!(restored.snapshots.len(), cache.llmq_snapshots.len());
+    // Checkpoint is only trusted along with the coinbase of its block
+    let bytes_of_diff = message_from_file(mainnet_reload_files().last().unwrap().clone());
+    let coinbase = MNListDiff::new(&bytes_of_diff, &mut 0, |_| list.known_height).unwrap().coinbase_transaction;
+    assert_eq!(coinbase.height, list.known_height, "Coinbase should be the one of the checkpoint block");
+    let first_diff = message_from_file(mainnet_reload_files()[0].clone());
+    let foreign_coinbase = MNListDiff::new(&first_diff, &mut 0, |_| 0).unwrap().coinbase_transaction;
+    let mut rejected = MasternodeProcessorCache::default();
+    assert_eq!(rejected.load_checkpoint(&restored, &foreign_coinbase).unwrap_err(), CheckpointError::CoinbaseMismatch);
+    assert!(rejected.mn_lists.is_empty(), "Rejected checkpoint shouldn't touch the cache");
+    let mut bootstrapped = MasternodeProcessorCache::default();
+    bootstrapped.load_checkpoint(&restored, &coinbase).unwrap();
+    let loaded = bootstrapped.mn_lists.get(&list.block_hash).unwrap();
+    assert_eq!(loaded.masternode_merkle_root, list.masternode_merkle_root);
+    assert_eq!(loaded.llmq_merkle_root, list.llmq_merkle_root);
+    let trees = bootstrapped.merkle_trees.get(&list.block_hash).unwrap();
+    assert_eq!(trees.masternode_merkle_root(), list.masternode_merkle_root, "Merkle trees should be ready for the next diff");
+    assert_eq!(bootstrapped.llmq_snapshots.len(), cache.llmq_snapshots.len());
+    // Any change of the content breaks the integrity hash
+    let mut corrupted = bytes.clone();
+    corrupted[100] ^= 1;
+    assert_eq!(MasternodeCheckpoint::from_bytes(&corrupted).unwrap_err(), CheckpointError::Corrupted);
+    let mut future = bytes.clone();
+    future[4] = 3;
+    assert_eq!(MasternodeCheckpoint::from_bytes(&future).unwrap_err(), CheckpointError::UnsupportedVersion(3));
+    assert_eq!(MasternodeCheckpoint::from_bytes(&bytes[4..]).unwrap_err(), CheckpointError::UnknownFormat);
+    // The list should match the roots the checkpoint is committed to
+    let mut forged = checkpoint;
+    forged.masternode_merkle_root = UInt256::MIN;
+    assert_eq!(MasternodeCheckpoint::from_bytes(&forged.to_bytes()).unwrap_err(), CheckpointError::InvalidMerkleRoots);
+}
+
+#[test]
+fn test_masternode_list_at_or_below() {
+    let mut cache = populated_cache();
+    let mut heights = cache.mn_lists.values().map(|list| list.known_height).collect::<Vec<u32>>();
+    heights.sort();
+    let (first, second, last) = (heights[0], heights[1], heights[heights.len() - 1]);
+    assert_eq!(cache.mn_list_height_index.len(), cache.mn_lists.len(), "Each list should be indexed by its height");
+    let (block_hash, list) = cache.masternode_list_at_or_below(second - 1).unwrap();
+    assert_eq!(list.known_height, first, "Nearest list below should be found");
+    assert_eq!(block_hash, list.block_hash);
+    assert!(cache.masternode_list_at_height(second - 1).is_none(), "There is no list exactly at the height");
+    assert_eq!(cache.masternode_list_at_height(last).unwrap().1.known_height, last);
+    assert!(cache.masternode_list_at_or_below(first - 1).is_none());
+    let (second_hash, _) = cache.masternode_list_at_height(second).unwrap();
+    cache.remove_masternode_list(&second_hash);
+    assert!(!cache.mn_list_height_index.contains_key(&second), "Removed list should be dropped from the index");
+    assert_eq!(cache.masternode_list_at_or_below(second).unwrap().1.known_height, first);
+
+    let mut memory_store = MemoryStore::default();
+    let mut diff_store = DiffCompressedStore::new(576);
+    memory_store.save_cache(&cache).unwrap();
+    diff_store.save_cache(&cache).unwrap();
+    let stores: [&dyn MasternodeStore; 2] = [&memory_store, &diff_store];
+    for store in stores {
+        assert_eq!(store.masternode_list_at_or_below(u32::MAX - 1).unwrap().unwrap().known_height, last);
+        assert_eq!(store.masternode_list_at_or_below(second - 1).unwrap().unwrap().known_height, first);
+        assert!(store.masternode_list_at_or_below(first - 1).unwrap().is_none());
+    }
+}
+
+#[test]
+fn test_masternode_lists_at_same_height() {
+    let mut cache = MasternodeProcessorCache::default();
+    let (first, second) = (UInt256([1; 32]), UInt256([2; 32]));
+    cache.add_masternode_list(first, Arc::new(SharedMasternodeList::empty(first, 100)));
+    cache.add_masternode_list(second, Arc::new(SharedMasternodeList::empty(second, 100)));
+    assert_eq!(cache.masternode_list_at_height(100).unwrap().0, second, "Last added list should be indexed");
+    cache.remove_masternode_list(&second);
+    assert_eq!(cache.masternode_list_at_height(100).unwrap().0, first, "List left at the height should be found");
+    assert_eq!(cache.masternode_list_at_or_below(150).unwrap().0, first);
+    cache.add_masternode_list(second, Arc::new(SharedMasternodeList::empty(second, 100)));
+    cache.remove_masternode_list(&first);
+    assert_eq!(cache.masternode_list_at_height(100).unwrap().0, second, "Removing the other list keeps the indexed one");
+    cache.remove_masternode_list(&second);
+    assert!(cache.mn_list_height_index.is_empty());
+}
@@ -0,0 +1,103 @@
+use crate::codec::network::{MAINNET_MAGIC, TESTNET_MAGIC};
+use crate::codec::{GetMNListDiff, GetQRInfo, QRInfoMessage};
+use crate::lib_tests::tests::{message_from_file, FFIContext};
+use crate::mock_peer::connection::{VersionMessage, PROTOCOL_VERSION};
+use crate::mock_peer::peer::MOCK_PEER_USER_AGENT;
+use crate::mock_peer::{MockPeer, PeerConnection, PeerFixtures};
+use crate::processing::{LLMQRotationConfig, ProcessingError, ProcessorSession, SessionChain, SyncPlanner, SyncRequest};
+use crate::tests::block_store::init_mainnet_store;
+use crate::tests::processor_session::default_processor;
+use dash_spv_models::common::chain_type::{ChainType, IHaveChainSettings};
+use dash_spv_primitives::crypto::UInt256;
+use std::io;
+use std::time::Duration;
+
+const FIXTURES_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/files");
+
+fn connect(peer: &MockPeer) -> PeerConnection {
+    let mut connection = PeerConnection::connect(peer.address(), MAINNET_MAGIC).unwrap();
+    connection.stream().set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    let version = connection.handshake(&VersionMessage::new("/test:0.1.0/", 0)).unwrap();
+    assert_eq!(version.version, PROTOCOL_VERSION);
+    assert_eq!(version.user_agent, MOCK_PEER_USER_AGENT);
+    connection
+}
+
+#[test]
+fn test_mock_peer_list_diff_sync() {
+    let fixtures = PeerFixtures::from_dir(FIXTURES_DIR).unwrap();
+    assert!(!fixtures.is_empty());
+    let peer = MockPeer::start(fixtures, MAINNET_MAGIC).unwrap();
+    let mut connection = connect(&peer);
+
+    let context = &mut (FFIContext {
+        chain: ChainType::MainNet,
+        cache: &mut Default::default(),
+        blocks: init_mainnet_store()
+    });
+    let blocks = init_mainnet_store();
+    let block_height_lookup = |hash: UInt256| blocks.iter().find(|block| block.hash == hash).map_or(u32::MAX, |block| block.height);
+    let block_hash_lookup = |height: u32| blocks.iter().find(|block| block.height == height).map(|block| block.hash);
+    let mut session = ProcessorSession::new(SessionChain::MainNet, default_processor());
+    let mut planner = SyncPlanner::new(session.genesis_hash(), LLMQRotationConfig::empty());
+    for tip_height in [1090944, 1091520] {
+        let plan = planner.plan(tip_height, session.cache(), block_height_lookup, block_hash_lookup);
+        let request = plan.iter().find(|request| request.block_height() == tip_height).unwrap();
+        let payload = connection.request(request).unwrap();
+        let result = session.process_mnlistdiff(&payload, false, false, context as *mut _ as *mut std::ffi::c_void);
+        assert_eq!(result.error_status, ProcessingError::None, "Diff to {} must be served and processed", tip_height);
+        assert!(result.has_found_coinbase && result.has_valid_mn_list_root);
+        assert_eq!(Some(result.block_hash), block_hash_lookup(tip_height));
+        planner.on_list_diff_result(&result);
+    }
+    let requests = peer.requests();
+    assert_eq!(requests.len(), 2);
+    assert!(requests.iter().all(|request| request.served));
+    assert_eq!(requests[0].block_hash, block_hash_lookup(1090944).unwrap());
+    assert_eq!(
+        session.cache().masternode_list_at_height(1091520).map(|(block_hash, _)| block_hash),
+        block_hash_lookup(1091520),
+        "Second diff is based on the list of the first one"
+    );
+}
+
+#[test]
+fn test_mock_peer_qr_info() {
+    let bytes = message_from_file("QRINFO_0_1739226.dat".to_string());
+    let message = QRInfoMessage::from_bytes(&bytes, &mut 0, |_| 0).unwrap();
+    let mut fixtures = PeerFixtures::default();
+    assert!(fixtures.add_qr_info(bytes.clone()));
+    assert!(!fixtures.add_list_diff(vec![0; 10]), "Payload which isn't the diff is rejected");
+    let peer = MockPeer::start(fixtures, MAINNET_MAGIC).unwrap();
+    let mut connection = connect(&peer);
+    let request = SyncRequest::QRInfo {
+        block_height: 1739226,
+        request: GetQRInfo {
+            base_block_hashes: vec![ChainType::MainNet.genesis_hash()],
+            block_request_hash: message.diff_tip.diff.block_hash,
+            extra_share: true,
+        },
+    };
+    assert_eq!(connection.request(&request).unwrap(), bytes);
+}
+
+#[test]
+fn test_mock_peer_unknown_requests() {
+    let peer = MockPeer::start(PeerFixtures::from_dir(FIXTURES_DIR).unwrap(), MAINNET_MAGIC).unwrap();
+    let mut connection = connect(&peer);
+    connection.stream().set_read_timeout(Some(Duration::from_millis(200))).unwrap();
+    let request = SyncRequest::ListDiff {
+        block_height: 0,
+        request: GetMNListDiff { base_block_hash: UInt256::MIN, block_hash: UInt256::MAX },
+    };
+    let error = connection.request(&request).unwrap_err();
+    assert!(matches!(error.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut), "Unknown diff gets no response");
+    let requests = peer.requests();
+    assert_eq!(requests.len(), 1);
+    assert!(!requests[0].served);
+
+    // Peer drops the connection of another network
+    let mut connection = PeerConnection::connect(peer.address(), TESTNET_MAGIC).unwrap();
+    connection.stream().set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    assert!(connection.handshake(&VersionMessage::new("/test:0.1.0/", 0)).is_err());
+}
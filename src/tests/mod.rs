@@ -1,13 +1,31 @@
+pub mod async_processor;
+pub mod cache_stats;
+pub mod core_json;
+pub mod list_changes;
+pub mod list_diff_encoding;
+pub mod list_integrity;
 pub mod llmq_rotation;
 pub mod llmq_rotation_testnet;
 pub mod llmq_snapshot;
 pub mod llmq_verification;
 pub mod mainnet_reload;
+#[cfg(feature = "mock-peer")]
+pub mod mock_peer;
 pub mod masternode_list_diff_1;
 pub mod masternode_list_diff_2;
 pub mod masternode_list_saving_to_disk;
+pub mod masternode_store;
 pub mod multiple_merkle_hashes;
+pub mod network_messages;
+pub mod processor_session;
+pub mod pull_processor;
+pub mod qr_info_encoding;
 pub mod quorum_issue;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_store;
+pub mod rotated_quorum_tracker;
+pub mod sync_planner;
+#[cfg(feature = "serde")]
+pub mod serde_results;
 pub mod test_quorums_from_dash_core_snapshot;
 pub mod block_store;
-pub mod json_from_core_snapshot;
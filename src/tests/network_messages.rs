@@ -0,0 +1,156 @@
+use crate::codec::network::{payload_checksum, GETMNLISTDIFF_COMMAND, GETQRINFO_COMMAND, MAINNET_MAGIC, MESSAGE_HEADER_SIZE, MNLISTDIFF_COMMAND, TESTNET_MAGIC};
+use crate::codec::{encode_network_message, BytesEncodable, GetMNListDiff, GetQRInfo, ListDiffMessage, MasternodeMessage, MessageError, MessageHeader, NetworkMessage};
+use crate::lib_tests::tests::message_from_file;
+use crate::{processor_destroy_message, processor_getmnlistdiff_message, processor_getqrinfo_message};
+use dash_spv_models::common::chain_type::{ChainType, IHaveChainSettings};
+use dash_spv_primitives::crypto::UInt256;
+use std::slice;
+
+const LIST_DIFF_FILE: &str = "MNL_0_1090944.dat";
+
+#[test]
+fn test_payload_checksum() {
+    // Checksum of the empty payload as in 'verack'
+    assert_eq!(payload_checksum(&[]), [0x5d, 0xf6, 0xe0, 0xe2]);
+}
+
+#[test]
+fn test_list_diff_network_message() {
+    let payload = message_from_file(LIST_DIFF_FILE.to_string());
+    let bytes = encode_network_message(MAINNET_MAGIC, MNLISTDIFF_COMMAND, &payload);
+    assert_eq!(bytes.len(), MESSAGE_HEADER_SIZE + payload.len());
+    assert_eq!(bytes[..4], [0xbf, 0x0c, 0x6b, 0xbd]);
+    assert_eq!(&bytes[4..16], b"mnlistdiff\0\0");
+    assert_eq!(bytes[16..20], (payload.len() as u32).to_le_bytes());
+
+    let (message, size) = NetworkMessage::from_bytes(&bytes, MAINNET_MAGIC).unwrap().unwrap();
+    assert_eq!(size, bytes.len());
+    assert_eq!(message.command, MNLISTDIFF_COMMAND);
+    assert_eq!(message.payload, &payload[..]);
+    let expected = ListDiffMessage::from_bytes(&payload, &mut 0, |_| 0).unwrap();
+    match message.masternode_message(|_| 0).unwrap() {
+        Some(MasternodeMessage::ListDiff(diff)) => {
+            assert_eq!(diff.diff.base_block_hash, ChainType::MainNet.genesis_hash());
+            assert_eq!(diff.diff.block_hash, expected.diff.block_hash);
+            assert_eq!(diff.to_bytes(), expected.to_bytes());
+        }
+        _ => panic!("'mnlistdiff' must be read as the list diff"),
+    }
+}
+
+#[test]
+fn test_network_message_stream() {
+    let payload = message_from_file(LIST_DIFF_FILE.to_string());
+    let message = encode_network_message(MAINNET_MAGIC, MNLISTDIFF_COMMAND, &payload);
+    let request = GetMNListDiff { base_block_hash: UInt256::MIN, block_hash: UInt256::MAX }.to_network_message(MAINNET_MAGIC);
+    let mut stream = message.clone();
+    stream.extend_from_slice(&request);
+
+    assert_eq!(NetworkMessage::from_bytes(&stream[..10], MAINNET_MAGIC), Ok(None), "Header isn't complete");
+    assert_eq!(NetworkMessage::from_bytes(&stream[..message.len() - 1], MAINNET_MAGIC), Ok(None), "Payload isn't complete");
+
+    let (first, size) = NetworkMessage::from_bytes(&stream, MAINNET_MAGIC).unwrap().unwrap();
+    assert_eq!(first.command, MNLISTDIFF_COMMAND);
+    assert_eq!(size, message.len(), "Only the first message is consumed");
+    let (second, size) = NetworkMessage::from_bytes(&stream[size..], MAINNET_MAGIC).unwrap().unwrap();
+    assert_eq!(second.command, GETMNLISTDIFF_COMMAND);
+    assert_eq!(size, request.len());
+    assert!(second.masternode_message(|_| 0).unwrap().is_none(), "Requests aren't handled by the processor");
+}
+
+#[test]
+fn test_network_message_errors() {
+    let payload = message_from_file(LIST_DIFF_FILE.to_string());
+    let bytes = encode_network_message(MAINNET_MAGIC, MNLISTDIFF_COMMAND, &payload);
+    assert_eq!(NetworkMessage::from_bytes(&bytes, TESTNET_MAGIC), Err(MessageError::WrongMagic(MAINNET_MAGIC)));
+
+    let mut corrupted = bytes.clone();
+    *corrupted.last_mut().unwrap() ^= 0xff;
+    assert_eq!(NetworkMessage::from_bytes(&corrupted, MAINNET_MAGIC), Err(MessageError::ChecksumMismatch));
+
+    let mut invalid_command = bytes.clone();
+    // Command must be null-padded without anything after the padding
+    invalid_command[15] = b'x';
+    assert_eq!(NetworkMessage::from_bytes(&invalid_command, MAINNET_MAGIC), Err(MessageError::InvalidCommand));
+
+    let mut too_large = bytes;
+    too_large[16..20].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert_eq!(NetworkMessage::from_bytes(&too_large, MAINNET_MAGIC), Err(MessageError::PayloadTooLarge(u32::MAX)));
+
+    let truncated = encode_network_message(MAINNET_MAGIC, MNLISTDIFF_COMMAND, &payload[..100]);
+    let (message, _) = NetworkMessage::from_bytes(&truncated, MAINNET_MAGIC).unwrap().unwrap();
+    assert_eq!(
+        message.masternode_message(|_| 0).err(),
+        Some(MessageError::InvalidPayload(MNLISTDIFF_COMMAND.to_string()))
+    );
+}
+
+#[test]
+fn test_get_mn_list_diff_message() {
+    let request = GetMNListDiff {
+        base_block_hash: ChainType::MainNet.genesis_hash(),
+        block_hash: UInt256::MAX,
+    };
+    let payload = request.to_bytes();
+    assert_eq!(payload.len(), 64);
+    assert_eq!(payload[..32], ChainType::MainNet.genesis_hash().0);
+    assert_eq!(GetMNListDiff::from_bytes(&payload, &mut 0), Some(request.clone()));
+
+    let bytes = request.to_network_message(MAINNET_MAGIC);
+    let header = MessageHeader::from_bytes(&bytes, &mut 0).unwrap().unwrap();
+    assert_eq!(header.command, GETMNLISTDIFF_COMMAND);
+    assert_eq!(header.payload_length, 64);
+    assert_eq!(header.checksum, payload_checksum(&payload));
+    assert_eq!(header.to_bytes(), bytes[..MESSAGE_HEADER_SIZE]);
+
+    let mut length = 0usize;
+    unsafe {
+        let message = processor_getmnlistdiff_message(
+            MAINNET_MAGIC,
+            request.base_block_hash.0.as_ptr(),
+            request.block_hash.0.as_ptr(),
+            &mut length,
+        );
+        assert_eq!(slice::from_raw_parts(message, length), &bytes[..]);
+        processor_destroy_message(message, length);
+    }
+}
+
+#[test]
+fn test_get_qr_info_message() {
+    let request = GetQRInfo {
+        base_block_hashes: vec![ChainType::TestNet.genesis_hash(), UInt256::MAX],
+        block_request_hash: UInt256::MIN,
+        extra_share: true,
+    };
+    let payload = request.to_bytes();
+    // Count, two hashes, block hash and extra share flag
+    assert_eq!(payload.len(), 1 + 32 * 2 + 32 + 1);
+    assert_eq!(payload[0], 2);
+    assert_eq!(*payload.last().unwrap(), 1);
+    assert_eq!(GetQRInfo::from_bytes(&payload, &mut 0), Some(request.clone()));
+
+    let bytes = request.to_network_message(TESTNET_MAGIC);
+    let (message, _) = NetworkMessage::from_bytes(&bytes, TESTNET_MAGIC).unwrap().unwrap();
+    assert_eq!(message.command, GETQRINFO_COMMAND);
+    assert_eq!(message.payload, &payload[..]);
+
+    let base_block_hashes = request
+        .base_block_hashes
+        .iter()
+        .map(|hash| hash.0.as_ptr())
+        .collect::<Vec<*const u8>>();
+    let mut length = 0usize;
+    unsafe {
+        let message = processor_getqrinfo_message(
+            TESTNET_MAGIC,
+            base_block_hashes.as_ptr(),
+            base_block_hashes.len(),
+            request.block_request_hash.0.as_ptr(),
+            request.extra_share,
+            &mut length,
+        );
+        assert_eq!(slice::from_raw_parts(message, length), &bytes[..]);
+        processor_destroy_message(message, length);
+    }
+}
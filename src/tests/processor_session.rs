@@ -0,0 +1,107 @@
+use crate::lib_tests::tests::{add_insight_lookup_default, get_block_hash_by_height_default, get_block_height_by_hash_from_context, get_llmq_snapshot_by_block_hash_default, get_masternode_list_by_block_hash_default, get_merkle_root_by_hash_default, hash_destroy_default, log_default, masternode_list_destroy_default, masternode_list_save_default, message_from_file, save_llmq_snapshot_default, should_process_diff_with_range_default, should_process_llmq_of_type, snapshot_destroy_default, validate_llmq_callback, FFIContext};
+use crate::processing::{MasternodeProcessor, ProcessingError, ProcessorSession, SessionChain};
+use crate::register_processor;
+use crate::tests::block_store::{init_mainnet_store, init_testnet_store};
+use dash_spv_models::common::chain_type::ChainType;
+use dash_spv_models::common::LLMQType;
+use dash_spv_primitives::crypto::UInt256;
+
+pub fn default_processor() -> MasternodeProcessor {
+    unsafe {
+        *Box::from_raw(register_processor(
+            get_merkle_root_by_hash_default,
+            get_block_height_by_hash_from_context,
+            get_block_hash_by_height_default,
+            get_llmq_snapshot_by_block_hash_default,
+            save_llmq_snapshot_default,
+            get_masternode_list_by_block_hash_default,
+            masternode_list_save_default,
+            masternode_list_destroy_default,
+            add_insight_lookup_default,
+            should_process_llmq_of_type,
+            validate_llmq_callback,
+            hash_destroy_default,
+            snapshot_destroy_default,
+            should_process_diff_with_range_default,
+            log_default,
+        ))
+    }
+}
+
+#[test]
+fn test_processor_session_chain_binding() {
+    let context = &mut (FFIContext {
+        chain: ChainType::MainNet,
+        cache: &mut Default::default(),
+        blocks: init_mainnet_store()
+    });
+    let context_ptr = context as *mut _ as *mut std::ffi::c_void;
+    let bytes = message_from_file("MNL_0_1090944.dat".to_string());
+
+    let mut mainnet_session = ProcessorSession::new(SessionChain::MainNet, default_processor());
+    let mut testnet_session = ProcessorSession::new(SessionChain::TestNet, default_processor());
+    assert_eq!(mainnet_session.genesis_hash(), ChainType::MainNet.genesis_hash());
+    assert_eq!(testnet_session.genesis_hash(), ChainType::TestNet.genesis_hash());
+
+    let result = mainnet_session.process_mnlistdiff(&bytes, false, false, context_ptr);
+    assert_eq!(result.error_status, ProcessingError::None, "Mainnet diff should be processed within mainnet session");
+    assert!(result.has_found_coinbase && result.has_valid_mn_list_root, "Result must be valid");
+    assert_eq!(mainnet_session.cache().mn_lists.len(), 1, "Session cache should keep the list");
+    assert!(testnet_session.cache().mn_lists.is_empty(), "Caches of the sessions must be isolated");
+
+    // Next diff is based on the mainnet block, which is unknown to the testnet
+    let testnet_context = &mut (FFIContext {
+        chain: ChainType::TestNet,
+        cache: &mut Default::default(),
+        blocks: init_testnet_store()
+    });
+    let bytes = message_from_file("MNL_1090944_1091520.dat".to_string());
+    let result = testnet_session.process_mnlistdiff(&bytes, false, false, testnet_context as *mut _ as *mut std::ffi::c_void);
+    assert_eq!(result.error_status, ProcessingError::InvalidChain, "Testnet session shouldn't accept mainnet diff");
+    assert!(testnet_session.cache().mn_lists.is_empty(), "Rejected diff shouldn't touch the cache");
+}
+
+#[test]
+fn test_processor_session_custom_chains() {
+    let devnet = SessionChain::DevNet { name: "devnet-333".to_string(), genesis_hash: UInt256([3; 32]) };
+    let regtest = SessionChain::RegTest { genesis_hash: UInt256([7; 32]) };
+    assert_eq!(devnet.rotation_config().types(), vec![LLMQType::LlmqtypeTestDIP0024, LLMQType::LlmqtypeDevnetDIP0024]);
+    assert_eq!(regtest.rotation_config().types(), vec![LLMQType::LlmqtypeTestDIP0024], "Regtest only runs the test rotating type");
+    let context = &mut (FFIContext {
+        chain: ChainType::MainNet,
+        cache: &mut Default::default(),
+        blocks: init_mainnet_store()
+    });
+    let context_ptr = context as *mut _ as *mut std::ffi::c_void;
+    // The diff is based on the mainnet genesis
+    let bytes = message_from_file("MNL_0_1090944.dat".to_string());
+    let mut session = ProcessorSession::new(regtest, default_processor());
+    assert_eq!(session.genesis_hash(), UInt256([7; 32]));
+    let result = session.process_mnlistdiff(&bytes, false, false, context_ptr);
+    assert_eq!(result.error_status, ProcessingError::InvalidChain, "Regtest session shouldn't accept the diff from another genesis");
+    assert!(session.cache().mn_lists.is_empty());
+}
+
+#[test]
+fn test_processor_session_qrinfo_chain_binding() {
+    let bytes = message_from_file("QRINFO_0_1739226.dat".to_string());
+    let context = &mut (FFIContext {
+        chain: ChainType::MainNet,
+        cache: &mut Default::default(),
+        blocks: init_mainnet_store()
+    });
+    let mut mainnet_session = ProcessorSession::new(SessionChain::MainNet, default_processor());
+    let result = mainnet_session.process_qrinfo(&bytes, false, false, context as *mut _ as *mut std::ffi::c_void);
+    assert_eq!(result.error_status, ProcessingError::None, "Mainnet QRINFO should be processed within mainnet session");
+    assert!(mainnet_session.cache().mn_lists.contains_key(&result.result_at_tip.block_hash));
+
+    let testnet_context = &mut (FFIContext {
+        chain: ChainType::TestNet,
+        cache: &mut Default::default(),
+        blocks: init_testnet_store()
+    });
+    let mut testnet_session = ProcessorSession::new(SessionChain::TestNet, default_processor());
+    let result = testnet_session.process_qrinfo(&bytes, false, false, testnet_context as *mut _ as *mut std::ffi::c_void);
+    assert_eq!(result.error_status, ProcessingError::InvalidChain, "Testnet session shouldn't accept mainnet QRINFO");
+    assert!(testnet_session.cache().mn_lists.is_empty(), "Rejected QRINFO shouldn't touch the cache");
+}
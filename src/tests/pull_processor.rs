@@ -0,0 +1,112 @@
+use crate::codec::QRInfoMessage;
+use crate::lib_tests::tests::{message_from_file, FFIContext, MerkleBlock};
+use crate::processing::{LLMQRotationConfig, ProcessingError, ProcessingNeeds, ProcessorSession, PullMessage, PullProcessor, PullResult, PullStep, SessionChain};
+use crate::tests::block_store::init_mainnet_store;
+use crate::tests::processor_session::default_processor;
+use dash_spv_models::common::chain_type::{ChainType, IHaveChainSettings};
+
+/// Answers the needs the way the host would: blocks from the store, nothing is stored
+/// and all the quorums are valid
+fn supply(processor: &mut PullProcessor, needs: &ProcessingNeeds, blocks: &[MerkleBlock]) {
+    needs.block_heights.iter().for_each(|hash| {
+        processor.supply_block_height(*hash, blocks.iter().find(|block| block.hash == *hash).map(|block| block.height))
+    });
+    needs.block_hashes.iter().for_each(|height| {
+        processor.supply_block_hash(*height, blocks.iter().find(|block| block.height == *height).map(|block| block.hash))
+    });
+    needs.merkle_roots.iter().for_each(|hash| processor.supply_merkle_root(*hash, None));
+    needs.masternode_lists.iter().for_each(|hash| processor.supply_masternode_list(*hash, None));
+    needs.snapshots.iter().for_each(|hash| processor.supply_snapshot(*hash, None));
+    needs.quorum_validations.keys().for_each(|hash| processor.supply_quorum_validation(*hash, true));
+}
+
+fn process(processor: &mut PullProcessor, message: PullMessage, blocks: &[MerkleBlock]) -> (PullResult, usize) {
+    let mut step = processor.start(message);
+    let mut suspensions = 0;
+    loop {
+        match step {
+            PullStep::Done(result) => return (result, suspensions),
+            PullStep::Needs(needs) => {
+                assert!(!needs.is_empty());
+                supply(processor, &needs, blocks);
+                suspensions += 1;
+                assert!(suspensions < 100, "Every need is asked once, so processing must complete");
+                step = processor.resume().unwrap();
+            }
+        }
+    }
+}
+
+#[test]
+fn test_pull_processor_list_diffs() {
+    let blocks = init_mainnet_store();
+    let mut processor = PullProcessor::for_chain(&SessionChain::MainNet);
+    assert!(processor.resume().is_none(), "Nothing to resume yet");
+
+    let bytes = message_from_file("MNL_0_1090944.dat".to_string());
+    match processor.start(PullMessage::ListDiff(bytes.clone())) {
+        PullStep::Needs(needs) => assert!(!needs.block_heights.is_empty(), "Height of the block is asked first"),
+        PullStep::Done(_) => panic!("Processing can't complete without the blocks"),
+    }
+    assert!(processor.cache().mn_lists.is_empty(), "Suspended processing leaves no trace in the cache");
+    assert!(processor.cache().mn_list_height_index.is_empty() && processor.cache().merkle_trees.is_empty());
+    assert!(processor.pending().is_some());
+
+    // Same messages processed with the callbacks give the reference results
+    let context = &mut (FFIContext {
+        chain: ChainType::MainNet,
+        cache: &mut Default::default(),
+        blocks: init_mainnet_store()
+    });
+    let mut session = ProcessorSession::new(SessionChain::MainNet, default_processor());
+    for file in ["MNL_0_1090944.dat", "MNL_1090944_1091520.dat"] {
+        let bytes = message_from_file(file.to_string());
+        let expected = session.process_mnlistdiff(&bytes, false, false, context as *mut _ as *mut std::ffi::c_void);
+        let (result, suspensions) = process(&mut processor, PullMessage::ListDiff(bytes), &blocks);
+        let result = match result {
+            PullResult::ListDiff(result) => result,
+            PullResult::QRInfo(_) => panic!("MNLISTDIFF gives the list diff result"),
+        };
+        assert!(suspensions > 0, "{}: processing must be suspended for the blocks", file);
+        assert_eq!(result.error_status, ProcessingError::None, "{}", file);
+        assert_eq!(result.block_hash, expected.block_hash, "{}", file);
+        assert_eq!(result.has_found_coinbase, expected.has_found_coinbase, "{}", file);
+        assert_eq!(result.has_valid_mn_list_root, expected.has_valid_mn_list_root, "{}", file);
+        assert_eq!(result.has_valid_llmq_list_root, expected.has_valid_llmq_list_root, "{}", file);
+        assert_eq!(
+            result.masternode_list.masternodes.keys().collect::<Vec<_>>(),
+            expected.masternode_list.masternodes.keys().collect::<Vec<_>>(),
+            "{}: list must be the same", file
+        );
+        assert!(processor.cache().mn_lists.contains_key(&result.block_hash), "{}: done processing goes into the cache", file);
+        assert!(processor.pending().is_none());
+    }
+}
+
+#[test]
+fn test_pull_processor_qr_info() {
+    let bytes = message_from_file("QRINFO_0_1739226.dat".to_string());
+    let message = QRInfoMessage::from_bytes(&bytes, &mut 0, |_| 0).unwrap();
+    let mut processor = PullProcessor::new(ChainType::MainNet.genesis_hash(), LLMQRotationConfig::default());
+    let (result, _) = process(&mut processor, PullMessage::QRInfo(bytes), &[]);
+    let result = match result {
+        PullResult::QRInfo(result) => result,
+        PullResult::ListDiff(_) => panic!("QRINFO gives the rotation info result"),
+    };
+    assert_eq!(result.error_status, ProcessingError::None);
+    assert_eq!(result.result_at_tip.block_hash, message.diff_tip.diff.block_hash);
+    assert_eq!(result.extra_share, message.extra_share.is_some());
+    let cache = processor.cache();
+    assert!(cache.mn_lists.contains_key(&message.diff_tip.diff.block_hash));
+    assert!(cache.mn_lists.contains_key(&message.diff_h_3c.diff.block_hash));
+    assert_eq!(
+        cache.llmq_snapshots.get(&message.diff_h_c.diff.block_hash).map(|snapshot| snapshot.member_list.clone()),
+        Some(message.snapshot_at_h_c.snapshot.member_list.clone()),
+        "Snapshots of the message are cached"
+    );
+
+    assert!(matches!(
+        processor.start(PullMessage::QRInfo(vec![0; 10])),
+        PullStep::Done(PullResult::QRInfo(result)) if result.error_status == ProcessingError::ParseError
+    ));
+}
@@ -0,0 +1,182 @@
+use crate::codec::{write_stored_snapshot, BytesEncodable, ListDiffMessage, QRInfoBlocks, QRInfoMessage, SnapshotMessage};
+use crate::lib_tests::tests::message_from_file;
+use crate::processing::SharedMasternodeList;
+use dash_spv_models::llmq::LLMQSnapshot;
+use dash_spv_models::masternode::MasternodeList;
+use dash_spv_primitives::crypto::UInt256;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+const QR_INFO_FILES: [&str; 4] = [
+    "QRINFO_0_1739226.dat",
+    "QRINFO_0_1740902.dat",
+    "QRINFO_0_1740910.dat",
+    "QRINFO_1_8344.dat",
+];
+
+/// Masternodes are compared regardless of their order within the message
+fn diff_content(message: &ListDiffMessage) -> (UInt256, UInt256, Vec<Vec<u8>>, Vec<Vec<u8>>) {
+    let diff = &message.diff;
+    let mut masternodes = diff
+        .added_or_modified_masternodes
+        .values()
+        .map(|entry| entry.to_bytes())
+        .collect::<Vec<Vec<u8>>>();
+    masternodes.sort();
+    let quorums = diff
+        .added_quorums
+        .values()
+        .flat_map(|quorums| quorums.values().map(|entry| entry.to_bytes()))
+        .collect::<Vec<Vec<u8>>>();
+    (diff.base_block_hash, diff.block_hash, masternodes, quorums)
+}
+
+fn snapshots_of(message: &QRInfoMessage) -> Vec<&SnapshotMessage> {
+    let mut snapshots = vec![&message.snapshot_at_h_c, &message.snapshot_at_h_2c, &message.snapshot_at_h_3c];
+    snapshots.extend(message.extra_share.as_ref().map(|(snapshot, _)| snapshot));
+    snapshots.extend(message.quorum_snapshot_list.iter());
+    snapshots
+}
+
+/// Snapshots are compared by their content, the count of members is only known for the received ones
+fn assert_same_qr_info(message: &QRInfoMessage, other: &QRInfoMessage, file: &str) {
+    let snapshots = |message: &QRInfoMessage| {
+        snapshots_of(message)
+            .into_iter()
+            .map(|snapshot| {
+                let mut bytes = Vec::<u8>::new();
+                write_stored_snapshot(&snapshot.snapshot, &mut bytes);
+                bytes
+            })
+            .collect::<Vec<Vec<u8>>>()
+    };
+    assert_eq!(snapshots(message), snapshots(other), "{}: snapshots must be the same", file);
+    assert_eq!(message.extra_share.is_some(), other.extra_share.is_some(), "{}", file);
+    assert_eq!(
+        message.diffs().into_iter().map(diff_content).collect::<Vec<_>>(),
+        other.diffs().into_iter().map(diff_content).collect::<Vec<_>>(),
+        "{}: diffs must be the same", file
+    );
+    assert_eq!(
+        message.last_quorum_per_index.iter().map(|entry| entry.to_bytes()).collect::<Vec<_>>(),
+        other.last_quorum_per_index.iter().map(|entry| entry.to_bytes()).collect::<Vec<_>>(),
+        "{}: last commitments must be the same", file
+    );
+}
+
+#[test]
+fn test_qr_info_message_round_trip() {
+    for file in QR_INFO_FILES {
+        let bytes = message_from_file(file.to_string());
+        let message = QRInfoMessage::from_bytes(&bytes, &mut 0, |_| 0).unwrap();
+        let encoded = message.to_bytes();
+        let decoded = QRInfoMessage::from_bytes(&encoded, &mut 0, |_| 0).unwrap();
+        assert_same_qr_info(&message, &decoded, file);
+        let member_counts = |message: &QRInfoMessage| {
+            snapshots_of(message)
+                .into_iter()
+                .map(|snapshot| snapshot.member_count)
+                .collect::<Vec<usize>>()
+        };
+        assert_eq!(member_counts(&message), member_counts(&decoded), "{}: member counts of the snapshots must be kept", file);
+        assert!(encoded == bytes, "{}: payload must be encoded byte-exact", file);
+        assert_eq!(decoded.to_bytes(), encoded, "{}: encoding must be stable", file);
+    }
+}
+
+#[test]
+fn test_qr_info_message_from_lists() {
+    let file = "QRINFO_0_1739226.dat";
+    let original = QRInfoMessage::from_bytes(&message_from_file(file.to_string()), &mut 0, |_| 0).unwrap();
+    let diffs = original.diffs();
+    let base_block_hash = diffs[0].diff.base_block_hash;
+    assert!(
+        diffs.iter().all(|message| message.diff.base_block_hash == base_block_hash),
+        "All diffs of the message are made from the same base"
+    );
+    // Diffs from the genesis carry whole lists, so they give the state the message is built from
+    let mut lists = BTreeMap::<UInt256, Arc<SharedMasternodeList>>::new();
+    let mut coinbases = BTreeMap::new();
+    diffs.iter().enumerate().for_each(|(height, message)| {
+        let diff = &message.diff;
+        lists.insert(
+            diff.block_hash,
+            Arc::new(SharedMasternodeList::from_list(&MasternodeList::new(
+                diff.added_or_modified_masternodes.clone(),
+                diff.added_quorums.clone(),
+                diff.block_hash,
+                height as u32 + 1,
+                true,
+            ))),
+        );
+        coinbases.insert(diff.block_hash, (message.merkle_tree(), message.coinbase_transaction.clone()));
+    });
+    let mut snapshots = BTreeMap::<UInt256, LLMQSnapshot>::new();
+    snapshots.insert(original.diff_h_c.diff.block_hash, original.snapshot_at_h_c.snapshot.clone());
+    snapshots.insert(original.diff_h_2c.diff.block_hash, original.snapshot_at_h_2c.snapshot.clone());
+    snapshots.insert(original.diff_h_3c.diff.block_hash, original.snapshot_at_h_3c.snapshot.clone());
+    if let Some((snapshot, diff)) = &original.extra_share {
+        snapshots.insert(diff.diff.block_hash, snapshot.snapshot.clone());
+    }
+    original
+        .quorum_snapshot_list
+        .iter()
+        .zip(original.mn_list_diff_list.iter())
+        .for_each(|(snapshot, diff)| {
+            snapshots.insert(diff.diff.block_hash, snapshot.snapshot.clone());
+        });
+    let blocks = QRInfoBlocks {
+        tip: original.diff_tip.diff.block_hash,
+        h: original.diff_h.diff.block_hash,
+        h_c: original.diff_h_c.diff.block_hash,
+        h_2c: original.diff_h_2c.diff.block_hash,
+        h_3c: original.diff_h_3c.diff.block_hash,
+        h_4c: original.extra_share.as_ref().map(|(_, diff)| diff.diff.block_hash),
+        extra: original.mn_list_diff_list.iter().map(|diff| diff.diff.block_hash).collect(),
+    };
+    let message = QRInfoMessage::from_lists(
+        &blocks,
+        &[],
+        base_block_hash,
+        &lists,
+        &snapshots,
+        original.last_quorum_per_index.clone(),
+        |block_hash| coinbases.get(&block_hash).cloned(),
+    )
+    .unwrap();
+    assert_same_qr_info(&original, &message, file);
+    let encoded = message.to_bytes();
+    let decoded = QRInfoMessage::from_bytes(&encoded, &mut 0, |_| 0).unwrap();
+    assert_same_qr_info(&original, &decoded, file);
+
+    let mut missing_snapshots = snapshots.clone();
+    missing_snapshots.remove(&blocks.h_2c);
+    assert!(
+        QRInfoMessage::from_lists(&blocks, &[], base_block_hash, &lists, &missing_snapshots, vec![], |block_hash| coinbases.get(&block_hash).cloned()).is_none(),
+        "Message can't be built without the snapshot"
+    );
+}
+
+#[test]
+fn test_qr_info_message_tail() {
+    let bytes = message_from_file("QRINFO_0_1739226.dat".to_string());
+    let mut message = QRInfoMessage::from_bytes(&bytes, &mut 0, |_| 0).unwrap();
+    assert!(message.has_tail);
+    message.last_quorum_per_index.clear();
+    message.quorum_snapshot_list.clear();
+    message.mn_list_diff_list.clear();
+    // Empty lists at the end take a byte each
+    let encoded = message.to_bytes();
+    let without_tail = &encoded[..encoded.len() - 3];
+    let decoded = QRInfoMessage::from_bytes(without_tail, &mut 0, |_| 0).expect("Message of older peers has no lists at the end");
+    assert!(!decoded.has_tail);
+    assert!(decoded.last_quorum_per_index.is_empty() && decoded.mn_list_diff_list.is_empty());
+    assert!(decoded.to_bytes() == without_tail, "Message without the lists at the end is encoded without them");
+    assert!(
+        QRInfoMessage::from_bytes(&encoded[..encoded.len() - 1], &mut 0, |_| 0).is_none(),
+        "Broken lists at the end must fail the message"
+    );
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    assert!(QRInfoMessage::from_bytes(&trailing, &mut 0, |_| 0).is_none(), "Payload must be consumed by the message");
+}
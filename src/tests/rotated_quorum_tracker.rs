@@ -0,0 +1,133 @@
+use crate::lib_tests::tests::{add_insight_lookup_default, get_block_hash_by_height_from_context, get_block_height_by_hash_from_context, get_llmq_snapshot_by_block_hash_default, get_masternode_list_by_block_hash_default, get_merkle_root_by_hash_default, hash_destroy_default, log_default, masternode_list_destroy_default, masternode_list_save_default, save_llmq_snapshot_default, should_process_diff_with_range_default, should_process_llmq_of_type, snapshot_destroy_default, validate_llmq_callback, FFIContext, MerkleBlock};
+use crate::processing::{LLMQRotationConfig, RotatedQuorumTracker};
+use crate::register_processor;
+use dash_spv_models::common::chain_type::ChainType;
+use dash_spv_models::common::LLMQType;
+use dash_spv_models::masternode;
+use dash_spv_primitives::consensus::encode::VarInt;
+use dash_spv_primitives::crypto::{UInt256, UInt384, UInt768};
+use dash_spv_primitives::hashes::{sha256d, Hash};
+
+fn block_hash_at(height: u32) -> UInt256 {
+    UInt256(sha256d::Hash::hash(&height.to_le_bytes()).into_inner())
+}
+
+fn quorum_at(llmq_type: LLMQType, height: u32, index: Option<u16>) -> masternode::LLMQEntry {
+    masternode::LLMQEntry::new(
+        2,
+        llmq_type,
+        block_hash_at(height),
+        index,
+        VarInt(0),
+        VarInt(0),
+        vec![],
+        vec![],
+        UInt384([0u8; 48]),
+        UInt256::MIN,
+        UInt768([0u8; 96]),
+        UInt768([0u8; 96]),
+    )
+}
+
+#[test]
+fn test_rotated_quorum_tracker_history() {
+    let llmq_type = LLMQType::Llmqtype60_75;
+    let mut tracker = RotatedQuorumTracker::default();
+    // 2 cycles of 288 blocks with 2 quorum indexes mined in each one
+    for cycle in [1000u32, 1288] {
+        for index in 0..2u16 {
+            assert!(tracker.add_quorum(cycle + index as u32, quorum_at(llmq_type, cycle + index as u32, Some(index))));
+        }
+    }
+    assert!(!tracker.add_quorum(1500, quorum_at(LLMQType::Llmqtype400_60, 1500, None)), "Non-rotated quorum shouldn't be tracked");
+    assert_eq!(tracker.len(), 4);
+    assert_eq!(tracker.indexes(llmq_type), vec![0, 1]);
+    assert!(tracker.active_quorum(llmq_type, 0, 999).is_none(), "Nothing is active before the first cycle");
+    assert!(tracker.active_quorum(llmq_type, 1, 1000).is_none(), "Index 1 is mined a block later");
+    assert_eq!(tracker.active_quorum(llmq_type, 0, 1000).unwrap().llmq_hash, block_hash_at(1000));
+    assert_eq!(tracker.active_quorum(llmq_type, 1, 1287).unwrap().llmq_hash, block_hash_at(1001));
+    assert_eq!(tracker.active_quorum(llmq_type, 1, 1289).unwrap().llmq_hash, block_hash_at(1289));
+    let active = tracker.active_quorums(llmq_type, 1288);
+    assert_eq!(active.iter().map(|quorum| quorum.llmq_hash).collect::<Vec<UInt256>>(), vec![block_hash_at(1288), block_hash_at(1001)]);
+    assert_eq!(tracker.history(llmq_type, 0).unwrap().keys().cloned().collect::<Vec<u32>>(), vec![1000, 1288]);
+    tracker.prune(1289);
+    assert_eq!(tracker.len(), 2);
+    assert_eq!(tracker.active_quorum(llmq_type, 0, 1289).unwrap().llmq_hash, block_hash_at(1288));
+    assert!(tracker.active_quorum(llmq_type, 0, 1287).is_none(), "Pruned commitment shouldn't be active");
+}
+
+#[test]
+fn test_rotated_quorum_tracker_closing() {
+    let llmq_type = LLMQType::Llmqtype60_75;
+    let mut tracker = RotatedQuorumTracker::default();
+    assert!(tracker.add_quorum(1000, quorum_at(llmq_type, 1000, Some(0))));
+    assert!(tracker.add_quorum(1001, quorum_at(llmq_type, 1001, Some(1))));
+    assert!(!tracker.close_quorum(llmq_type, block_hash_at(1288), 1300), "Unknown commitment can't be closed");
+    assert!(tracker.close_quorum(llmq_type, block_hash_at(1001), 1100));
+    assert_eq!(tracker.closed_at(llmq_type, block_hash_at(1001)), Some(1100));
+    assert_eq!(tracker.active_quorum(llmq_type, 1, 1099).unwrap().llmq_hash, block_hash_at(1001));
+    assert!(tracker.active_quorum(llmq_type, 1, 1100).is_none(), "Deleted commitment isn't active from its list on");
+    assert_eq!(
+        tracker.active_quorums(llmq_type, 1100).iter().map(|quorum| quorum.llmq_hash).collect::<Vec<UInt256>>(),
+        vec![block_hash_at(1000)]
+    );
+    tracker.prune(1100);
+    assert_eq!(tracker.len(), 1, "Closed commitment is pruned");
+    assert_eq!(tracker.indexes(llmq_type), vec![0]);
+}
+
+#[test]
+fn test_rotated_quorum_tracking_by_processor() {
+    let chain = ChainType::MainNet;
+    let heights = [1000u32, 1001, 1288];
+    let context = &mut (FFIContext {
+        chain,
+        cache: &mut Default::default(),
+        blocks: heights
+            .iter()
+            .map(|&height| MerkleBlock { hash: block_hash_at(height), height, merkleroot: UInt256::MIN })
+            .collect()
+    });
+    let processor = unsafe {
+        &mut *register_processor(
+            get_merkle_root_by_hash_default,
+            get_block_height_by_hash_from_context,
+            get_block_hash_by_height_from_context,
+            get_llmq_snapshot_by_block_hash_default,
+            save_llmq_snapshot_default,
+            get_masternode_list_by_block_hash_default,
+            masternode_list_save_default,
+            masternode_list_destroy_default,
+            add_insight_lookup_default,
+            should_process_llmq_of_type,
+            validate_llmq_callback,
+            hash_destroy_default,
+            snapshot_destroy_default,
+            should_process_diff_with_range_default,
+            log_default,
+        )
+    };
+    processor.opaque_context = context as *mut _ as *mut std::ffi::c_void;
+    processor.rotation_config = LLMQRotationConfig::for_chain(&chain);
+    let verified = |mut quorum: masternode::LLMQEntry| {
+        quorum.verified = true;
+        quorum
+    };
+    let quorums = vec![
+        verified(quorum_at(LLMQType::Llmqtype60_75, 1000, Some(0))),
+        verified(quorum_at(LLMQType::Llmqtype60_75, 1001, Some(1))),
+        verified(quorum_at(LLMQType::Llmqtype60_75, 1288, Some(0))),
+        verified(quorum_at(LLMQType::Llmqtype400_60, 1000, Some(0))),
+        // unknown block
+        verified(quorum_at(LLMQType::Llmqtype60_75, 2000, Some(1))),
+        // not validated
+        quorum_at(LLMQType::Llmqtype60_75, 1288, Some(1)),
+    ];
+    let mut tracker = RotatedQuorumTracker::default();
+    processor.track_rotated_quorums(&quorums, &mut tracker);
+    assert_eq!(tracker.len(), 3, "Only validated rotated quorums with known heights should be tracked");
+    assert!(tracker.indexes(LLMQType::Llmqtype400_60).is_empty());
+    assert_eq!(tracker.active_quorum(LLMQType::Llmqtype60_75, 0, 1100).unwrap().llmq_hash, block_hash_at(1000));
+    assert_eq!(tracker.active_quorum(LLMQType::Llmqtype60_75, 0, 1300).unwrap().llmq_hash, block_hash_at(1288));
+    assert_eq!(tracker.active_quorum(LLMQType::Llmqtype60_75, 1, 5000).unwrap().llmq_hash, block_hash_at(1001), "Unverified commitment doesn't replace the validated one");
+}
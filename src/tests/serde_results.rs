@@ -0,0 +1,47 @@
+use crate::processing::{MNListDiffResult, MasternodeProcessorCache, ProcessingError};
+use crate::tests::mainnet_reload::process_mainnet_reload;
+use crate::tests::masternode_store::{assert_same_state, populated_cache};
+use dash_spv_primitives::crypto::byte_util::Reversable;
+use dash_spv_primitives::hashes::hex::ToHex;
+
+#[test]
+fn test_serde_mnlistdiff_results() {
+    let mut cache = MasternodeProcessorCache::default();
+    let results = process_mainnet_reload(&mut cache);
+    results.iter().for_each(|result| {
+        let json = serde_json::to_string(result).unwrap();
+        let restored: MNListDiffResult = serde_json::from_str(&json).unwrap();
+        assert_eq!(result.error_status, restored.error_status);
+        assert_eq!(result.block_hash, restored.block_hash);
+        assert_eq!(result.base_block_hash, restored.base_block_hash);
+        assert_eq!(result.has_valid_mn_list_root, restored.has_valid_mn_list_root);
+        assert_eq!(result.added_masternodes.keys().collect::<Vec<_>>(), restored.added_masternodes.keys().collect::<Vec<_>>());
+        assert_eq!(result.added_quorums.len(), restored.added_quorums.len());
+        // entries are rehashed on the way back, so the roots are the same only if nothing is lost
+        assert_eq!(result.masternode_list.masternode_merkle_root, restored.masternode_list.masternode_merkle_root);
+        assert_eq!(result.masternode_list.llmq_merkle_root, restored.masternode_list.llmq_merkle_root);
+    });
+    // Hashes are shown as Dash Core does
+    let value = serde_json::to_value(&results[0]).unwrap();
+    let block_hash = value["block_hash"].as_str().unwrap();
+    assert_eq!(block_hash, results[0].block_hash.clone().reversed().0.to_hex());
+    // Commitments are in the form of 'protx diff'
+    let quorum = &value["masternode_list"]["quorums"][0];
+    assert!(quorum["quorumHash"].is_string() && quorum["verified"].is_boolean(), "Commitment should be shown as Dash Core does");
+}
+
+#[test]
+fn test_serde_processor_cache() {
+    let cache = populated_cache();
+    let json = serde_json::to_vec(&cache).unwrap();
+    let restored: MasternodeProcessorCache = serde_json::from_slice(&json).unwrap();
+    assert_same_state(&cache, &restored);
+    assert!(restored.merkle_trees.is_empty(), "Merkle trees aren't serialized");
+}
+
+#[test]
+fn test_serde_processing_error() {
+    let json = serde_json::to_string(&ProcessingError::HasNoBaseBlockHash).unwrap();
+    assert_eq!(json, "\"HasNoBaseBlockHash\"");
+    assert_eq!(serde_json::from_str::<ProcessingError>(&json).unwrap(), ProcessingError::HasNoBaseBlockHash);
+}
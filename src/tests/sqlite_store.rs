@@ -0,0 +1,85 @@
+use crate::lib_tests::tests::{message_from_file, FFIContext};
+use crate::processing::{MasternodeProcessorCache, ProcessingError, ProcessorSession, SessionChain};
+use crate::store::{MasternodeStore, SqliteStore};
+use crate::tests::block_store::init_mainnet_store;
+use crate::tests::masternode_store::{assert_same_state, populated_cache, temp_store_path};
+use crate::tests::processor_session::default_processor;
+use crate::{processor_create_sqlite_store, processor_destroy_masternode_list, processor_destroy_sqlite_store, processor_sqlite_store_lookup_masternode_list, processor_sqlite_store_save_masternode_list};
+use dash_spv_ffi::ffi::from::FromFFI;
+use dash_spv_models::common::chain_type::ChainType;
+use dash_spv_ffi::ffi::to::ToFFI;
+use dash_spv_primitives::crypto::UInt256;
+use std::ffi::CString;
+use std::fs;
+
+#[test]
+fn test_sqlite_store_round_trip() {
+    let cache = populated_cache();
+    let mut store = SqliteStore::open_in_memory().unwrap();
+    assert_eq!(store.schema_version().unwrap(), 3, "All migrations should be applied");
+    store.save_cache(&cache).unwrap();
+    let mut restored = MasternodeProcessorCache::default();
+    store.load_cache(&mut restored).unwrap();
+    assert_same_state(&cache, &restored);
+    // lists are indexed by height
+    let mut heights = cache.mn_lists.values().map(|list| list.known_height).collect::<Vec<u32>>();
+    heights.sort();
+    let (first, last) = (heights[0], heights[heights.len() - 1]);
+    assert_eq!(store.masternode_list_hashes_in_range(first, last).unwrap().len(), cache.mn_lists.len());
+    assert_eq!(store.masternode_list_hashes_in_range(first + 1, last).unwrap().len(), cache.mn_lists.len() - 1);
+    let list = store.masternode_list_at_height(last).unwrap().unwrap();
+    assert_eq!(list.known_height, last);
+    assert!(store.masternode_list_at_height(0).unwrap().is_none());
+    assert_eq!(store.masternode_list_at_or_below(last + 10).unwrap().unwrap().known_height, last);
+    assert_eq!(store.masternode_list_at_or_below(heights[1] - 1).unwrap().unwrap().known_height, first);
+    assert!(store.masternode_list_at_or_below(first - 1).unwrap().is_none());
+}
+
+#[test]
+fn test_sqlite_store_ffi_flow() {
+    let path = temp_store_path("test_sqlite_store_ffi_flow");
+    let cache = populated_cache();
+    let (block_hash, list) = cache.mn_lists.iter().last().unwrap();
+    let c_path = CString::new(path.to_str().unwrap()).unwrap();
+    let mut processor = default_processor();
+    unsafe {
+        let store = processor_create_sqlite_store(c_path.as_ptr(), &mut processor);
+        assert!(!store.is_null());
+        let encoded = list.to_list().encode();
+        assert!(processor_sqlite_store_save_masternode_list(store, block_hash.0.as_ptr(), &encoded));
+        assert!(processor_sqlite_store_lookup_masternode_list(store, UInt256::MIN.0.as_ptr()).is_null());
+        processor_destroy_sqlite_store(store);
+        // reopening doesn't apply migrations again
+        let store = processor_create_sqlite_store(c_path.as_ptr(), &mut processor);
+        assert_eq!((*store).schema_version().unwrap(), 3);
+        let looked = processor_sqlite_store_lookup_masternode_list(store, block_hash.0.as_ptr());
+        assert!(!looked.is_null());
+        let decoded = (*looked).decode();
+        assert_eq!(decoded.block_hash, list.block_hash);
+        assert_eq!(decoded.masternodes.len(), list.masternodes.len());
+        assert_eq!(decoded.masternode_merkle_root, list.masternode_merkle_root);
+        processor_destroy_masternode_list(looked);
+        processor_destroy_sqlite_store(store);
+    }
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_sqlite_store_saves_each_session_diff() {
+    let context = &mut (FFIContext {
+        chain: ChainType::MainNet,
+        cache: &mut Default::default(),
+        blocks: init_mainnet_store()
+    });
+    let context_ptr = context as *mut _ as *mut std::ffi::c_void;
+    let mut session = ProcessorSession::new(SessionChain::MainNet, default_processor())
+        .with_store(Box::new(SqliteStore::open_in_memory().unwrap()));
+    let bytes = message_from_file("MNL_0_1090944.dat".to_string());
+    let result = session.process_mnlistdiff(&bytes, false, false, context_ptr);
+    assert_eq!(result.error_status, ProcessingError::None, "Diff should be processed and saved");
+    let store = session.store().unwrap();
+    assert_eq!(store.masternode_list_hashes().unwrap(), vec![result.block_hash], "Store should have the list right after the diff");
+    let stored = store.masternode_list(&result.block_hash).unwrap().unwrap();
+    assert_eq!(stored.masternodes.len(), result.masternode_list.masternodes.len());
+    assert_eq!(stored.masternode_merkle_root, result.masternode_list.masternode_merkle_root);
+}
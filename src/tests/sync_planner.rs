@@ -0,0 +1,158 @@
+use crate::processing::{LLMQRotationConfig, MNListDiffResult, MasternodeProcessorCache, ProcessingError, QRInfoResult, SharedMasternodeList, SyncPlanner, SyncRequest};
+use crate::processing::sync_planner::MAX_QR_INFO_BASE_BLOCK_HASHES;
+use dash_spv_models::common::chain_type::{ChainType, IHaveChainSettings};
+use dash_spv_models::common::{LLMQSnapshotSkipMode, LLMQType};
+use dash_spv_models::llmq::LLMQSnapshot;
+use dash_spv_primitives::crypto::UInt256;
+use std::sync::Arc;
+
+/// Blocks of the test chain carry their height within the hash
+fn block_hash(height: u32) -> UInt256 {
+    let mut hash = [0xaa; 32];
+    hash[..4].copy_from_slice(&height.to_le_bytes());
+    UInt256(hash)
+}
+
+fn block_height(hash: UInt256) -> u32 {
+    if hash.0[4..].iter().all(|&byte| byte == 0xaa) {
+        u32::from_le_bytes([hash.0[0], hash.0[1], hash.0[2], hash.0[3]])
+    } else {
+        u32::MAX
+    }
+}
+
+fn add_list(cache: &mut MasternodeProcessorCache, height: u32) {
+    cache.add_masternode_list(
+        block_hash(height),
+        Arc::new(SharedMasternodeList::empty(block_hash(height), height)),
+    );
+}
+
+/// List and snapshot at the work block of the cycle as they're cached by QRINFO
+fn add_snapshot(cache: &mut MasternodeProcessorCache, height: u32) {
+    add_list(cache, height);
+    cache
+        .llmq_snapshots
+        .insert(block_hash(height), LLMQSnapshot::new(vec![], vec![], LLMQSnapshotSkipMode::NoSkipping));
+}
+
+fn plan(planner: &SyncPlanner, tip_height: u32, cache: &MasternodeProcessorCache) -> Vec<SyncRequest> {
+    planner.plan(tip_height, cache, block_height, |height| Some(block_hash(height)))
+}
+
+fn list_diff(base_block_hash: UInt256, block_height: u32) -> (UInt256, UInt256) {
+    (base_block_hash, block_hash(block_height))
+}
+
+fn list_diffs(requests: &[SyncRequest]) -> Vec<(UInt256, UInt256)> {
+    requests
+        .iter()
+        .filter_map(|request| match request {
+            SyncRequest::ListDiff { request, .. } => Some((request.base_block_hash, request.block_hash)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_sync_planner_list_diffs() {
+    let genesis_hash = ChainType::MainNet.genesis_hash();
+    let mut planner = SyncPlanner::new(genesis_hash, LLMQRotationConfig::empty());
+    let mut cache = MasternodeProcessorCache::default();
+    assert_eq!(list_diffs(&plan(&planner, 1000, &cache)), vec![list_diff(genesis_hash, 1000)], "Whole list is requested from the genesis");
+    assert!(planner.plan(1000, &cache, block_height, |_| None).is_empty(), "Nothing is planned for the unknown tip");
+
+    add_list(&mut cache, 900);
+    let requests = plan(&planner, 1000, &cache);
+    assert_eq!(list_diffs(&requests), vec![list_diff(block_hash(900), 1000)], "Diff is based on the nearest list");
+    assert_eq!(requests[0].block_height(), 1000);
+
+    let mut result = MNListDiffResult::default();
+    result.block_hash = block_hash(1000);
+    result.needed_masternode_lists = vec![block_hash(950), block_hash(500), UInt256::MAX];
+    planner.on_list_diff_result(&result);
+    add_list(&mut cache, 1000);
+    assert_eq!(
+        list_diffs(&plan(&planner, 1000, &cache)),
+        vec![list_diff(genesis_hash, 500), list_diff(block_hash(900), 950)],
+        "Needed lists are requested from the lowest one"
+    );
+    assert!(planner.needed_lists().contains(&UInt256::MAX), "Unknown block is kept until its height is known");
+
+    let mut failed = MNListDiffResult::default_with_error(ProcessingError::ParseError);
+    failed.block_hash = block_hash(500);
+    planner.on_list_diff_result(&failed);
+    assert_eq!(plan(&planner, 1000, &cache).len(), 2, "Failed diff is requested again");
+
+    result.block_hash = block_hash(500);
+    result.needed_masternode_lists = vec![];
+    planner.on_list_diff_result(&result);
+    add_list(&mut cache, 500);
+    add_list(&mut cache, 950);
+    assert!(plan(&planner, 1000, &cache).is_empty(), "Cache is synced to the tip");
+    assert_eq!(list_diffs(&plan(&planner, 1010, &cache)), vec![list_diff(block_hash(1000), 1010)]);
+}
+
+#[test]
+fn test_sync_planner_rotation_cycles() {
+    let llmq_type = LLMQType::Llmqtype60_75;
+    let dkg_params = llmq_type.params().dkg_params;
+    let cycle_height = dkg_params.interval * 100;
+    let mined_height = cycle_height + dkg_params.mining_window_end;
+    assert_eq!(SyncPlanner::mined_cycle_height(llmq_type, mined_height), Some(cycle_height));
+    assert_eq!(
+        SyncPlanner::mined_cycle_height(llmq_type, mined_height - 1),
+        Some(cycle_height - dkg_params.interval),
+        "Quorums of the cycle aren't mined yet"
+    );
+    assert_eq!(SyncPlanner::mined_cycle_height(llmq_type, 0), None);
+
+    let mut planner = SyncPlanner::new(ChainType::MainNet.genesis_hash(), LLMQRotationConfig::empty().with_type(llmq_type));
+    let mut cache = MasternodeProcessorCache::default();
+    (1..=6).for_each(|cycle| add_list(&mut cache, cycle_height - cycle * dkg_params.interval));
+    let mut result = MNListDiffResult::default();
+    result.block_hash = block_hash(cycle_height - 8);
+    result.needed_masternode_lists = vec![block_hash(cycle_height - 10)];
+    planner.on_list_diff_result(&result);
+
+    let requests = plan(&planner, mined_height, &cache);
+    match requests.first() {
+        Some(SyncRequest::QRInfo { block_height, request }) => {
+            assert_eq!(*block_height, mined_height);
+            assert_eq!(request.block_request_hash, block_hash(mined_height));
+            assert!(request.extra_share);
+            assert_eq!(request.base_block_hashes.len(), MAX_QR_INFO_BASE_BLOCK_HASHES);
+            assert_eq!(request.base_block_hashes[0], block_hash(cycle_height - dkg_params.interval), "Latest lists are the bases");
+        }
+        _ => panic!("Rotation info must be requested first"),
+    }
+    assert_eq!(
+        list_diffs(&requests),
+        vec![list_diff(block_hash(cycle_height - dkg_params.interval), cycle_height - 10)],
+        "Tip list comes with rotation info, so only needed lists are requested"
+    );
+
+    planner.on_qr_info_result(&QRInfoResult::default_with_error(ProcessingError::ParseError));
+    assert!(matches!(plan(&planner, mined_height, &cache).first(), Some(SyncRequest::QRInfo { .. })));
+
+    planner.on_qr_info_result(&QRInfoResult::default());
+    assert!(
+        matches!(plan(&planner, mined_height, &cache).first(), Some(SyncRequest::QRInfo { .. })),
+        "Cycle is synced by the cached lists and snapshots, not by the result"
+    );
+    add_list(&mut cache, cycle_height - 8);
+    (1..=3).for_each(|cycle| add_snapshot(&mut cache, cycle_height - cycle * dkg_params.interval - 8));
+    add_list(&mut cache, mined_height);
+    assert!(SyncPlanner::is_cycle_synced(llmq_type, cycle_height, &cache));
+    assert_eq!(
+        list_diffs(&plan(&planner, mined_height + 1, &cache)),
+        vec![list_diff(block_hash(cycle_height - dkg_params.interval), cycle_height - 10), list_diff(block_hash(mined_height), mined_height + 1)],
+        "Rotation info isn't requested again within the cycle"
+    );
+    let next_cycle_height = cycle_height + dkg_params.interval + dkg_params.mining_window_end;
+    assert!(matches!(plan(&planner, next_cycle_height, &cache).first(), Some(SyncRequest::QRInfo { .. })));
+
+    cache.llmq_snapshots.remove(&block_hash(cycle_height - 2 * dkg_params.interval - 8));
+    assert!(!SyncPlanner::is_cycle_synced(llmq_type, cycle_height, &cache), "Cycle without the snapshot isn't synced");
+    assert!(matches!(plan(&planner, mined_height + 1, &cache).first(), Some(SyncRequest::QRInfo { .. })));
+}
@@ -5,7 +5,7 @@ use dash_spv_primitives::crypto::UInt256;
 use crate::{processor_create_cache, register_processor};
 use crate::lib_tests::tests::{add_insight_lookup_default, FFIContext, get_block_hash_by_height_from_context, get_block_height_by_hash_from_context, get_llmq_snapshot_by_block_hash_default, get_masternode_list_by_block_hash_default, get_merkle_root_by_hash_default, hash_destroy_default, log_default, masternode_list_destroy_default, masternode_list_save_default, message_from_file, save_llmq_snapshot_default, should_process_diff_with_range_default, snapshot_destroy_default};
 use crate::tests::block_store::init_testnet_store;
-use crate::tests::json_from_core_snapshot::{masternode_list_from_genesis_diff, QRInfo, snapshot_to_snapshot};
+use crate::core_json::{list_diff_from_json, QRInfo, snapshot_from_json};
 use crate::tests::llmq_rotation::{should_process_isd_quorum, validate_llmq_callback_throuh_rust_bls};
 
 #[test]
@@ -24,15 +24,15 @@ pub fn test_from_snapshot() {
     });
     let block_height_lookup = |hash: UInt256| context.block_for_hash(hash).unwrap().height;
 
-    let quorum_snapshot_h_c = snapshot_to_snapshot(qrinfo.quorum_snapshot_at_hminus_c);
-    let quorum_snapshot_h_2c = snapshot_to_snapshot(qrinfo.quorum_snapshot_at_hminus2c);
-    let quorum_snapshot_h_3c = snapshot_to_snapshot(qrinfo.quorum_snapshot_at_hminus3c);
+    let quorum_snapshot_h_c = snapshot_from_json(&qrinfo.quorum_snapshot_at_hminus_c);
+    let quorum_snapshot_h_2c = snapshot_from_json(&qrinfo.quorum_snapshot_at_hminus2c);
+    let quorum_snapshot_h_3c = snapshot_from_json(&qrinfo.quorum_snapshot_at_hminus3c);
 
-    let mn_list_diff_tip = masternode_list_from_genesis_diff(qrinfo.mn_list_diff_tip, block_height_lookup);
-    let mn_list_diff_h = masternode_list_from_genesis_diff(qrinfo.mn_list_diff_h, block_height_lookup);
-    let mn_list_diff_h_c = masternode_list_from_genesis_diff(qrinfo.mn_list_diff_at_hminus_c, block_height_lookup);
-    let mn_list_diff_h_2c = masternode_list_from_genesis_diff(qrinfo.mn_list_diff_at_hminus2c, block_height_lookup);
-    let mn_list_diff_h_3c = masternode_list_from_genesis_diff(qrinfo.mn_list_diff_at_hminus3c, block_height_lookup);
+    let mn_list_diff_tip = list_diff_from_json(&qrinfo.mn_list_diff_tip, block_height_lookup).unwrap();
+    let mn_list_diff_h = list_diff_from_json(&qrinfo.mn_list_diff_h, block_height_lookup).unwrap();
+    let mn_list_diff_h_c = list_diff_from_json(&qrinfo.mn_list_diff_at_hminus_c, block_height_lookup).unwrap();
+    let mn_list_diff_h_2c = list_diff_from_json(&qrinfo.mn_list_diff_at_hminus2c, block_height_lookup).unwrap();
+    let mn_list_diff_h_3c = list_diff_from_json(&qrinfo.mn_list_diff_at_hminus3c, block_height_lookup).unwrap();
 
 
     let processor = unsafe { &mut *register_processor(
@@ -74,7 +74,9 @@ pub fn test_from_snapshot() {
                 cached_llmq_members,
                 cached_llmq_indexed_members,
                 &context.cache.mn_lists,
+                &context.cache.mn_list_height_index,
                 &context.cache.llmq_snapshots,
+                &mut context.cache.merkle_trees,
                 &mut context.cache.needed_masternode_lists);
             println!("masternodes: {:#?}", masternodes);
         });